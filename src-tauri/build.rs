@@ -1,3 +1,106 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// RP ID release builds fall back to when Cargo.toml doesn't configure one
+const DEFAULT_RP_ID: &str = "app.sparktype.org";
+
 fn main() {
+  println!("cargo:rerun-if-changed=Cargo.toml");
+  println!("cargo:rustc-env=SPARKTYPE_RP_ID={}", configured_rp_id());
+  println!("cargo:rerun-if-env-changed=SPARKTYPE_RP_ID_HASH");
+  println!("cargo:rustc-env=SPARKTYPE_RP_ID_HASH={}", pinned_rp_id_hash());
+  println!("cargo:rustc-env=SPARKTYPE_GIT_SHA={}", git_sha());
+  println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+  println!("cargo:rustc-env=SPARKTYPE_BUILD_TIMESTAMP={}", build_timestamp());
+
   tauri_build::build()
 }
+
+/// `rp-id` from the `[package.metadata.webauthn]` table of Cargo.toml
+///
+/// Validated when the crate compiles (see `PRODUCTION_RP_ID` in webauthn.rs).
+fn configured_rp_id() -> String {
+  let manifest = std::fs::read_to_string("Cargo.toml").expect("Failed to read Cargo.toml");
+  let manifest: toml::Table = manifest.parse().expect("Cargo.toml is not valid TOML");
+
+  manifest
+    .get("package")
+    .and_then(|package| package.get("metadata"))
+    .and_then(|metadata| metadata.get("webauthn"))
+    .and_then(|webauthn| webauthn.get("rp-id"))
+    .and_then(|rp_id| rp_id.as_str())
+    .unwrap_or(DEFAULT_RP_ID)
+    .trim()
+    .to_ascii_lowercase()
+}
+
+/// `SPARKTYPE_RP_ID_HASH`, the hex SHA-256 of the RP ID release builds must
+/// use; empty when unset
+///
+/// Checked against `rp-id` when the crate compiles (see rp_id_pin.rs).
+fn pinned_rp_id_hash() -> String {
+  let hash = std::env::var("SPARKTYPE_RP_ID_HASH").unwrap_or_default().trim().to_ascii_lowercase();
+  if !hash.is_empty() && (hash.len() != 64 || !hash.bytes().all(|byte| byte.is_ascii_hexdigit())) {
+    panic!("SPARKTYPE_RP_ID_HASH must be the 64 hex digits of a SHA-256, got {:?}", hash);
+  }
+  hash
+}
+
+/// Short hash of the commit being built, `unknown` outside a git checkout
+///
+/// Has the build script rerun when HEAD moves, so the hash does not go stale.
+fn git_sha() -> String {
+  if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+    let git_dir = Path::new(&git_dir);
+    let mut watched = vec![git_dir.join("HEAD"), git_dir.join("packed-refs")];
+    if let Some(branch) = git(&["symbolic-ref", "-q", "HEAD"]) {
+      watched.push(git_dir.join(branch));
+    }
+    // Cargo reruns the script on every build for a path that does not exist
+    for path in watched.iter().filter(|path| path.exists()) {
+      println!("cargo:rerun-if-changed={}", path.display());
+    }
+  }
+  git(&["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| "unknown".to_string())
+}
+
+/// What `git args` prints, unless git is missing or fails
+fn git(args: &[&str]) -> Option<String> {
+  let output = Command::new("git").args(args).output().ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  let output = String::from_utf8(output.stdout).ok()?.trim().to_string();
+  (!output.is_empty()).then_some(output)
+}
+
+/// When the build script ran, or `SOURCE_DATE_EPOCH` for reproducible builds,
+/// as an RFC 3339 UTC timestamp
+fn build_timestamp() -> String {
+  let seconds = std::env::var("SOURCE_DATE_EPOCH")
+    .ok()
+    .and_then(|epoch| epoch.trim().parse().ok())
+    .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs()));
+
+  // Days since the epoch to a civil date, after Howard Hinnant's `civil_from_days`
+  let days = (seconds / 86_400) as i64 + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days.rem_euclid(146_097);
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+  let time = seconds % 86_400;
+  format!(
+    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+    year,
+    month,
+    day,
+    time / 3600,
+    time % 3600 / 60,
+    time % 60
+  )
+}
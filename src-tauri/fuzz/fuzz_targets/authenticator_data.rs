@@ -0,0 +1,36 @@
+// src-tauri/fuzz/fuzz_targets/authenticator_data.rs
+
+//! `parse_authenticator_data` and `parse_cose_key` on arbitrary bytes.
+//!
+//! Both may refuse the input, but neither may panic, whether it is empty, far
+//! longer than real authenticator data or cut short anywhere. Input that
+//! parses is also parsed again at every shorter length, to cover truncations of
+//! well-formed data, and must carry the attested credential exactly when its AT
+//! flag is set.
+//!
+//! Run with `cargo fuzz run authenticator_data` from src-tauri.
+
+#![no_main]
+
+use app_lib::{parse_authenticator_data, parse_cose_key, AuthenticatorDataFlags};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_cose_key(data);
+    let Ok(auth_data) = parse_authenticator_data(data) else {
+        return;
+    };
+
+    let attested = auth_data.flags.contains(AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA);
+    assert_eq!(auth_data.aaguid.is_some(), attested);
+    assert_eq!(auth_data.credential_id.is_some(), attested);
+    assert_eq!(auth_data.cose_key.is_some(), attested);
+    let _ = auth_data.aaguid_string();
+    if let Some(cose_key) = &auth_data.cose_key {
+        let _ = cose_key.to_pem();
+    }
+
+    for length in 0..data.len() {
+        let _ = parse_authenticator_data(&data[..length]);
+    }
+});
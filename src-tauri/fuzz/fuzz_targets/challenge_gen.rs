@@ -0,0 +1,42 @@
+// src-tauri/fuzz/fuzz_targets/challenge_gen.rs
+
+//! `generate_challenge` for whatever length a config asks for.
+//!
+//! The first two input bytes pick the length. `ChallengeConfig::validate`
+//! refuses most of them, as `configure_challenge` would; for the others the
+//! challenge must be unpadded base64url that decodes to exactly that many
+//! bytes. One generator is shared between runs, as the app shares one between
+//! commands.
+//!
+//! Run with `cargo fuzz run challenge_gen` from src-tauri.
+
+#![no_main]
+
+use std::sync::OnceLock;
+
+use app_lib::{generate_challenge, ChallengeConfig, ChallengeRng};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use libfuzzer_sys::fuzz_target;
+
+static RNG: OnceLock<ChallengeRng> = OnceLock::new();
+
+fuzz_target!(|data: &[u8]| {
+    let [low, high, ..] = *data else {
+        return;
+    };
+    let config = ChallengeConfig {
+        length_bytes: u16::from_le_bytes([low, high]) as usize,
+    };
+    if config.validate().is_err() {
+        return;
+    }
+
+    let challenge = generate_challenge(RNG.get_or_init(ChallengeRng::new), &config);
+    assert!(
+        challenge.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'),
+        "{:?} is not unpadded base64url",
+        challenge
+    );
+    let decoded = URL_SAFE_NO_PAD.decode(&challenge).expect("the challenge does not decode");
+    assert_eq!(decoded.len(), config.length_bytes);
+});
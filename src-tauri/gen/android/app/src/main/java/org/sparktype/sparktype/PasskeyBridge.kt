@@ -0,0 +1,79 @@
+package org.sparktype.sparktype
+
+import android.app.Activity
+import androidx.annotation.Keep
+import androidx.core.content.ContextCompat
+import androidx.credentials.CreateCredentialResponse
+import androidx.credentials.CreatePublicKeyCredentialRequest
+import androidx.credentials.CreatePublicKeyCredentialResponse
+import androidx.credentials.CredentialManager
+import androidx.credentials.CredentialManagerCallback
+import androidx.credentials.GetCredentialRequest
+import androidx.credentials.GetCredentialResponse
+import androidx.credentials.GetPublicKeyCredentialOption
+import androidx.credentials.PublicKeyCredential
+import androidx.credentials.exceptions.CreateCredentialException
+import androidx.credentials.exceptions.GetCredentialException
+
+/**
+ * Runs Credential Manager passkey ceremonies on behalf of src-tauri/src/webauthn_android.rs.
+ *
+ * Rust calls [create] / [get] with a WebAuthn request JSON and a handle; the outcome is
+ * reported back through the native [deliver] method with the same handle.
+ */
+@Keep
+object PasskeyBridge {
+    @JvmStatic
+    external fun deliver(handle: Long, responseJson: String?, errorType: String?, errorMessage: String?)
+
+    @JvmStatic
+    fun create(activity: Activity, requestJson: String, handle: Long) {
+        activity.runOnUiThread {
+            CredentialManager.create(activity).createCredentialAsync(
+                activity,
+                CreatePublicKeyCredentialRequest(requestJson),
+                null,
+                ContextCompat.getMainExecutor(activity),
+                object : CredentialManagerCallback<CreateCredentialResponse, CreateCredentialException> {
+                    override fun onResult(result: CreateCredentialResponse) {
+                        if (result is CreatePublicKeyCredentialResponse) {
+                            deliver(handle, result.registrationResponseJson, null, null)
+                        } else {
+                            deliver(handle, null, result.type, "Unexpected credential type")
+                        }
+                    }
+
+                    override fun onError(e: CreateCredentialException) {
+                        deliver(handle, null, e.type, e.errorMessage?.toString())
+                    }
+                },
+            )
+        }
+    }
+
+    @JvmStatic
+    fun get(activity: Activity, requestJson: String, handle: Long) {
+        activity.runOnUiThread {
+            CredentialManager.create(activity).getCredentialAsync(
+                activity,
+                GetCredentialRequest(listOf(GetPublicKeyCredentialOption(requestJson))),
+                null,
+                ContextCompat.getMainExecutor(activity),
+                object : CredentialManagerCallback<GetCredentialResponse, GetCredentialException> {
+                    override fun onResult(result: GetCredentialResponse) {
+                        val credential = result.credential
+                        if (credential is PublicKeyCredential) {
+                            deliver(handle, credential.authenticationResponseJson, null, null)
+                        } else {
+                            deliver(handle, null, credential.type, "Unexpected credential type")
+                        }
+                    }
+
+                    override fun onError(e: GetCredentialException) {
+                        deliver(handle, null, e.type, e.errorMessage?.toString())
+                    }
+                },
+            )
+        }
+    }
+}
@@ -0,0 +1,208 @@
+// src-tauri/src/app_lock.rs
+
+//! Locking the app after a period without user activity.
+//!
+//! Once `configure_app_lock` sets an idle timeout, the frontend reports user
+//! interaction through `record_activity`, and a background task checks every 30
+//! seconds how long ago that last happened. When the timeout has passed, every
+//! open session is ended and `webauthn://app-locked` goes out. The next
+//! successful `authenticate_passkey`, for any site, unlocks the app and reopens
+//! the sessions the lock ended. With `requireBiometric`, that authentication
+//! must verify the user. The lock is off until configured, and its settings
+//! last until the app quits.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Deserialize;
+use tauri::{command, AppHandle, Manager, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::session_store::SessionStore;
+use crate::webauthn::{UserVerification, WebAuthnError};
+use crate::webauthn_events::WebAuthnEvent;
+use crate::webauthn_state::WebAuthnState;
+
+/// How often the background task checks for idleness, unless configured otherwise
+pub const DEFAULT_IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Longest idle timeout `configure_app_lock` accepts
+pub const MAX_IDLE_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+/// When the app locks, from `configure_app_lock`
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct AppLockConfig {
+    /// Seconds without activity before the app locks; 0 turns the lock off
+    #[serde(rename = "idleTimeoutSeconds")]
+    pub idle_timeout_seconds: u64,
+    /// The authentication that unlocks the app must verify the user
+    #[serde(rename = "requireBiometric", default)]
+    pub require_biometric: bool,
+}
+
+/// The app lock's settings and the user activity it watches
+pub struct AppLock {
+    config: Mutex<Option<AppLockConfig>>,
+    /// Unix time of the last recorded activity, in seconds
+    last_activity: AtomicU64,
+    /// Sites whose sessions the lock ended, while the app is locked
+    locked_sites: Mutex<Option<Vec<String>>>,
+}
+
+impl Default for AppLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppLock {
+    pub fn new() -> Self {
+        Self {
+            config: Mutex::new(None),
+            last_activity: AtomicU64::new(now_seconds()),
+            locked_sites: Mutex::new(None),
+        }
+    }
+
+    /// Replace the lock's settings, counting from now
+    pub fn configure(&self, config: AppLockConfig) -> Result<(), WebAuthnError> {
+        if config.idle_timeout_seconds > MAX_IDLE_TIMEOUT_SECS {
+            return Err(WebAuthnError::InvalidInput(format!(
+                "Idle timeout must be at most {} seconds, got {}",
+                MAX_IDLE_TIMEOUT_SECS, config.idle_timeout_seconds
+            )));
+        }
+        *lock(&self.config) = (config.idle_timeout_seconds > 0).then_some(config);
+        self.record_activity();
+        Ok(())
+    }
+
+    pub fn record_activity(&self) {
+        self.last_activity.store(now_seconds(), Ordering::Relaxed);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        lock(&self.locked_sites).is_some()
+    }
+
+    /// Lock the app if it has been idle for longer than its timeout, ending
+    /// every session; returns whether it locked
+    pub fn lock_if_idle(&self, sessions: &SessionStore) -> bool {
+        let Some(config) = *lock(&self.config) else {
+            return false;
+        };
+        let idle_seconds = now_seconds().saturating_sub(self.last_activity.load(Ordering::Relaxed));
+        if idle_seconds <= config.idle_timeout_seconds {
+            return false;
+        }
+        let mut locked_sites = lock(&self.locked_sites);
+        if locked_sites.is_some() {
+            return false;
+        }
+        let closed: Vec<String> = sessions
+            .close_all()
+            .into_iter()
+            .map(|session| session.site_id)
+            .collect();
+        log::info!(
+            "Locked the app after {} seconds without activity, ending {} sessions",
+            idle_seconds,
+            closed.len()
+        );
+        *locked_sites = Some(closed);
+        true
+    }
+
+    /// The user verification an authentication needs, given the one asked for
+    pub fn user_verification(&self, requested: UserVerification) -> UserVerification {
+        let require_biometric = lock(&self.config).is_some_and(|config| config.require_biometric);
+        if require_biometric && self.is_locked() {
+            UserVerification::Required
+        } else {
+            requested
+        }
+    }
+
+    /// Keep the unlock from reopening `site_id`'s session, e.g. after a logout
+    pub fn forget_site(&self, site_id: &str) {
+        if let Some(site_ids) = lock(&self.locked_sites).as_mut() {
+            site_ids.retain(|locked| locked != site_id);
+        }
+    }
+
+    /// Keep the unlock from reopening any session, leaving the app locked
+    pub fn forget_all_sites(&self) {
+        if let Some(site_ids) = lock(&self.locked_sites).as_mut() {
+            site_ids.clear();
+        }
+    }
+
+    /// Count a successful authentication as activity and, if the app was
+    /// locked, unlock it and reopen the sessions the lock ended
+    pub fn authenticated(&self, sessions: &SessionStore, grace_period_seconds: u64) {
+        self.record_activity();
+        let Some(site_ids) = lock(&self.locked_sites).take() else {
+            return;
+        };
+        for site_id in &site_ids {
+            sessions.open(site_id, grace_period_seconds);
+        }
+        log::info!("Unlocked the app, reopening {} sessions", site_ids.len());
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn now_seconds() -> u64 {
+    Utc::now().timestamp().max(0) as u64
+}
+
+/// Check for idleness every `interval`, locking the app once it times out
+pub fn spawn_idle_check<R: Runtime>(app: AppHandle<R>, interval: Duration) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            let state = app.state::<WebAuthnState>();
+            if state.app_lock.lock_if_idle(&state.sessions) {
+                WebAuthnEvent::AppLocked.emit(&app);
+            }
+        }
+    });
+}
+
+/// Lock the app after `idleTimeoutSeconds` without activity, at most 86400
+/// (24 hours); 0 turns the lock off
+///
+/// Restarts the idle countdown. A locked app stays locked until it is unlocked
+/// by authenticating.
+#[command]
+pub async fn configure_app_lock<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    config: AppLockConfig,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    state.app_lock.configure(config)?;
+    log::info!(
+        "App lock set to {} seconds of inactivity{}",
+        config.idle_timeout_seconds,
+        if config.require_biometric { ", unlocking with user verification" } else { "" }
+    );
+    Ok(())
+}
+
+/// Record that the user interacted with the app, restarting the idle countdown
+#[command]
+pub async fn record_activity<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    state.app_lock.record_activity();
+    Ok(())
+}
@@ -0,0 +1,292 @@
+// src-tauri/src/audit_log.rs
+
+//! A tamper-evident record of the WebAuthn operations the app ran.
+//!
+//! Registrations, authentications, passkey deletions and unlocks, credential
+//! exports and imports, and sync offers made and accepted each append one
+//! JSON line to `webauthn_audit.jsonl`, next to the credential store, with
+//! their outcome: `success` or the code of the error they failed with. Panics are recorded
+//! too, with their message and location. A file that would
+//! grow past 10 MB is rotated to `webauthn_audit.jsonl.1`, pushing older ones
+//! along to `.3`, which is then the oldest kept.
+//!
+//! Records are numbered from 1 across rotations, and each carries an
+//! HMAC-SHA256 over its sequence number and its JSON without the `mac` field.
+//! The MAC key is derived from the credential store key, so it is kept in the
+//! OS keychain with it; `verify_audit_log` checks the MACs and that no record
+//! is missing in between. Failing to write a record is logged and does not
+//! fail the operation it records.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use tauri::{command, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_store::CredentialStore;
+use crate::webauthn::{timestamp, WebAuthnError};
+
+const AUDIT_LOG_FILE_NAME: &str = "webauthn_audit.jsonl";
+
+/// Size past which the log is rotated
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Rotated files kept, `.1` being the newest
+const ROTATED_FILES: usize = 3;
+
+/// HKDF info the MAC key is derived from the store key with
+const MAC_KEY_INFO: &[u8] = b"sparktype-webauthn-audit-log";
+
+/// An operation to record, with what it was run on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case", rename_all_fields = "camelCase")]
+pub(crate) enum AuditEvent {
+    Registration {
+        site_id: String,
+        device_name: Option<String>,
+        outcome: String,
+    },
+    Authentication {
+        site_id: String,
+        /// The passkey that signed in; `None` when none did
+        credential_id: Option<String>,
+        duration_ms: u64,
+        outcome: String,
+    },
+    PasskeyDeletion {
+        site_id: String,
+        credential_id: String,
+        outcome: String,
+    },
+    PasskeyUnlock {
+        site_id: String,
+        credential_id: String,
+        outcome: String,
+    },
+    CredentialExport {
+        outcome: String,
+    },
+    CredentialImport {
+        outcome: String,
+    },
+    CredentialSyncOffer {
+        outcome: String,
+    },
+    CredentialSyncAccept {
+        outcome: String,
+    },
+    /// A panic caught by the plugin's panic hook
+    Panic {
+        thread: Option<String>,
+        message: String,
+        location: Option<String>,
+    },
+}
+
+/// `success`, or the code of the error an operation failed with
+pub(crate) fn outcome<T>(result: &Result<T, WebAuthnError>) -> String {
+    match result {
+        Ok(_) => "success".to_string(),
+        Err(error) => error.code().to_string(),
+    }
+}
+
+/// A record without its MAC, which is computed over this
+#[derive(Serialize, Deserialize)]
+struct AuditRecord {
+    seq: u64,
+    #[serde(with = "timestamp")]
+    timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    event: AuditEvent,
+}
+
+/// One line of the log
+#[derive(Serialize, Deserialize)]
+struct SignedRecord {
+    #[serde(flatten)]
+    record: AuditRecord,
+    mac: String,
+}
+
+/// Just the sequence number of a line, to carry on from the last one
+#[derive(Deserialize)]
+struct Sequence {
+    seq: u64,
+}
+
+struct Writer {
+    key: hmac::Key,
+    last_seq: u64,
+}
+
+/// The audit log, picking up its sequence from its file the first time it is
+/// written to
+#[derive(Default)]
+pub struct AuditLog {
+    writer: Mutex<Option<Writer>>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a record of `event`, logging why if it could not be written
+    pub(crate) fn record(&self, store: &CredentialStore, event: AuditEvent) {
+        if let Err(error) = self.append(store, event) {
+            log::warn!("Failed to write the WebAuthn audit log: {}", error);
+        }
+    }
+
+    fn append(&self, store: &CredentialStore, event: AuditEvent) -> Result<(), WebAuthnError> {
+        let path = audit_log_path(store);
+        let mut writer = self.lock();
+        let writer = match &mut *writer {
+            Some(writer) => writer,
+            None => writer.insert(Writer {
+                key: store.derive_hmac_key(MAC_KEY_INFO)?,
+                last_seq: last_sequence(&path),
+            }),
+        };
+        let record = AuditRecord {
+            seq: writer.last_seq + 1,
+            timestamp: Utc::now(),
+            event,
+        };
+        let mut line = serde_json::to_string(&SignedRecord {
+            mac: URL_SAFE_NO_PAD.encode(hmac::sign(&writer.key, &mac_input(&record)?)),
+            record,
+        })
+        .map_err(|e| WebAuthnError::InternalError(e.to_string()))?;
+        line.push('\n');
+        write_line(&path, &line).map_err(|e| WebAuthnError::InternalError(e.to_string()))?;
+        writer.last_seq += 1;
+        Ok(())
+    }
+
+    /// Have the records written so far reach the disk
+    pub(crate) fn flush(&self, store: &CredentialStore) -> io::Result<()> {
+        // Held so no record is being appended meanwhile
+        let _writer = self.lock();
+        // Windows only flushes files opened for writing
+        match OpenOptions::new().append(true).open(audit_log_path(store)) {
+            Ok(file) => file.sync_all(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Writer>> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Check every record of the audit log and its rotated files, oldest first
+///
+/// Fails on the first record that is not valid JSON, whose MAC does not match,
+/// or whose sequence number does not follow the one before. Returns how many
+/// records were checked.
+pub fn verify_audit_log(store: &CredentialStore) -> Result<usize, WebAuthnError> {
+    let key = store.derive_hmac_key(MAC_KEY_INFO)?;
+    let path = audit_log_path(store);
+    let mut previous: Option<u64> = None;
+    let mut count = 0;
+    for file in (1..=ROTATED_FILES).rev().map(|index| rotated_path(&path, index)).chain([path.clone()]) {
+        let contents = match fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => {
+                return Err(WebAuthnError::InternalError(format!(
+                    "Failed to read the audit log: {}",
+                    error
+                )))
+            }
+        };
+        for line in contents.lines().filter(|line| !line.is_empty()) {
+            let signed: SignedRecord = serde_json::from_str(line)
+                .map_err(|e| WebAuthnError::InternalError(format!("Unreadable audit record: {}", e)))?;
+            let seq = signed.record.seq;
+            let input = mac_input(&signed.record)?;
+            let authentic = URL_SAFE_NO_PAD
+                .decode(&signed.mac)
+                .is_ok_and(|mac| hmac::verify(&key, &input, &mac).is_ok());
+            if !authentic {
+                return Err(WebAuthnError::InternalError(format!("Audit record {} was altered", seq)));
+            }
+            if previous.is_some_and(|previous| seq != previous + 1) {
+                return Err(WebAuthnError::InternalError(format!(
+                    "Audit records are missing before record {}",
+                    seq
+                )));
+            }
+            previous = Some(seq);
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// What a record's MAC is computed over: its sequence number, then its JSON
+fn mac_input(record: &AuditRecord) -> Result<Vec<u8>, WebAuthnError> {
+    let json = serde_json::to_string(record).map_err(|e| WebAuthnError::InternalError(e.to_string()))?;
+    Ok([record.seq.to_string(), json].concat().into_bytes())
+}
+
+fn audit_log_path(store: &CredentialStore) -> PathBuf {
+    store.data_dir().join(AUDIT_LOG_FILE_NAME)
+}
+
+/// `webauthn_audit.jsonl.<index>`
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// The sequence number of the newest record, in the log or the newest rotated
+/// file; 0 when there is none
+fn last_sequence(path: &Path) -> u64 {
+    [path.to_path_buf(), rotated_path(path, 1)]
+        .iter()
+        .find_map(|file| {
+            let contents = fs::read_to_string(file).ok()?;
+            let line = contents.lines().rev().find(|line| !line.is_empty())?;
+            serde_json::from_str::<Sequence>(line).ok().map(|sequence| sequence.seq)
+        })
+        .unwrap_or(0)
+}
+
+/// Append `line`, rotating the log first if it would grow past its limit
+fn write_line(path: &Path, line: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let size = fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 > MAX_FILE_BYTES {
+        for index in (1..ROTATED_FILES).rev() {
+            let from = rotated_path(path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(path, index + 1))?;
+            }
+        }
+        fs::rename(path, rotated_path(path, 1))?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(line.as_bytes())
+}
+
+/// Where the audit log is written
+#[command]
+pub async fn get_audit_log_path<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+) -> Result<String, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    Ok(audit_log_path(&store).display().to_string())
+}
@@ -0,0 +1,146 @@
+// src-tauri/src/auth_notifications.rs
+
+//! OS alerts for authentication outcomes.
+//!
+//! Screen readers announce Notification Center alerts, so on macOS and iOS
+//! `authenticate_passkey` can post one when it finishes, besides emitting
+//! `webauthn://authentication-completed`. Other platforms only get the event.
+//! Alerts are off unless the `webauthn` plugin is built with
+//! `auth_notifications`, and permission to post them is asked for at startup so
+//! the prompt never lands in the middle of a ceremony.
+
+use crate::webauthn::WebAuthnError;
+
+/// Which authentication outcomes get an alert
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AuthNotificationConfig {
+    pub on_success: bool,
+    pub on_failure: bool,
+}
+
+impl AuthNotificationConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.on_success || self.on_failure
+    }
+}
+
+/// Ask the OS for permission to post alerts, if the config enables any
+pub(crate) fn request_permission(config: &AuthNotificationConfig) {
+    if config.is_enabled() {
+        platform::request_authorization();
+    }
+}
+
+/// Post the alert for a finished `authenticate_passkey` call, if the config
+/// asks for one
+pub(crate) fn notify(
+    config: &AuthNotificationConfig,
+    site_id: &str,
+    user_display_name: Option<&str>,
+    outcome: Result<(), &WebAuthnError>,
+) {
+    let (title, body) = match outcome {
+        Ok(()) if config.on_success => (
+            "Site Authenticated",
+            match user_display_name {
+                Some(name) => format!("{} authenticated to {}", name, site_id),
+                None => format!("Authenticated to {}", site_id),
+            },
+        ),
+        Err(_) if config.on_failure => ("Site Authentication Failed", format!("Authentication to {} failed", site_id)),
+        _ => return,
+    };
+    // One alert per site, so a newer outcome replaces the one before it
+    platform::post(&format!("webauthn.{}", site_id), title, &body);
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod platform {
+    use block2::RcBlock;
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyClass, AnyObject, Bool};
+    use objc2::{msg_send, msg_send_id};
+    use objc2_foundation::{NSBundle, NSError, NSString};
+
+    // UserNotifications is not among the bindings, so its classes are looked up
+    // at runtime; linking the framework makes sure they are loaded
+    #[link(name = "UserNotifications", kind = "framework")]
+    extern "C" {}
+
+    /// UNAuthorizationOptionSound | UNAuthorizationOptionAlert
+    const ALERT_AND_SOUND: usize = (1 << 1) | (1 << 2);
+
+    /// The shared UNUserNotificationCenter, or `None` where there is none to use
+    fn notification_center() -> Option<Retained<AnyObject>> {
+        // The center raises an exception in a process without a bundle, such as
+        // the bare binary of `cargo run`
+        if unsafe { NSBundle::mainBundle().bundleIdentifier() }.is_none() {
+            log::debug!("Not running from an app bundle; authentication alerts are off");
+            return None;
+        }
+        // Missing before macOS 10.14
+        let Some(class) = AnyClass::get("UNUserNotificationCenter") else {
+            log::warn!("UNUserNotificationCenter is unavailable; authentication alerts are off");
+            return None;
+        };
+        Some(unsafe { msg_send_id![class, currentNotificationCenter] })
+    }
+
+    pub(super) fn request_authorization() {
+        let Some(center) = notification_center() else {
+            return;
+        };
+        let on_reply = RcBlock::new(|granted: Bool, error: *mut NSError| {
+            if !granted.as_bool() {
+                let reason = unsafe { error.as_ref() }.map(|error| error.localizedDescription().to_string());
+                log::warn!(
+                    "Notification permission was not granted; authentication alerts will not show: {}",
+                    reason.unwrap_or_else(|| "declined".to_string())
+                );
+            }
+        });
+        unsafe {
+            let _: () = msg_send![&*center, requestAuthorizationWithOptions: ALERT_AND_SOUND,
+                completionHandler: &*on_reply];
+        }
+    }
+
+    pub(super) fn post(identifier: &str, title: &str, body: &str) {
+        let Some(center) = notification_center() else {
+            return;
+        };
+        let (Some(content_class), Some(request_class)) =
+            (AnyClass::get("UNMutableNotificationContent"), AnyClass::get("UNNotificationRequest"))
+        else {
+            return;
+        };
+
+        let on_added = RcBlock::new(|error: *mut NSError| {
+            if let Some(error) = unsafe { error.as_ref() } {
+                log::warn!("Failed to post an authentication alert: {}", error.localizedDescription());
+            }
+        });
+        unsafe {
+            let content: Retained<AnyObject> = msg_send_id![content_class, new];
+            let _: () = msg_send![&*content, setTitle: &*NSString::from_str(title)];
+            let _: () = msg_send![&*content, setBody: &*NSString::from_str(body)];
+            // Without a trigger the alert is delivered right away
+            let request: Retained<AnyObject> = msg_send_id![
+                request_class,
+                requestWithIdentifier: &*NSString::from_str(identifier),
+                content: &*content,
+                trigger: std::ptr::null::<AnyObject>()
+            ];
+            let _: () = msg_send![&*center, addNotificationRequest: &*request, withCompletionHandler: &*on_added];
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+mod platform {
+    pub(super) fn request_authorization() {
+        log::debug!("Authentication alerts are only posted on macOS and iOS");
+    }
+
+    pub(super) fn post(_identifier: &str, _title: &str, _body: &str) {}
+}
@@ -0,0 +1,79 @@
+// src-tauri/src/capability_filter.rs
+
+//! Which windows may invoke the sensitive WebAuthn commands.
+//!
+//! A multi-window app can host untrusted content, such as a site preview, in a
+//! window of its own, and that window should not be able to register or delete
+//! passkeys. Windows are granted a `CapabilityTier` by label with the webauthn
+//! plugin's `Builder`, and the commands of a tier refuse windows without it
+//! with `Unauthorized`, before touching the platform. While no window has been
+//! granted a tier, every window may invoke every command, as a single-window
+//! app expects; once any has, windows left out get none.
+
+use std::collections::HashMap;
+
+use tauri::{Manager, Runtime, WebviewWindow};
+
+use crate::webauthn::WebAuthnError;
+use crate::webauthn_state::WebAuthnState;
+
+/// A set of WebAuthn commands; each tier includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CapabilityTier {
+    /// `is_webauthn_available`, `get_webauthn_capabilities`, `get_plugin_version`, `get_rp_id`, `get_site_auth_status`,
+    /// `get_credential_last_used`, `verify_stored_credential`, `load_site_credential`, `list_site_credentials`,
+    /// `list_protected_sites`, `get_login_hints`, `get_audit_log_path`, `webauthn_health_check`,
+    /// `get_webauthn_metrics`, `get_onboarding_state`, `check_clipboard_write`, `verify_assertion` and, with the
+    /// `schema-export` feature, `export_webauthn_json_schema`
+    ReadOnly,
+    /// Adds `create_challenge`, `authenticate_passkey`, `discover_credentials`, `submit_security_key_pin`,
+    /// `initiate_hybrid_auth` and `poll_hybrid_auth`
+    Authenticate,
+    /// Adds `register_passkey`, `upgrade_site_to_passkey`, `delete_passkey`, `unlock_credential`,
+    /// `update_credential_display_name`, `should_reenroll`, `save_site_credential`, `delete_site_credential`,
+    /// `remove_site_credential`, `migrate_credentials_from_frontend`, `purge_orphaned_credentials`, `emergency_wipe`,
+    /// `export_credentials`, `import_credentials`, `request_sync_offer`, `generate_sync_offer`, `accept_sync_offer`,
+    /// `set_rp_id`, `configure_challenge`, `rotate_challenge_seed`, `logout_site`, `logout_all_sites`,
+    /// `set_site_session_ttl`, `set_grace_period`, `configure_app_lock`, `record_activity`, `clear_pin_cache`,
+    /// `cancel_credential_refresh`, `reset_webauthn_metrics` and `reset_onboarding`
+    Manage,
+}
+
+/// The tier granted to each window label
+#[derive(Debug, Clone, Default)]
+pub struct WebAuthnCapabilityFilter {
+    tiers: HashMap<String, CapabilityTier>,
+}
+
+impl WebAuthnCapabilityFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant the window labelled `label` the commands of `tier`
+    pub fn allow(&mut self, label: impl Into<String>, tier: CapabilityTier) {
+        self.tiers.insert(label.into(), tier);
+    }
+
+    /// Fail with `Unauthorized` unless the window may invoke commands of `required`
+    pub fn check(&self, label: &str, required: CapabilityTier) -> Result<(), WebAuthnError> {
+        if self.tiers.is_empty() {
+            return Ok(());
+        }
+        match self.tiers.get(label) {
+            Some(tier) if *tier >= required => Ok(()),
+            _ => {
+                log::warn!("Refused a {:?} WebAuthn command from window {:?}", required, label);
+                Err(WebAuthnError::Unauthorized)
+            }
+        }
+    }
+}
+
+/// Fail with `Unauthorized` unless the invoking window may use commands of `required`
+pub(crate) fn check_window<R: Runtime>(
+    window: &WebviewWindow<R>,
+    required: CapabilityTier,
+) -> Result<(), WebAuthnError> {
+    window.state::<WebAuthnState>().capabilities.check(window.label(), required)
+}
@@ -0,0 +1,103 @@
+// src-tauri/src/ceremony_watchdog.rs
+
+//! A last resort for native ceremonies whose callback never comes.
+//!
+//! The native ceremonies hand their outcome to the awaiting command through a
+//! `tokio::sync::oneshot` channel whose sender the platform callback holds. A
+//! bug in that callback, such as an `ASAuthorizationController` delegate that
+//! is never called, would leave the command awaiting forever, and a sheet that
+//! is up cannot always be cancelled. `watched_channel` therefore pairs the
+//! channel with a watchdog task: the callback reports through
+//! `CeremonyReply::send`, which raises a flag, and the watchdog checks the flag
+//! every 100ms. Once the timeout passes without it, the watchdog sends
+//! `Timeout` in the callback's place. A plain `tokio::time::timeout` would not
+//! do, as it gives up on the receiver while the callback may still hold the
+//! sender.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::webauthn::WebAuthnError;
+
+/// How often the watchdog checks whether the callback reported back
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+type ReplySender<T> = oneshot::Sender<Result<T, WebAuthnError>>;
+
+/// The sending half of a watched ceremony channel, for the platform callback
+pub struct CeremonyReply<T> {
+    /// Set once the callback has reported, so the watchdog can stand down
+    fired: Arc<AtomicBool>,
+    /// Taken by whichever of the callback and the watchdog reports first
+    sender: Arc<Mutex<Option<ReplySender<T>>>>,
+}
+
+impl<T> CeremonyReply<T> {
+    /// Report the ceremony's outcome, unless the watchdog already gave up on it
+    pub fn send(&self, result: Result<T, WebAuthnError>) {
+        self.fired.store(true, Ordering::Release);
+        if let Some(sender) = take_sender(&self.sender) {
+            // The receiver is gone if the command future was dropped; nothing to report to.
+            let _ = sender.send(result);
+        }
+    }
+
+    /// Whether the callback has reported
+    pub fn has_fired(&self) -> bool {
+        self.fired.load(Ordering::Acquire)
+    }
+}
+
+/// A oneshot channel whose receiver gets `Timeout` if the returned
+/// `CeremonyReply` has not been used within `timeout`
+///
+/// The watchdog also stands down once the receiver is dropped.
+pub fn watched_channel<T: Send + 'static>(
+    timeout: Duration,
+) -> (CeremonyReply<T>, oneshot::Receiver<Result<T, WebAuthnError>>) {
+    let (sender, receiver) = oneshot::channel();
+    let reply = CeremonyReply {
+        fired: Arc::new(AtomicBool::new(false)),
+        sender: Arc::new(Mutex::new(Some(sender))),
+    };
+    let fired = Arc::clone(&reply.fired);
+    let sender = Arc::clone(&reply.sender);
+    tauri::async_runtime::spawn(watch(fired, sender, timeout));
+    (reply, receiver)
+}
+
+async fn watch<T>(fired: Arc<AtomicBool>, sender: Arc<Mutex<Option<ReplySender<T>>>>, timeout: Duration) {
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(deadline);
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            _ = poll.tick() => {
+                let abandoned = sender
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .as_ref()
+                    .map_or(true, oneshot::Sender::is_closed);
+                if fired.load(Ordering::Acquire) || abandoned {
+                    return;
+                }
+            }
+        }
+    }
+
+    if let Some(sender) = take_sender(&sender) {
+        log::warn!(
+            "The platform did not report back within {} ms; giving up on the ceremony",
+            timeout.as_millis()
+        );
+        let _ = sender.send(Err(WebAuthnError::Timeout));
+    }
+}
+
+fn take_sender<T>(sender: &Mutex<Option<ReplySender<T>>>) -> Option<ReplySender<T>> {
+    sender.lock().unwrap_or_else(PoisonError::into_inner).take()
+}
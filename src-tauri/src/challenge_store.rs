@@ -0,0 +1,313 @@
+// src-tauri/src/challenge_store.rs
+
+//! Issued WebAuthn challenges and their expiry.
+//!
+//! Every authentication starts with `create_challenge`, which records the
+//! challenge with its issue time and, when given one, the site it is for. The
+//! ceremony must then present the same challenge within the TTL, for that site;
+//! each challenge is accepted at most once. Logging out of a site drops the
+//! challenges issued for it.
+//! Challenges that completed an authentication are also logged, so a response
+//! replayed with one of them is reported as `ReplayAttack` rather than as an
+//! expired challenge.
+//!
+//! Challenges are drawn from a ChaCha20 generator seeded from the OS when the
+//! plugin starts. `rotate_challenge_seed` reseeds it, for deployments that
+//! rotate secrets on a schedule, and drops the pending challenges drawn from
+//! the old seed.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use dashmap::DashMap;
+use rand::rngs::OsRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Manager, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::webauthn::{generate_challenge, validate_site_id, WebAuthnError};
+use crate::webauthn_state::WebAuthnState;
+
+/// How long an issued challenge stays valid
+pub const DEFAULT_CHALLENGE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How often expired challenges are swept from the store
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Shortest challenge the WebAuthn spec recommends
+pub const MIN_CHALLENGE_BYTES: usize = 16;
+
+/// Longest challenge accepted; anything beyond adds no security
+pub const MAX_CHALLENGE_BYTES: usize = 64;
+
+/// How challenges are generated
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ChallengeConfig {
+    #[serde(rename = "lengthBytes")]
+    pub length_bytes: usize,
+}
+
+impl Default for ChallengeConfig {
+    fn default() -> Self {
+        Self { length_bytes: 32 }
+    }
+}
+
+impl ChallengeConfig {
+    /// Check the length is within `[MIN_CHALLENGE_BYTES, MAX_CHALLENGE_BYTES]`
+    pub fn validate(&self) -> Result<(), WebAuthnError> {
+        if (MIN_CHALLENGE_BYTES..=MAX_CHALLENGE_BYTES).contains(&self.length_bytes) {
+            Ok(())
+        } else {
+            Err(WebAuthnError::InvalidChallengeLength(self.length_bytes))
+        }
+    }
+}
+
+/// The generator challenges are drawn from
+pub struct ChallengeRng {
+    rng: Mutex<ChaCha20Rng>,
+}
+
+impl Default for ChallengeRng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChallengeRng {
+    /// A generator seeded from the OS
+    pub fn new() -> Self {
+        Self {
+            rng: Mutex::new(ChaCha20Rng::from_rng(OsRng).expect("the OS has no entropy to seed challenges with")),
+        }
+    }
+
+    pub fn fill_bytes(&self, bytes: &mut [u8]) {
+        // A panic mid-draw leaves the generator usable
+        self.rng.lock().unwrap_or_else(PoisonError::into_inner).fill_bytes(bytes);
+    }
+
+    /// Replace the seed with a fresh one from the OS
+    pub fn reseed(&self) -> Result<(), WebAuthnError> {
+        let rng = ChaCha20Rng::from_rng(OsRng)
+            .map_err(|e| WebAuthnError::InternalError(format!("Failed to draw a challenge seed: {}", e)))?;
+        *self.rng.lock().unwrap_or_else(PoisonError::into_inner) = rng;
+        Ok(())
+    }
+}
+
+/// Most used challenges remembered; the oldest are forgotten first
+pub const USED_CHALLENGE_CAPACITY: usize = 1000;
+
+/// When a challenge was issued, and the site it may be redeemed for
+struct IssuedChallenge {
+    issued_at: Instant,
+    /// `None` for challenges any site may redeem
+    site_id: Option<String>,
+}
+
+/// Challenges handed out to the frontend, keyed by their base64url encoding
+pub struct ChallengeStore {
+    issued: DashMap<String, IssuedChallenge>,
+    ttl: Duration,
+    config: RwLock<ChallengeConfig>,
+    rng: ChallengeRng,
+}
+
+impl Default for ChallengeStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHALLENGE_TTL)
+    }
+}
+
+impl ChallengeStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            issued: DashMap::new(),
+            ttl,
+            config: RwLock::new(ChallengeConfig::default()),
+            rng: ChallengeRng::new(),
+        }
+    }
+
+    /// How long an issued challenge stays valid
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The configuration new challenges are generated with
+    pub fn config(&self) -> ChallengeConfig {
+        self.config.read().map(|config| *config).unwrap_or_default()
+    }
+
+    /// Replace the configuration after validating it
+    pub fn configure(&self, config: ChallengeConfig) -> Result<(), WebAuthnError> {
+        config.validate()?;
+        *self
+            .config
+            .write()
+            .map_err(|_| WebAuthnError::InternalError("Challenge config lock is poisoned".to_string()))? = config;
+        Ok(())
+    }
+
+    /// Generate a challenge with the current configuration, without tracking it
+    pub fn generate(&self) -> String {
+        generate_challenge(&self.rng, &self.config())
+    }
+
+    /// Generate a fresh challenge for `site_id`, or any site, and start its TTL
+    pub fn issue(&self, site_id: Option<&str>) -> String {
+        let challenge = self.generate();
+        self.issued.insert(
+            challenge.clone(),
+            IssuedChallenge {
+                issued_at: Instant::now(),
+                site_id: site_id.map(str::to_string),
+            },
+        );
+        challenge
+    }
+
+    /// Consume a challenge for `site_id`, failing with `Timeout` if it expired
+    /// or was never issued for that site
+    pub fn redeem(&self, challenge: &str, site_id: &str) -> Result<(), WebAuthnError> {
+        let redeemed = self.issued.remove_if(challenge, |_, issued| {
+            issued.site_id.as_deref().map_or(true, |issued_for| issued_for == site_id)
+        });
+        match redeemed {
+            Some((_, issued)) if issued.issued_at.elapsed() <= self.ttl => Ok(()),
+            _ => Err(WebAuthnError::Timeout),
+        }
+    }
+
+    /// Drop the pending challenges issued for `site_id`, returning how many
+    pub fn revoke(&self, site_id: &str) -> usize {
+        let pending = self.issued.len();
+        self.issued.retain(|_, issued| issued.site_id.as_deref() != Some(site_id));
+        pending - self.issued.len()
+    }
+
+    /// Drop the pending challenges issued for any particular site, returning how many
+    pub fn revoke_all_sites(&self) -> usize {
+        let pending = self.issued.len();
+        self.issued.retain(|_, issued| issued.site_id.is_none());
+        pending - self.issued.len()
+    }
+
+    /// Drop every pending challenge, returning how many
+    pub fn clear(&self) -> usize {
+        let pending = self.issued.len();
+        self.issued.clear();
+        pending
+    }
+
+    /// Reseed the generator and drop every pending challenge, returning how many
+    pub fn rotate_seed(&self) -> Result<usize, WebAuthnError> {
+        self.rng.reseed()?;
+        let pending = self.issued.len();
+        self.issued.clear();
+        Ok(pending)
+    }
+
+    /// Drop every challenge whose TTL has run out
+    pub fn evict_expired(&self) {
+        self.issued.retain(|_, issued| issued.issued_at.elapsed() <= self.ttl);
+    }
+}
+
+/// Challenges that completed an authentication, most recent last
+#[derive(Default)]
+pub struct UsedChallengeLog {
+    used: Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl UsedChallengeLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the challenge completed an authentication before
+    pub fn contains(&self, challenge: &str) -> bool {
+        self.used.lock().is_ok_and(|used| used.0.contains(challenge))
+    }
+
+    /// Log the challenge as used, forgetting the oldest one when the log is full
+    pub fn mark_used(&self, challenge: &str) {
+        let Ok(mut used) = self.used.lock() else {
+            return;
+        };
+        let (set, order) = &mut *used;
+        if !set.insert(challenge.to_string()) {
+            return;
+        }
+        order.push_back(challenge.to_string());
+        if order.len() > USED_CHALLENGE_CAPACITY {
+            if let Some(oldest) = order.pop_front() {
+                set.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Periodically evict expired challenges from the managed `WebAuthnState`
+pub fn spawn_eviction<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            app.state::<WebAuthnState>().challenges.evict_expired();
+        }
+    });
+}
+
+/// Issue a challenge for the next authentication ceremony
+///
+/// With a `site_id`, only an authentication for that site can redeem it, and
+/// `logout_site` drops it. Without one, any site's can, until its TTL runs out.
+#[command]
+pub async fn create_challenge<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    site_id: Option<String>,
+) -> Result<String, WebAuthnError> {
+    check_window(&window, CapabilityTier::Authenticate)?;
+    if let Some(site_id) = &site_id {
+        validate_site_id(site_id)?;
+    }
+    Ok(state.challenges.issue(site_id.as_deref()))
+}
+
+/// Change how challenges are generated, e.g. their length
+#[command]
+pub async fn configure_challenge<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    config: ChallengeConfig,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    state.challenges.configure(config)
+}
+
+/// Reseed the challenge generator from the OS, without restarting the app
+///
+/// Challenges issued before, and not yet used, can no longer be redeemed; the
+/// ceremonies they were meant for fail as expired and must be retried.
+#[command]
+pub async fn rotate_challenge_seed<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let dropped = state.challenges.rotate_seed()?;
+    log::info!(
+        "Rotated the challenge seed at {}, dropping {} pending challenges",
+        Utc::now().to_rfc3339(),
+        dropped
+    );
+    Ok(())
+}
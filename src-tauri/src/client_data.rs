@@ -0,0 +1,90 @@
+// src-tauri/src/client_data.rs
+
+//! The `clientDataJSON` the authenticator signs over, for platforms that take
+//! it from the caller.
+//!
+//! Windows Hello takes the client data itself and libfido2 takes its SHA-256,
+//! the `clientDataHash`; both are built from a `ClientDataJson`. The JSON
+//! follows the serialization of WebAuthn Level 2, 5.8.1.1: `type`,
+//! `challenge`, `origin` and `crossOrigin`, in that order and without
+//! whitespace, so a relying party can check it with the limited verification
+//! algorithm of 5.8.1.2 instead of a full JSON parser. Apple builds the client
+//! data inside AuthenticationServices, and Android inside Credential Manager.
+
+use sha2::{Digest, Sha256};
+
+/// `CollectedClientData` for one ceremony
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientDataJson {
+    ceremony: &'static str,
+    challenge: String,
+    origin: String,
+    cross_origin: bool,
+}
+
+impl ClientDataJson {
+    /// Client data for an assertion; `challenge` is base64url without padding
+    pub fn new_get(challenge: &str, origin: &str) -> Self {
+        Self::new("webauthn.get", challenge, origin)
+    }
+
+    /// Client data for a registration; `challenge` is base64url without padding
+    pub fn new_create(challenge: &str, origin: &str) -> Self {
+        Self::new("webauthn.create", challenge, origin)
+    }
+
+    fn new(ceremony: &'static str, challenge: &str, origin: &str) -> Self {
+        Self {
+            ceremony,
+            challenge: challenge.to_string(),
+            origin: origin.to_string(),
+            cross_origin: false,
+        }
+    }
+
+    /// Mark the ceremony as run from an origin other than its top-level one
+    pub fn cross_origin(mut self, cross_origin: bool) -> Self {
+        self.cross_origin = cross_origin;
+        self
+    }
+
+    /// The serialized client data, byte for byte as it is signed
+    pub fn to_json_bytes(&self) -> Vec<u8> {
+        let mut json = String::from("{\"type\":");
+        push_string(&mut json, self.ceremony);
+        json.push_str(",\"challenge\":");
+        push_string(&mut json, &self.challenge);
+        json.push_str(",\"origin\":");
+        push_string(&mut json, &self.origin);
+        json.push_str(",\"crossOrigin\":");
+        json.push_str(if self.cross_origin { "true" } else { "false" });
+        json.push('}');
+        json.into_bytes()
+    }
+
+    /// SHA-256 of `to_json_bytes`, the `clientDataHash` authenticators take
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::digest(self.to_json_bytes()).into()
+    }
+}
+
+/// The origin ceremonies run from, the app being bound to its RP ID
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+pub(crate) fn app_origin(rp_id: &str) -> String {
+    format!("https://{}", rp_id)
+}
+
+/// Append `value` as a JSON string the way `CCDToString` does: only `"`, `\`
+/// and control characters are escaped, the rest is copied as is
+fn push_string(json: &mut String, value: &str) {
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\u{0}'..='\u{1f}' => json.push_str(&format!("\\u{:04x}", u32::from(c))),
+            _ => json.push(c),
+        }
+    }
+    json.push('"');
+}
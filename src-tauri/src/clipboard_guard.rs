@@ -0,0 +1,83 @@
+// src-tauri/src/clipboard_guard.rs
+
+//! Keeping credential IDs off the system clipboard.
+//!
+//! A credential ID on the clipboard can be pasted somewhere it helps a phishing
+//! attempt. The app has no native clipboard access, so clipboard writes cannot
+//! be intercepted here; instead the frontend asks `check_clipboard_write`
+//! before copying text. A write is refused when it contains a credential ID the
+//! credential store holds, or a value registered as sensitive less than 30
+//! seconds ago, which covers a passkey registration still being saved. Refused
+//! writes emit `webauthn://clipboard-blocked`.
+
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use tauri::{command, Manager, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_store::CredentialStore;
+use crate::webauthn::WebAuthnError;
+use crate::webauthn_events::{Severity, WebAuthnEvent};
+use crate::webauthn_state::WebAuthnState;
+
+/// How long a registered value stays blocked
+const SENSITIVE_TTL: Duration = Duration::from_secs(30);
+
+/// Values recently registered as sensitive
+#[derive(Default)]
+pub struct ClipboardGuard {
+    /// When each value was registered
+    sensitive: DashMap<String, Instant>,
+}
+
+impl ClipboardGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Block clipboard writes containing `value` for the next 30 seconds
+    pub(crate) fn register_clipboard_sensitive(&self, value: String) {
+        self.sensitive.retain(|_, registered_at| registered_at.elapsed() < SENSITIVE_TTL);
+        if !value.is_empty() {
+            self.sensitive.insert(value, Instant::now());
+        }
+    }
+
+    /// Whether `text` contains a value registered less than 30 seconds ago
+    fn contains_sensitive(&self, text: &str) -> bool {
+        self.sensitive
+            .iter()
+            .any(|entry| entry.value().elapsed() < SENSITIVE_TTL && text.contains(entry.key().as_str()))
+    }
+}
+
+/// Check whether `text` may be written to the clipboard
+///
+/// Returns false, and emits `webauthn://clipboard-blocked`, when it contains a
+/// stored credential ID or a recently registered one.
+#[command]
+pub async fn check_clipboard_write<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    text: String,
+) -> Result<bool, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    let app = window.app_handle();
+    let blocked = state.clipboard_guard.contains_sensitive(&text)
+        || app.state::<CredentialStore>().load_all()?.iter().any(|(_, config)| {
+            config
+                .credentials
+                .iter()
+                .any(|entry| !entry.credential_id.is_empty() && text.contains(&entry.credential_id))
+        });
+    if blocked {
+        // The text itself is not logged, since it holds the credential ID
+        log::warn!("Blocked a clipboard write containing a passkey credential ID");
+        WebAuthnEvent::ClipboardBlocked {
+            severity: Severity::Warning,
+        }
+        .emit(app);
+    }
+    Ok(!blocked)
+}
@@ -0,0 +1,317 @@
+// src-tauri/src/credential_backup.rs
+
+//! Passphrase-protected backups of the credential store.
+//!
+//! A backup carries every `SiteAuthConfig` in the store so a user can move
+//! their site configuration to another device. The store key stays in this
+//! device's keychain, so the backup is sealed with AES-256-GCM under a key
+//! derived from the user's passphrase with Argon2id instead.
+//!
+//! The archive is a JSON envelope `{ "version", "salt", "nonce", "ciphertext" }`.
+//! The Argon2 parameters are fixed per version: bump `BACKUP_VERSION` when they
+//! change, and keep deriving older archives' keys with the parameters they used.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tauri::{command, Runtime, State, WebviewWindow};
+use tauri_plugin_fs::{FilePath, FsExt, OpenOptions};
+
+use crate::audit_log::{self, AuditEvent};
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_store::CredentialStore;
+use crate::webauthn::{credential_import_allowed, validate_site_id, SiteAuthConfig, WebAuthnError};
+use crate::webauthn_state::WebAuthnState;
+
+/// Current version of the backup archive format
+const BACKUP_VERSION: u32 = 1;
+
+const SALT_BYTES: usize = 16;
+const NONCE_BYTES: usize = 12;
+
+/// Argon2id cost: 64 MiB of memory, 3 passes, one lane
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 1;
+
+/// On-disk envelope around the encrypted backup
+#[derive(Serialize, Deserialize)]
+struct BackupFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Decrypted backup contents
+#[derive(Serialize, Deserialize)]
+struct BackupContents {
+    #[serde(rename = "exportedAt")]
+    exported_at: DateTime<Utc>,
+    sites: HashMap<String, SiteAuthConfig>,
+}
+
+/// Summary of a written backup
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct ExportResult {
+    #[serde(rename = "siteCount")]
+    pub site_count: usize,
+    #[serde(rename = "credentialCount")]
+    pub credential_count: usize,
+}
+
+/// Summary of a backup merged into the store
+#[derive(Debug, Default, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct ImportResult {
+    /// Credentials added to the store
+    #[serde(rename = "importedCount")]
+    pub imported_count: usize,
+    /// Credentials already in the store, left untouched
+    #[serde(rename = "skippedCount")]
+    pub skipped_count: usize,
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), WebAuthnError> {
+    if passphrase.is_empty() {
+        return Err(WebAuthnError::InvalidInput("Backup passphrase must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+/// Derive the archive key from the passphrase
+///
+/// Deliberately slow and memory-hard; run it off the async runtime.
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, WebAuthnError> {
+    let params = Params::new(KDF_MEMORY_KIB, KDF_ITERATIONS, KDF_PARALLELISM, Some(32))
+        .map_err(|e| WebAuthnError::InternalError(format!("Invalid backup key parameters: {}", e)))?;
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| WebAuthnError::InternalError(format!("Failed to derive backup key: {}", e)))?;
+    Ok(key)
+}
+
+fn seal(passphrase: &str, contents: &BackupContents) -> Result<BackupFile, WebAuthnError> {
+    let mut salt = [0u8; SALT_BYTES];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let plaintext = serde_json::to_vec(contents).map_err(|e| WebAuthnError::InternalError(e.to_string()))?;
+    let ciphertext = Aes256Gcm::new(&key)
+        .encrypt(&nonce, plaintext.as_ref())
+        .map_err(|_| WebAuthnError::InternalError("Failed to encrypt credential backup".to_string()))?;
+
+    Ok(BackupFile {
+        version: BACKUP_VERSION,
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+fn open(passphrase: &str, file: &BackupFile) -> Result<BackupContents, WebAuthnError> {
+    if file.version > BACKUP_VERSION {
+        return Err(WebAuthnError::InvalidInput(format!(
+            "Backup version {} is newer than this app supports",
+            file.version
+        )));
+    }
+
+    let malformed = |_| WebAuthnError::InvalidInput("Credential backup is malformed".to_string());
+    let salt = STANDARD.decode(&file.salt).map_err(malformed)?;
+    let nonce = STANDARD.decode(&file.nonce).map_err(malformed)?;
+    let ciphertext = STANDARD.decode(&file.ciphertext).map_err(malformed)?;
+    if salt.len() != SALT_BYTES || nonce.len() != NONCE_BYTES {
+        return Err(WebAuthnError::InvalidInput("Credential backup is malformed".to_string()));
+    }
+
+    // GCM cannot tell a wrong passphrase from a tampered archive
+    let key = derive_key(passphrase, &salt)?;
+    let plaintext = Aes256Gcm::new(&key)
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| {
+            WebAuthnError::InvalidInput("Wrong passphrase, or the credential backup is damaged".to_string())
+        })?;
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| WebAuthnError::InvalidInput(format!("Credential backup is malformed: {}", e)))
+}
+
+/// Merge a backup's sites into the store's, skipping credentials the store
+/// already holds
+///
+/// A site already in the store keeps its own user handle and settings; only
+/// the missing credentials are added to it.
+pub(crate) fn merge(
+    sites: &mut HashMap<String, SiteAuthConfig>,
+    imported: HashMap<String, SiteAuthConfig>,
+) -> ImportResult {
+    let mut result = ImportResult::default();
+    for (site_id, imported) in imported {
+        let config = sites.entry(site_id).or_insert_with(|| SiteAuthConfig {
+            credentials: Vec::new(),
+            requires_auth: imported.requires_auth,
+            user_display_name: imported.user_display_name.clone(),
+            user_id: imported.user_id.clone(),
+            site_name: imported.site_name.clone(),
+        });
+        if config.user_id != imported.user_id {
+            log::warn!("Imported credentials use a different user handle than the stored site");
+        }
+
+        for entry in imported.credentials {
+            if config
+                .credentials
+                .iter()
+                .any(|stored| stored.credential_id == entry.credential_id)
+            {
+                result.skipped_count += 1;
+            } else {
+                config.credentials.push(entry);
+                result.imported_count += 1;
+            }
+        }
+    }
+    result
+}
+
+/// Run the key derivation and its cipher work on the blocking pool
+async fn run_blocking<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, WebAuthnError> + Send + 'static,
+) -> Result<T, WebAuthnError> {
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| WebAuthnError::InternalError(e.to_string()))?
+}
+
+/// Paths from the dialog plugin are plain paths on desktop and `content://`
+/// URLs on Android
+fn file_path(path: &str) -> FilePath {
+    match path.parse() {
+        Ok(path) => path,
+        Err(never) => match never {},
+    }
+}
+
+/// Write every stored site configuration to a passphrase-protected backup
+#[command]
+pub async fn export_credentials<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    output_path: String,
+    passphrase: String,
+) -> Result<ExportResult, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let result = export(&window, &store, output_path, passphrase).await;
+    state.audit_log.record(
+        &store,
+        AuditEvent::CredentialExport {
+            outcome: audit_log::outcome(&result),
+        },
+    );
+    result
+}
+
+async fn export<R: Runtime>(
+    window: &WebviewWindow<R>,
+    store: &CredentialStore,
+    output_path: String,
+    passphrase: String,
+) -> Result<ExportResult, WebAuthnError> {
+    check_passphrase(&passphrase)?;
+
+    let sites = store.load_all()?;
+    let result = ExportResult {
+        site_count: sites.len(),
+        credential_count: sites.values().map(|config| config.credentials.len()).sum(),
+    };
+    let contents = BackupContents {
+        exported_at: Utc::now(),
+        sites,
+    };
+    let file = run_blocking(move || seal(&passphrase, &contents)).await?;
+    let bytes = serde_json::to_vec(&file).map_err(|e| WebAuthnError::InternalError(e.to_string()))?;
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    window
+        .fs()
+        .open(file_path(&output_path), options)
+        .and_then(|mut output| output.write_all(&bytes))
+        .map_err(|e| WebAuthnError::InternalError(format!("Failed to write credential backup: {}", e)))?;
+
+    log::info!(
+        "Exported {} credentials for {} sites",
+        result.credential_count,
+        result.site_count
+    );
+    Ok(result)
+}
+
+/// Merge a passphrase-protected backup into the credential store
+///
+/// Credentials whose ID the store already holds for the same site are skipped.
+/// Only builds that allow credential imports, as for `accept_sync_offer`, can
+/// import, and a backup naming a malformed site ID is refused whole with
+/// `InvalidInput`.
+#[command]
+pub async fn import_credentials<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    input_path: String,
+    passphrase: String,
+) -> Result<ImportResult, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let result = import(&window, &store, input_path, passphrase).await;
+    state.audit_log.record(
+        &store,
+        AuditEvent::CredentialImport {
+            outcome: audit_log::outcome(&result),
+        },
+    );
+    result
+}
+
+async fn import<R: Runtime>(
+    window: &WebviewWindow<R>,
+    store: &CredentialStore,
+    input_path: String,
+    passphrase: String,
+) -> Result<ImportResult, WebAuthnError> {
+    if !credential_import_allowed() {
+        return Err(WebAuthnError::InvalidInput(
+            "Importing credentials is not enabled in this build".to_string(),
+        ));
+    }
+    check_passphrase(&passphrase)?;
+
+    let bytes = window
+        .fs()
+        .read(file_path(&input_path))
+        .map_err(|e| WebAuthnError::InvalidInput(format!("Failed to read credential backup: {}", e)))?;
+    let file: BackupFile = serde_json::from_slice(&bytes)
+        .map_err(|_| WebAuthnError::InvalidInput("Not a Sparktype credential backup".to_string()))?;
+    let contents = run_blocking(move || open(&passphrase, &file)).await?;
+    for site_id in contents.sites.keys() {
+        validate_site_id(site_id)?;
+    }
+
+    let result = store.modify_all(|sites| merge(sites, contents.sites))?;
+    log::info!(
+        "Imported {} credentials, skipped {} already stored",
+        result.imported_count,
+        result.skipped_count
+    );
+    Ok(result)
+}
@@ -0,0 +1,74 @@
+// src-tauri/src/credential_id.rs
+
+//! Credential IDs, checked against the limits of the WebAuthn spec.
+//!
+//! The store and the frontend keep credential IDs as base64url strings, which
+//! `CredentialId` decodes and checks: the spec has authenticators produce at
+//! least 16 bytes of entropy and caps IDs at 1023 bytes. The length also hints
+//! at the kind of authenticator that made the ID. Platform authenticators
+//! (Touch ID, Face ID, Windows Hello) produce 32 to 96 bytes and CTAP2 security
+//! keys such as YubiKeys 64, so `authenticator_type` can only guess; the
+//! attachment an authenticator reports during a ceremony is the reliable source.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+use crate::webauthn::WebAuthnError;
+
+/// Shortest credential ID the spec allows
+pub const MIN_CREDENTIAL_ID_BYTES: usize = 16;
+
+/// Longest credential ID the spec allows
+pub const MAX_CREDENTIAL_ID_BYTES: usize = 1023;
+
+/// The kind of authenticator a credential ID looks like it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthenticatorType {
+    /// Touch ID, Face ID, Windows Hello and the like
+    Platform,
+    /// A CTAP2 security key
+    SecurityKey,
+    /// The length fits neither
+    Unknown,
+}
+
+/// A decoded credential ID of a length the spec allows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialId(Vec<u8>);
+
+impl CredentialId {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Guess the authenticator from the ID's length
+    ///
+    /// 64 bytes is what CTAP2 security keys produce and is taken for one, although
+    /// platform authenticators can produce IDs of that length too.
+    pub fn authenticator_type(&self) -> AuthenticatorType {
+        match self.0.len() {
+            64 => AuthenticatorType::SecurityKey,
+            32..=96 => AuthenticatorType::Platform,
+            _ => AuthenticatorType::Unknown,
+        }
+    }
+}
+
+impl TryFrom<&str> for CredentialId {
+    type Error = WebAuthnError;
+
+    /// Decode a base64url ID, refusing one shorter than 16 bytes or longer than 1023
+    fn try_from(encoded: &str) -> Result<Self, Self::Error> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|_| WebAuthnError::InvalidInput(format!("credentialId is not base64url: {}", encoded)))?;
+        if !(MIN_CREDENTIAL_ID_BYTES..=MAX_CREDENTIAL_ID_BYTES).contains(&bytes.len()) {
+            return Err(WebAuthnError::InvalidInput(format!(
+                "credentialId must be between {} and {} bytes, got {}",
+                MIN_CREDENTIAL_ID_BYTES,
+                MAX_CREDENTIAL_ID_BYTES,
+                bytes.len()
+            )));
+        }
+        Ok(Self(bytes))
+    }
+}
@@ -0,0 +1,218 @@
+// src-tauri/src/credential_migration.rs
+
+//! One-off migration of passkeys the frontend kept before the native store.
+//!
+//! Sites registered before the credential store existed kept their
+//! `SiteAuthConfig` in the webview's `localStorage`. The frontend hands those
+//! over once, each tagged with its site ID. Every credential is checked on its
+//! own, so a damaged entry is reported without holding back the others, and a
+//! credential the store already holds is left as it is.
+
+use std::collections::{HashMap, HashSet};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+use tauri::{command, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_store::CredentialStore;
+use crate::webauthn::{timestamp, validate_site_id, CredentialEntry, SiteAuthConfig, WebAuthnError};
+
+/// A site's configuration as the frontend stored it
+///
+/// The fields of a `SiteAuthConfig`, plus the site they belong to. Credentials
+/// are read loosely so that validation can report each one instead of failing
+/// the whole migration.
+#[derive(Debug, Deserialize)]
+pub struct FrontendSiteConfig {
+    #[serde(rename = "siteId")]
+    site_id: String,
+    #[serde(default)]
+    credentials: Vec<FrontendCredential>,
+    #[serde(rename = "requiresAuth")]
+    requires_auth: bool,
+    #[serde(rename = "userDisplayName", default)]
+    user_display_name: Option<String>,
+    #[serde(rename = "userId", default)]
+    user_id: String,
+    // The single-credential shape, as `SiteAuthConfig` also still reads it
+    #[serde(rename = "credentialId", default)]
+    credential_id: Option<String>,
+    #[serde(rename = "publicKey", default)]
+    public_key: Option<String>,
+    #[serde(rename = "registeredAt", default)]
+    registered_at: Option<serde_json::Value>,
+}
+
+/// A stored credential, unvalidated
+#[derive(Debug, Deserialize)]
+struct FrontendCredential {
+    #[serde(rename = "credentialId", default)]
+    credential_id: String,
+    #[serde(rename = "publicKey", default)]
+    public_key: String,
+    #[serde(rename = "registeredAt", default)]
+    registered_at: Option<serde_json::Value>,
+    #[serde(rename = "deviceName", default)]
+    device_name: Option<String>,
+    #[serde(rename = "signCount", default)]
+    sign_count: u32,
+}
+
+/// Outcome of `migrate_credentials_from_frontend`
+#[derive(Debug, Default, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct MigrationReport {
+    /// Credentials added to the store
+    pub migrated: usize,
+    /// Credentials the store already held, left untouched
+    pub skipped: usize,
+    /// Credentials that were not migrated because they are malformed
+    pub errors: Vec<MigrationError>,
+}
+
+/// A credential, or a whole site, that could not be migrated
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct MigrationError {
+    #[serde(rename = "siteId")]
+    pub site_id: String,
+    /// `None` when the site itself was rejected
+    #[serde(rename = "credentialId")]
+    pub credential_id: Option<String>,
+    pub reason: String,
+}
+
+impl FrontendCredential {
+    /// Check the credential is usable, as a store entry
+    ///
+    /// Usage is recorded afresh once the credential authenticates natively.
+    fn validate(self) -> Result<CredentialEntry, String> {
+        if self.credential_id.trim().is_empty() {
+            return Err("credentialId must not be empty".to_string());
+        }
+        // Authentication decodes it to offer the credential to the authenticator
+        if URL_SAFE_NO_PAD.decode(&self.credential_id).is_err() {
+            return Err("credentialId is not base64url".to_string());
+        }
+        if self.public_key.trim().is_empty() {
+            return Err("publicKey must not be empty".to_string());
+        }
+        let registered_at = match &self.registered_at {
+            Some(serde_json::Value::String(text)) if !text.trim().is_empty() => timestamp::parse(text)?,
+            Some(serde_json::Value::Number(seconds)) => timestamp::parse(&seconds.to_string())?,
+            Some(serde_json::Value::String(_)) | None => return Err("registeredAt is missing".to_string()),
+            Some(other) => return Err(format!("Invalid timestamp: {}", other)),
+        };
+
+        Ok(CredentialEntry {
+            credential_id: self.credential_id,
+            public_key: self.public_key,
+            registered_at,
+            device_name: self.device_name,
+            last_used_at: None,
+            sign_count: self.sign_count,
+            failure_count: 0,
+            locked: false,
+        })
+    }
+}
+
+/// Add the frontend's credentials to the store's sites
+///
+/// A credential is skipped when any site in the store already holds its ID. A
+/// site already in the store keeps its own user handle and settings.
+fn migrate(sites: &mut HashMap<String, SiteAuthConfig>, configs: Vec<FrontendSiteConfig>) -> MigrationReport {
+    let mut report = MigrationReport::default();
+    let mut stored: HashSet<String> = sites
+        .values()
+        .flat_map(|config| config.credentials.iter().map(|entry| entry.credential_id.clone()))
+        .collect();
+
+    for config in configs {
+        if let Err(error) = validate_site_id(&config.site_id) {
+            report.errors.push(MigrationError {
+                site_id: config.site_id,
+                credential_id: None,
+                reason: error.to_string(),
+            });
+            continue;
+        }
+
+        let mut credentials = config.credentials;
+        if let Some(credential_id) = config.credential_id {
+            if !credentials.iter().any(|credential| credential.credential_id == credential_id) {
+                credentials.push(FrontendCredential {
+                    credential_id,
+                    public_key: config.public_key.unwrap_or_default(),
+                    registered_at: config.registered_at,
+                    device_name: None,
+                    sign_count: 0,
+                });
+            }
+        }
+
+        for credential in credentials {
+            let credential_id = credential.credential_id.clone();
+            let entry = match credential.validate() {
+                Ok(entry) => entry,
+                Err(reason) => {
+                    report.errors.push(MigrationError {
+                        site_id: config.site_id.clone(),
+                        credential_id: Some(credential_id).filter(|id| !id.is_empty()),
+                        reason,
+                    });
+                    continue;
+                }
+            };
+            if !stored.insert(entry.credential_id.clone()) {
+                report.skipped += 1;
+                continue;
+            }
+
+            sites
+                .entry(config.site_id.clone())
+                .or_insert_with(|| SiteAuthConfig {
+                    credentials: Vec::new(),
+                    requires_auth: config.requires_auth,
+                    user_display_name: config.user_display_name.clone(),
+                    user_id: config.user_id.clone(),
+                    site_name: None,
+                })
+                .credentials
+                .push(entry);
+            report.migrated += 1;
+        }
+    }
+    report
+}
+
+/// Move site configurations the frontend kept into the credential store
+///
+/// Credentials whose ID the store already holds are skipped rather than
+/// overwritten, so running the migration again is harmless. Malformed
+/// credentials are listed in the report's `errors` and left out, as are whole
+/// sites whose ID fails `validate_site_id`.
+#[command]
+pub async fn migrate_credentials_from_frontend<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    configs: Vec<FrontendSiteConfig>,
+) -> Result<MigrationReport, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let report = store.modify_all(|sites| migrate(sites, configs))?;
+    log::info!(
+        "Migrated {} credentials from the frontend, skipped {} already stored, rejected {}",
+        report.migrated,
+        report.skipped,
+        report.errors.len()
+    );
+    for error in &report.errors {
+        log::warn!(
+            "Could not migrate a credential for site {}: {}",
+            error.site_id,
+            error.reason
+        );
+    }
+    Ok(report)
+}
@@ -0,0 +1,138 @@
+// src-tauri/src/credential_purge.rs
+
+//! Removal of the passkeys of sites the app no longer has.
+//!
+//! Deleting a site in the app leaves its passkeys in the credential store and
+//! on the platform authenticator. `purge_orphaned_credentials` takes the IDs of
+//! the sites the app still has and deletes every passkey of the other sites, as
+//! `delete_passkey` would. The IDs are kept in a file next to the credential
+//! store, and the background credential refresh purges against the last ones
+//! given. A site that got a passkey after those were recorded may be one the
+//! list could not know about, so its passkeys are left alone.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Manager, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_store::CredentialStore;
+use crate::log_event::Redacted;
+use crate::webauthn::{delete_credential, timestamp, WebAuthnError};
+
+const ACTIVE_SITES_FILE_NAME: &str = "active-sites.json";
+
+/// Contents of the active sites file
+#[derive(Serialize, Deserialize)]
+struct ActiveSites {
+    #[serde(rename = "siteIds")]
+    site_ids: HashSet<String>,
+    #[serde(rename = "recordedAt", with = "timestamp")]
+    recorded_at: DateTime<Utc>,
+}
+
+/// What a purge removed, from `purge_orphaned_credentials`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct PurgeReport {
+    /// Passkeys deleted
+    pub purged: usize,
+    /// Why each passkey that is still there could not be deleted
+    pub errors: Vec<String>,
+}
+
+/// Delete the passkeys of every site not in `active_site_ids`
+///
+/// Each passkey is deleted from the platform authenticator and the credential
+/// store; one the platform could not delete stays in the store and is reported
+/// in `errors`. The sites given are remembered for the background credential
+/// refresh, so call this on startup with all of the app's sites.
+#[command]
+pub async fn purge_orphaned_credentials<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    active_site_ids: Vec<String>,
+) -> Result<PurgeReport, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let active = ActiveSites {
+        site_ids: active_site_ids.into_iter().collect(),
+        recorded_at: Utc::now(),
+    };
+    if let Err(error) = write_active_sites(&active_sites_path(&store), &active) {
+        log::warn!("Failed to record the active sites: {}", error);
+    }
+    purge(window.app_handle(), &store, &active).await
+}
+
+/// Purge against the sites last given to `purge_orphaned_credentials`, if any
+pub(crate) async fn purge_recorded<R: Runtime>(app: &AppHandle<R>) {
+    let store = app.state::<CredentialStore>();
+    let Some(active) = read_active_sites(&active_sites_path(&store)) else {
+        log::debug!("No active sites recorded; skipping the purge of removed sites");
+        return;
+    };
+    if let Err(error) = purge(app, &store, &active).await {
+        log::warn!("Could not purge the passkeys of removed sites: {}", error);
+    }
+}
+
+async fn purge<R: Runtime>(
+    app: &AppHandle<R>,
+    store: &CredentialStore,
+    active: &ActiveSites,
+) -> Result<PurgeReport, WebAuthnError> {
+    let mut report = PurgeReport::default();
+    for (site_id, config) in store.load_all()? {
+        if active.site_ids.contains(&site_id) {
+            continue;
+        }
+        if config.credentials.iter().any(|entry| entry.registered_at > active.recorded_at) {
+            log::debug!("Keeping the passkeys of site {}, registered after the active sites were", site_id);
+            continue;
+        }
+        for entry in config.credentials {
+            match delete_credential(app, store, &site_id, entry.credential_id.clone()).await {
+                Ok(()) => report.purged += 1,
+                Err(error) => {
+                    // CredentialNotFound would repeat the ID in full
+                    let error = match error {
+                        WebAuthnError::CredentialNotFound(_) => error.code().to_string(),
+                        error => error.to_string(),
+                    };
+                    report.errors.push(format!(
+                        "Could not delete passkey {} of site {}: {}",
+                        Redacted(&entry.credential_id),
+                        site_id,
+                        error
+                    ));
+                }
+            }
+        }
+    }
+    log::info!(
+        "Purged {} passkeys of removed sites, {} could not be deleted",
+        report.purged,
+        report.errors.len()
+    );
+    Ok(report)
+}
+
+fn active_sites_path(store: &CredentialStore) -> PathBuf {
+    store.data_dir().join(ACTIVE_SITES_FILE_NAME)
+}
+
+/// The recorded active sites; `None` if none were or the file is unreadable
+fn read_active_sites(path: &Path) -> Option<ActiveSites> {
+    let contents = fs::read(path).ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+fn write_active_sites(path: &Path, active: &ActiveSites) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec(active)?)
+}
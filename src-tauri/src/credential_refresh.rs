@@ -0,0 +1,143 @@
+// src-tauri/src/credential_refresh.rs
+
+//! Background re-validation of stored passkeys.
+//!
+//! A major OS update can leave the platform authenticator without passkeys the
+//! credential store still lists. On startup every site is checked with
+//! `verify_stored_credential`, which never prompts, and
+//! `webauthn://credential-stale` is emitted for each site whose passkeys are
+//! gone, so the frontend can offer to register a new one. Before that, the
+//! passkeys of sites the app no longer has are purged (see `credential_purge`).
+//! The refresh runs at most once a day; the time it last completed is kept in a
+//! file next to the credential store.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::{command, AppHandle, Manager, Runtime, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_purge;
+use crate::credential_store::CredentialStore;
+use crate::webauthn::{stored_credential_status, timestamp, WebAuthnError};
+use crate::webauthn_events::WebAuthnEvent;
+
+/// Least time between two refreshes
+const REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const LAST_RUN_FILE_NAME: &str = "credential-refresh.json";
+
+/// Contents of the last-run file
+#[derive(Serialize, Deserialize)]
+struct LastRun {
+    #[serde(rename = "lastRunAt", with = "timestamp")]
+    last_run_at: DateTime<Utc>,
+}
+
+/// The refresh task started at launch, managed as Tauri state
+pub struct CredentialRefresh(Mutex<Option<JoinHandle<()>>>);
+
+/// Re-validate the stored passkeys in the background, unless that was done in
+/// the last 24 hours
+///
+/// Expects the `CredentialStore` and the `webauthn` plugin's state to be managed.
+pub fn spawn_credential_refresh_task<R: Runtime>(app_handle: AppHandle<R>) {
+    let app = app_handle.clone();
+    let task = tauri::async_runtime::spawn(async move { refresh(&app).await });
+    app_handle.manage(CredentialRefresh(Mutex::new(Some(task))));
+}
+
+async fn refresh<R: Runtime>(app: &AppHandle<R>) {
+    let store = app.state::<CredentialStore>();
+    let last_run_path = store.data_dir().join(LAST_RUN_FILE_NAME);
+    if let Some(last_run_at) = read_last_run(&last_run_path) {
+        // A last run in the future means the clock moved back; refresh anyway
+        let due = Utc::now()
+            .signed_duration_since(last_run_at)
+            .to_std()
+            .map_or(true, |elapsed| elapsed >= REFRESH_INTERVAL);
+        if !due {
+            log::debug!("Skipping the credential refresh; it last ran at {}", last_run_at);
+            return;
+        }
+    }
+
+    credential_purge::purge_recorded(app).await;
+    let sites = match store.load_all() {
+        Ok(sites) => sites,
+        Err(error) => {
+            log::warn!("Credential refresh could not read the credential store: {}", error);
+            return;
+        }
+    };
+
+    let mut checked = 0;
+    let mut stale = 0;
+    for (site_id, config) in sites {
+        if config.credentials.is_empty() {
+            continue;
+        }
+        checked += 1;
+        match stored_credential_status(app, &store, &site_id).await {
+            Ok(status) if !status.exists_locally => {
+                log::warn!("Passkeys for site {} are no longer on this device", site_id);
+                WebAuthnEvent::CredentialStale { site_id }.emit(app);
+                stale += 1;
+            }
+            Ok(_) => {}
+            Err(error) => log::warn!("Could not verify the passkeys of site {}: {}", site_id, error),
+        }
+    }
+    log::info!("Credential refresh checked {} sites, {} with stale passkeys", checked, stale);
+
+    if let Err(error) = write_last_run(&last_run_path, Utc::now()) {
+        log::warn!("Failed to record the credential refresh: {}", error);
+    }
+}
+
+/// When the refresh last completed for the store; `None` if it never did
+pub(crate) fn last_run(store: &CredentialStore) -> Option<DateTime<Utc>> {
+    read_last_run(&store.data_dir().join(LAST_RUN_FILE_NAME))
+}
+
+/// When the refresh last completed; `None` if it never did or the file is unreadable
+fn read_last_run(path: &Path) -> Option<DateTime<Utc>> {
+    let contents = fs::read(path).ok()?;
+    serde_json::from_slice::<LastRun>(&contents)
+        .map(|last_run| last_run.last_run_at)
+        .ok()
+}
+
+fn write_last_run(path: &Path, last_run_at: DateTime<Utc>) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec(&LastRun { last_run_at })?)
+}
+
+/// Stop the credential refresh, e.g. when the app shuts down
+///
+/// A refresh stopped midway is not recorded, so it runs again at the next
+/// launch. Does nothing when no refresh is running.
+#[command]
+pub async fn cancel_credential_refresh<R: Runtime>(window: WebviewWindow<R>) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let Some(refresh) = window.try_state::<CredentialRefresh>() else {
+        return Ok(());
+    };
+    let task = refresh
+        .0
+        .lock()
+        .map_err(|_| WebAuthnError::InternalError("Credential refresh lock is poisoned".to_string()))?
+        .take();
+    if let Some(task) = task {
+        task.abort();
+        log::info!("Cancelled the credential refresh");
+    }
+    Ok(())
+}
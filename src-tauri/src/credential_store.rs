@@ -0,0 +1,801 @@
+// src-tauri/src/credential_store.rs
+
+//! Encrypted on-disk storage for per-site passkey configuration.
+//!
+//! Every `SiteAuthConfig` lives in a single file under the app data directory,
+//! sealed with AES-256-GCM. The 256-bit key never touches the disk in the clear:
+//! it is held by the OS keychain (Keychain on macOS/iOS, the Secret Service via
+//! libsecret on Linux) or wrapped with DPAPI on Windows. Android keeps it in the
+//! app-private data directory, which only this app can read.
+//!
+//! The file is a JSON envelope `{ "version", "nonce", "ciphertext" }`. Bump
+//! `STORE_VERSION` and extend `migrate` when the decrypted schema changes, so
+//! existing stores are upgraded on first load instead of being discarded.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
+use ring::{hkdf, hmac};
+use serde::{Deserialize, Serialize, Serializer};
+use tauri::{command, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::webauthn::{timestamp, validate_site_id, CredentialEntry, SiteAuthConfig, WebAuthnError};
+use crate::webauthn_state::WebAuthnState;
+
+/// Current version of the decrypted store schema
+///
+/// 1. One credential per site
+/// 2. A list of credentials per site
+/// 3. RFC 3339 credential timestamps instead of epoch seconds
+const STORE_VERSION: u32 = 3;
+
+const STORE_FILE_NAME: &str = "site-credentials.json";
+
+/// Keychain service / account under which the store key is kept
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "linux"))]
+const KEYCHAIN_SERVICE: &str = "org.sparktype.sparktype";
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "linux"))]
+const KEYCHAIN_ACCOUNT: &str = "credential-store-key";
+
+/// Errors raised while reading or writing the credential store
+#[derive(Debug)]
+pub enum StoreError {
+    /// The store file or its directory could not be read or written
+    Io(std::io::Error),
+    /// The store contents could not be (de)serialized
+    Format(String),
+    /// The store could not be decrypted, e.g. because the key changed
+    Decryption,
+    /// The OS keychain refused to provide the store key
+    Keychain(String),
+    /// The store was written by a newer version of the app
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(error) => write!(f, "Credential store I/O error: {}", error),
+            StoreError::Format(error) => write!(f, "Credential store is malformed: {}", error),
+            StoreError::Decryption => write!(f, "Credential store could not be decrypted"),
+            StoreError::Keychain(error) => write!(f, "Credential store key is unavailable: {}", error),
+            StoreError::UnsupportedVersion(version) => {
+                write!(f, "Credential store version {} is newer than this app supports", version)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<std::io::Error> for StoreError {
+    fn from(error: std::io::Error) -> Self {
+        StoreError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(error: serde_json::Error) -> Self {
+        StoreError::Format(error.to_string())
+    }
+}
+
+impl From<StoreError> for WebAuthnError {
+    fn from(error: StoreError) -> Self {
+        WebAuthnError::InternalError(error.to_string())
+    }
+}
+
+// Commands surface the error to the frontend as its message
+impl Serialize for StoreError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// On-disk envelope around the encrypted store
+#[derive(Serialize, Deserialize)]
+struct StoreFile {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Decrypted store contents (schema version 3)
+#[derive(Default, Serialize, Deserialize)]
+struct StoreContents {
+    sites: HashMap<String, SiteAuthConfig>,
+}
+
+/// Encrypted credential store, managed as Tauri state
+pub struct CredentialStore {
+    data_dir: PathBuf,
+    // Serializes read-modify-write cycles and caches the key once fetched
+    key: Mutex<Option<Key<Aes256Gcm>>>,
+}
+
+impl CredentialStore {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            data_dir,
+            key: Mutex::new(None),
+        }
+    }
+
+    /// A store sealed with the given key instead of one from the OS keychain,
+    /// for tests running where no keychain is reachable
+    pub fn with_key(data_dir: PathBuf, key: [u8; 32]) -> Self {
+        Self {
+            data_dir,
+            key: Mutex::new(Some(key.into())),
+        }
+    }
+
+    /// Persist the configuration for a site, replacing any previous one
+    pub fn save_credential(&self, site_id: &str, config: &SiteAuthConfig) -> Result<(), StoreError> {
+        self.modify_credential(site_id, |stored| *stored = Some(config.clone()))
+    }
+
+    /// Load the configuration for a site, if one was saved
+    pub fn load_credential(&self, site_id: &str) -> Result<Option<SiteAuthConfig>, StoreError> {
+        let mut key = self.lock_key()?;
+        let key = self.key(&mut key)?;
+        Ok(self.read(&key)?.sites.remove(site_id))
+    }
+
+    /// Load the configuration of every site in the store
+    pub fn load_all(&self) -> Result<HashMap<String, SiteAuthConfig>, StoreError> {
+        let mut key = self.lock_key()?;
+        let key = self.key(&mut key)?;
+        Ok(self.read(&key)?.sites)
+    }
+
+    /// Forget the configuration for a site. Deleting an unknown site is not an error.
+    pub fn delete_credential(&self, site_id: &str) -> Result<(), StoreError> {
+        self.modify_credential(site_id, |stored| *stored = None)
+    }
+
+    /// Read-modify-write a site's configuration under the store lock
+    ///
+    /// `change` sees `None` for unknown sites; leaving `None`, or a config that
+    /// requires auth but has no credentials, behind removes the site from the
+    /// store. Public sites are kept without credentials.
+    pub fn modify_credential<T>(
+        &self,
+        site_id: &str,
+        change: impl FnOnce(&mut Option<SiteAuthConfig>) -> T,
+    ) -> Result<T, StoreError> {
+        let mut key = self.lock_key()?;
+        let key = self.key(&mut key)?;
+        let mut contents = self.read(&key)?;
+
+        let mut config = contents.sites.remove(site_id);
+        let result = change(&mut config);
+        if let Some(config) = config.filter(is_worth_keeping) {
+            contents.sites.insert(site_id.to_string(), config);
+        }
+
+        self.write(&key, &contents)?;
+        Ok(result)
+    }
+
+    /// Read-modify-write every site's configuration under the store lock
+    ///
+    /// Sites left requiring auth with no credentials are removed from the store.
+    pub fn modify_all<T>(
+        &self,
+        change: impl FnOnce(&mut HashMap<String, SiteAuthConfig>) -> T,
+    ) -> Result<T, StoreError> {
+        let mut key = self.lock_key()?;
+        let key = self.key(&mut key)?;
+        let mut contents = self.read(&key)?;
+
+        let result = change(&mut contents.sites);
+        contents.sites.retain(|_, config| is_worth_keeping(config));
+
+        self.write(&key, &contents)?;
+        Ok(result)
+    }
+
+    /// Record that a credential was just used to authenticate
+    ///
+    /// The stored signature counter only ever moves forward, and the failure
+    /// count starts over. Locked credentials are left alone; only
+    /// `unlock_credential` clears their failures. Returns the counter stored
+    /// before this use, or `None` for an unknown or locked credential.
+    pub fn touch_credential(
+        &self,
+        site_id: &str,
+        credential_id: &str,
+        used_at: DateTime<Utc>,
+        sign_count: u32,
+    ) -> Result<Option<u32>, StoreError> {
+        self.modify_credential(site_id, |config| {
+            let entry = config
+                .iter_mut()
+                .flat_map(|config| config.credentials.iter_mut())
+                .find(|entry| entry.credential_id == credential_id && !entry.locked)?;
+            let stored_count = entry.sign_count;
+            entry.last_used_at = Some(used_at);
+            entry.sign_count = entry.sign_count.max(sign_count);
+            entry.failure_count = 0;
+            Some(stored_count)
+        })
+    }
+
+    /// Count a failed authentication against each of a site's `credential_ids`,
+    /// locking those that reach `threshold` failures
+    ///
+    /// Returns the IDs this failure locked. Unknown IDs are skipped.
+    pub fn record_failure(
+        &self,
+        site_id: &str,
+        credential_ids: &[String],
+        threshold: u32,
+    ) -> Result<Vec<String>, StoreError> {
+        self.modify_credential(site_id, |config| {
+            let mut locked = Vec::new();
+            let entries = config.iter_mut().flat_map(|config| config.credentials.iter_mut());
+            for entry in entries.filter(|entry| credential_ids.contains(&entry.credential_id)) {
+                entry.failure_count = entry.failure_count.saturating_add(1);
+                if !entry.locked && entry.failure_count >= threshold {
+                    entry.locked = true;
+                    locked.push(entry.credential_id.clone());
+                }
+            }
+            locked
+        })
+    }
+
+    /// Unlock a credential and clear its failure count
+    ///
+    /// Returns false for an unknown credential.
+    pub fn unlock_credential(&self, site_id: &str, credential_id: &str) -> Result<bool, StoreError> {
+        self.modify_credential(site_id, |config| {
+            let Some(entry) = config
+                .iter_mut()
+                .flat_map(|config| config.credentials.iter_mut())
+                .find(|entry| entry.credential_id == credential_id)
+            else {
+                return false;
+            };
+            entry.failure_count = 0;
+            entry.locked = false;
+            true
+        })
+    }
+
+    /// Name the user of the site that holds `credential_id` `display_name`
+    ///
+    /// Returns false when the site has no such credential.
+    pub fn set_user_display_name(
+        &self,
+        site_id: &str,
+        credential_id: &str,
+        display_name: &str,
+    ) -> Result<bool, StoreError> {
+        self.modify_credential(site_id, |config| {
+            let Some(config) = config
+                .as_mut()
+                .filter(|config| config.credentials.iter().any(|entry| entry.credential_id == credential_id))
+            else {
+                return false;
+            };
+            config.user_display_name = Some(display_name.to_string());
+            true
+        })
+    }
+
+    fn lock_key(&self) -> Result<std::sync::MutexGuard<'_, Option<Key<Aes256Gcm>>>, StoreError> {
+        self.key
+            .lock()
+            .map_err(|_| StoreError::Format("Credential store lock is poisoned".to_string()))
+    }
+
+    fn key(&self, cached: &mut Option<Key<Aes256Gcm>>) -> Result<Key<Aes256Gcm>, StoreError> {
+        if let Some(key) = cached {
+            return Ok(*key);
+        }
+        let key = keychain::load_or_create_key(&self.data_dir)?;
+        *cached = Some(key);
+        Ok(key)
+    }
+
+    /// An HMAC-SHA256 key derived from the store key with HKDF, for the
+    /// purpose `info` names, so it is kept as safe as the store key without
+    /// a keychain entry of its own
+    pub(crate) fn derive_hmac_key(&self, info: &[u8]) -> Result<hmac::Key, StoreError> {
+        let mut key = self.lock_key()?;
+        let key = self.key(&mut key)?;
+        let info = [info];
+        hkdf::Salt::new(hkdf::HKDF_SHA256, &[])
+            .extract(key.as_slice())
+            .expand(&info, hmac::HMAC_SHA256)
+            .map(hmac::Key::from)
+            .map_err(|_| StoreError::Format("Failed to derive a key from the store key".to_string()))
+    }
+
+    /// Remove the store file, and a temporary one left by an interrupted write,
+    /// returning the paths removed; the store reads as empty afterwards
+    pub fn wipe(&self) -> Result<Vec<PathBuf>, StoreError> {
+        let _key = self.lock_key()?;
+        let mut removed = Vec::new();
+        for path in [self.temp_path(), self.store_path()] {
+            match fs::remove_file(&path) {
+                Ok(()) => removed.push(path),
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Directory the store file lives in
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    fn store_path(&self) -> PathBuf {
+        self.data_dir.join(STORE_FILE_NAME)
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        self.store_path().with_extension("json.tmp")
+    }
+
+    fn read(&self, key: &Key<Aes256Gcm>) -> Result<StoreContents, StoreError> {
+        let path = self.store_path();
+        if !path.exists() {
+            return Ok(StoreContents::default());
+        }
+
+        let file: StoreFile = serde_json::from_slice(&fs::read(&path)?)?;
+        if file.version > STORE_VERSION {
+            return Err(StoreError::UnsupportedVersion(file.version));
+        }
+
+        let nonce = STANDARD
+            .decode(&file.nonce)
+            .map_err(|e| StoreError::Format(e.to_string()))?;
+        let ciphertext = STANDARD
+            .decode(&file.ciphertext)
+            .map_err(|e| StoreError::Format(e.to_string()))?;
+        if nonce.len() != 12 {
+            return Err(StoreError::Format("Invalid nonce length".to_string()));
+        }
+        let plaintext = Aes256Gcm::new(key)
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| StoreError::Decryption)?;
+
+        let contents = migrate(file.version, serde_json::from_slice(&plaintext)?)?;
+        if file.version < STORE_VERSION {
+            self.upgrade(key, file.version, &contents)?;
+        }
+        Ok(contents)
+    }
+
+    /// Rewrite a store read from an older schema version in the current one, so
+    /// the upgrade happens once instead of on every read
+    fn upgrade(&self, key: &Key<Aes256Gcm>, from_version: u32, contents: &StoreContents) -> Result<(), StoreError> {
+        self.write(key, contents)?;
+        log::info!(
+            "Upgraded credential store from version {} to {}",
+            from_version,
+            STORE_VERSION
+        );
+        Ok(())
+    }
+
+    fn write(&self, key: &Key<Aes256Gcm>, contents: &StoreContents) -> Result<(), StoreError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(contents)?;
+        let ciphertext = Aes256Gcm::new(key)
+            .encrypt(&nonce, plaintext.as_ref())
+            .map_err(|_| StoreError::Format("Failed to encrypt credential store".to_string()))?;
+
+        let file = StoreFile {
+            version: STORE_VERSION,
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+
+        // Write to a sibling file first, synced and read back before it replaces
+        // the store, so neither a crash nor a short write leaves a truncated store
+        fs::create_dir_all(&self.data_dir)?;
+        let path = self.store_path();
+        let temp_path = self.temp_path();
+        let bytes = serde_json::to_vec(&file)?;
+        let mut temp_file = fs::File::create(&temp_path)?;
+        temp_file.write_all(&bytes)?;
+        temp_file.sync_all()?;
+        drop(temp_file);
+        if fs::read(&temp_path)? != bytes {
+            let _ = fs::remove_file(&temp_path);
+            return Err(StoreError::Format("Credential store did not read back as written".to_string()));
+        }
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+}
+
+/// Whether a site's config stays stored: a public site needs no passkeys, a
+/// protected one is gone with its last
+fn is_worth_keeping(config: &SiteAuthConfig) -> bool {
+    !config.requires_auth || !config.credentials.is_empty()
+}
+
+/// Upgrade decrypted contents written by an older schema version
+fn migrate(version: u32, contents: serde_json::Value) -> Result<StoreContents, StoreError> {
+    match version {
+        // `SiteAuthConfig` still reads the single-credential shape of version 1,
+        // and credential timestamps still read the epoch seconds of versions 1 and 2
+        1..=3 => Ok(serde_json::from_value(contents)?),
+        other => Err(StoreError::UnsupportedVersion(other)),
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod keychain {
+    use super::*;
+    use security_framework::passwords::{get_generic_password, set_generic_password};
+
+    /// `errSecItemNotFound`
+    const ITEM_NOT_FOUND: i32 = -25300;
+
+    pub(super) fn load_or_create_key(_data_dir: &Path) -> Result<Key<Aes256Gcm>, StoreError> {
+        match get_generic_password(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT) {
+            Ok(secret) => key_from_bytes(&secret),
+            Err(error) if error.code() == ITEM_NOT_FOUND => {
+                let key = Aes256Gcm::generate_key(&mut OsRng);
+                set_generic_password(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT, &key)
+                    .map_err(|e| StoreError::Keychain(e.to_string()))?;
+                Ok(key)
+            }
+            Err(error) => Err(StoreError::Keychain(error.to_string())),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod keychain {
+    use super::*;
+
+    pub(super) fn load_or_create_key(_data_dir: &Path) -> Result<Key<Aes256Gcm>, StoreError> {
+        let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)
+            .map_err(|e| StoreError::Keychain(e.to_string()))?;
+        match entry.get_secret() {
+            Ok(secret) => key_from_bytes(&secret),
+            Err(keyring::Error::NoEntry) => {
+                let key = Aes256Gcm::generate_key(&mut OsRng);
+                entry
+                    .set_secret(&key)
+                    .map_err(|e| StoreError::Keychain(e.to_string()))?;
+                Ok(key)
+            }
+            Err(error) => Err(StoreError::Keychain(error.to_string())),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod keychain {
+    use super::*;
+    use windows::Win32::Foundation::{LocalFree, HLOCAL};
+    use windows::Win32::Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    /// File next to the store holding the DPAPI-wrapped key
+    const WRAPPED_KEY_FILE_NAME: &str = "site-credentials.key";
+
+    pub(super) fn load_or_create_key(data_dir: &Path) -> Result<Key<Aes256Gcm>, StoreError> {
+        let path = data_dir.join(WRAPPED_KEY_FILE_NAME);
+        if path.exists() {
+            return key_from_bytes(&unprotect(&fs::read(&path)?)?);
+        }
+
+        let key = Aes256Gcm::generate_key(&mut OsRng);
+        fs::create_dir_all(data_dir)?;
+        fs::write(&path, protect(&key)?)?;
+        Ok(key)
+    }
+
+    fn protect(data: &[u8]) -> Result<Vec<u8>, StoreError> {
+        let input = CRYPT_INTEGER_BLOB {
+            cbData: data.len() as u32,
+            pbData: data.as_ptr() as *mut u8,
+        };
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptProtectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| StoreError::Keychain(e.message()))?;
+            Ok(take_blob(output))
+        }
+    }
+
+    fn unprotect(data: &[u8]) -> Result<Vec<u8>, StoreError> {
+        let input = CRYPT_INTEGER_BLOB {
+            cbData: data.len() as u32,
+            pbData: data.as_ptr() as *mut u8,
+        };
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| StoreError::Keychain(e.message()))?;
+            Ok(take_blob(output))
+        }
+    }
+
+    /// Copy a DPAPI output blob and release its `LocalAlloc`ed buffer
+    unsafe fn take_blob(blob: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+        let bytes = std::slice::from_raw_parts(blob.pbData, blob.cbData as usize).to_vec();
+        LocalFree(Some(HLOCAL(blob.pbData.cast())));
+        bytes
+    }
+}
+
+// No system keychain is reachable without a Java bridge; the app data directory
+// is private to the app, so the key is kept there next to the store.
+#[cfg(not(any(target_os = "macos", target_os = "ios", target_os = "linux", target_os = "windows")))]
+mod keychain {
+    use super::*;
+
+    const KEY_FILE_NAME: &str = "site-credentials.key";
+
+    pub(super) fn load_or_create_key(data_dir: &Path) -> Result<Key<Aes256Gcm>, StoreError> {
+        let path = data_dir.join(KEY_FILE_NAME);
+        if path.exists() {
+            return key_from_bytes(&fs::read(&path)?);
+        }
+
+        let key = Aes256Gcm::generate_key(&mut OsRng);
+        fs::create_dir_all(data_dir)?;
+        fs::write(&path, key)?;
+        Ok(key)
+    }
+}
+
+fn key_from_bytes(bytes: &[u8]) -> Result<Key<Aes256Gcm>, StoreError> {
+    if bytes.len() != 32 {
+        return Err(StoreError::Keychain("Stored key has the wrong length".to_string()));
+    }
+    Ok(*Key::<Aes256Gcm>::from_slice(bytes))
+}
+
+/// Persist a site's passkey configuration in the encrypted store
+///
+/// Only what the frontend owns is taken from `auth_config`: the site's names,
+/// which of its passkeys to keep and their device names. Everything else about
+/// a kept passkey, such as its lock, failure count and signature counter, and
+/// the site's `requiresAuth` and `userId` stay as stored. Passkeys the store
+/// does not hold fail with `CredentialNotFound`; `register_passkey` adds them.
+#[command]
+pub async fn save_site_credential<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    mut auth_config: SiteAuthConfig,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    validate_site_id(&site_id)?;
+    store.modify_credential(&site_id, |stored| {
+        for entry in &mut auth_config.credentials {
+            let kept = stored
+                .as_ref()
+                .and_then(|stored| stored.find_credential(&entry.credential_id))
+                .ok_or_else(|| WebAuthnError::CredentialNotFound(entry.credential_id.clone()))?;
+            *entry = CredentialEntry {
+                device_name: entry.device_name.take(),
+                ..kept.clone()
+            };
+        }
+        if let Some(stored) = stored.as_ref() {
+            auth_config.requires_auth = stored.requires_auth;
+            auth_config.user_id.clone_from(&stored.user_id);
+        }
+        *stored = Some(auth_config);
+        Ok(())
+    })?
+}
+
+/// Load a site's passkey configuration from the encrypted store
+#[command]
+pub async fn load_site_credential<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+) -> Result<Option<SiteAuthConfig>, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    Ok(store.load_credential(&site_id)?)
+}
+
+/// Remove a site's passkey configuration from the encrypted store
+#[command]
+pub async fn delete_site_credential<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    Ok(store.delete_credential(&site_id)?)
+}
+
+/// List the passkeys registered for a site, one per device
+#[command]
+pub async fn list_site_credentials<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+) -> Result<Vec<CredentialEntry>, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    Ok(store
+        .load_credential(&site_id)?
+        .map(|config| config.credentials)
+        .unwrap_or_default())
+}
+
+/// A site with passkeys, from `list_protected_sites`
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SiteCredentialSummary {
+    pub site_id: String,
+    pub site_name: Option<String>,
+    pub credential_count: usize,
+    /// When any of the site's passkeys was last used
+    #[serde(with = "timestamp::optional")]
+    #[cfg_attr(feature = "schema-export", schemars(with = "Option<String>"))]
+    pub most_recent_use: Option<DateTime<Utc>>,
+    /// When the site's first passkey was registered
+    #[serde(with = "timestamp")]
+    #[cfg_attr(feature = "schema-export", schemars(with = "String"))]
+    pub registered_at: DateTime<Utc>,
+}
+
+/// Narrows down `list_protected_sites`; every condition given must hold
+#[derive(Debug, Default, Deserialize)]
+pub struct SiteFilter {
+    /// Case-insensitive text the site's ID or name must contain
+    #[serde(rename = "nameContains", default)]
+    pub name_contains: Option<String>,
+    /// The site's first passkey must have been registered after this time
+    #[serde(rename = "registeredAfter", with = "timestamp::optional", default)]
+    pub registered_after: Option<DateTime<Utc>>,
+}
+
+impl SiteFilter {
+    fn matches(&self, summary: &SiteCredentialSummary) -> bool {
+        let name_matches = self.name_contains.as_ref().map_or(true, |text| {
+            let text = text.to_lowercase();
+            summary.site_id.to_lowercase().contains(&text)
+                || summary
+                    .site_name
+                    .as_ref()
+                    .is_some_and(|name| name.to_lowercase().contains(&text))
+        });
+        name_matches
+            && self
+                .registered_after
+                .map_or(true, |after| summary.registered_at > after)
+    }
+}
+
+fn summarize(site_id: String, config: SiteAuthConfig) -> Option<SiteCredentialSummary> {
+    let registered_at = config.credentials.iter().map(|entry| entry.registered_at).min()?;
+    Some(SiteCredentialSummary {
+        site_id,
+        site_name: config.site_name,
+        credential_count: config.credentials.len(),
+        most_recent_use: config.credentials.iter().filter_map(|entry| entry.last_used_at).max(),
+        registered_at,
+    })
+}
+
+/// List the sites that have passkeys, most recently protected first
+///
+/// `filter` keeps only the sites matching it, so large stores need not cross
+/// the IPC boundary whole.
+#[command]
+pub async fn list_protected_sites<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    filter: Option<SiteFilter>,
+) -> Result<Vec<SiteCredentialSummary>, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    let filter = filter.unwrap_or_default();
+    let mut sites: Vec<SiteCredentialSummary> = store
+        .load_all()?
+        .into_iter()
+        .filter_map(|(site_id, config)| summarize(site_id, config))
+        .filter(|summary| filter.matches(summary))
+        .collect();
+    sites.sort_unstable_by(|a, b| b.registered_at.cmp(&a.registered_at).then_with(|| a.site_id.cmp(&b.site_id)));
+    Ok(sites)
+}
+
+/// A passkey to suggest before the prompt, from `get_login_hints`
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LoginHint {
+    pub credential_id: String,
+    pub user_display_name: Option<String>,
+    pub site_id: String,
+    pub device_name: Option<String>,
+}
+
+/// List the passkeys a user could sign in with, most recently used first, so
+/// the frontend can offer a choice before the prompt
+///
+/// Passkeys are created for the RP ID in use, so an `rp_id` other than that
+/// yields none; without one, the RP ID in use is assumed. Locked passkeys are
+/// left out. Never prompts: the hints come from the credential store, not the
+/// authenticator.
+#[command]
+pub async fn get_login_hints<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    rp_id: Option<String>,
+) -> Result<Vec<LoginHint>, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    let editing_domain = state.relying_party.editing_domain();
+    if rp_id.is_some_and(|rp_id| !rp_id.trim().eq_ignore_ascii_case(&editing_domain)) {
+        return Ok(Vec::new());
+    }
+    let mut hints: Vec<(DateTime<Utc>, LoginHint)> = store
+        .load_all()?
+        .into_iter()
+        .flat_map(|(site_id, config)| {
+            let user_display_name = config.user_display_name;
+            config.credentials.into_iter().filter(|entry| !entry.locked).map(move |entry| {
+                let last_used = entry.last_used_at.unwrap_or(entry.registered_at);
+                let hint = LoginHint {
+                    credential_id: entry.credential_id,
+                    user_display_name: user_display_name.clone(),
+                    site_id: site_id.clone(),
+                    device_name: entry.device_name,
+                };
+                (last_used, hint)
+            })
+        })
+        .collect();
+    hints.sort_unstable_by(|(a_used, a), (b_used, b)| {
+        b_used.cmp(a_used).then_with(|| a.credential_id.cmp(&b.credential_id))
+    });
+    Ok(hints.into_iter().map(|(_, hint)| hint).collect())
+}
+
+/// Remove a single device's passkey from a site
+#[command]
+pub async fn remove_site_credential<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    credential_id: String,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let removed = store.modify_credential(&site_id, |config| {
+        let Some(config) = config else {
+            return false;
+        };
+        let count = config.credentials.len();
+        config.credentials.retain(|entry| entry.credential_id != credential_id);
+        config.credentials.len() != count
+    })?;
+
+    if removed {
+        Ok(())
+    } else {
+        Err(WebAuthnError::CredentialNotFound(credential_id))
+    }
+}
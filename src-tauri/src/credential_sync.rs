@@ -0,0 +1,394 @@
+// src-tauri/src/credential_sync.rs
+
+//! Copying sites' passkey configuration to another device through QR codes.
+//!
+//! The device receiving the sites starts: `request_sync_offer` makes a fresh
+//! X25519 key pair and shows its public key, with a random offer ID, as a QR
+//! code. The sending device scans it, and `generate_sync_offer` agrees a secret
+//! with a fresh X25519 key pair of its own, derives an AES-256-GCM key from it
+//! with HKDF-SHA256 and seals the chosen sites' `SiteAuthConfig`s with that
+//! key. The offer, drawn as a QR code in turn, carries the sender's public key
+//! and the ciphertext but no key that opens it. The receiver scans it, and
+//! `accept_sync_offer` opens it with the private key it kept and merges the
+//! sites into its store the way `import_credentials` merges a backup.
+//!
+//! Seeing either QR code is not enough to read the sites; someone who wanted
+//! them would have to get the sender to scan a request of their own instead.
+//! Each request answers a single offer, whether or not it opens, and only for
+//! 60 seconds from when it was made as read by the receiver's own clock, so a
+//! replayed offer or a wrong clock on the sender cannot keep one acceptable.
+//! Passkeys taken from an offer were not seen created on the receiver, so like
+//! the imports of `register_passkey`, requesting and accepting offers is only
+//! allowed in debug builds and builds with the `allow-credential-import`
+//! feature.
+//!
+//! A request is `sparktype-sync-request:1:` followed by the offer ID and the
+//! receiver's public key, an offer `sparktype-sync:2:` followed by the offer
+//! ID, the sender's public key, the nonce and the ciphertext, each concatenated
+//! and base64url encoded. A QR code holds at most 2331 bytes, and each passkey
+//! takes about 600 of them, so an offer carries three or so; offers for more
+//! are refused.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use rand::RngCore;
+use ring::agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, X25519};
+use ring::hkdf;
+use ring::rand::SystemRandom;
+use serde::{Deserialize, Serialize};
+use tauri::{command, Runtime, State, WebviewWindow};
+
+use crate::audit_log::{self, AuditEvent};
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_backup::merge;
+use crate::credential_store::CredentialStore;
+use crate::qr_code::QrCode;
+use crate::webauthn::{credential_import_allowed, timestamp, validate_site_id, SiteAuthConfig, WebAuthnError};
+use crate::webauthn_state::WebAuthnState;
+
+/// What every request starts with, naming the format version
+const REQUEST_PREFIX: &str = "sparktype-sync-request:1:";
+
+/// What every offer starts with, naming the format version
+const PAYLOAD_PREFIX: &str = "sparktype-sync:2:";
+
+/// How long a request can be answered by an offer, unless configured otherwise
+pub const DEFAULT_SYNC_OFFER_TTL: Duration = Duration::from_secs(60);
+
+/// HKDF info the offer key is derived with, ahead of both public keys
+const KEY_INFO: &[u8] = b"sparktype-sync-offer";
+
+const OFFER_ID_BYTES: usize = 16;
+const PUBLIC_KEY_BYTES: usize = 32;
+const NONCE_BYTES: usize = 12;
+
+/// Decrypted offer contents
+#[derive(Serialize, Deserialize)]
+struct SyncContents {
+    sites: HashMap<String, SiteAuthConfig>,
+}
+
+/// A request for another device's sites, from `request_sync_offer`
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct SyncRequest {
+    /// What `generate_sync_offer` takes on the other device
+    pub payload: String,
+    /// `payload` as a QR code, a base64 encoded PNG
+    #[serde(rename = "qrCodePng")]
+    pub qr_code_png: String,
+    /// When this device stops accepting an offer answering the request
+    #[serde(rename = "expiresAt", with = "timestamp")]
+    #[cfg_attr(feature = "schema-export", schemars(with = "String"))]
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Sites offered to another device, from `generate_sync_offer`
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct SyncOffer {
+    /// What `accept_sync_offer` takes on the other device
+    pub payload: String,
+    /// `payload` as a QR code, a base64 encoded PNG
+    #[serde(rename = "qrCodePng")]
+    pub qr_code_png: String,
+}
+
+/// Summary of an offer merged into the store, from `accept_sync_offer`
+#[derive(Debug, Default, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct SyncAcceptResult {
+    /// The sites the offer carried, sorted
+    #[serde(rename = "siteIds")]
+    pub site_ids: Vec<String>,
+    /// Credentials added to the store
+    #[serde(rename = "importedCount")]
+    pub imported_count: usize,
+    /// Credentials already in the store, left untouched
+    #[serde(rename = "skippedCount")]
+    pub skipped_count: usize,
+}
+
+struct PendingSyncRequest {
+    private_key: EphemeralPrivateKey,
+    started: Instant,
+}
+
+/// Requests made by `request_sync_offer` and not answered yet, keyed by offer ID
+pub struct SyncRequests {
+    pending: DashMap<[u8; OFFER_ID_BYTES], PendingSyncRequest>,
+    ttl: Duration,
+}
+
+impl SyncRequests {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            pending: DashMap::new(),
+            ttl,
+        }
+    }
+
+    /// Remember `private_key` until the TTL has passed, returning the offer ID
+    fn start(&self, private_key: EphemeralPrivateKey) -> [u8; OFFER_ID_BYTES] {
+        self.pending.retain(|_, pending| pending.started.elapsed() <= self.ttl);
+        let mut offer_id = [0; OFFER_ID_BYTES];
+        OsRng.fill_bytes(&mut offer_id);
+        self.pending.insert(
+            offer_id,
+            PendingSyncRequest {
+                private_key,
+                started: Instant::now(),
+            },
+        );
+        offer_id
+    }
+
+    /// Forget the request `offer_id` answers, returning its private key
+    ///
+    /// Fails with `Timeout` once the TTL has passed, and with `InvalidInput`
+    /// for a request this device did not make or already took.
+    fn take(&self, offer_id: &[u8]) -> Result<EphemeralPrivateKey, WebAuthnError> {
+        let (_, pending) = <[u8; OFFER_ID_BYTES]>::try_from(offer_id)
+            .ok()
+            .and_then(|offer_id| self.pending.remove(&offer_id))
+            .ok_or_else(|| {
+                WebAuthnError::InvalidInput("The sync offer answers no request this device has open".to_string())
+            })?;
+        if pending.started.elapsed() > self.ttl {
+            return Err(WebAuthnError::Timeout);
+        }
+        Ok(pending.private_key)
+    }
+}
+
+fn import_disabled() -> WebAuthnError {
+    WebAuthnError::InvalidInput("Importing credentials is not enabled in this build".to_string())
+}
+
+fn key_pair() -> Result<(EphemeralPrivateKey, agreement::PublicKey), WebAuthnError> {
+    let private_key = EphemeralPrivateKey::generate(&X25519, &SystemRandom::new())
+        .map_err(|_| WebAuthnError::InternalError("Failed to generate a sync key".to_string()))?;
+    let public_key = private_key
+        .compute_public_key()
+        .map_err(|_| WebAuthnError::InternalError("Failed to generate a sync key".to_string()))?;
+    Ok((private_key, public_key))
+}
+
+/// The AES key both ends derive from their X25519 agreement, bound to the offer
+/// ID and both public keys
+///
+/// Fails with `None` for a peer public key that is not an X25519 point.
+fn offer_key(
+    private_key: EphemeralPrivateKey,
+    peer_public_key: &[u8],
+    offer_id: &[u8],
+    receiver_public_key: &[u8],
+    sender_public_key: &[u8],
+) -> Option<Key<Aes256Gcm>> {
+    let info = [KEY_INFO, receiver_public_key, sender_public_key];
+    agreement::agree_ephemeral(private_key, &UnparsedPublicKey::new(&X25519, peer_public_key), |secret| {
+        let mut key = Key::<Aes256Gcm>::default();
+        hkdf::Salt::new(hkdf::HKDF_SHA256, offer_id)
+            .extract(secret)
+            .expand(&info, hkdf::HKDF_SHA256)
+            .and_then(|okm| okm.fill(&mut key))
+            .ok()
+            .map(|()| key)
+    })
+    .ok()
+    .flatten()
+}
+
+/// Ask another device for its sites, showing it the QR code of the request
+///
+/// The request is answered by one offer, generated within 60 seconds. Fails
+/// with `InvalidInput` in builds that do not allow importing credentials.
+#[command]
+pub async fn request_sync_offer<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+) -> Result<SyncRequest, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    if !credential_import_allowed() {
+        return Err(import_disabled());
+    }
+    let (private_key, public_key) = key_pair()?;
+    let offer_id = state.sync_requests.start(private_key);
+    let expires_at = Utc::now() + state.sync_requests.ttl;
+
+    let payload = format!(
+        "{}{}",
+        REQUEST_PREFIX,
+        URL_SAFE_NO_PAD.encode([&offer_id[..], public_key.as_ref()].concat())
+    );
+    let png = QrCode::encode(payload.as_bytes())?.render_png()?;
+    Ok(SyncRequest {
+        payload,
+        qr_code_png: STANDARD.encode(png),
+        expires_at,
+    })
+}
+
+/// Offer the passkey configuration of `sites` to the device that made `request`
+///
+/// Every site must have a passkey in the store. Fails with `InvalidInput` for
+/// a `request` that is not one, or when the sites need more than one QR code
+/// holds.
+#[command]
+pub async fn generate_sync_offer<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    sites: Vec<String>,
+    request: String,
+) -> Result<SyncOffer, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let result = offer(&store, sites, &request);
+    state.audit_log.record(
+        &store,
+        AuditEvent::CredentialSyncOffer {
+            outcome: audit_log::outcome(&result),
+        },
+    );
+    result
+}
+
+fn offer(store: &CredentialStore, sites: Vec<String>, request: &str) -> Result<SyncOffer, WebAuthnError> {
+    let request = request
+        .trim()
+        .strip_prefix(REQUEST_PREFIX)
+        .and_then(|encoded| URL_SAFE_NO_PAD.decode(encoded).ok())
+        .filter(|request| request.len() == OFFER_ID_BYTES + PUBLIC_KEY_BYTES)
+        .ok_or_else(|| WebAuthnError::InvalidInput("Not a Sparktype sync request".to_string()))?;
+    let (offer_id, receiver_public_key) = request.split_at(OFFER_ID_BYTES);
+    if sites.is_empty() {
+        return Err(WebAuthnError::InvalidInput("A sync offer needs at least one site".to_string()));
+    }
+    let mut stored = store.load_all()?;
+    let mut offered = HashMap::new();
+    for site_id in sites {
+        validate_site_id(&site_id)?;
+        if offered.contains_key(&site_id) {
+            continue;
+        }
+        let config = stored
+            .remove(&site_id)
+            .filter(|config| !config.credentials.is_empty())
+            .ok_or_else(|| WebAuthnError::InvalidInput(format!("Site {} has no passkeys to sync", site_id)))?;
+        offered.insert(site_id, config);
+    }
+
+    let (private_key, public_key) = key_pair()?;
+    let sender_public_key = public_key.as_ref();
+    let key = offer_key(private_key, receiver_public_key, offer_id, receiver_public_key, sender_public_key)
+        .ok_or_else(|| WebAuthnError::InvalidInput("The sync request is damaged".to_string()))?;
+    let site_count = offered.len();
+    let plaintext = serde_json::to_vec(&SyncContents { sites: offered })
+        .map_err(|e| WebAuthnError::InternalError(e.to_string()))?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(&key)
+        .encrypt(&nonce, plaintext.as_ref())
+        .map_err(|_| WebAuthnError::InternalError("Failed to encrypt the sync offer".to_string()))?;
+    let sealed = [offer_id, sender_public_key, nonce.as_slice(), &ciphertext].concat();
+    let payload = format!("{}{}", PAYLOAD_PREFIX, URL_SAFE_NO_PAD.encode(sealed));
+
+    let qr_code = QrCode::encode(payload.as_bytes()).map_err(|_| {
+        WebAuthnError::InvalidInput(format!(
+            "{} sites hold more passkeys than one sync offer can carry; offer fewer",
+            site_count
+        ))
+    })?;
+    log::info!("Offered {} sites to another device", site_count);
+    Ok(SyncOffer {
+        payload,
+        qr_code_png: STANDARD.encode(qr_code.render_png()?),
+    })
+}
+
+/// Merge the sites of another device's sync offer into the credential store
+///
+/// The offer must answer a request this device made with `request_sync_offer`
+/// in the last 60 seconds, and is only accepted once; a later one fails with
+/// `Timeout`, and one that answers no open request, is damaged or is not an
+/// offer with `InvalidInput`. Credentials whose ID the store already holds for
+/// the same site are skipped. Fails with `InvalidInput` in builds that do not
+/// allow importing credentials.
+#[command]
+pub async fn accept_sync_offer<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    payload: String,
+) -> Result<SyncAcceptResult, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let result = accept(&state, &store, &payload);
+    state.audit_log.record(
+        &store,
+        AuditEvent::CredentialSyncAccept {
+            outcome: audit_log::outcome(&result),
+        },
+    );
+    result
+}
+
+fn accept(state: &WebAuthnState, store: &CredentialStore, payload: &str) -> Result<SyncAcceptResult, WebAuthnError> {
+    if !credential_import_allowed() {
+        return Err(import_disabled());
+    }
+    let sealed = payload
+        .trim()
+        .strip_prefix(PAYLOAD_PREFIX)
+        .and_then(|encoded| URL_SAFE_NO_PAD.decode(encoded).ok())
+        .filter(|sealed| sealed.len() > OFFER_ID_BYTES + PUBLIC_KEY_BYTES + NONCE_BYTES)
+        .ok_or_else(|| WebAuthnError::InvalidInput("Not a Sparktype sync offer".to_string()))?;
+    let (offer_id, rest) = sealed.split_at(OFFER_ID_BYTES);
+    let (sender_public_key, rest) = rest.split_at(PUBLIC_KEY_BYTES);
+    let (nonce, ciphertext) = rest.split_at(NONCE_BYTES);
+
+    let private_key = state.sync_requests.take(offer_id)?;
+    let receiver_public_key = private_key
+        .compute_public_key()
+        .map_err(|_| WebAuthnError::InternalError("Failed to read the sync key".to_string()))?;
+    let damaged = || WebAuthnError::InvalidInput("The sync offer is damaged".to_string());
+    let key = offer_key(
+        private_key,
+        sender_public_key,
+        offer_id,
+        receiver_public_key.as_ref(),
+        sender_public_key,
+    )
+    .ok_or_else(damaged)?;
+    let plaintext = Aes256Gcm::new(&key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| damaged())?;
+    let contents: SyncContents = serde_json::from_slice(&plaintext)
+        .map_err(|e| WebAuthnError::InvalidInput(format!("The sync offer is malformed: {}", e)))?;
+
+    let mut site_ids: Vec<String> = contents.sites.keys().cloned().collect();
+    for site_id in &site_ids {
+        validate_site_id(site_id)?;
+    }
+    site_ids.sort_unstable();
+
+    let imported = store.modify_all(|sites| merge(sites, contents.sites))?;
+    log::info!(
+        "Accepted a sync offer for {} sites: imported {} credentials, skipped {} already stored",
+        site_ids.len(),
+        imported.imported_count,
+        imported.skipped_count
+    );
+    Ok(SyncAcceptResult {
+        site_ids,
+        imported_count: imported.imported_count,
+        skipped_count: imported.skipped_count,
+    })
+}
@@ -0,0 +1,148 @@
+// src-tauri/src/deep_link.rs
+
+//! Passkey sign-ins started from outside the app.
+//!
+//! Another app, or a browser extension, can ask for a sign-in by opening
+//! `webauthn://authenticate?site_id=X&credential_id=Y`. The link must name a
+//! site the credential store holds and one of its passkeys. Before the prompt
+//! appears `webauthn://deep-link-received` is emitted, so the frontend can show
+//! which site is signing in, and `authenticate_passkey` then runs over the main
+//! window with only that passkey allowed; its usual events report the outcome.
+//!
+//! macOS and iOS hand the link to the running app as `RunEvent::Opened`, the
+//! scheme being declared in `Info.plist`. Windows and Linux start the app with
+//! the link as an argument, which `handle_launch_args` picks up; registering
+//! the scheme there is up to the installer. Android intents are not handled.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use tauri::{AppHandle, Manager, RunEvent, Runtime, Url};
+
+use crate::credential_store::CredentialStore;
+use crate::webauthn::{self, validate_site_id, WebAuthnError};
+use crate::webauthn_events::WebAuthnEvent;
+use crate::webauthn_state::WebAuthnState;
+
+/// URL scheme of the deep links
+pub(crate) const DEEP_LINK_SCHEME: &str = "webauthn";
+
+/// The window the sign-in prompt is presented over
+const MAIN_WINDOW_LABEL: &str = "main";
+
+/// The sign-in a deep link asks for
+#[derive(Debug)]
+struct DeepLinkRequest {
+    site_id: String,
+    credential_id: String,
+}
+
+/// Read the site and credential out of a `webauthn://authenticate` link
+fn parse_deep_link(url: &Url) -> Result<DeepLinkRequest, WebAuthnError> {
+    if url.scheme() != DEEP_LINK_SCHEME || url.host_str() != Some("authenticate") {
+        return Err(WebAuthnError::InvalidInput(format!(
+            "Unsupported deep link: {}://{}",
+            url.scheme(),
+            url.host_str().unwrap_or_default()
+        )));
+    }
+    let mut site_id = None;
+    let mut credential_id = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "site_id" => site_id = Some(value.into_owned()),
+            "credential_id" => credential_id = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+    let (Some(site_id), Some(credential_id)) = (site_id, credential_id) else {
+        return Err(WebAuthnError::InvalidInput("Deep link must give a site_id and a credential_id".to_string()));
+    };
+    validate_site_id(&site_id)?;
+    if credential_id.is_empty() || URL_SAFE_NO_PAD.decode(&credential_id).is_err() {
+        return Err(WebAuthnError::InvalidInput("credential_id must be base64url".to_string()));
+    }
+    Ok(DeepLinkRequest { site_id, credential_id })
+}
+
+/// Start the sign-in `url` asks for, in the background
+///
+/// Links that do not check out are logged and ignored.
+pub(crate) fn handle_deep_link<R: Runtime>(app: &AppHandle<R>, url: &Url) {
+    let request = match parse_deep_link(url) {
+        Ok(request) => request,
+        Err(error) => {
+            log::warn!("Ignored a deep link: {}", error);
+            return;
+        }
+    };
+    log::info!("Received a deep link to authenticate to site {}", request.site_id);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let site_id = request.site_id.clone();
+        if let Err(error) = authenticate(&app, request).await {
+            log::warn!("Deep-linked authentication to site {} failed: {}", site_id, error);
+        }
+    });
+}
+
+/// Handle the deep links macOS and iOS hand to the running app
+pub(crate) fn handle_run_event<R: Runtime>(app: &AppHandle<R>, event: RunEvent) {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    if let RunEvent::Opened { urls } = event {
+        for url in &urls {
+            handle_deep_link(app, url);
+        }
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+    let _ = (app, event);
+}
+
+/// Handle the deep links among the arguments the app was started with
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub(crate) fn handle_launch_args<R: Runtime>(app: &AppHandle<R>) {
+    let prefix = format!("{}://", DEEP_LINK_SCHEME);
+    for arg in std::env::args().skip(1).filter(|arg| arg.starts_with(&prefix)) {
+        match Url::parse(&arg) {
+            Ok(url) => handle_deep_link(app, &url),
+            Err(error) => log::warn!("Ignored a malformed deep link: {}", error),
+        }
+    }
+}
+
+async fn authenticate<R: Runtime>(app: &AppHandle<R>, request: DeepLinkRequest) -> Result<(), WebAuthnError> {
+    let window = app
+        .get_webview_window(MAIN_WINDOW_LABEL)
+        .ok_or_else(|| WebAuthnError::InternalError("The main window is not open".to_string()))?;
+    let mut auth_config = app
+        .state::<CredentialStore>()
+        .load_credential(&request.site_id)?
+        .ok_or_else(|| WebAuthnError::CredentialNotFound(request.site_id.clone()))?;
+    auth_config.credentials.retain(|entry| entry.credential_id == request.credential_id);
+    if auth_config.credentials.is_empty() {
+        return Err(WebAuthnError::CredentialNotFound(request.credential_id));
+    }
+
+    WebAuthnEvent::DeepLinkReceived {
+        site_id: request.site_id.clone(),
+        credential_id: request.credential_id,
+    }
+    .emit(app);
+    let challenge = app.state::<WebAuthnState>().challenges.issue(Some(&request.site_id));
+    webauthn::authenticate_passkey(
+        window.clone(),
+        window.state(),
+        window.state(),
+        window.state(),
+        request.site_id,
+        auth_config,
+        challenge,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .await?;
+    Ok(())
+}
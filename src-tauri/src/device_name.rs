@@ -0,0 +1,146 @@
+// src-tauri/src/device_name.rs
+
+//! A label for the device a passkey is registered on.
+//!
+//! `register_passkey` stores it as the credential's `deviceName` when the
+//! frontend gives none, so the owner can tell their passkeys apart. OS device
+//! names often lead with the owner's name ("Alice's MacBook Pro"), which is
+//! dropped before the name is stored. A name another of the site's passkeys
+//! already has is numbered, "MacBook Pro (2)", so two devices that report the
+//! same name can still be told apart.
+
+/// Stored when the OS does not report a usable name
+pub const UNKNOWN_DEVICE: &str = "Unknown Device";
+
+/// Longest device name stored
+const MAX_DEVICE_NAME_CHARS: usize = 64;
+
+/// A human-readable name for this device, or `UNKNOWN_DEVICE`
+///
+/// Only asks the OS for locally held information, but not every OS promises
+/// that is quick, so async callers should run it on a blocking thread.
+pub fn get_device_name() -> String {
+    platform_device_name()
+        .map(|name| sanitize(&name))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| UNKNOWN_DEVICE.to_string())
+}
+
+/// `base`, or the first of `base (2)`, `base (3)` and so on that none of
+/// `existing` matches, ignoring case
+#[doc(hidden)]
+pub fn make_unique_device_name(base: &str, existing: &[&str]) -> String {
+    let existing: Vec<String> = existing.iter().map(|name| name.to_lowercase()).collect();
+    let taken = |name: &str| existing.contains(&name.to_lowercase());
+    if !taken(base) {
+        return base.to_string();
+    }
+    let mut suffix = 2;
+    loop {
+        let name = format!("{} ({})", base, suffix);
+        if !taken(&name) {
+            return name;
+        }
+        suffix += 1;
+    }
+}
+
+/// Strip control characters and an owner's name, and cap the length
+fn sanitize(name: &str) -> String {
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim();
+    let name = ["'s ", "\u{2019}s "]
+        .iter()
+        .find_map(|possessive| name.split_once(possessive).map(|(_, device)| device))
+        .unwrap_or(name)
+        .trim();
+    let name: String = name.chars().take(MAX_DEVICE_NAME_CHARS).collect();
+    name.trim_end().to_string()
+}
+
+/// The computer name set in System Settings > Sharing
+#[cfg(target_os = "macos")]
+fn platform_device_name() -> Option<String> {
+    use std::ffi::c_void;
+
+    use objc2::rc::Retained;
+    use objc2_foundation::NSString;
+
+    #[link(name = "SystemConfiguration", kind = "framework")]
+    extern "C" {
+        // Returns a CFStringRef, which is toll-free bridged with NSString
+        fn SCDynamicStoreCopyComputerName(store: *const c_void, name_encoding: *mut u32) -> *mut NSString;
+    }
+
+    // Follows the Create rule, so the returned reference is owned
+    let name = unsafe { Retained::from_raw(SCDynamicStoreCopyComputerName(std::ptr::null(), std::ptr::null_mut())) };
+    if name.is_none() {
+        log::warn!("Could not read the computer name");
+    }
+    name.map(|name| name.to_string())
+}
+
+/// The name in Settings > General > About; from iOS 16 just the model, such as
+/// "iPhone", unless the app holds the user-assigned device name entitlement
+#[cfg(target_os = "ios")]
+fn platform_device_name() -> Option<String> {
+    use objc2::msg_send_id;
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyClass, AnyObject};
+    use objc2_foundation::NSString;
+
+    // UIKit is not among the bindings, so UIDevice is looked up at runtime
+    let class = AnyClass::get("UIDevice")?;
+    let device: Retained<AnyObject> = unsafe { msg_send_id![class, currentDevice] };
+    let name: Option<Retained<NSString>> = unsafe { msg_send_id![&*device, name] };
+    name.map(|name| name.to_string())
+}
+
+/// The computer's DNS host name
+#[cfg(target_os = "windows")]
+fn platform_device_name() -> Option<String> {
+    use windows::core::PWSTR;
+    use windows::Win32::System::SystemInformation::{ComputerNameDnsHostname, GetComputerNameExW};
+
+    // The first call fails, reporting the length needed including the terminator
+    let mut length = 0u32;
+    let _ = unsafe { GetComputerNameExW(ComputerNameDnsHostname, None, &mut length) };
+    let mut buffer = vec![0u16; length as usize];
+    match unsafe { GetComputerNameExW(ComputerNameDnsHostname, Some(PWSTR(buffer.as_mut_ptr())), &mut length) } {
+        Ok(()) => Some(String::from_utf16_lossy(&buffer[..length as usize])),
+        Err(error) => {
+            log::warn!("Could not read the computer name: {}", error);
+            None
+        }
+    }
+}
+
+/// The host name
+#[cfg(target_os = "linux")]
+fn platform_device_name() -> Option<String> {
+    // Host names are at most 64 bytes on Linux; the rest leaves room to spare
+    let mut buffer = [0u8; 256];
+    if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } != 0 {
+        log::warn!("Could not read the host name: {}", std::io::Error::last_os_error());
+        return None;
+    }
+    let length = buffer.iter().position(|&byte| byte == 0).unwrap_or(buffer.len());
+    Some(String::from_utf8_lossy(&buffer[..length]).into_owned())
+}
+
+/// The device model, such as "Pixel 8"; the host name is always "localhost"
+#[cfg(target_os = "android")]
+fn platform_device_name() -> Option<String> {
+    crate::webauthn_android::device_model()
+}
+
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "android"
+)))]
+fn platform_device_name() -> Option<String> {
+    None
+}
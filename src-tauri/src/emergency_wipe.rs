@@ -0,0 +1,131 @@
+// src-tauri/src/emergency_wipe.rs
+
+//! Removal of every passkey and all sign-in state, for a device that may be
+//! compromised.
+//!
+//! `emergency_wipe` deletes each stored passkey from the platform
+//! authenticator as `delete_passkey` would, then removes the credential store
+//! file whether or not the platform could delete them all, and ends every
+//! session. Pending challenges, rate limits and cached security key PINs are
+//! dropped too. A passkey the platform failed to delete is reported in
+//! `errors`. Android's Credential Manager and security keys on Linux offer no
+//! way to delete one, so as with `delete_passkey` their passkeys count as
+//! deleted but stay for the user to remove from their password manager or key.
+//! The store key stays in the OS keychain, as the audit log, which records each
+//! deletion, is sealed with a key derived from it.
+
+use serde::Serialize;
+use tauri::{command, Manager, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_store::CredentialStore;
+use crate::log_event::Redacted;
+use crate::webauthn::{delete_credential, WebAuthnError};
+use crate::webauthn_events::{Severity, WebAuthnEvent};
+use crate::webauthn_state::WebAuthnState;
+
+/// What `emergency_wipe` must be given to run
+const CONFIRMATION: &str = "CONFIRM_WIPE";
+
+/// What a wipe removed, from `emergency_wipe`
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct WipeReport {
+    /// Passkeys deleted from the platform authenticator
+    pub credentials_deleted: usize,
+    /// Sessions ended that had not expired yet
+    pub sessions_cleared: usize,
+    /// Paths of the credential store files removed
+    pub files_removed: Vec<String>,
+    /// What could not be deleted, and why
+    pub errors: Vec<String>,
+}
+
+/// Delete every passkey and end every session; `confirmation` must be `CONFIRM_WIPE`
+///
+/// Each passkey is deleted from the platform authenticator, and the credential
+/// store file is removed even when some could not be. Emits
+/// `webauthn://session-invalidated` for each session ended, then
+/// `webauthn://emergency-wipe-completed` with the report.
+#[command]
+pub async fn emergency_wipe<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    confirmation: String,
+) -> Result<WipeReport, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    if confirmation != CONFIRMATION {
+        return Err(WebAuthnError::InvalidInput(format!(
+            "confirmation must be {:?}",
+            CONFIRMATION
+        )));
+    }
+    log::warn!("Emergency wipe requested; deleting every passkey");
+    let app = window.app_handle();
+    let mut report = WipeReport::default();
+
+    match store.load_all() {
+        Ok(sites) => {
+            for (site_id, config) in sites {
+                for entry in config.credentials {
+                    match delete_credential(app, &store, &site_id, entry.credential_id.clone()).await {
+                        Ok(()) => report.credentials_deleted += 1,
+                        Err(error) => {
+                            // CredentialNotFound would repeat the ID in full
+                            let error = match error {
+                                WebAuthnError::CredentialNotFound(_) => error.code().to_string(),
+                                error => error.to_string(),
+                            };
+                            log::warn!(
+                                "Emergency wipe could not delete passkey {} of site {}: {}",
+                                Redacted(&entry.credential_id),
+                                site_id,
+                                error
+                            );
+                            report.errors.push(format!(
+                                "Could not delete passkey {} of site {}: {}",
+                                Redacted(&entry.credential_id),
+                                site_id,
+                                error
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        Err(error) => report.errors.push(format!("Could not read the credential store: {}", error)),
+    }
+    match store.wipe() {
+        Ok(removed) => {
+            report.files_removed = removed.iter().map(|path| path.display().to_string()).collect();
+        }
+        Err(error) => report.errors.push(format!("Could not remove the credential store: {}", error)),
+    }
+
+    let closed = state.sessions.close_all();
+    report.sessions_cleared = closed.len();
+    for session in closed {
+        WebAuthnEvent::SessionInvalidated {
+            site_id: session.site_id,
+        }
+        .emit(app);
+    }
+    state.challenges.clear();
+    state.rate_limiter.clear();
+    state.pin_cache.clear();
+
+    log::warn!(
+        "Emergency wipe deleted {} passkeys and ended {} sessions; {} errors",
+        report.credentials_deleted,
+        report.sessions_cleared,
+        report.errors.len()
+    );
+    WebAuthnEvent::EmergencyWipeCompleted {
+        severity: Severity::Critical,
+        report: report.clone(),
+    }
+    .emit(app);
+    Ok(report)
+}
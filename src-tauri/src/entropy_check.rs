@@ -0,0 +1,115 @@
+// src-tauri/src/entropy_check.rs
+
+//! A self-test of the OS random number generator, run before the plugin starts.
+//!
+//! Challenges and their seed come from `OsRng`, so a generator that fails, or
+//! hands out bytes that are plainly not random, as can happen early in boot on
+//! some embedded devices, would make every challenge guessable. The plugin's
+//! `setup` draws a sample from `OsRng` and checks the byte frequencies with a
+//! chi-squared test, failing when the p-value is below 0.001, and checks the
+//! draw ran at 1000 bytes a second or faster. A failed test fails the plugin's
+//! setup, so the app does not start.
+//!
+//! The sample is 2048 bytes, 8 per possible byte value. Fewer would leave most
+//! values unseen and the chi-squared approximation meaningless. A healthy
+//! generator still fails a single sample one time in 1000, so a failed sample
+//! is only reported after a second, fresh one fails as well.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+/// Bytes drawn for each sample
+pub const SAMPLE_BYTES: usize = 2048;
+
+/// Slowest the OS generator may be, in bytes a second
+pub const MIN_BYTES_PER_SECOND: f64 = 1000.0;
+
+/// The chi-squared statistic with 255 degrees of freedom that a p-value of
+/// 0.001 corresponds to
+const CHI_SQUARED_CRITICAL: f64 = 330.52;
+
+/// Samples drawn before the generator is given up on
+const SAMPLES: usize = 2;
+
+/// Why the OS generator failed the self-test
+#[derive(Debug)]
+pub(crate) enum EntropyFailure {
+    /// `OsRng` returned an error
+    Unavailable(rand::Error),
+    /// The byte frequencies of every sample gave a p-value below 0.001
+    NotRandom { chi_squared: f64 },
+    /// A sample took longer than `MIN_BYTES_PER_SECOND` allows
+    TooSlow { elapsed: Duration },
+}
+
+impl fmt::Display for EntropyFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unavailable(error) => write!(f, "the OS random number generator failed: {}", error),
+            Self::NotRandom { chi_squared } => write!(
+                f,
+                "the OS random number generator returned bytes that are not plausibly random \
+                 (chi-squared {:.1} over {} bytes, p < 0.001)",
+                chi_squared, SAMPLE_BYTES
+            ),
+            Self::TooSlow { elapsed } => write!(
+                f,
+                "the OS random number generator took {} ms for {} bytes, slower than {} bytes a second",
+                elapsed.as_millis(),
+                SAMPLE_BYTES,
+                MIN_BYTES_PER_SECOND
+            ),
+        }
+    }
+}
+
+/// Draw samples from `OsRng` and check each is random and came quickly enough
+pub(crate) fn self_test() -> Result<(), EntropyFailure> {
+    let mut chi_squared = 0.0;
+    for _ in 0..SAMPLES {
+        let mut sample = [0u8; SAMPLE_BYTES];
+        let started = Instant::now();
+        OsRng.try_fill_bytes(&mut sample).map_err(EntropyFailure::Unavailable)?;
+        let elapsed = started.elapsed();
+        if SAMPLE_BYTES as f64 / elapsed.as_secs_f64() < MIN_BYTES_PER_SECOND {
+            return Err(EntropyFailure::TooSlow { elapsed });
+        }
+        chi_squared = byte_frequency_chi_squared(&sample);
+        if is_plausibly_random(chi_squared) {
+            return Ok(());
+        }
+        log::warn!(
+            "An OS random sample failed the chi-squared test ({:.1}); drawing another",
+            chi_squared
+        );
+    }
+    Err(EntropyFailure::NotRandom { chi_squared })
+}
+
+/// Pearson's chi-squared statistic of the byte values in `bytes` against a
+/// uniform distribution over all 256
+#[doc(hidden)]
+pub fn byte_frequency_chi_squared(bytes: &[u8]) -> f64 {
+    let mut counts = [0u64; 256];
+    for &byte in bytes {
+        counts[usize::from(byte)] += 1;
+    }
+    let expected = bytes.len() as f64 / 256.0;
+    counts
+        .iter()
+        .map(|&count| {
+            let deviation = count as f64 - expected;
+            deviation * deviation / expected
+        })
+        .sum()
+}
+
+/// Whether a statistic from `byte_frequency_chi_squared` has a p-value of
+/// 0.001 or more
+#[doc(hidden)]
+pub fn is_plausibly_random(chi_squared: f64) -> bool {
+    chi_squared <= CHI_SQUARED_CRITICAL
+}
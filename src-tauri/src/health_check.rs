@@ -0,0 +1,95 @@
+// src-tauri/src/health_check.rs
+
+//! A snapshot of the WebAuthn subsystem for diagnostics.
+//!
+//! `webauthn_health_check` only reads state the app already holds and never
+//! runs a ceremony, so it can be called at any time without a prompt. Which
+//! sites have passkeys and which are being throttled would help an attacker who
+//! gets to invoke commands, so those are only reported in developer mode.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::{command, Manager, Runtime, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_refresh;
+use crate::credential_store::CredentialStore;
+use crate::rate_limiter::RateLimitEntry;
+use crate::webauthn::{developer_mode, timestamp, WebAuthnError, BUILD_TIMESTAMP, GIT_SHA, PLUGIN_VERSION};
+use crate::webauthn_state::WebAuthnState;
+
+/// State of the WebAuthn subsystem, from `webauthn_health_check`
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct HealthReport {
+    /// Target OS of the build
+    pub platform: String,
+    #[serde(rename = "rpId")]
+    pub rp_id: String,
+    #[serde(rename = "challengeTtlSeconds")]
+    pub challenge_ttl_seconds: u64,
+    /// Sessions that have not expired
+    #[serde(rename = "activeSessions")]
+    pub active_sessions: usize,
+    /// Sites the credential store holds passkeys for; developer mode only
+    #[serde(rename = "storedCredentialSites", skip_serializing_if = "Option::is_none")]
+    pub stored_credential_sites: Option<Vec<String>>,
+    /// Sites with authentication attempts in the current window; developer mode only
+    #[serde(rename = "rateLimitStates", skip_serializing_if = "Option::is_none")]
+    pub rate_limit_states: Option<Vec<RateLimitEntry>>,
+    /// When the background credential refresh last completed
+    #[serde(rename = "lastCredentialRefresh", with = "timestamp::optional")]
+    #[cfg_attr(feature = "schema-export", schemars(with = "Option<String>"))]
+    pub last_credential_refresh: Option<DateTime<Utc>>,
+    /// Version of the app the WebAuthn commands ship in
+    #[serde(rename = "pluginVersion")]
+    pub plugin_version: String,
+    /// Short hash of the commit the app was built from
+    #[serde(rename = "gitSha")]
+    pub git_sha: String,
+    /// When the app was built, RFC 3339
+    #[serde(rename = "buildTimestamp")]
+    pub build_timestamp: String,
+    /// The system clock read a time before 2001 when the plugin started
+    #[serde(rename = "clockSkewDetected")]
+    pub clock_skew_detected: bool,
+}
+
+/// Report the state of the WebAuthn subsystem, without prompting the user
+///
+/// `storedCredentialSites` and `rateLimitStates` are only included in debug
+/// builds or with `SPARKTYPE_DEVELOPER_MODE=1`.
+#[command]
+pub async fn webauthn_health_check<R: Runtime>(window: WebviewWindow<R>) -> Result<HealthReport, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    let store = window.state::<CredentialStore>();
+    let state = window.state::<WebAuthnState>();
+    let (stored_credential_sites, rate_limit_states) = if developer_mode() {
+        let mut sites: Vec<String> = store
+            .load_all()?
+            .into_iter()
+            .filter(|(_, config)| !config.credentials.is_empty())
+            .map(|(site_id, _)| site_id)
+            .collect();
+        sites.sort_unstable();
+        let mut rate_limits = state.rate_limiter.entries();
+        rate_limits.sort_unstable_by(|a, b| a.site_id.cmp(&b.site_id));
+        (Some(sites), Some(rate_limits))
+    } else {
+        (None, None)
+    };
+
+    Ok(HealthReport {
+        platform: std::env::consts::OS.to_string(),
+        rp_id: state.relying_party.editing_domain(),
+        challenge_ttl_seconds: state.challenges.ttl().as_secs(),
+        active_sessions: state.sessions.active_count(),
+        stored_credential_sites,
+        rate_limit_states,
+        last_credential_refresh: credential_refresh::last_run(&store),
+        plugin_version: PLUGIN_VERSION.to_string(),
+        git_sha: GIT_SHA.to_string(),
+        build_timestamp: BUILD_TIMESTAMP.to_string(),
+        clock_skew_detected: state.clock_skew_detected,
+    })
+}
@@ -0,0 +1,171 @@
+// src-tauri/src/hybrid_auth.rs
+
+//! Signing in with a passkey held by another device, over the hybrid transport.
+//!
+//! Android and recent Apple systems let a phone authenticate for a desktop by
+//! scanning a QR code (caBLE, "hybrid" in CTAP 2.2). `initiate_hybrid_auth`
+//! issues a challenge, puts it in a `webauthn://hybrid` link along with the
+//! site, relying party and a session token, and returns the link drawn as a QR
+//! code; the frontend then calls `poll_hybrid_auth` with the token until the
+//! phone has answered. Only that much exists so far: the tunnel the phone
+//! answers through is not implemented, so polling a live session fails with
+//! `NotImplemented`. Sessions last as long as their challenge.
+
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, TimeDelta, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tauri::{command, Runtime, State, Url, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::deep_link::DEEP_LINK_SCHEME;
+use crate::qr_code::QrCode;
+use crate::webauthn::{timestamp, validate_site_id, WebAuthnError};
+use crate::webauthn_state::WebAuthnState;
+
+/// A hybrid sign-in waiting for the other device, returned by `initiate_hybrid_auth`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridAuthSession {
+    /// What `poll_hybrid_auth` takes
+    #[serde(rename = "sessionToken")]
+    pub session_token: String,
+    /// The link the other device scans
+    pub url: String,
+    /// `url` as a QR code, a base64 encoded PNG
+    #[serde(rename = "qrCodePng")]
+    pub qr_code_png: String,
+    /// When the challenge in `url` runs out, and the session with it
+    #[serde(rename = "expiresAt", with = "timestamp")]
+    pub expires_at: DateTime<Utc>,
+}
+
+/// How far a hybrid sign-in got, from `poll_hybrid_auth`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum HybridAuthPollResult {
+    /// The other device has not answered yet
+    Pending,
+    /// The other device signed in with one of its passkeys
+    Completed {
+        #[serde(rename = "siteId")]
+        site_id: String,
+        #[serde(rename = "credentialId")]
+        credential_id: String,
+        /// The assertion's WebAuthn response, as `authenticate_passkey` returns it
+        response: serde_json::Value,
+    },
+}
+
+struct PendingHybridAuth {
+    site_id: String,
+    challenge: String,
+    started: Instant,
+    ttl: Duration,
+}
+
+impl PendingHybridAuth {
+    fn is_expired(&self) -> bool {
+        self.started.elapsed() > self.ttl
+    }
+}
+
+/// Hybrid sign-ins started and not yet expired, keyed by session token
+#[derive(Default)]
+pub struct HybridAuthSessions {
+    pending: DashMap<String, PendingHybridAuth>,
+}
+
+impl HybridAuthSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember a sign-in for `site_id` with `challenge` until `ttl` has passed,
+    /// returning its session token
+    pub fn start(&self, site_id: &str, challenge: &str, ttl: Duration) -> String {
+        self.pending.retain(|_, pending| !pending.is_expired());
+        let token = uuid::Uuid::new_v4().to_string();
+        self.pending.insert(
+            token.clone(),
+            PendingHybridAuth {
+                site_id: site_id.to_string(),
+                challenge: challenge.to_string(),
+                started: Instant::now(),
+                ttl,
+            },
+        );
+        token
+    }
+
+    /// The site and challenge of an unexpired session
+    pub fn get(&self, token: &str) -> Option<(String, String)> {
+        self.pending
+            .get(token)
+            .filter(|pending| !pending.is_expired())
+            .map(|pending| (pending.site_id.clone(), pending.challenge.clone()))
+    }
+}
+
+/// Start a sign-in to a site with a passkey on another device
+///
+/// Returns the QR code for that device to scan and the token to poll with. The
+/// site needs no passkey in the credential store, as the passkey is on the
+/// other device.
+#[command]
+pub async fn initiate_hybrid_auth<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    site_id: String,
+) -> Result<HybridAuthSession, WebAuthnError> {
+    check_window(&window, CapabilityTier::Authenticate)?;
+    validate_site_id(&site_id)?;
+
+    let rp_id = state.relying_party.ceremony_domain()?;
+    let challenge = state.challenges.issue(Some(&site_id));
+    let ttl = state.challenges.ttl();
+    let session_token = state.hybrid_auth.start(&site_id, &challenge, ttl);
+    let url = Url::parse_with_params(
+        &format!("{}://hybrid", DEEP_LINK_SCHEME),
+        [
+            ("token", session_token.as_str()),
+            ("site_id", site_id.as_str()),
+            ("rp_id", rp_id.as_str()),
+            ("challenge", challenge.as_str()),
+        ],
+    )
+    .map_err(|e| WebAuthnError::InternalError(format!("Failed to build the hybrid link: {}", e)))?
+    .to_string();
+    let png = QrCode::encode(url.as_bytes())?.render_png()?;
+
+    log::info!("Started a hybrid sign-in to site {}", site_id);
+    Ok(HybridAuthSession {
+        session_token,
+        url,
+        qr_code_png: STANDARD.encode(png),
+        expires_at: TimeDelta::from_std(ttl)
+            .ok()
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC),
+    })
+}
+
+/// Check whether the other device has answered a hybrid sign-in
+///
+/// Fails with `InvalidInput` for a token that is unknown or has expired. The
+/// hybrid tunnel is not implemented yet, so for a live session this fails with
+/// `NotImplemented`.
+#[command]
+pub async fn poll_hybrid_auth<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    session_token: String,
+) -> Result<HybridAuthPollResult, WebAuthnError> {
+    check_window(&window, CapabilityTier::Authenticate)?;
+    let Some((site_id, _challenge)) = state.hybrid_auth.get(&session_token) else {
+        return Err(WebAuthnError::InvalidInput("Unknown or expired hybrid session".to_string()));
+    };
+    log::debug!("Polled the hybrid sign-in to site {}", site_id);
+    Err(WebAuthnError::NotImplemented)
+}
@@ -0,0 +1,62 @@
+// src-tauri/src/in_flight.rs
+
+//! Registrations in progress, per site.
+//!
+//! A double-clicked register button would otherwise run two `register_passkey`
+//! ceremonies for the same site, and both would add a credential. The second
+//! call is refused with `RegistrationInProgress` while the first is running.
+//!
+//! It also keeps the sites `should_reenroll` found to have lost their passkeys,
+//! so the next registration for such a site deletes the orphaned ones first.
+
+use dashmap::DashSet;
+use scopeguard::ScopeGuard;
+
+use crate::webauthn::WebAuthnError;
+
+/// Sites with a `register_passkey` call in progress
+#[derive(Default)]
+pub struct InFlightRegistry {
+    sites: DashSet<String>,
+    /// Sites due to be re-enrolled by their next registration
+    reenrollments: DashSet<String>,
+}
+
+impl InFlightRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark a registration for the site as started, until the returned guard is
+    /// dropped
+    ///
+    /// The guard also releases the site when the registration panics or its
+    /// future is dropped, so a site can never stay locked.
+    pub fn begin(&self, site_id: &str) -> Result<ScopeGuard<String, impl FnOnce(String) + '_>, WebAuthnError> {
+        if !self.sites.insert(site_id.to_string()) {
+            return Err(WebAuthnError::RegistrationInProgress);
+        }
+        Ok(scopeguard::guard(site_id.to_string(), move |site_id| {
+            self.sites.remove(&site_id);
+        }))
+    }
+
+    /// Have the site's next registration replace its stored passkeys
+    pub fn mark_reenrollment(&self, site_id: &str) {
+        self.reenrollments.insert(site_id.to_string());
+    }
+
+    /// Whether the site was marked for re-enrollment, clearing the mark
+    pub fn take_reenrollment(&self, site_id: &str) -> bool {
+        self.reenrollments.remove(site_id).is_some()
+    }
+
+    /// Forget every registration in progress and every re-enrollment mark, as
+    /// the app exits, returning how many registrations were in progress
+    pub fn drain(&self) -> usize {
+        let in_progress = self.sites.len();
+        self.sites.clear();
+        self.reenrollments.clear();
+        in_progress
+    }
+}
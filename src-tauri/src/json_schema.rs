@@ -0,0 +1,81 @@
+// src-tauri/src/json_schema.rs
+
+//! A JSON Schema of the types the WebAuthn commands exchange with the frontend.
+//!
+//! Only built with the `schema-export` feature, which release builds refuse.
+//! The schema is checked in at `src/core/types/webauthn-schema.json` for the
+//! frontend's type generation; `tests/json_schema.rs` fails when it no longer
+//! matches these types, and rewrites it when run with
+//! `UPDATE_WEBAUTHN_SCHEMA=1`.
+
+use schemars::gen::SchemaSettings;
+use schemars::schema::{Metadata, RootSchema, SchemaObject};
+use tauri::{command, Runtime, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_backup::{ExportResult, ImportResult};
+use crate::credential_migration::MigrationReport;
+use crate::credential_purge::PurgeReport;
+use crate::credential_store::{LoginHint, SiteCredentialSummary};
+use crate::credential_sync::{SyncAcceptResult, SyncOffer, SyncRequest};
+use crate::emergency_wipe::WipeReport;
+use crate::health_check::HealthReport;
+use crate::session_store::SiteAuthStatus;
+use crate::webauthn::{
+    AuthenticationResult, AuthenticatorAttachment, CredentialEntry, CredentialStatus, DiscoveredCredential,
+    OperationTimings, PrfExtensionInput, PrfValues, RegistrationResult, ResidentKeyRequirement, SiteAuthConfig,
+    UserVerification, WebAuthnCapabilities, WebAuthnError,
+};
+
+/// Get a JSON Schema (draft 7) whose `definitions` describe every WebAuthn
+/// type the commands take or return, pretty-printed
+#[command]
+pub fn export_webauthn_json_schema<R: Runtime>(window: WebviewWindow<R>) -> Result<String, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    webauthn_json_schema()
+}
+
+/// `export_webauthn_json_schema` without the window check, for the schema test
+pub fn webauthn_json_schema() -> Result<String, WebAuthnError> {
+    let mut generator = SchemaSettings::draft07().into_generator();
+    generator.subschema_for::<SiteAuthConfig>();
+    generator.subschema_for::<CredentialEntry>();
+    generator.subschema_for::<AuthenticationResult>();
+    generator.subschema_for::<RegistrationResult>();
+    generator.subschema_for::<OperationTimings>();
+    generator.subschema_for::<DiscoveredCredential>();
+    generator.subschema_for::<CredentialStatus>();
+    generator.subschema_for::<WebAuthnCapabilities>();
+    generator.subschema_for::<UserVerification>();
+    generator.subschema_for::<ResidentKeyRequirement>();
+    generator.subschema_for::<AuthenticatorAttachment>();
+    generator.subschema_for::<PrfExtensionInput>();
+    generator.subschema_for::<PrfValues>();
+    generator.subschema_for::<WebAuthnError>();
+    generator.subschema_for::<SiteCredentialSummary>();
+    generator.subschema_for::<LoginHint>();
+    generator.subschema_for::<SiteAuthStatus>();
+    generator.subschema_for::<HealthReport>();
+    generator.subschema_for::<WipeReport>();
+    generator.subschema_for::<PurgeReport>();
+    generator.subschema_for::<SyncRequest>();
+    generator.subschema_for::<SyncOffer>();
+    generator.subschema_for::<SyncAcceptResult>();
+    generator.subschema_for::<ExportResult>();
+    generator.subschema_for::<ImportResult>();
+    generator.subschema_for::<MigrationReport>();
+
+    let schema = RootSchema {
+        meta_schema: generator.settings().meta_schema.clone(),
+        schema: SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                title: Some("Sparktype WebAuthn types".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        },
+        definitions: generator.take_definitions(),
+    };
+    serde_json::to_string_pretty(&schema)
+        .map_err(|e| WebAuthnError::InternalError(format!("Failed to serialize the WebAuthn schema: {}", e)))
+}
@@ -1,28 +1,217 @@
+use std::fmt;
+
+use log::kv::{Key, Source, Value, VisitSource};
+use tauri::{Manager, Runtime};
+
+mod app_lock;
+mod audit_log;
+mod auth_notifications;
+mod capability_filter;
+mod ceremony_watchdog;
+mod challenge_store;
+mod client_data;
+mod clipboard_guard;
+mod credential_backup;
+mod credential_id;
+mod credential_migration;
+mod credential_purge;
+mod credential_refresh;
+mod credential_store;
+mod credential_sync;
+mod deep_link;
+mod device_name;
+mod emergency_wipe;
+mod entropy_check;
+mod health_check;
+mod hybrid_auth;
+mod in_flight;
+#[cfg(feature = "schema-export")]
+mod json_schema;
+mod log_event;
+mod metrics;
+mod onboarding;
+mod panic_hook;
+mod pin_cache;
+mod platform_retry;
+mod qr_code;
+mod rate_limiter;
+mod rp_id_pin;
+mod session_store;
+mod shutdown;
+mod signature_verification;
 mod webauthn;
+#[cfg(target_os = "android")]
+mod webauthn_android;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod webauthn_apple;
+mod webauthn_cbor;
+mod webauthn_config;
+mod webauthn_events;
+#[cfg(target_os = "linux")]
+mod webauthn_linux;
+mod webauthn_platform;
+mod webauthn_state;
+#[cfg(target_os = "windows")]
+mod webauthn_windows;
+
+#[cfg(all(feature = "schema-export", not(debug_assertions)))]
+compile_error!("the schema-export feature is for development builds; release builds must not enable it");
+
+// What the integration tests need to stand up the commands on a mock app, the
+// authenticator data parser, signature check, credential ID and site ID checks
+// they run against sample data, the QR encoder they decode again, the ceremony
+// watchdog they run without a platform, the audit log check and the log level
+// filters; the fuzz targets in fuzz/ use the parser and the challenge generator
+pub use audit_log::verify_audit_log;
+pub use capability_filter::CapabilityTier;
+pub use ceremony_watchdog::{watched_channel, CeremonyReply};
+pub use challenge_store::{ChallengeConfig, ChallengeRng};
+pub use client_data::ClientDataJson;
+pub use credential_id::{AuthenticatorType, CredentialId};
+pub use credential_store::CredentialStore;
+#[doc(hidden)]
+pub use device_name::make_unique_device_name;
+#[doc(hidden)]
+pub use entropy_check::{byte_frequency_chi_squared, is_plausibly_random};
+#[cfg(feature = "schema-export")]
+pub use json_schema::webauthn_json_schema;
+pub use log_event::{OperationContext, Redacted, WebAuthnLogConfig};
+pub use pin_cache::{PinCache, SecretPin};
+pub use platform_retry::RetryConfig;
+#[doc(hidden)]
+pub use qr_code::QrCode;
+pub use rate_limiter::RateLimitConfig;
+pub use signature_verification::verify_signature;
+pub use webauthn::{generate_challenge, validate_site_id, Builder as WebAuthnBuilder, SiteAuthConfig, WebAuthnError};
+pub use webauthn_cbor::{
+  parse_authenticator_data, parse_cose_key, AuthenticatorData, AuthenticatorDataFlags, CoseKey, CoseKeyParameters,
+};
+pub use webauthn_config::WebAuthnConfig;
+pub use webauthn_platform::MockPlatform;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-  tauri::Builder::default()
+  let builder = tauri::Builder::default()
     .plugin(tauri_plugin_http::init())
     .plugin(tauri_plugin_mcp::Builder.build())
     .plugin(tauri_plugin_fs::init())
     .plugin(tauri_plugin_dialog::init())
-    .invoke_handler(tauri::generate_handler![
-      webauthn::is_webauthn_available,
-      webauthn::register_passkey,
-      webauthn::authenticate_passkey
-    ])
+    // The RP ID comes from [package.metadata.webauthn] in Cargo.toml
+    .plugin(webauthn::Builder::new().build());
 
+  with_commands(builder)
     .setup(|app| {
+      let data_dir = app.path().app_data_dir()?;
+      app.manage(CredentialStore::new(data_dir));
+      credential_refresh::spawn_credential_refresh_task(app.handle().clone());
+      // macOS and iOS deliver deep links as RunEvent::Opened instead
+      #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+      deep_link::handle_launch_args(app.handle());
+
       if cfg!(debug_assertions) {
         app.handle().plugin(
           tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
+            // Quiet the dependencies; this crate's own modules log in full
+            .level(log::LevelFilter::Warn)
+            .level_for("app_lib", log::LevelFilter::Debug)
+            .format(|out, message, record| {
+              out.finish(format_args!(
+                "{}[{}][{}] {}{}",
+                chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                record.target(),
+                record.level(),
+                message,
+                Fields(record.key_values())
+              ))
+            })
             .build(),
         )?;
       }
       Ok(())
     })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(deep_link::handle_run_event);
+}
+
+/// Register the app's commands on `builder`
+///
+/// The commands expect the `webauthn` plugin and a `CredentialStore`; `run`
+/// sets those up for the real app.
+pub fn with_commands<R: Runtime>(builder: tauri::Builder<R>) -> tauri::Builder<R> {
+  builder.invoke_handler(tauri::generate_handler![
+    webauthn::is_webauthn_available,
+    webauthn::get_webauthn_capabilities,
+    webauthn::get_plugin_version,
+    webauthn::get_rp_id,
+    webauthn::set_rp_id,
+    challenge_store::create_challenge,
+    challenge_store::configure_challenge,
+    challenge_store::rotate_challenge_seed,
+    webauthn::register_passkey,
+    webauthn::upgrade_site_to_passkey,
+    webauthn::authenticate_passkey,
+    webauthn::delete_passkey,
+    webauthn::unlock_credential,
+    webauthn::update_credential_display_name,
+    webauthn::discover_credentials,
+    webauthn::verify_stored_credential,
+    webauthn::get_credential_last_used,
+    webauthn::should_reenroll,
+    webauthn::submit_security_key_pin,
+    pin_cache::clear_pin_cache,
+    hybrid_auth::initiate_hybrid_auth,
+    hybrid_auth::poll_hybrid_auth,
+    credential_store::save_site_credential,
+    credential_store::load_site_credential,
+    credential_store::delete_site_credential,
+    credential_store::list_site_credentials,
+    credential_store::list_protected_sites,
+    credential_store::get_login_hints,
+    credential_store::remove_site_credential,
+    credential_backup::export_credentials,
+    credential_backup::import_credentials,
+    credential_sync::request_sync_offer,
+    credential_sync::generate_sync_offer,
+    credential_sync::accept_sync_offer,
+    credential_migration::migrate_credentials_from_frontend,
+    credential_refresh::cancel_credential_refresh,
+    credential_purge::purge_orphaned_credentials,
+    emergency_wipe::emergency_wipe,
+    session_store::get_site_auth_status,
+    session_store::logout_site,
+    session_store::logout_all_sites,
+    session_store::set_site_session_ttl,
+    session_store::set_grace_period,
+    app_lock::configure_app_lock,
+    app_lock::record_activity,
+    health_check::webauthn_health_check,
+    clipboard_guard::check_clipboard_write,
+    metrics::get_webauthn_metrics,
+    metrics::reset_webauthn_metrics,
+    audit_log::get_audit_log_path,
+    onboarding::get_onboarding_state,
+    onboarding::reset_onboarding,
+    signature_verification::verify_assertion,
+    #[cfg(feature = "schema-export")]
+    json_schema::export_webauthn_json_schema
+  ])
+}
+
+/// A log record's structured fields, written as ` key=value` pairs after the message
+struct Fields<'a>(&'a dyn Source);
+
+impl fmt::Display for Fields<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    struct Writer<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+    impl<'kvs> VisitSource<'kvs> for Writer<'_, '_> {
+      fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        write!(self.0, " {}={}", key, value)?;
+        Ok(())
+      }
+    }
+
+    self.0.visit(&mut Writer(f)).map_err(|_| fmt::Error)
+  }
 }
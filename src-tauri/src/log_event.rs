@@ -0,0 +1,260 @@
+// src-tauri/src/log_event.rs
+
+//! Machine-readable companions to the WebAuthn log lines.
+//!
+//! `webauthn_log!` logs a message as `log::log!` would and attaches a
+//! `WebAuthnLogEvent`, serialized as JSON, as its `event` field, so a log
+//! collector can aggregate outcomes without parsing the message. The message
+//! itself is left as it was for people reading the logs. The JSON shape is
+//! documented in `LOG_FORMAT.md` and only grows: fields are never renamed or
+//! removed, and new operations or error codes may appear.
+//!
+//! Which lines are written depends on the operation they belong to rather than
+//! on one level for the whole plugin. A `WebAuthnLogConfig` sets a level for
+//! lines about challenges, authentications and registrations, and
+//! `webauthn_log!` drops lines more verbose than the level of the current
+//! thread's `OperationContext`. Lines reporting a failure are kept whenever
+//! they are at least as severe as `error_level`, and in development builds
+//! challenge lines are kept down to `debug`, so both stay visible however
+//! quiet the operations are set.
+
+use std::cell::Cell;
+use std::fmt;
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+use log::{Level, LevelFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::webauthn::WebAuthnError;
+
+/// What a logged event concerns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogOperation {
+    Authentication,
+    Registration,
+    PasskeyDeletion,
+    ReenrollmentCheck,
+    CredentialDiscovery,
+    RpIdOverride,
+    /// A panic caught by the plugin's panic hook
+    Panic,
+}
+
+/// The structured fields of one log line; unknown fields are null
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct WebAuthnLogEvent<'a> {
+    pub operation: LogOperation,
+    pub site_id: Option<&'a str>,
+    /// Set on the line that reports how the operation ended
+    pub success: Option<bool>,
+    /// Time from the start of the operation, on the line that reports how it ended
+    pub duration_ms: Option<u64>,
+    /// The `code` the frontend receives for the error, on failures
+    pub error_code: Option<&'static str>,
+}
+
+impl<'a> WebAuthnLogEvent<'a> {
+    pub fn new(operation: LogOperation) -> Self {
+        Self {
+            operation,
+            site_id: None,
+            success: None,
+            duration_ms: None,
+            error_code: None,
+        }
+    }
+
+    pub fn site(mut self, site_id: &'a str) -> Self {
+        self.site_id = Some(site_id);
+        self
+    }
+
+    /// Mark the line as reporting that the operation succeeded
+    pub fn succeeded(mut self) -> Self {
+        self.success = Some(true);
+        self
+    }
+
+    /// Mark the line as reporting that the operation failed with `error`
+    pub fn failed(mut self, error: &WebAuthnError) -> Self {
+        self.success = Some(false);
+        self.error_code = Some(error.code());
+        self
+    }
+
+    /// How long the operation took
+    pub fn took(mut self, duration: Duration) -> Self {
+        self.duration_ms = Some(duration.as_millis() as u64);
+        self
+    }
+}
+
+/// The event as a single line of JSON
+impl fmt::Display for WebAuthnLogEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
+
+/// The kind of operation a thread is running, which picks the level
+/// `webauthn_log!` filters at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationContext {
+    /// Issuing a challenge or handing one to the authenticator
+    Challenge,
+    Authentication,
+    Registration,
+}
+
+thread_local! {
+    static CURRENT_CONTEXT: Cell<Option<OperationContext>> = const { Cell::new(None) };
+}
+
+impl OperationContext {
+    /// Run `f` with this as the current thread's context
+    ///
+    /// Only for synchronous code: an async task can move to another thread at
+    /// an `.await`, so a context must not be held across one. Lines logged
+    /// outside any scope take the context of their event's operation.
+    pub fn scope<T>(self, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT_CONTEXT.with(|current| current.replace(Some(self)));
+        let _restore = scopeguard::guard(previous, |previous| CURRENT_CONTEXT.with(|current| current.set(previous)));
+        f()
+    }
+
+    /// The current thread's context, or `None` outside a `scope`
+    pub fn current() -> Option<Self> {
+        CURRENT_CONTEXT.with(Cell::get)
+    }
+
+    fn of(operation: LogOperation) -> Option<Self> {
+        match operation {
+            LogOperation::Authentication => Some(Self::Authentication),
+            LogOperation::Registration => Some(Self::Registration),
+            _ => None,
+        }
+    }
+}
+
+/// Log levels by kind of operation, the `[log]` table of `webauthn.toml`
+///
+/// Each level is the most verbose one logged for its operation, as in
+/// `"info"` or `"off"`. Lines of other operations, such as passkey deletions
+/// and panics, are not filtered here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebAuthnLogConfig {
+    /// Lines about challenges, such as the challenge a ceremony was given;
+    /// development builds log them down to `debug` whatever this says
+    pub challenge_level: LevelFilter,
+    pub authentication_level: LevelFilter,
+    pub registration_level: LevelFilter,
+    /// Lines reporting a failure are logged when at least this severe,
+    /// whatever the level of their operation
+    pub error_level: LevelFilter,
+}
+
+impl WebAuthnLogConfig {
+    const DEFAULT: Self = Self {
+        challenge_level: LevelFilter::Info,
+        authentication_level: LevelFilter::Info,
+        registration_level: LevelFilter::Info,
+        error_level: LevelFilter::Error,
+    };
+
+    /// The level set for `context`
+    pub fn level_for(&self, context: OperationContext) -> LevelFilter {
+        match context {
+            // Challenges are what a replay turns on, so they are always
+            // there to read while developing
+            OperationContext::Challenge if cfg!(debug_assertions) => self.challenge_level.max(LevelFilter::Debug),
+            OperationContext::Challenge => self.challenge_level,
+            OperationContext::Authentication => self.authentication_level,
+            OperationContext::Registration => self.registration_level,
+        }
+    }
+
+    /// Whether a line at `level` is logged in `context`; `failure` marks lines
+    /// that report an operation failing
+    pub fn enabled(&self, context: Option<OperationContext>, level: Level, failure: bool) -> bool {
+        (failure && level <= self.error_level) || context.map_or(true, |context| level <= self.level_for(context))
+    }
+}
+
+impl Default for WebAuthnLogConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The levels `webauthn_log!` filters at, set as the plugin starts
+static LOG_CONFIG: RwLock<WebAuthnLogConfig> = RwLock::new(WebAuthnLogConfig::DEFAULT);
+
+pub(crate) fn configure(config: WebAuthnLogConfig) {
+    *LOG_CONFIG.write().unwrap_or_else(PoisonError::into_inner) = config;
+}
+
+/// Whether `webauthn_log!` writes a line at `level` with `event`
+pub(crate) fn enabled(event: &WebAuthnLogEvent<'_>, level: Level) -> bool {
+    let context = OperationContext::current().or_else(|| OperationContext::of(event.operation));
+    LOG_CONFIG
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .enabled(context, level, event.success == Some(false))
+}
+
+/// Characters of a redacted value kept at its start and at its end
+const REDACTED_PREFIX_CHARS: usize = 8;
+const REDACTED_SUFFIX_CHARS: usize = 4;
+
+/// A credential ID or public key, printed for the logs as its first 8 and last
+/// 4 characters around `****`, e.g. `AbCdEfGh****wxyz`
+///
+/// Values shorter than 12 characters print as `****` alone. Credential IDs
+/// run well past 12 characters of base64url, so most of theirs stays hidden.
+#[derive(Clone, Copy)]
+pub struct Redacted<T>(pub T);
+
+impl<T: AsRef<str>> fmt::Display for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.0.as_ref();
+        let length = value.chars().count();
+        if length < REDACTED_PREFIX_CHARS + REDACTED_SUFFIX_CHARS {
+            return f.write_str("****");
+        }
+        let prefix: String = value.chars().take(REDACTED_PREFIX_CHARS).collect();
+        let suffix: String = value.chars().skip(length - REDACTED_SUFFIX_CHARS).collect();
+        write!(f, "{}****{}", prefix, suffix)
+    }
+}
+
+impl<T: AsRef<str>> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Log a message with a `WebAuthnLogEvent` attached as its `event` field
+///
+/// Takes a `log::Level` and the event, then whatever `log::log!` takes after
+/// its level: `webauthn_log!(Level::Info, event, operation_id:% = id; "Signed in to {}", site_id)`.
+/// The line is dropped when the `WebAuthnLogConfig` level of its operation
+/// context is quieter than `level`.
+macro_rules! webauthn_log {
+    ($level:expr, $event:expr; $($arg:tt)+) => {{
+        let (level, event) = ($level, $event);
+        if $crate::log_event::enabled(&event, level) {
+            log::log!(level, event:% = event; $($arg)+)
+        }
+    }};
+    ($level:expr, $event:expr, $($key:tt $(:$capture:tt)? = $value:expr),+; $($arg:tt)+) => {{
+        let (level, event) = ($level, $event);
+        if $crate::log_event::enabled(&event, level) {
+            log::log!(level, $($key $(:$capture)? = $value,)+ event:% = event; $($arg)+)
+        }
+    }};
+}
+
+pub(crate) use webauthn_log;
@@ -0,0 +1,147 @@
+// src-tauri/src/metrics.rs
+
+//! Success and failure counts for the passkey ceremonies.
+//!
+//! The counters live in `WebAuthnState` from launch and are never persisted.
+//! An attempt is counted when a ceremony starts, or an import begins, and its
+//! outcome when it finishes. Requests refused before that, e.g. for invalid
+//! input, are not attempts; rate-limited attempts and expired challenges have
+//! counters of their own. Only totals are needed, so the counters use `Relaxed`
+//! ordering and a snapshot taken while commands run may be a little behind.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+use tauri::{command, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::webauthn::WebAuthnError;
+use crate::webauthn_state::WebAuthnState;
+
+/// Counters since launch or the last reset
+#[derive(Default)]
+pub struct WebAuthnMetrics {
+    registration_attempts: AtomicU64,
+    registration_successes: AtomicU64,
+    registration_failures: AtomicU64,
+    authentication_attempts: AtomicU64,
+    authentication_successes: AtomicU64,
+    authentication_failures: AtomicU64,
+    rate_limit_hits: AtomicU64,
+    challenge_expirations: AtomicU64,
+}
+
+/// The counters at one point in time, from `get_webauthn_metrics`
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct WebAuthnMetricsSnapshot {
+    #[serde(rename = "registrationAttempts")]
+    pub registration_attempts: u64,
+    #[serde(rename = "registrationSuccesses")]
+    pub registration_successes: u64,
+    #[serde(rename = "registrationFailures")]
+    pub registration_failures: u64,
+    #[serde(rename = "authenticationAttempts")]
+    pub authentication_attempts: u64,
+    #[serde(rename = "authenticationSuccesses")]
+    pub authentication_successes: u64,
+    #[serde(rename = "authenticationFailures")]
+    pub authentication_failures: u64,
+    #[serde(rename = "rateLimitHits")]
+    pub rate_limit_hits: u64,
+    #[serde(rename = "challengeExpirations")]
+    pub challenge_expirations: u64,
+}
+
+fn increment(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+impl WebAuthnMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn registration_started(&self) {
+        increment(&self.registration_attempts);
+    }
+
+    pub(crate) fn registration_finished(&self, succeeded: bool) {
+        increment(if succeeded {
+            &self.registration_successes
+        } else {
+            &self.registration_failures
+        });
+    }
+
+    pub(crate) fn authentication_started(&self) {
+        increment(&self.authentication_attempts);
+    }
+
+    pub(crate) fn authentication_finished(&self, succeeded: bool) {
+        increment(if succeeded {
+            &self.authentication_successes
+        } else {
+            &self.authentication_failures
+        });
+    }
+
+    pub(crate) fn rate_limit_hit(&self) {
+        increment(&self.rate_limit_hits);
+    }
+
+    pub(crate) fn challenge_expired(&self) {
+        increment(&self.challenge_expirations);
+    }
+
+    pub fn snapshot(&self) -> WebAuthnMetricsSnapshot {
+        let read = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        WebAuthnMetricsSnapshot {
+            registration_attempts: read(&self.registration_attempts),
+            registration_successes: read(&self.registration_successes),
+            registration_failures: read(&self.registration_failures),
+            authentication_attempts: read(&self.authentication_attempts),
+            authentication_successes: read(&self.authentication_successes),
+            authentication_failures: read(&self.authentication_failures),
+            rate_limit_hits: read(&self.rate_limit_hits),
+            challenge_expirations: read(&self.challenge_expirations),
+        }
+    }
+
+    /// Set every counter back to zero
+    pub fn reset(&self) {
+        for counter in [
+            &self.registration_attempts,
+            &self.registration_successes,
+            &self.registration_failures,
+            &self.authentication_attempts,
+            &self.authentication_successes,
+            &self.authentication_failures,
+            &self.rate_limit_hits,
+            &self.challenge_expirations,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Report the ceremony counters since launch or the last reset
+#[command]
+pub async fn get_webauthn_metrics<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+) -> Result<WebAuthnMetricsSnapshot, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    Ok(state.metrics.snapshot())
+}
+
+/// Set the ceremony counters back to zero
+#[command]
+pub async fn reset_webauthn_metrics<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    state.metrics.reset();
+    log::info!("Reset the WebAuthn metrics");
+    Ok(())
+}
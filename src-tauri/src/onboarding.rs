@@ -0,0 +1,182 @@
+// src-tauri/src/onboarding.rs
+
+//! Where a new user is in setting up their first passkey.
+//!
+//! `register_passkey` moves the state along: the first ceremony checks the
+//! platform can create passkeys (`platform-check-done`), marks the ceremony as
+//! under way (`registration-in-progress`) and, once a passkey is saved, marks
+//! onboarding `complete`. A failed ceremony goes back a step, so the next one
+//! picks up from there. The state is kept in a file next to the credential
+//! store and survives restarts; a ceremony cut short by quitting the app is
+//! read back as `platform-check-done`. `reset_onboarding` starts over.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+use tauri::{command, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_store::CredentialStore;
+use crate::webauthn::WebAuthnError;
+use crate::webauthn_state::WebAuthnState;
+
+const ONBOARDING_FILE_NAME: &str = "onboarding.json";
+
+/// A step of the first passkey setup, from `get_onboarding_state`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnboardingState {
+    #[default]
+    NotStarted,
+    /// The platform can create passkeys
+    PlatformCheckDone,
+    /// The first registration ceremony is under way
+    RegistrationInProgress,
+    /// A passkey was registered
+    Complete,
+}
+
+/// Contents of the onboarding file
+#[derive(Serialize, Deserialize)]
+struct OnboardingFile {
+    state: OnboardingState,
+}
+
+/// The onboarding state, read from its file the first time it is needed
+#[derive(Default)]
+pub struct Onboarding {
+    current: Mutex<Option<OnboardingState>>,
+}
+
+impl Onboarding {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current state, reading it from the store's directory if need be
+    pub fn get(&self, store: &CredentialStore) -> Result<OnboardingState, WebAuthnError> {
+        let mut current = self.lock()?;
+        Ok(*current.get_or_insert_with(|| read_state(&onboarding_path(store))))
+    }
+
+    /// Start over from `NotStarted`
+    pub fn reset(&self, store: &CredentialStore) -> Result<(), WebAuthnError> {
+        let mut current = self.lock()?;
+        match fs::remove_file(onboarding_path(store)) {
+            Ok(()) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(WebAuthnError::InternalError(format!(
+                    "Failed to reset the onboarding state: {}",
+                    error
+                )))
+            }
+        }
+        *current = Some(OnboardingState::NotStarted);
+        Ok(())
+    }
+
+    /// Advance as a registration ceremony starts, checking the platform with
+    /// `platform_available` if that was not done yet
+    pub(crate) fn registration_started(&self, store: &CredentialStore, platform_available: impl FnOnce() -> bool) {
+        self.transition(store, |state| match state {
+            OnboardingState::NotStarted if platform_available() => OnboardingState::PlatformCheckDone,
+            OnboardingState::NotStarted => {
+                log::info!("Onboarding is waiting for a platform that can create passkeys");
+                state
+            }
+            other => other,
+        });
+        self.transition(store, |state| match state {
+            OnboardingState::PlatformCheckDone => OnboardingState::RegistrationInProgress,
+            other => other,
+        });
+    }
+
+    /// Advance as a registration ceremony ends
+    pub(crate) fn registration_finished(&self, store: &CredentialStore, succeeded: bool) {
+        self.transition(store, |state| match state {
+            _ if succeeded => OnboardingState::Complete,
+            OnboardingState::RegistrationInProgress => OnboardingState::PlatformCheckDone,
+            other => other,
+        });
+    }
+
+    /// Apply `next` to the current state and save the result if it changed
+    ///
+    /// A failure to save is logged; onboarding never fails a ceremony.
+    fn transition(&self, store: &CredentialStore, next: impl FnOnce(OnboardingState) -> OnboardingState) {
+        let Ok(mut current) = self.lock() else {
+            return;
+        };
+        let path = onboarding_path(store);
+        let state = *current.get_or_insert_with(|| read_state(&path));
+        let next = next(state);
+        if next == state {
+            return;
+        }
+        log::debug!("Onboarding moved from {:?} to {:?}", state, next);
+        *current = Some(next);
+        if let Err(error) = write_state(&path, next) {
+            log::warn!("Failed to save the onboarding state: {}", error);
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Option<OnboardingState>>, WebAuthnError> {
+        self.current
+            .lock()
+            .map_err(|_| WebAuthnError::InternalError("Onboarding lock is poisoned".to_string()))
+    }
+}
+
+fn onboarding_path(store: &CredentialStore) -> PathBuf {
+    store.data_dir().join(ONBOARDING_FILE_NAME)
+}
+
+/// The saved state; `NotStarted` if there is none or the file is unreadable
+fn read_state(path: &Path) -> OnboardingState {
+    let state = fs::read(path)
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<OnboardingFile>(&contents).ok())
+        .map(|file| file.state)
+        .unwrap_or_default();
+    // The app quit during the ceremony
+    if state == OnboardingState::RegistrationInProgress {
+        OnboardingState::PlatformCheckDone
+    } else {
+        state
+    }
+}
+
+fn write_state(path: &Path, state: OnboardingState) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec(&OnboardingFile { state })?)
+}
+
+/// Report how far the first passkey setup got
+#[command]
+pub async fn get_onboarding_state<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+) -> Result<OnboardingState, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    state.onboarding.get(&store)
+}
+
+/// Start onboarding over, e.g. to show the setup flow again
+#[command]
+pub async fn reset_onboarding<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    state.onboarding.reset(&store)?;
+    log::info!("Reset onboarding");
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// src-tauri/src/panic_hook.rs
+
+//! Panics reported to the log and the audit log before the app goes down.
+//!
+//! A panic in the platform code, e.g. an Objective-C exception bridged wrong,
+//! otherwise leaves nothing but the default handler's line on stderr, which
+//! nobody sees in a GUI app. The plugin installs a panic hook that reports each
+//! panic as an error line with a `panic` `WebAuthnLogEvent` and as a `panic`
+//! audit log record, then runs the hook installed before it.
+//!
+//! The panicking thread may hold any lock, the logger's and the audit log's
+//! included, so the hook takes none: it hands the report to a thread of its own
+//! over a channel and waits a moment for it to be written. When that thread is
+//! stuck on a lock the panicking thread holds, the wait runs out and the panic
+//! goes on unreported. Panics while another is being reported, those of the
+//! reporting thread among them, are left to the previous hook alone.
+
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+use log::Level;
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::audit_log::AuditEvent;
+use crate::credential_store::CredentialStore;
+use crate::log_event::{webauthn_log, LogOperation, WebAuthnLogEvent};
+use crate::webauthn_state::WebAuthnState;
+
+/// How long a panicking thread waits for its report to be written
+const REPORT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Set while a panic is being reported
+static REPORTING: AtomicBool = AtomicBool::new(false);
+
+static INSTALL: Once = Once::new();
+
+struct PanicReport {
+    thread: Option<String>,
+    message: String,
+    location: Option<String>,
+    /// Signalled once the report is written
+    written: Sender<()>,
+}
+
+/// Install the panic hook, reporting through `app`; later calls do nothing
+pub(crate) fn install<R: Runtime>(app: AppHandle<R>) {
+    INSTALL.call_once(|| {
+        let (reports, received) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("webauthn-panic-reporter".to_string())
+            .spawn(move || report(app, received));
+        if let Err(error) = spawned {
+            log::warn!("Failed to start the panic reporter, panics will not be logged: {}", error);
+            return;
+        }
+
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !REPORTING.swap(true, Ordering::SeqCst) {
+                let payload = info.payload();
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "Box<dyn Any>".to_string());
+                let (written, done) = mpsc::channel();
+                let report = PanicReport {
+                    thread: thread::current().name().map(str::to_string),
+                    message,
+                    location: info.location().map(|location| location.to_string()),
+                    written,
+                };
+                if reports.send(report).is_ok() {
+                    let _ = done.recv_timeout(REPORT_TIMEOUT);
+                }
+                REPORTING.store(false, Ordering::SeqCst);
+            }
+            previous(info);
+        }));
+    });
+}
+
+fn report<R: Runtime>(app: AppHandle<R>, reports: Receiver<PanicReport>) {
+    for report in reports {
+        webauthn_log!(
+            Level::Error,
+            WebAuthnLogEvent::new(LogOperation::Panic);
+            "Thread {} panicked at {}: {}",
+            report.thread.as_deref().unwrap_or("<unnamed>"),
+            report.location.as_deref().unwrap_or("an unknown location"),
+            report.message
+        );
+        if let (Some(state), Some(store)) = (app.try_state::<WebAuthnState>(), app.try_state::<CredentialStore>()) {
+            state.audit_log.record(
+                &store,
+                AuditEvent::Panic {
+                    thread: report.thread,
+                    message: report.message,
+                    location: report.location,
+                },
+            );
+        }
+        let _ = report.written.send(());
+    }
+}
@@ -0,0 +1,122 @@
+// src-tauri/src/pin_cache.rs
+
+//! Security key PINs kept for a few minutes after they worked.
+//!
+//! CTAP2 keys with a client PIN need it for every ceremony. Once a PIN entered
+//! in the PIN window gets a ceremony through, it is kept for the key it was
+//! entered for and supplied to the next ceremonies on that key without asking,
+//! until it expires (5 minutes by default), the key rejects it, or
+//! `clear_pin_cache` is called. PINs are wiped from memory once they are
+//! evicted and never show up in debug output.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use secrecy::{ExposeSecret, SecretString};
+use tauri::{command, Runtime, State, WebviewWindow};
+use zeroize::Zeroizing;
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::webauthn::WebAuthnError;
+use crate::webauthn_state::WebAuthnState;
+
+/// How long a PIN is kept, unless configured otherwise
+pub const DEFAULT_PIN_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A PIN, zeroed on drop and redacted when debug-printed
+#[derive(Clone)]
+pub struct SecretPin(SecretString);
+
+impl SecretPin {
+    /// Copies `pin` into a `SecretString`; `pin` is wiped as it is dropped
+    pub fn new(pin: Zeroizing<String>) -> Self {
+        Self(SecretString::from(pin.as_str()))
+    }
+
+    pub fn expose(&self) -> &str {
+        self.0.expose_secret()
+    }
+}
+
+impl fmt::Debug for SecretPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretPin([REDACTED])")
+    }
+}
+
+struct CachedPin {
+    pin: SecretPin,
+    cached_at: Instant,
+}
+
+/// PINs that worked recently, keyed by the security key they were entered for
+pub struct PinCache {
+    pins: Mutex<HashMap<String, CachedPin>>,
+    ttl: Duration,
+}
+
+impl Default for PinCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PIN_CACHE_TTL)
+    }
+}
+
+impl PinCache {
+    /// A cache keeping PINs for `ttl`; `Duration::ZERO` keeps none
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            pins: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// The PIN kept for `authenticator`, unless it expired
+    pub fn get(&self, authenticator: &str) -> Option<SecretPin> {
+        let mut pins = self.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        pins.retain(|_, cached| cached.cached_at.elapsed() < self.ttl);
+        pins.get(authenticator).map(|cached| cached.pin.clone())
+    }
+
+    /// Keep a PIN that `authenticator` accepted
+    pub fn insert(&self, authenticator: &str, pin: SecretPin) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut pins = self.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        pins.insert(
+            authenticator.to_string(),
+            CachedPin {
+                pin,
+                cached_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Forget the PIN of `authenticator`, e.g. because it rejected it
+    pub fn evict(&self, authenticator: &str) {
+        let mut pins = self.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        pins.remove(authenticator);
+    }
+
+    /// Forget every PIN, returning how many were kept
+    pub fn clear(&self) -> usize {
+        let mut pins = self.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = pins.len();
+        pins.clear();
+        count
+    }
+}
+
+/// Forget every security key PIN, so the next ceremonies ask for it again
+#[command]
+pub async fn clear_pin_cache<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    let cleared = state.pin_cache.clear();
+    log::info!("Cleared {} cached security key PINs", cleared);
+    Ok(())
+}
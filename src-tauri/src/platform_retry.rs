@@ -0,0 +1,95 @@
+// src-tauri/src/platform_retry.rs
+
+//! Assertions retried when the platform fails in a way that may pass.
+//!
+//! Each platform maps the failures of its passkey API that are worth another
+//! try, e.g. Credential Manager being interrupted or a security key dropping a
+//! packet, to `PlatformUnavailable`. `authenticate_passkey` calls the platform
+//! again after those, waiting `initial_backoff_ms` before the second attempt
+//! and `backoff_multiplier` times longer before each one after, until
+//! `max_attempts` were made. Every other error, the user cancelling or no
+//! passkey being found among them, is returned at once. The retries stay within
+//! the ceremony's timeout.
+
+use std::future::Future;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::webauthn::WebAuthnError;
+
+/// Attempts made in all, unless configured otherwise
+pub const DEFAULT_MAX_ATTEMPTS: u8 = 3;
+
+/// Wait before the second attempt, unless configured otherwise
+pub const DEFAULT_INITIAL_BACKOFF_MS: u64 = 100;
+
+/// How much longer each wait is than the one before, unless configured otherwise
+pub const DEFAULT_BACKOFF_MULTIPLIER: f64 = 2.0;
+
+/// How many times a platform call is attempted, and how long to wait in between
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Attempts made in all; 1 never retries
+    pub max_attempts: u8,
+    pub initial_backoff_ms: u64,
+    pub backoff_multiplier: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff_ms: DEFAULT_INITIAL_BACKOFF_MS,
+            backoff_multiplier: DEFAULT_BACKOFF_MULTIPLIER,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// At least one attempt, and waits that do not shrink
+    pub(crate) fn is_valid(&self) -> bool {
+        self.max_attempts > 0 && self.backoff_multiplier.is_finite() && self.backoff_multiplier >= 1.0
+    }
+
+    /// The wait after failed attempt `attempt`, counting from 1
+    fn backoff(&self, attempt: u8) -> Duration {
+        let millis = self.initial_backoff_ms as f64 * self.backoff_multiplier.powi(i32::from(attempt) - 1);
+        // Saturates rather than overflowing; the ceremony's timeout ends the wait anyway
+        Duration::from_millis(millis as u64)
+    }
+}
+
+/// A failure worth calling the platform again for
+pub(crate) fn is_transient(error: &WebAuthnError) -> bool {
+    matches!(error, WebAuthnError::PlatformUnavailable(_))
+}
+
+/// Run `call` until it succeeds, fails for good or has been attempted
+/// `max_attempts` times, returning its last result and the attempts made
+///
+/// `on_retry` is told of each transient failure that is retried, with the
+/// attempt that failed and the wait before the next.
+pub(crate) async fn with_retries<T, F, Fut>(
+    config: &RetryConfig,
+    mut call: F,
+    mut on_retry: impl FnMut(u8, Duration, &WebAuthnError),
+) -> (Result<T, WebAuthnError>, u8)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, WebAuthnError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match call().await {
+            Err(error) if is_transient(&error) && attempt < config.max_attempts => {
+                let backoff = config.backoff(attempt);
+                on_retry(attempt, backoff, &error);
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            result => return (result, attempt),
+        }
+    }
+}
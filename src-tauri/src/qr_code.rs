@@ -0,0 +1,75 @@
+// src-tauri/src/qr_code.rs
+
+//! QR codes for the links another device scans.
+//!
+//! The `qrcode` crate encodes them at error correction level M, in the smallest
+//! version that fits; byte mode holds up to 2331 bytes. `render_png` draws the
+//! code as a grayscale PNG with the four-module quiet zone around it.
+
+use qrcode::types::QrError;
+use qrcode::{Color, EcLevel};
+
+use crate::webauthn::WebAuthnError;
+
+/// Pixels per module in the rendered PNG
+const MODULE_PIXELS: usize = 8;
+
+/// Light modules around the code, as the standard asks for
+const QUIET_ZONE_MODULES: usize = 4;
+
+/// A QR code's modules, `true` being dark
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encode `data` at error correction level M, refusing data longer than
+    /// the largest code holds
+    pub fn encode(data: &[u8]) -> Result<Self, WebAuthnError> {
+        let code = qrcode::QrCode::with_error_correction_level(data, EcLevel::M).map_err(|error| match error {
+            QrError::DataTooLong => {
+                WebAuthnError::InvalidInput(format!("QR code payload of {} bytes is too long", data.len()))
+            }
+            error => WebAuthnError::InternalError(format!("Failed to encode the QR code: {}", error)),
+        })?;
+        Ok(Self {
+            size: code.width(),
+            modules: code.into_colors().into_iter().map(|color| color == Color::Dark).collect(),
+        })
+    }
+
+    /// Whether the module `x` from the left and `y` from the top is dark
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    /// Draw the code as an 8-bit grayscale PNG, 8 pixels to a module
+    pub fn render_png(&self) -> Result<Vec<u8>, WebAuthnError> {
+        let modules = self.size + 2 * QUIET_ZONE_MODULES;
+        let pixels = modules * MODULE_PIXELS;
+        let mut image = vec![0xFFu8; pixels * pixels];
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if !self.is_dark(x, y) {
+                    continue;
+                }
+                let left = (x + QUIET_ZONE_MODULES) * MODULE_PIXELS;
+                let top = (y + QUIET_ZONE_MODULES) * MODULE_PIXELS;
+                for row in top..top + MODULE_PIXELS {
+                    image[row * pixels + left..row * pixels + left + MODULE_PIXELS].fill(0);
+                }
+            }
+        }
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, pixels as u32, pixels as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&image))
+            .map_err(|e| WebAuthnError::InternalError(format!("Failed to render the QR code: {}", e)))?;
+        Ok(png)
+    }
+}
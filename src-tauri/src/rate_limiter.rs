@@ -0,0 +1,131 @@
+// src-tauri/src/rate_limiter.rs
+
+//! Throttling of authentication attempts.
+//!
+//! `authenticate_passkey` records every attempt per site and refuses new ones
+//! once a site has used up its attempts within the sliding window, before any
+//! challenge is redeemed or prompt shown. The limits come from the
+//! `RateLimitConfig` given to the webauthn plugin's `Builder`, or the defaults below.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use serde::Serialize;
+
+use crate::webauthn::WebAuthnError;
+
+/// Attempts allowed per site within the window, unless configured otherwise
+pub const DEFAULT_MAX_ATTEMPTS: usize = 5;
+
+/// Length of the sliding window, unless configured otherwise
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+/// How many authentication attempts a site gets, and over how long
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    pub max_attempts: usize,
+    pub window: Duration,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+/// A site's recent attempts, as `webauthn_health_check` reports them
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct RateLimitEntry {
+    #[serde(rename = "siteId")]
+    pub site_id: String,
+    /// Attempts within the current window
+    pub attempts: usize,
+    /// Set while the site has no attempts left
+    #[serde(rename = "retryAfterSeconds")]
+    pub retry_after_seconds: Option<u64>,
+}
+
+/// Recent authentication attempts, keyed by site ID
+pub struct RateLimiter {
+    attempts: DashMap<String, VecDeque<Instant>>,
+    config: RateLimitConfig,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            attempts: DashMap::new(),
+            config,
+        }
+    }
+
+    /// Record an attempt for a site, or fail with `RateLimited` if it has none left
+    ///
+    /// A refused attempt is not recorded, so retrying once `retry_after_seconds`
+    /// has passed succeeds.
+    pub fn check(&self, site_id: &str) -> Result<(), WebAuthnError> {
+        let now = Instant::now();
+        let mut attempts = self.attempts.entry(site_id.to_string()).or_default();
+        while attempts
+            .front()
+            .is_some_and(|attempt| now.duration_since(*attempt) >= self.config.window)
+        {
+            attempts.pop_front();
+        }
+
+        if attempts.len() >= self.config.max_attempts {
+            return Err(WebAuthnError::RateLimited {
+                retry_after_seconds: self.retry_after_seconds(attempts.front(), now),
+            });
+        }
+
+        attempts.push_back(now);
+        Ok(())
+    }
+
+    /// Forget the attempts of every site
+    pub fn clear(&self) {
+        self.attempts.clear();
+    }
+
+    /// Every site with attempts in the current window, without recording any
+    pub fn entries(&self) -> Vec<RateLimitEntry> {
+        let now = Instant::now();
+        self.attempts
+            .iter()
+            .filter_map(|entry| {
+                let mut recent = entry
+                    .value()
+                    .iter()
+                    .filter(|attempt| now.duration_since(**attempt) < self.config.window)
+                    .peekable();
+                let oldest = recent.peek().copied();
+                let attempts = recent.count();
+                (attempts > 0).then(|| RateLimitEntry {
+                    site_id: entry.key().clone(),
+                    attempts,
+                    retry_after_seconds: (attempts >= self.config.max_attempts)
+                        .then(|| self.retry_after_seconds(oldest, now)),
+                })
+            })
+            .collect()
+    }
+
+    /// Seconds until the oldest attempt in the window drops out of it
+    fn retry_after_seconds(&self, oldest: Option<&Instant>, now: Instant) -> u64 {
+        let retry_after = oldest.map_or(self.config.window, |oldest| self.config.window - now.duration_since(*oldest));
+        // Round up so retrying after the advertised delay is never refused
+        retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
+    }
+}
@@ -0,0 +1,170 @@
+// src-tauri/src/rp_id_pin.rs
+
+//! The RP ID of release builds pinned to a hash supplied at build time.
+//!
+//! A release pipeline can set `SPARKTYPE_RP_ID_HASH` to the hex SHA-256 of the
+//! RP ID the app is meant to use. The crate then fails to compile unless
+//! `[package.metadata.webauthn] rp-id` hashes to it, and release builds refuse
+//! every ceremony whose RP ID does not, with `RpIdTampered` and before the
+//! platform is asked for anything, whether that RP ID came from Cargo.toml,
+//! the `Builder`, `webauthn.toml` or `set_rp_id`. An RP ID swapped in the
+//! sources or the config then stops the build or the ceremony instead of
+//! scoping passkeys to another domain. Without the variable nothing is pinned.
+//! Debug builds, which run against `localhost`, are not checked at runtime.
+
+use sha2::{Digest, Sha256};
+
+use crate::webauthn::WebAuthnError;
+
+/// SHA-256 of the RP ID release builds must use, when the build pinned one
+pub(crate) const PINNED_RP_ID_HASH: Option<[u8; 32]> = parse_hash(env!("SPARKTYPE_RP_ID_HASH"));
+
+/// Fail with `RpIdTampered` when something is pinned and `rp_id` does not hash to it
+pub(crate) fn check(rp_id: &str) -> Result<(), WebAuthnError> {
+    match PINNED_RP_ID_HASH {
+        Some(pinned) if Sha256::digest(rp_id.as_bytes())[..] != pinned[..] => {
+            log::error!(
+                "The WebAuthn RP ID {:?} does not match the SHA-256 this build was pinned to; refusing the ceremony",
+                rp_id
+            );
+            Err(WebAuthnError::RpIdTampered)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether `rp_id` hashes to `pinned`, or nothing is pinned; for checks when
+/// the crate compiles
+pub(crate) const fn matches_pin(pinned: Option<[u8; 32]>, rp_id: &str) -> bool {
+    let Some(pinned) = pinned else {
+        return true;
+    };
+    let hash = sha256(rp_id.as_bytes());
+    let mut i = 0;
+    while i < 32 {
+        if hash[i] != pinned[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// The 32 bytes behind 64 hex digits; `None` for an empty string
+const fn parse_hash(hex: &str) -> Option<[u8; 32]> {
+    let hex = hex.as_bytes();
+    if hex.is_empty() {
+        return None;
+    }
+    assert!(hex.len() == 64, "SPARKTYPE_RP_ID_HASH must be 64 hex digits");
+    let mut hash = [0u8; 32];
+    let mut i = 0;
+    while i < 32 {
+        hash[i] = hex_digit(hex[2 * i]) << 4 | hex_digit(hex[2 * i + 1]);
+        i += 1;
+    }
+    Some(hash)
+}
+
+const fn hex_digit(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        b'A'..=b'F' => digit - b'A' + 10,
+        _ => panic!("SPARKTYPE_RP_ID_HASH must be 64 hex digits"),
+    }
+}
+
+/// SHA-256 round constants (FIPS 180-4, 4.2.2)
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5, 0xd807aa98,
+    0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8,
+    0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819,
+    0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+    0xc67178f2,
+];
+
+/// SHA-256 of `message`, computable when the crate compiles, where `sha2` is
+/// not available
+const fn sha256(message: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+    // The message, a 1 bit, zeros and the bit length fill whole 64-byte blocks
+    let padded_len = (message.len() + 9).div_ceil(64) * 64;
+    let bit_len = (message.len() as u64).wrapping_mul(8);
+    let mut block_start = 0;
+    while block_start < padded_len {
+        let mut w = [0u32; 64];
+        let mut t = 0;
+        while t < 16 {
+            let mut word = 0u32;
+            let mut b = 0;
+            while b < 4 {
+                let index = block_start + 4 * t + b;
+                let byte = if index < message.len() {
+                    message[index]
+                } else if index == message.len() {
+                    0x80
+                } else if index >= padded_len - 8 {
+                    (bit_len >> (8 * (padded_len - 1 - index))) as u8
+                } else {
+                    0
+                };
+                word = word << 8 | byte as u32;
+                b += 1;
+            }
+            w[t] = word;
+            t += 1;
+        }
+        while t < 64 {
+            let s0 = w[t - 15].rotate_right(7) ^ w[t - 15].rotate_right(18) ^ (w[t - 15] >> 3);
+            let s1 = w[t - 2].rotate_right(17) ^ w[t - 2].rotate_right(19) ^ (w[t - 2] >> 10);
+            w[t] = w[t - 16].wrapping_add(s0).wrapping_add(w[t - 7]).wrapping_add(s1);
+            t += 1;
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        t = 0;
+        while t < 64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(K[t]).wrapping_add(w[t]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+            t += 1;
+        }
+        let round = [a, b, c, d, e, f, g, h];
+        let mut i = 0;
+        while i < 8 {
+            state[i] = state[i].wrapping_add(round[i]);
+            i += 1;
+        }
+        block_start += 64;
+    }
+
+    let mut hash = [0u8; 32];
+    let mut i = 0;
+    while i < 8 {
+        let bytes = state[i].to_be_bytes();
+        let mut j = 0;
+        while j < 4 {
+            hash[4 * i + j] = bytes[j];
+            j += 1;
+        }
+        i += 1;
+    }
+    hash
+}
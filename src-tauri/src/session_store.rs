@@ -0,0 +1,304 @@
+// src-tauri/src/session_store.rs
+
+//! Authenticated sessions, per site.
+//!
+//! A successful `authenticate_passkey` opens a session for its site, which
+//! `get_site_auth_status` reports until the session's TTL runs out or
+//! `logout_site` ends it. The TTL comes from `set_site_session_ttl` for sites
+//! that have one, otherwise from the webauthn plugin's `Builder` or the default
+//! below. Sessions live in memory only, so every launch starts signed out.
+//!
+//! For a few seconds after the ceremony, the session's grace period,
+//! `authenticate_passkey` succeeds again without prompting, so that leaving an
+//! editor and coming straight back does not ask for the passkey twice.
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use tauri::{command, Manager, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::credential_store::CredentialStore;
+use crate::webauthn::{timestamp, validate_site_id, WebAuthnError};
+use crate::webauthn_events::WebAuthnEvent;
+use crate::webauthn_state::WebAuthnState;
+
+/// How long a site stays authenticated, unless configured otherwise
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Shortest TTL a site can be given
+pub const MIN_SITE_SESSION_TTL: Duration = Duration::from_secs(60);
+
+/// Longest TTL a site can be given
+pub const MAX_SITE_SESSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Seconds after a ceremony during which authenticating again does not prompt,
+/// unless configured otherwise
+pub const DEFAULT_GRACE_PERIOD_SECS: u64 = 10;
+
+/// Longest grace period `set_grace_period` accepts
+pub const MAX_GRACE_PERIOD_SECS: u64 = 60;
+
+/// A site the user authenticated for
+#[derive(Debug, Clone)]
+pub struct AuthSession {
+    pub site_id: String,
+    pub authenticated_at: DateTime<Utc>,
+    pub ttl: Duration,
+    /// The grace period in force when the session opened
+    pub grace_period_seconds: u64,
+}
+
+impl AuthSession {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        TimeDelta::from_std(self.ttl)
+            .ok()
+            .and_then(|ttl| self.authenticated_at.checked_add_signed(ttl))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at()
+    }
+
+    fn is_in_grace_period(&self, now: DateTime<Utc>) -> bool {
+        TimeDelta::try_seconds(self.grace_period_seconds as i64)
+            .and_then(|grace_period| self.authenticated_at.checked_add_signed(grace_period))
+            .is_some_and(|grace_period_end| now < grace_period_end)
+    }
+}
+
+/// Open sessions and per-site TTLs, keyed by site ID
+pub struct SessionStore {
+    sessions: DashMap<String, AuthSession>,
+    ttl: Duration,
+    site_ttls: DashMap<String, Duration>,
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_TTL)
+    }
+}
+
+impl SessionStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            sessions: DashMap::new(),
+            ttl,
+            site_ttls: DashMap::new(),
+        }
+    }
+
+    /// The TTL of the site's sessions: its own if it has one, the default otherwise
+    pub fn ttl_for(&self, site_id: &str) -> Duration {
+        self.site_ttls.get(site_id).map_or(self.ttl, |ttl| *ttl)
+    }
+
+    /// Give a site's sessions their own TTL, within
+    /// `[MIN_SITE_SESSION_TTL, MAX_SITE_SESSION_TTL]`
+    ///
+    /// An open session takes the new TTL at once, so shortening it can end the
+    /// session.
+    pub fn set_site_ttl(&self, site_id: &str, ttl: Duration) -> Result<(), WebAuthnError> {
+        if !(MIN_SITE_SESSION_TTL..=MAX_SITE_SESSION_TTL).contains(&ttl) {
+            return Err(WebAuthnError::InvalidInput(format!(
+                "Session TTL must be between {} and {} seconds, got {}",
+                MIN_SITE_SESSION_TTL.as_secs(),
+                MAX_SITE_SESSION_TTL.as_secs(),
+                ttl.as_secs()
+            )));
+        }
+        self.site_ttls.insert(site_id.to_string(), ttl);
+        if let Some(mut session) = self.sessions.get_mut(site_id) {
+            session.ttl = ttl;
+        }
+        Ok(())
+    }
+
+    /// Start a session for a site, replacing any previous one
+    pub fn open(&self, site_id: &str, grace_period_seconds: u64) -> AuthSession {
+        let session = AuthSession {
+            site_id: site_id.to_string(),
+            authenticated_at: Utc::now(),
+            ttl: self.ttl_for(site_id),
+            grace_period_seconds,
+        };
+        self.sessions.insert(site_id.to_string(), session.clone());
+        session
+    }
+
+    /// The site's session, unless it expired or was never opened
+    pub fn active(&self, site_id: &str) -> Option<AuthSession> {
+        let now = Utc::now();
+        self.sessions.remove_if(site_id, |_, session| !session.is_active(now));
+        self.sessions.get(site_id).map(|session| session.clone())
+    }
+
+    /// The site's session, if it opened within its grace period
+    pub fn in_grace_period(&self, site_id: &str) -> Option<AuthSession> {
+        let now = Utc::now();
+        self.active(site_id).filter(|session| session.is_in_grace_period(now))
+    }
+
+    /// How many sessions have not expired
+    pub fn active_count(&self) -> usize {
+        let now = Utc::now();
+        self.sessions.iter().filter(|session| session.is_active(now)).count()
+    }
+
+    /// End the site's session, returning it if it had not expired yet
+    pub fn close(&self, site_id: &str) -> Option<AuthSession> {
+        let now = Utc::now();
+        self.sessions
+            .remove(site_id)
+            .map(|(_, session)| session)
+            .filter(|session| session.is_active(now))
+    }
+
+    /// End every session, returning those that had not expired yet
+    pub fn close_all(&self) -> Vec<AuthSession> {
+        let now = Utc::now();
+        let site_ids: Vec<String> = self.sessions.iter().map(|entry| entry.key().clone()).collect();
+        site_ids
+            .into_iter()
+            .filter_map(|site_id| self.sessions.remove(&site_id))
+            .map(|(_, session)| session)
+            .filter(|session| session.is_active(now))
+            .collect()
+    }
+}
+
+/// Whether a site can be edited without authenticating again, from `get_site_auth_status`
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+pub struct SiteAuthStatus {
+    /// The credential store holds a passkey for the site
+    #[serde(rename = "hasCredential")]
+    pub has_credential: bool,
+    /// The site has a session that has not expired
+    #[serde(rename = "isAuthenticated")]
+    pub is_authenticated: bool,
+    #[serde(rename = "authenticatedAt", with = "timestamp::optional")]
+    #[cfg_attr(feature = "schema-export", schemars(with = "Option<String>"))]
+    pub authenticated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "expiresAt", with = "timestamp::optional")]
+    #[cfg_attr(feature = "schema-export", schemars(with = "Option<String>"))]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Report whether a site has a passkey and an unexpired session
+#[command]
+pub async fn get_site_auth_status<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    state: State<'_, WebAuthnState>,
+    site_id: String,
+) -> Result<SiteAuthStatus, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    validate_site_id(&site_id)?;
+    let has_credential = store
+        .load_credential(&site_id)?
+        .is_some_and(|config| !config.credentials.is_empty());
+    let session = state.sessions.active(&site_id);
+
+    Ok(SiteAuthStatus {
+        has_credential,
+        is_authenticated: session.is_some(),
+        authenticated_at: session.as_ref().map(|session| session.authenticated_at),
+        expires_at: session.as_ref().map(AuthSession::expires_at),
+    })
+}
+
+/// End the session for a site, so the next edit authenticates again
+///
+/// Emits `webauthn://session-invalidated` when the site had a session. Succeeds
+/// when it had none. The challenges issued for the site are dropped, and a
+/// locked app no longer reopens its session when unlocked, so the next
+/// authentication prompts rather than passing within a grace period.
+/// Challenges issued without a site stay redeemable until their TTL runs out.
+#[command]
+pub async fn logout_site<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    site_id: String,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    validate_site_id(&site_id)?;
+    state.challenges.revoke(&site_id);
+    state.app_lock.forget_site(&site_id);
+    if state.sessions.close(&site_id).is_some() {
+        log::info!("Logged out of site: {}", site_id);
+        WebAuthnEvent::SessionInvalidated { site_id }.emit(window.app_handle());
+    }
+    Ok(())
+}
+
+/// End every site's session, returning how many had not expired yet
+///
+/// Emits `webauthn://session-invalidated` for each of those. As `logout_site`
+/// does for one site, drops every challenge issued for a site and keeps a
+/// locked app from reopening any session.
+#[command]
+pub async fn logout_all_sites<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+) -> Result<usize, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    state.challenges.revoke_all_sites();
+    state.app_lock.forget_all_sites();
+    let closed = state.sessions.close_all();
+    let count = closed.len();
+    log::info!("Logged out of {} sites", count);
+    for session in closed {
+        WebAuthnEvent::SessionInvalidated {
+            site_id: session.site_id,
+        }
+        .emit(window.app_handle());
+    }
+    Ok(count)
+}
+
+/// Give a site's sessions their own TTL, e.g. a shorter one for sensitive content
+///
+/// `ttl_seconds` must be between 60 and 86400 (24 hours). Applies to the
+/// site's open session too.
+#[command]
+pub async fn set_site_session_ttl<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    site_id: String,
+    ttl_seconds: u64,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    validate_site_id(&site_id)?;
+    state.sessions.set_site_ttl(&site_id, Duration::from_secs(ttl_seconds))?;
+    log::info!("Sessions for site {} now last {} seconds", site_id, ttl_seconds);
+    Ok(())
+}
+
+/// Set how many seconds after a ceremony `authenticate_passkey` succeeds again
+/// without prompting
+///
+/// `seconds` must be at most 60; 0 prompts every time. Sessions already open
+/// keep the grace period they opened with.
+#[command]
+pub async fn set_grace_period<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    seconds: u64,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    if seconds > MAX_GRACE_PERIOD_SECS {
+        return Err(WebAuthnError::InvalidInput(format!(
+            "Grace period must be at most {} seconds, got {}",
+            MAX_GRACE_PERIOD_SECS, seconds
+        )));
+    }
+    state.grace_period_seconds.store(seconds, Ordering::Relaxed);
+    log::info!("Authentications now skip the prompt for {} seconds after a ceremony", seconds);
+    Ok(())
+}
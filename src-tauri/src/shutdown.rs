@@ -0,0 +1,55 @@
+// src-tauri/src/shutdown.rs
+
+//! What the plugin winds down as the app exits.
+//!
+//! On `RunEvent::Exit` the platform dismisses the ceremonies still waiting on
+//! the user, so no system sheet calls back into a runtime that is going away.
+//! Registrations in progress and pending challenges are then dropped, and the
+//! audit log is synced to disk. The sync gets 2 seconds, so a stalled disk
+//! cannot keep the app from exiting; the records it did not sync are left to
+//! the OS.
+
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::credential_store::CredentialStore;
+use crate::webauthn_platform::Platform;
+use crate::webauthn_state::WebAuthnState;
+
+/// How long the audit log gets to reach the disk
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Cancel, drop and flush what the plugin holds; run once, on the main thread
+pub(crate) fn on_exit<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(platform) = app.try_state::<Platform<R>>() {
+        platform.cancel_ceremonies();
+    }
+    let Some(state) = app.try_state::<WebAuthnState>() else {
+        return;
+    };
+    let registrations = state.registrations.drain();
+    let challenges = state.challenges.clear();
+    log::debug!(
+        "Exiting with {} registrations in progress and {} pending challenges",
+        registrations,
+        challenges
+    );
+
+    if app.try_state::<CredentialStore>().is_none() {
+        return;
+    }
+    let handle = app.clone();
+    let flush = tauri::async_runtime::spawn_blocking(move || {
+        handle.state::<WebAuthnState>().audit_log.flush(&handle.state::<CredentialStore>())
+    });
+    match tauri::async_runtime::block_on(tokio::time::timeout(FLUSH_TIMEOUT, flush)) {
+        Ok(Ok(Ok(()))) => {}
+        Ok(Ok(Err(error))) => log::warn!("Failed to flush the WebAuthn audit log: {}", error),
+        Ok(Err(error)) => log::warn!("Failed to flush the WebAuthn audit log: {}", error),
+        Err(_) => log::warn!(
+            "Gave up flushing the WebAuthn audit log after {} seconds",
+            FLUSH_TIMEOUT.as_secs()
+        ),
+    }
+}
@@ -0,0 +1,134 @@
+// src-tauri/src/signature_verification.rs
+
+//! Checking assertion signatures without a ceremony.
+//!
+//! An assertion the frontend received some other way, such as from a browser
+//! flow on another device, can be handed to `verify_assertion` to check that
+//! the credential's key signed it. Authenticators sign their authenticator data
+//! followed by the SHA-256 of the client data, with ES256 or RS256 depending on
+//! the key, and `ring` checks the signature. Malformed input fails with
+//! `InvalidInput` and a signature that does not verify with `SignatureInvalid`,
+//! so the frontend can tell the two apart.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{command, Runtime, State, WebviewWindow};
+
+use crate::capability_filter::{check_window, CapabilityTier};
+use crate::log_event::Redacted;
+use crate::webauthn::WebAuthnError;
+use crate::webauthn_cbor::{parse_authenticator_data, parse_cose_key, CoseKey, CoseKeyParameters};
+use crate::webauthn_state::WebAuthnState;
+
+/// Result returned by `verify_assertion`
+#[derive(Debug, Serialize)]
+pub struct VerificationResult {
+    /// The signature verifies and the assertion is scoped to the editing domain
+    pub verified: bool,
+    #[serde(rename = "signCount")]
+    pub sign_count: u32,
+    /// Whether the authenticator data is scoped to the editing domain
+    #[serde(rename = "rpIdHashMatches")]
+    pub rp_id_hash_matches: bool,
+}
+
+fn decode(name: &str, value: &str) -> Result<Vec<u8>, WebAuthnError> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| WebAuthnError::InvalidInput(format!("{} is not base64url", name)))
+}
+
+/// Refuse client data that is not from an assertion
+fn check_client_data(client_data: &[u8]) -> Result<(), WebAuthnError> {
+    let client_data: serde_json::Value = serde_json::from_slice(client_data)
+        .map_err(|e| WebAuthnError::InvalidInput(format!("client_data_json is not JSON: {}", e)))?;
+    match client_data.get("type").and_then(serde_json::Value::as_str) {
+        Some("webauthn.get") => Ok(()),
+        other => Err(WebAuthnError::InvalidInput(format!(
+            "client_data_json is of type {:?}, expected \"webauthn.get\"",
+            other.unwrap_or_default()
+        ))),
+    }
+}
+
+/// Check that `public_key` signed `authenticator_data` followed by the SHA-256
+/// of `client_data_json`, failing with `SignatureInvalid` when it did not
+///
+/// `signature` is as the authenticator returned it: DER for ES256, PKCS #1
+/// v1.5 for RS256.
+pub fn verify_signature(
+    public_key: &CoseKey,
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<(), WebAuthnError> {
+    let signed = [authenticator_data, &Sha256::digest(client_data_json)].concat();
+    let valid = match &public_key.parameters {
+        CoseKeyParameters::Ec2 { x, y } => {
+            let point = [&[0x04][..], x, y].concat();
+            UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point)
+                .verify(&signed, signature)
+                .is_ok()
+        }
+        CoseKeyParameters::Rsa { n, e } => {
+            // ring takes the modulus without leading zeros
+            let n: Vec<u8> = n.iter().copied().skip_while(|byte| *byte == 0).collect();
+            RsaPublicKeyComponents { n: &n[..], e: &e[..] }
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, &signed, signature)
+                .is_ok()
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(WebAuthnError::SignatureInvalid)
+    }
+}
+
+/// Check an assertion's signature against the credential's public key
+///
+/// Every argument is base64url: `client_data_json`, `authenticator_data` and
+/// `signature` as the authenticator returned them, and `public_key` as the COSE
+/// key the credential store keeps. Only ES256 and RS256 keys are supported.
+/// A signature that does not verify fails with `SignatureInvalid`; one that
+/// does but is scoped to another RP than the editing domain returns `verified`
+/// false.
+#[command]
+pub async fn verify_assertion<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    credential_id: String,
+    client_data_json: String,
+    authenticator_data: String,
+    signature: String,
+    public_key: String,
+) -> Result<VerificationResult, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    decode("credential_id", &credential_id)?;
+    let client_data = decode("client_data_json", &client_data_json)?;
+    let raw_auth_data = decode("authenticator_data", &authenticator_data)?;
+    let signature = decode("signature", &signature)?;
+    let public_key = parse_cose_key(&decode("public_key", &public_key)?)
+        .map_err(|e| WebAuthnError::InvalidInput(format!("public_key is not a supported COSE key: {}", e)))?;
+    let auth_data = parse_authenticator_data(&raw_auth_data)
+        .map_err(|e| WebAuthnError::InvalidInput(format!("authenticator_data is malformed: {}", e)))?;
+    check_client_data(&client_data)?;
+
+    if let Err(error) = verify_signature(&public_key, &raw_auth_data, &client_data, &signature) {
+        log::warn!("Assertion signature for credential {} does not verify", Redacted(&credential_id));
+        return Err(error);
+    }
+
+    let rp_id_hash_matches =
+        auth_data.rp_id_hash[..] == Sha256::digest(state.relying_party.editing_domain().as_bytes())[..];
+    if !rp_id_hash_matches {
+        log::warn!("Assertion for credential {} is scoped to another RP", Redacted(&credential_id));
+    }
+    Ok(VerificationResult {
+        verified: rp_id_hash_matches,
+        sign_count: auth_data.sign_count,
+        rp_id_hash_matches,
+    })
+}
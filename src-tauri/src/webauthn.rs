@@ -1,269 +1,3048 @@
 // src-tauri/src/webauthn.rs
 
 use serde::{Deserialize, Serialize};
-use tauri::command;
+use tauri::plugin::TauriPlugin;
+use tauri::{command, AppHandle, Manager, RunEvent, Runtime, State, WebviewWindow};
 use rand::RngCore;
 use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
-use std::time::Duration;
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::Level;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+use zeroize::Zeroizing;
 
-// Note: The objc2 bindings may not have all WebAuthn types available yet
-// This is a framework implementation that can be extended when the APIs are stable
-#[cfg(any(target_os = "macos", target_os = "ios"))]
-use objc2_foundation::NSObject;
+use crate::app_lock::{self, AppLock, DEFAULT_IDLE_CHECK_INTERVAL};
+use crate::audit_log::{self, AuditEvent, AuditLog};
+use crate::auth_notifications::{self, AuthNotificationConfig};
+use crate::capability_filter::{check_window, CapabilityTier, WebAuthnCapabilityFilter};
+use crate::challenge_store::{
+    self, ChallengeConfig, ChallengeRng, ChallengeStore, UsedChallengeLog, DEFAULT_CHALLENGE_TTL, MAX_CHALLENGE_BYTES,
+    MIN_CHALLENGE_BYTES,
+};
+use crate::clipboard_guard::ClipboardGuard;
+use crate::credential_id::CredentialId;
+use crate::credential_store::CredentialStore;
+use crate::credential_sync::{SyncRequests, DEFAULT_SYNC_OFFER_TTL};
+use crate::device_name::{get_device_name, make_unique_device_name, UNKNOWN_DEVICE};
+use crate::entropy_check;
+use crate::hybrid_auth::HybridAuthSessions;
+use crate::in_flight::InFlightRegistry;
+use crate::log_event::{
+    self, webauthn_log, LogOperation, OperationContext, Redacted, WebAuthnLogConfig, WebAuthnLogEvent,
+};
+use crate::metrics::WebAuthnMetrics;
+use crate::onboarding::Onboarding;
+use crate::panic_hook;
+use crate::pin_cache::{PinCache, DEFAULT_PIN_CACHE_TTL};
+use crate::platform_retry::{self, RetryConfig};
+use crate::rate_limiter::{RateLimitConfig, RateLimiter};
+use crate::rp_id_pin::{self, PINNED_RP_ID_HASH};
+use crate::session_store::{SessionStore, DEFAULT_GRACE_PERIOD_SECS, DEFAULT_SESSION_TTL};
+use crate::shutdown;
+use crate::webauthn_cbor::{parse_authenticator_data, parse_cose_key, AuthenticatorData, CoseKey};
+use crate::webauthn_events::{Ceremony, WebAuthnEvent};
+use crate::webauthn_platform::{
+    AuthenticationRequest, DeletionRequest, LargeBlobOperation, Platform, PrfInput, RegistrationRequest,
+    WebAuthnPlatform,
+};
+use crate::webauthn_config::{WebAuthnConfig, CONFIG_FILE_NAME};
+use crate::webauthn_state::WebAuthnState;
 
-/// Configuration for site-specific WebAuthn authentication
+#[cfg(target_os = "linux")]
+use crate::webauthn_linux;
+
+/// A passkey registered for a site on one device
 /// Matches the structure in webauthn.service.ts
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SiteAuthConfig {
-    #[serde(rename = "publicKey")]
-    pub public_key: String,
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub struct CredentialEntry {
     #[serde(rename = "credentialId")]
     pub credential_id: String,
+    #[serde(rename = "publicKey")]
+    pub public_key: String,
+    #[serde(rename = "registeredAt", with = "timestamp")]
+    #[cfg_attr(feature = "schema-export", schemars(with = "String"))]
+    pub registered_at: DateTime<Utc>,
+    #[serde(rename = "deviceName", default)]
+    pub device_name: Option<String>,
+    #[serde(rename = "lastUsedAt", with = "timestamp::optional", default)]
+    #[cfg_attr(feature = "schema-export", schemars(with = "Option<String>"))]
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Highest signature counter seen from the authenticator; 0 if it keeps none
+    #[serde(rename = "signCount", default)]
+    pub sign_count: u32,
+    /// Failed authentications since the credential last succeeded
+    #[serde(rename = "failureCount", default)]
+    pub failure_count: u32,
+    /// Set once `failure_count` reached the lockout threshold; cleared by `unlock_credential`
+    #[serde(default)]
+    pub locked: bool,
+}
+
+impl CredentialEntry {
+    /// The credential ID, decoded and checked against the spec's length limits
+    pub fn parsed_credential_id(&self) -> Result<CredentialId, WebAuthnError> {
+        CredentialId::try_from(self.credential_id.as_str())
+    }
+}
+
+/// Credential timestamps, written as RFC 3339 (`2025-01-01T00:00:00.000Z`, the
+/// same shape as JavaScript's `toISOString()`)
+///
+/// Earlier versions stored Unix epoch seconds as a string, so those are still
+/// accepted on read.
+pub(crate) mod timestamp {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Seconds(i64),
+        Text(String),
+    }
+
+    pub fn serialize<S: Serializer>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_rfc3339_opts(SecondsFormat::Millis, true))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::Seconds(seconds) => from_seconds(seconds),
+            Repr::Text(text) => parse(&text),
+        }
+        .map_err(de::Error::custom)
+    }
+
+    /// Parse an RFC 3339 timestamp or a legacy epoch-seconds string
+    pub fn parse(text: &str) -> Result<DateTime<Utc>, String> {
+        let text = text.trim();
+        // Single-credential configs without a `registeredAt` were migrated with an empty one
+        if text.is_empty() {
+            return Ok(DateTime::UNIX_EPOCH);
+        }
+        if text.bytes().all(|byte| byte.is_ascii_digit()) {
+            let seconds = text.parse().map_err(|_| format!("Invalid timestamp: {}", text))?;
+            return from_seconds(seconds);
+        }
+        DateTime::parse_from_rfc3339(text)
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+            .map_err(|e| format!("Invalid timestamp {:?}: {}", text, e))
+    }
+
+    fn from_seconds(seconds: i64) -> Result<DateTime<Utc>, String> {
+        DateTime::from_timestamp(seconds, 0).ok_or_else(|| format!("Timestamp out of range: {}", seconds))
+    }
+
+    /// The same format for optional timestamps
+    pub mod optional {
+        use chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(value: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+            #[derive(Deserialize)]
+            struct Timestamp(#[serde(with = "super")] DateTime<Utc>);
+
+            Ok(Option::<Timestamp>::deserialize(deserializer)?.map(|Timestamp(value)| value))
+        }
+    }
+}
+
+/// Configuration for site-specific WebAuthn authentication
+/// Matches the structure in webauthn.service.ts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(from = "SiteAuthConfigRepr")]
+pub struct SiteAuthConfig {
+    pub credentials: Vec<CredentialEntry>,
     #[serde(rename = "requiresAuth")]
     pub requires_auth: bool,
     #[serde(rename = "userDisplayName")]
     pub user_display_name: Option<String>,
-    #[serde(rename = "registeredAt")]
-    pub registered_at: String,
+    /// Opaque WebAuthn user handle (base64url), shared by all of the site's credentials
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    /// Site name the latest passkey was registered under; absent for sites
+    /// registered before it was kept
+    #[serde(rename = "siteName", skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+}
+
+impl SiteAuthConfig {
+    pub(crate) fn find_credential(&self, credential_id: &str) -> Option<&CredentialEntry> {
+        self.credentials
+            .iter()
+            .find(|entry| entry.credential_id == credential_id)
+    }
+}
+
+/// Accepts both the current shape and the single-credential shape written
+/// before sites could hold several passkeys (`publicKey` / `credentialId` /
+/// `registeredAt` at the top level)
+#[derive(Deserialize)]
+struct SiteAuthConfigRepr {
+    #[serde(default)]
+    credentials: Vec<CredentialEntry>,
+    #[serde(rename = "requiresAuth")]
+    requires_auth: bool,
+    #[serde(rename = "userDisplayName", default)]
+    user_display_name: Option<String>,
+    #[serde(rename = "userId", default)]
+    user_id: String,
+    #[serde(rename = "siteName", default)]
+    site_name: Option<String>,
+    #[serde(rename = "publicKey", default)]
+    public_key: Option<String>,
+    #[serde(rename = "credentialId", default)]
+    credential_id: Option<String>,
+    #[serde(rename = "registeredAt", with = "timestamp::optional", default)]
+    registered_at: Option<DateTime<Utc>>,
+}
+
+impl From<SiteAuthConfigRepr> for SiteAuthConfig {
+    fn from(repr: SiteAuthConfigRepr) -> Self {
+        let mut credentials = repr.credentials;
+        if let (Some(credential_id), Some(public_key)) = (repr.credential_id, repr.public_key) {
+            if !credentials.iter().any(|entry| entry.credential_id == credential_id) {
+                credentials.push(CredentialEntry {
+                    credential_id,
+                    public_key,
+                    registered_at: repr.registered_at.unwrap_or_default(),
+                    device_name: None,
+                    last_used_at: None,
+                    sign_count: 0,
+                    failure_count: 0,
+                    locked: false,
+                });
+            }
+        }
+
+        SiteAuthConfig {
+            credentials,
+            requires_auth: repr.requires_auth,
+            user_display_name: repr.user_display_name,
+            user_id: repr.user_id,
+            site_name: repr.site_name,
+        }
+    }
 }
 
 /// Result of a WebAuthn authentication attempt
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(rename_all = "camelCase")]
 pub struct AuthenticationResult {
     pub success: bool,
-    pub error: Option<String>,
-    #[serde(rename = "credentialId")]
     pub credential_id: Option<String>,
+    /// Name the site's passkeys were registered under, from the credential
+    /// store; null when the store has no name for the site
+    pub site_name: Option<String>,
+    /// Signature counter reported with the assertion
+    pub sign_count: u32,
+    /// The counter did not move past the stored one, so the authenticator may
+    /// have been cloned. Authentication still succeeds; the user should be warned.
+    pub possible_clone_detected: bool,
+    /// `platform` or `cross-platform`: the kind of authenticator the user asserted
+    /// with; empty when the site does not require authentication
+    pub authenticator_attachment: String,
+    /// PRF extension output for the credential used, base64url-encoded, when
+    /// `prf` was given and the authenticator evaluated it
+    ///
+    /// These bytes are key material: they must never be logged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prf_output: Option<String>,
+    /// largeBlob data stored with the credential used, base64url-encoded, when
+    /// `large_blob_read` was set and the authenticator holds some
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub large_blob_data: Option<String>,
+    /// Where the time went; only in debug builds or with `SPARKTYPE_PERF_TRACE=1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<OperationTimings>,
+    /// The `operation_id` logged for this attempt
+    pub operation_id: String,
+    /// The site authenticated moments ago, so no ceremony ran; `credentialId`
+    /// is then null
+    pub from_cache: bool,
+    /// Times the platform was called, more than 1 when it failed in a way that
+    /// may pass and was retried; 0 when no ceremony ran
+    pub attempts: u8,
 }
 
 /// Result of WebAuthn credential registration
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(rename_all = "camelCase")]
 pub struct RegistrationResult {
     pub success: bool,
-    #[serde(rename = "authConfig")]
     pub auth_config: Option<SiteAuthConfig>,
-    pub error: Option<String>,
+    /// Authenticator model, as a UUID, when the authenticator disclosed it
+    pub aaguid: Option<String>,
+    /// Initial signature counter; 0 when the authenticator keeps none
+    pub sign_count: u32,
+    /// `platform` for a built-in authenticator, `cross-platform` for a security
+    /// key; empty for an imported credential
+    pub authenticator_attachment: String,
+    /// Whether the credential is discoverable, so `discover_credentials` can find it
+    pub is_resident: bool,
+    /// Whether the authenticator may sync the credential to the user's other devices
+    pub backup_eligible: bool,
+    /// Whether the passkey is already synced through iCloud Keychain, so it is
+    /// available on the user's other Apple devices; `None` off Apple platforms
+    pub icloud_synced: Option<bool>,
+    /// The credentials came from `existing_config` instead of a ceremony, so
+    /// nothing is known of the authenticator that holds them
+    pub was_imported: bool,
+    /// The `operation_id` logged for this registration
+    pub operation_id: String,
+}
+
+/// Identifies one `authenticate_passkey` or `register_passkey` invocation
+///
+/// Every log line the invocation writes carries it as the `operation_id` field,
+/// so the lines of one attempt can be found together across await points.
+#[derive(Debug, Clone, Copy)]
+pub struct OperationId(Uuid);
+
+impl OperationId {
+    fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl fmt::Display for OperationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Time spent in each phase of an `authenticate_passkey` invocation
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(rename_all = "camelCase")]
+pub struct OperationTimings {
+    /// Checking and consuming the challenge
+    pub challenge_gen_us: u64,
+    /// The platform ceremony, including the time the user spends in the prompt
+    pub platform_api_ms: u64,
+    /// Recording the credential's use and opening the site's session
+    pub session_write_us: u64,
+    pub total_ms: u64,
+}
+
+impl fmt::Display for OperationTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "challenge {}us, platform {}ms, session {}us, total {}ms",
+            self.challenge_gen_us, self.platform_api_ms, self.session_write_us, self.total_ms
+        )
+    }
+}
+
+/// Environment variable that adds `timings` to authentication results in release builds
+const PERF_TRACE_ENV: &str = "SPARKTYPE_PERF_TRACE";
+
+/// Whether authentication results carry their `timings`
+fn perf_trace_enabled() -> bool {
+    cfg!(debug_assertions) || std::env::var(PERF_TRACE_ENV).is_ok_and(|value| value == "1")
+}
+
+/// Whole microseconds in a duration, saturating
+fn as_micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Whole milliseconds in a duration, saturating
+fn as_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// A credential picked by the user in a `discover_credentials` ceremony
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredCredential {
+    pub credential_id: String,
+    /// User handle stored with the credential (base64url)
+    pub user_handle: Option<String>,
+    /// The site the credential is registered for, when the credential store knows it
+    pub site_id: Option<String>,
+    /// Signature counter reported with the assertion
+    pub sign_count: u32,
+}
+
+/// Whether a site's passkey can be used on this device, from `verify_stored_credential`
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialStatus {
+    /// The authenticator still holds one of the site's credentials, as far as
+    /// the platform can tell without a ceremony
+    pub exists_locally: bool,
+    /// The device can verify the user in a ceremony now
+    pub platform_available: bool,
+    /// When the site last authenticated with any of its credentials
+    #[serde(with = "timestamp::optional")]
+    #[cfg_attr(feature = "schema-export", schemars(with = "Option<String>"))]
+    pub last_verified_at: Option<DateTime<Utc>>,
+}
+
+/// Kinds of authenticators the passkey ceremonies can use on this device
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(rename_all = "camelCase")]
+pub struct WebAuthnCapabilities {
+    /// A built-in authenticator (Touch ID, Windows Hello, the Android screen lock)
+    pub platform_authenticator: bool,
+    /// A security key over USB, NFC or Bluetooth
+    pub roaming_authenticator: bool,
+    /// Credentials can be discoverable (resident) on the authenticator
+    pub resident_key_support: bool,
+    /// The authenticator can verify the user (biometrics or a PIN), not just their presence
+    pub user_verification_support: bool,
+    /// Platform passkeys are kept in secure hardware (a TEE, StrongBox or the
+    /// Secure Enclave); `None` where the platform does not say
+    pub hardware_backed: Option<bool>,
+    /// The OS release to upgrade to for platform passkeys, e.g. `macOS 13`,
+    /// when this one is too old for them; `None` when it is recent enough
+    pub min_os_version_required: Option<String>,
+}
+
+/// How strongly a ceremony asks the authenticator to verify the user, as in
+/// the WebAuthn `userVerification` option
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(rename_all = "lowercase")]
+pub enum UserVerification {
+    /// Fail the ceremony unless the user is verified (biometrics or a PIN)
+    Required,
+    /// Verify the user when the authenticator can
+    #[default]
+    Preferred,
+    /// Only check for user presence, where the authenticator allows it
+    Discouraged,
+}
+
+/// Whether registration should create a discoverable (resident) credential, as
+/// in the WebAuthn `residentKey` option
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(rename_all = "lowercase")]
+pub enum ResidentKeyRequirement {
+    /// Fail the ceremony unless the authenticator stores the credential
+    Required,
+    /// Store the credential when the authenticator can
+    #[default]
+    Preferred,
+    /// Leave the credential non-discoverable, where the authenticator allows it
+    Discouraged,
+}
+
+/// Which kind of authenticator a ceremony may use, as in the WebAuthn
+/// `authenticatorAttachment` option
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthenticatorAttachment {
+    /// The device's built-in authenticator (Touch ID, Windows Hello, the Android screen lock)
+    Platform,
+    /// A roaming authenticator: a security key over USB, NFC or Bluetooth
+    CrossPlatform,
+    /// Whichever the user picks
+    Any,
+}
+
+/// Input to the WebAuthn PRF extension, for deriving a key from a passkey
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub struct PrfExtensionInput {
+    /// Evaluated for whichever credential the user asserts with
+    pub eval: PrfValues,
+    /// Inputs for particular credentials, keyed by base64url credential ID,
+    /// used instead of `eval` when the user asserts with that credential
+    #[serde(rename = "evalByCredential", default)]
+    pub eval_by_credential: Option<HashMap<String, PrfValues>>,
+}
+
+/// A PRF input, as in the WebAuthn `AuthenticationExtensionsPRFValues`
+///
+/// Only `first` is evaluated; a `second` input is not supported.
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub struct PrfValues {
+    /// base64url-encoded
+    pub first: String,
+}
+
+impl PrfExtensionInput {
+    /// Decode the inputs, checking each per-credential one names a credential of the site
+    fn decode(&self, auth_config: &SiteAuthConfig) -> Result<PrfInput, WebAuthnError> {
+        let decode = |value: &str, field: &str| {
+            URL_SAFE_NO_PAD
+                .decode(value)
+                .map_err(|_| WebAuthnError::InvalidInput(format!("{} is not base64url", field)))
+        };
+        let mut eval_by_credential = Vec::new();
+        for (credential_id, values) in self.eval_by_credential.iter().flatten() {
+            if auth_config.find_credential(credential_id).is_none() {
+                return Err(WebAuthnError::InvalidInput(format!(
+                    "prf.evalByCredential names a credential the site does not have: {}",
+                    credential_id
+                )));
+            }
+            eval_by_credential.push((
+                decode(credential_id, "A prf.evalByCredential key")?,
+                decode(&values.first, "A prf.evalByCredential input")?,
+            ));
+        }
+        Ok(PrfInput {
+            eval: decode(&self.eval.first, "prf.eval.first")?,
+            eval_by_credential,
+        })
+    }
+}
+
+/// Failure modes of the WebAuthn commands
+///
+/// Serialized as `{ "code": "<variant>", "detail": <payload> }` so the frontend
+/// can branch on `code` without parsing messages.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema-export", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+#[serde(tag = "code", content = "detail")]
+#[allow(dead_code)] // Not every platform backend raises every variant
+pub enum WebAuthnError {
+    /// No native authenticator is available on this platform
+    PlatformNotSupported,
+    /// The user dismissed the system passkey sheet
+    UserCancelled,
+    /// The challenge could not be decoded or used
+    InvalidChallenge,
+    /// A command argument was rejected
+    InvalidInput(String),
+    /// A challenge length outside the accepted range was configured
+    InvalidChallengeLength(usize),
+    /// The expected credential was not offered by the authenticator
+    CredentialNotFound(String),
+    /// User verification (biometrics or device PIN) failed
+    BiometricFailed,
+    /// User verification was required but the authenticator cannot provide it
+    UserVerificationFailed,
+    /// A discoverable credential was required but the authenticator cannot store one
+    ResidentKeyNotSupported,
+    /// Too many authentication attempts for the site; try again after the delay
+    RateLimited {
+        #[serde(rename = "retryAfterSeconds")]
+        retry_after_seconds: u64,
+    },
+    /// The invoking window has not been granted the command's capability tier
+    Unauthorized,
+    /// A `register_passkey` call for the same site has not finished yet
+    RegistrationInProgress,
+    /// The challenge already completed an authentication, so the response is a replay
+    ReplayAttack,
+    /// The ceremony did not complete in time
+    Timeout,
+    /// An assertion's signature does not verify with the credential's public key
+    SignatureInvalid,
+    /// The command exists but what it does is not implemented yet
+    NotImplemented,
+    /// The site already has as many passkeys as it may; one must be removed first
+    LimitExceeded { current: usize, max: usize },
+    /// The passkeys offered failed too often and stay locked until `unlock_credential`
+    CredentialLocked,
+    /// Passkeys must stay on this device (`no_sync`), and the authenticator can
+    /// only create ones that may sync
+    SyncControlUnsupported,
+    /// The RP ID does not hash to the one the build was pinned to, so it was
+    /// changed after the fact; no ceremony runs
+    RpIdTampered,
+    /// The platform failed in a way that may pass on another try, e.g. its
+    /// passkey service was interrupted; `authenticate_passkey` retries these
+    PlatformUnavailable(String),
+    /// Any other platform or plumbing failure
+    InternalError(String),
+}
+
+impl fmt::Display for WebAuthnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebAuthnError::PlatformNotSupported => write!(f, "WebAuthn not supported on this platform"),
+            WebAuthnError::UserCancelled => write!(f, "The passkey request was cancelled"),
+            WebAuthnError::InvalidChallenge => write!(f, "Invalid WebAuthn challenge"),
+            WebAuthnError::InvalidInput(message) => write!(f, "Invalid input: {}", message),
+            WebAuthnError::InvalidChallengeLength(length) => write!(
+                f,
+                "Challenge length must be between {} and {} bytes, got {}",
+                MIN_CHALLENGE_BYTES, MAX_CHALLENGE_BYTES, length
+            ),
+            WebAuthnError::CredentialNotFound(credential_id) => {
+                write!(f, "Credential not found: {}", credential_id)
+            }
+            WebAuthnError::BiometricFailed => write!(f, "User verification failed"),
+            WebAuthnError::UserVerificationFailed => {
+                write!(f, "User verification is required but this authenticator cannot verify the user")
+            }
+            WebAuthnError::ResidentKeyNotSupported => {
+                write!(f, "A discoverable credential is required but this authenticator cannot store one")
+            }
+            WebAuthnError::RateLimited { retry_after_seconds } => write!(
+                f,
+                "Too many authentication attempts, try again in {} seconds",
+                retry_after_seconds
+            ),
+            WebAuthnError::Unauthorized => write!(f, "This window may not use this WebAuthn command"),
+            WebAuthnError::RegistrationInProgress => {
+                write!(f, "A passkey is already being registered for this site")
+            }
+            WebAuthnError::ReplayAttack => write!(f, "The authentication challenge was already used"),
+            WebAuthnError::Timeout => write!(f, "The passkey request timed out"),
+            WebAuthnError::SignatureInvalid => write!(f, "The assertion signature is invalid"),
+            WebAuthnError::NotImplemented => write!(f, "This passkey feature is not available yet"),
+            WebAuthnError::LimitExceeded { current, max } => write!(
+                f,
+                "This site already has {}/{} passkeys, remove one before adding another",
+                current, max
+            ),
+            WebAuthnError::CredentialLocked => {
+                write!(f, "This passkey is locked after too many failed attempts")
+            }
+            WebAuthnError::SyncControlUnsupported => {
+                write!(f, "This authenticator cannot create a passkey that stays on this device")
+            }
+            WebAuthnError::RpIdTampered => {
+                write!(f, "The relying party ID does not match the one this build was pinned to")
+            }
+            WebAuthnError::PlatformUnavailable(message) => {
+                write!(f, "The passkey service is temporarily unavailable: {}", message)
+            }
+            WebAuthnError::InternalError(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for WebAuthnError {}
+
+impl WebAuthnError {
+    /// The `code` the error is serialized with
+    pub fn code(&self) -> &'static str {
+        match self {
+            WebAuthnError::PlatformNotSupported => "PlatformNotSupported",
+            WebAuthnError::UserCancelled => "UserCancelled",
+            WebAuthnError::InvalidChallenge => "InvalidChallenge",
+            WebAuthnError::InvalidInput(_) => "InvalidInput",
+            WebAuthnError::InvalidChallengeLength(_) => "InvalidChallengeLength",
+            WebAuthnError::CredentialNotFound(_) => "CredentialNotFound",
+            WebAuthnError::BiometricFailed => "BiometricFailed",
+            WebAuthnError::UserVerificationFailed => "UserVerificationFailed",
+            WebAuthnError::ResidentKeyNotSupported => "ResidentKeyNotSupported",
+            WebAuthnError::RateLimited { .. } => "RateLimited",
+            WebAuthnError::Unauthorized => "Unauthorized",
+            WebAuthnError::RegistrationInProgress => "RegistrationInProgress",
+            WebAuthnError::ReplayAttack => "ReplayAttack",
+            WebAuthnError::Timeout => "Timeout",
+            WebAuthnError::SignatureInvalid => "SignatureInvalid",
+            WebAuthnError::NotImplemented => "NotImplemented",
+            WebAuthnError::LimitExceeded { .. } => "LimitExceeded",
+            WebAuthnError::CredentialLocked => "CredentialLocked",
+            WebAuthnError::SyncControlUnsupported => "SyncControlUnsupported",
+            WebAuthnError::RpIdTampered => "RpIdTampered",
+            WebAuthnError::PlatformUnavailable(_) => "PlatformUnavailable",
+            WebAuthnError::InternalError(_) => "InternalError",
+        }
+    }
 }
 
 /// Generate a cryptographically secure random challenge for WebAuthn operations
 ///
-/// Creates a 32-byte random challenge encoded as base64url (URL-safe base64).
+/// Creates a random challenge of `config.length_bytes` bytes (32 by default)
+/// encoded as base64url (URL-safe base64).
 /// This challenge prevents replay attacks and ensures each authentication
 /// request is unique.
 ///
+/// The bytes come from `rng`, which `rotate_challenge_seed` can reseed. They
+/// are zeroed once encoded, so they do not linger in freed memory.
+///
 /// Mirrors the generateChallenge() function from webauthn.service.ts
-fn generate_challenge() -> String {
-    let mut array = [0u8; 32];
-    rand::thread_rng().fill_bytes(&mut array);
-    URL_SAFE_NO_PAD.encode(array)
+pub fn generate_challenge(rng: &ChallengeRng, config: &ChallengeConfig) -> String {
+    let mut bytes = Zeroizing::new(vec![0u8; config.length_bytes]);
+    rng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(&*bytes)
+}
+
+/// Clocks reading earlier than this (2001-09-09) are taken to be wrong
+const EARLIEST_PLAUSIBLE_TIME: Duration = Duration::from_secs(1_000_000_000);
+
+/// Whether the system clock reads a time before `EARLIEST_PLAUSIBLE_TIME`, as
+/// devices with a corrupted clock do
+///
+/// Credentials registered meanwhile get timestamps from the wrong time.
+fn clock_is_skewed() -> bool {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(true, |since_epoch| since_epoch < EARLIEST_PLAUSIBLE_TIME)
 }
 
-/// Get the appropriate editing domain for WebAuthn authentication
-/// Uses localhost in development, configured domain in production
+/// Length of a generated WebAuthn user handle
+const USER_ID_BYTES: usize = 16;
+
+/// Generate a random opaque user handle, base64url encoded
 ///
-/// Mirrors the getEditingDomain() function from webauthn.service.ts
-fn get_editing_domain() -> String {
-    // In development, use localhost
-    if cfg!(debug_assertions) {
-        "localhost".to_string()
-    } else {
-        // Use configured editing domain for production
-        "app.sparktype.org".to_string()
-    }
+/// The WebAuthn spec asks for a handle that carries no identifying information,
+/// so it is not derived from the site.
+fn generate_user_id() -> String {
+    let mut bytes = [0u8; USER_ID_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
 }
 
-/// Check if WebAuthn is available on the current platform
-#[command]
-pub async fn is_webauthn_available() -> Result<bool, String> {
-    #[cfg(target_os = "macos")]
-    {
-        Ok(true) // ASWebAuthenticationSession is available on macOS 10.15+
+/// Check that authenticator data is scoped to our RP, the user was present and,
+/// when required, verified
+fn verify_scope(
+    auth_data: &AuthenticatorData,
+    rp_id: &str,
+    user_verification: UserVerification,
+) -> Result<(), WebAuthnError> {
+    if auth_data.rp_id_hash[..] != Sha256::digest(rp_id.as_bytes())[..] {
+        return Err(WebAuthnError::InternalError(format!(
+            "Authenticator data is scoped to another RP than {}",
+            rp_id
+        )));
     }
-    #[cfg(target_os = "ios")]
-    {
-        Ok(true) // ASWebAuthenticationSession is available on iOS 12+
+    if !auth_data.user_present() {
+        return Err(WebAuthnError::InternalError(
+            "Authenticator did not confirm user presence".to_string(),
+        ));
     }
-    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
-    {
-        Ok(false)
+    if user_verification == UserVerification::Required && !auth_data.user_verified() {
+        return Err(WebAuthnError::UserVerificationFailed);
     }
+    Ok(())
 }
 
-/// Authenticate user for site access using native WebAuthn
-#[command]
-pub async fn authenticate_passkey(
-    site_id: String,
-    auth_config: SiteAuthConfig,
-) -> Result<AuthenticationResult, String> {
-    #[cfg(any(target_os = "macos", target_os = "ios"))]
-    {
-        let editing_domain = get_editing_domain();
-        match authenticate_with_native_webauthn(&site_id, &auth_config, &editing_domain).await {
-            Ok(result) => Ok(result),
-            Err(error) => Ok(AuthenticationResult {
-                success: false,
-                error: Some(error),
-                credential_id: None,
-            }),
-        }
+/// Refuse a registration that requires a discoverable credential the platform
+/// cannot store, before any prompt is shown
+fn check_resident_key<R: Runtime>(
+    platform: &dyn WebAuthnPlatform<R>,
+    resident_key: ResidentKeyRequirement,
+) -> Result<(), WebAuthnError> {
+    if resident_key == ResidentKeyRequirement::Required && !platform.capabilities().resident_key_support {
+        return Err(WebAuthnError::ResidentKeyNotSupported);
     }
-    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
-    {
-        Ok(AuthenticationResult {
-            success: false,
-            error: Some("WebAuthn not supported on this platform".to_string()),
-            credential_id: None,
-        })
+    Ok(())
+}
+
+/// Refuse a registration that must stay on the device when the authenticator
+/// syncs every passkey it creates, before any prompt is shown
+fn check_device_bound<R: Runtime>(
+    platform: &dyn WebAuthnPlatform<R>,
+    authenticator_attachment: AuthenticatorAttachment,
+    device_bound: bool,
+) -> Result<(), WebAuthnError> {
+    if device_bound && !platform.creates_device_bound(authenticator_attachment) {
+        return Err(WebAuthnError::SyncControlUnsupported);
     }
+    Ok(())
 }
 
-/// Register a new WebAuthn credential using native WebAuthn
-#[command]
-pub async fn register_passkey(
-    site_id: String,
-    site_name: String,
-    user_display_name: Option<String>,
-) -> Result<RegistrationResult, String> {
-    #[cfg(any(target_os = "macos", target_os = "ios"))]
-    {
-        let editing_domain = get_editing_domain();
-        match register_with_native_webauthn(&site_id, &site_name, &user_display_name, &editing_domain).await {
-            Ok(result) => Ok(result),
-            Err(error) => Ok(RegistrationResult {
-                success: false,
-                auth_config: None,
-                error: Some(error),
-            }),
+/// The attachment a ceremony runs with when the caller does not ask for one:
+/// the platform authenticator where there is one, security keys otherwise
+fn resolve_attachment<R: Runtime>(
+    platform: &dyn WebAuthnPlatform<R>,
+    authenticator_attachment: Option<AuthenticatorAttachment>,
+) -> AuthenticatorAttachment {
+    authenticator_attachment.unwrap_or_else(|| {
+        if platform.capabilities().platform_authenticator {
+            AuthenticatorAttachment::Platform
+        } else {
+            AuthenticatorAttachment::CrossPlatform
         }
+    })
+}
+
+/// Refuse a ceremony for a kind of authenticator the platform cannot use,
+/// before any prompt is shown
+fn check_authenticator_attachment<R: Runtime>(
+    platform: &dyn WebAuthnPlatform<R>,
+    authenticator_attachment: AuthenticatorAttachment,
+) -> Result<(), WebAuthnError> {
+    let capabilities = platform.capabilities();
+    let supported = match authenticator_attachment {
+        AuthenticatorAttachment::Platform => capabilities.platform_authenticator,
+        AuthenticatorAttachment::CrossPlatform => capabilities.roaming_authenticator,
+        AuthenticatorAttachment::Any => capabilities.platform_authenticator || capabilities.roaming_authenticator,
+    };
+    if !supported {
+        return Err(WebAuthnError::PlatformNotSupported);
     }
-    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
-    {
-        Ok(RegistrationResult {
-            success: false,
-            auth_config: None,
-            error: Some("WebAuthn not supported on this platform".to_string()),
-        })
+    Ok(())
+}
+
+/// Longest site ID accepted, as for a domain name
+const MAX_SITE_ID_CHARS: usize = 253;
+
+/// Check a site ID has the shape of a domain name or a site slug
+///
+/// Site IDs key the credential store and label passkeys, so they must not
+/// carry URLs, paths or whitespace: only ASCII letters, digits, hyphens, dots
+/// and the underscores site slugs can hold, at most 253 of them, without
+/// leading, trailing or consecutive dots.
+pub fn validate_site_id(id: &str) -> Result<(), WebAuthnError> {
+    let invalid = |reason: &str| Err(WebAuthnError::InvalidInput(format!("Invalid site_id {:?}: {}", id, reason)));
+    if id.is_empty() {
+        return invalid("must not be empty");
+    }
+    if id.len() > MAX_SITE_ID_CHARS {
+        return invalid(&format!("must be at most {} characters", MAX_SITE_ID_CHARS));
     }
+    if id.contains("://") {
+        return invalid("must not be a URL");
+    }
+    if let Some(c) = id.chars().find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))) {
+        return invalid(&format!("must not contain {:?}", c));
+    }
+    if id.starts_with('.') || id.ends_with('.') {
+        return invalid("must not start or end with a dot");
+    }
+    if id.contains("..") {
+        return invalid("must not contain consecutive dots");
+    }
+    Ok(())
 }
 
-#[cfg(any(target_os = "macos", target_os = "ios"))]
-async fn authenticate_with_native_webauthn(
-    site_id: &str,
-    auth_config: &SiteAuthConfig,
-    editing_domain: &str,
-) -> Result<AuthenticationResult, String> {
-    // Generate a fresh challenge for this authentication
-    let challenge = generate_challenge();
-
-    log::info!("Starting native WebAuthn authentication for site: {}", site_id);
-    log::info!("Challenge: {}", challenge);
-    log::info!("Editing domain: {}", editing_domain);
-    log::info!("Credential ID: {}", auth_config.credential_id);
-
-    // TODO: Implement actual ASAuthorizationController integration
-    // This would involve:
-    // 1. Creating ASAuthorizationPlatformPublicKeyCredentialProvider
-    // 2. Setting up ASAuthorizationController with delegate
-    // 3. Configuring presentation context
-    // 4. Handling biometric authentication prompt
-    // 5. Processing the authentication response
-
-    // For now, simulate successful authentication in development
-    if cfg!(debug_assertions) {
-        log::warn!("Development mode: simulating successful authentication");
-
-        // Use async sleep from tokio
-        tokio::time::sleep(Duration::from_millis(1500)).await;
-
-        return Ok(AuthenticationResult {
-            success: true,
-            error: None,
-            credential_id: Some(auth_config.credential_id.clone()),
-        });
+/// Rate limiter key shared by every `discover_credentials` call, which names no
+/// site; no valid site ID can take it
+const DISCOVERY_RATE_LIMIT_KEY: &str = "<discovery>";
+
+/// Longest prompt message accepted; system prompts cut longer ones off mid-word
+const MAX_PROMPT_MESSAGE_CHARS: usize = 128;
+
+/// Validate a caller-supplied reason for the authenticator prompt
+fn check_prompt_message(prompt_message: &Option<String>) -> Result<(), WebAuthnError> {
+    let Some(message) = prompt_message else {
+        return Ok(());
+    };
+    if message.trim().is_empty() {
+        return Err(WebAuthnError::InvalidInput("prompt_message must not be empty".to_string()));
+    }
+    if message.chars().count() > MAX_PROMPT_MESSAGE_CHARS {
+        return Err(WebAuthnError::InvalidInput(format!(
+            "prompt_message must be at most {} characters",
+            MAX_PROMPT_MESSAGE_CHARS
+        )));
     }
+    Ok(())
+}
 
-    // In production, return framework status
-    Ok(AuthenticationResult {
-        success: false,
-        error: Some("Native WebAuthn authentication requires ASAuthorizationController integration".to_string()),
-        credential_id: Some(auth_config.credential_id.clone()),
-    })
+/// Longest user display name accepted, as for prompt messages
+const MAX_DISPLAY_NAME_CHARS: usize = 128;
+
+/// Validate a user display name given after registration
+fn check_display_name(display_name: &str) -> Result<(), WebAuthnError> {
+    if display_name.trim().is_empty() {
+        return Err(WebAuthnError::InvalidInput("display_name must not be empty".to_string()));
+    }
+    if display_name.chars().count() > MAX_DISPLAY_NAME_CHARS {
+        return Err(WebAuthnError::InvalidInput(format!(
+            "display_name must be at most {} characters",
+            MAX_DISPLAY_NAME_CHARS
+        )));
+    }
+    if display_name.chars().any(char::is_control) {
+        return Err(WebAuthnError::InvalidInput(
+            "display_name must not contain control characters".to_string(),
+        ));
+    }
+    Ok(())
 }
 
-#[cfg(any(target_os = "macos", target_os = "ios"))]
-async fn register_with_native_webauthn(
-    site_id: &str,
-    site_name: &str,
-    user_display_name: &Option<String>,
-    editing_domain: &str,
-) -> Result<RegistrationResult, String> {
-    // Generate a random challenge for this registration
-    let challenge = generate_challenge();
-
-    log::info!("Starting native WebAuthn registration for site: {}", site_id);
-    log::info!("Challenge: {}", challenge);
-    log::info!("Site name: {}", site_name);
-    log::info!("User display name: {:?}", user_display_name);
-    log::info!("Editing domain: {}", editing_domain);
-
-    // TODO: Implement actual ASAuthorizationController integration
-    // This would involve:
-    // 1. Creating ASAuthorizationPlatformPublicKeyCredentialProvider
-    // 2. Setting up registration request with challenge
-    // 3. Configuring ASAuthorizationController with delegate
-    // 4. Handling biometric registration prompt
-    // 5. Extracting public key and credential ID from response
-
-    // Create a placeholder auth config with proper structure
-    let auth_config = SiteAuthConfig {
-        public_key: if cfg!(debug_assertions) {
-            format!("dev_public_key_{}", challenge)
-        } else {
-            format!("placeholder_public_key_{}", challenge)
-        },
-        credential_id: if cfg!(debug_assertions) {
-            format!("dev_credential_id_{}", site_id)
-        } else {
-            format!("placeholder_credential_id_{}", site_id)
-        },
-        requires_auth: true,
-        user_display_name: user_display_name.clone(),
-        registered_at: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            .to_string(),
+/// Passkeys a site may have unless the builder or config allow another number
+const DEFAULT_MAX_CREDENTIALS_PER_SITE: usize = 10;
+
+/// Failed authentications that lock a passkey unless the builder or config allow
+/// another number
+const DEFAULT_LOCKOUT_THRESHOLD: u32 = 10;
+
+/// Whether a failed ceremony counts against the passkeys it offered
+///
+/// Only failures that point at the passkey or the user count: the platform
+/// rejecting the biometric or PIN (`BiometricFailed`), an assertion without the
+/// user verification asked for (`UserVerificationFailed`), and the
+/// authenticator not holding any of the passkeys or asserting with another one
+/// (`CredentialNotFound`). A dismissed or timed out prompt, or a platform
+/// fault, says nothing about the credential. Platform assertions carry no
+/// signature to check, so `SignatureInvalid` never comes up here.
+fn counts_toward_lockout(error: &WebAuthnError) -> bool {
+    matches!(
+        error,
+        WebAuthnError::BiometricFailed | WebAuthnError::UserVerificationFailed | WebAuthnError::CredentialNotFound(_)
+    )
+}
+
+/// How long an authentication waits for the platform by default
+const DEFAULT_AUTHENTICATION_TIMEOUT_SECS: u64 = 60;
+
+/// Bounds for a caller-supplied timeout; a shorter one leaves no time for the
+/// prompt and a longer one lets a forgotten prompt go on holding the command
+const MIN_AUTHENTICATION_TIMEOUT_SECS: u64 = 10;
+const MAX_AUTHENTICATION_TIMEOUT_SECS: u64 = 300;
+
+/// The caller's authentication timeout, brought within bounds
+fn authentication_timeout(timeout_seconds: Option<u64>) -> Duration {
+    let requested = timeout_seconds.unwrap_or(DEFAULT_AUTHENTICATION_TIMEOUT_SECS);
+    let seconds = requested.clamp(MIN_AUTHENTICATION_TIMEOUT_SECS, MAX_AUTHENTICATION_TIMEOUT_SECS);
+    if seconds != requested {
+        webauthn_log!(
+            Level::Warn,
+            WebAuthnLogEvent::new(LogOperation::Authentication);
+            "Authentication timeout of {} seconds is out of range; using {} seconds",
+            requested,
+            seconds
+        );
+    }
+    Duration::from_secs(seconds)
+}
+
+/// The largeBlob operation asked for, if any
+///
+/// The extension reads or writes, never both in one assertion.
+fn large_blob_operation(read: bool, write: Option<&str>) -> Result<Option<LargeBlobOperation>, WebAuthnError> {
+    match (read, write) {
+        (true, Some(_)) => Err(WebAuthnError::InvalidInput(
+            "large_blob_read and large_blob_write cannot be used together".to_string(),
+        )),
+        (true, None) => Ok(Some(LargeBlobOperation::Read)),
+        (false, Some(data)) => URL_SAFE_NO_PAD
+            .decode(data)
+            .map(|data| Some(LargeBlobOperation::Write(data)))
+            .map_err(|_| WebAuthnError::InvalidInput("large_blob_write is not base64url".to_string())),
+        (false, None) => Ok(None),
+    }
+}
+
+/// Refuse a ceremony that requires user verification the platform cannot offer,
+/// before any prompt is shown
+fn check_user_verification<R: Runtime>(
+    platform: &dyn WebAuthnPlatform<R>,
+    user_verification: UserVerification,
+) -> Result<(), WebAuthnError> {
+    if user_verification == UserVerification::Required && !platform.capabilities().user_verification_support {
+        return Err(WebAuthnError::UserVerificationFailed);
+    }
+    Ok(())
+}
+
+/// Check a new credential's authenticator data against the registration request
+///
+/// The signature over the attestation statement is left to whoever consumes
+/// the credential; this makes sure the data is scoped to our RP, the user was
+/// present and the credential is the one the platform reported.
+fn verify_registration(
+    auth_data: &AuthenticatorData,
+    rp_id: &str,
+    credential_id: &[u8],
+    user_verification: UserVerification,
+) -> Result<CoseKey, WebAuthnError> {
+    verify_scope(auth_data, rp_id, user_verification)?;
+
+    let (Some(attested_id), Some(cose_key)) = (&auth_data.credential_id, &auth_data.cose_key) else {
+        return Err(WebAuthnError::InternalError(
+            "Authenticator data carries no attested credential".to_string(),
+        ));
     };
+    if attested_id != credential_id {
+        return Err(WebAuthnError::InternalError(
+            "Authenticator data describes another credential than the one returned".to_string(),
+        ));
+    }
+    Ok(cose_key.clone())
+}
 
-    // In development mode, simulate successful registration
-    if cfg!(debug_assertions) {
-        log::warn!("Development mode: simulating successful registration");
+/// RP ID release builds use, from `[package.metadata.webauthn] rp-id` in Cargo.toml
+const PRODUCTION_RP_ID: &str = env!("SPARKTYPE_RP_ID");
 
-        // Use async sleep from tokio
-        tokio::time::sleep(Duration::from_millis(2000)).await;
+const _: () = assert!(
+    is_registrable_domain(PRODUCTION_RP_ID),
+    "[package.metadata.webauthn] rp-id must be a domain with at least one label below its TLD"
+);
 
-        return Ok(RegistrationResult {
-            success: true,
-            auth_config: Some(auth_config),
-            error: None,
-        });
+const _: () = assert!(
+    rp_id_pin::matches_pin(PINNED_RP_ID_HASH, PRODUCTION_RP_ID),
+    "[package.metadata.webauthn] rp-id does not hash to SPARKTYPE_RP_ID_HASH"
+);
+
+/// Whether `domain` is a lowercase DNS name of the form `label(.label)*.tld`
+///
+/// Passkeys are scoped to a registrable domain or one of its subdomains, so a
+/// bare TLD, an IP address or `localhost` is rejected. Public suffixes with
+/// several labels (`co.uk`) are not known here.
+const fn is_registrable_domain(domain: &str) -> bool {
+    let bytes = domain.as_bytes();
+    if bytes.is_empty() || bytes.len() > 253 {
+        return false;
     }
 
-    // In production, return framework status
-    Ok(RegistrationResult {
-        success: false,
-        auth_config: Some(auth_config),
-        error: Some("Native WebAuthn registration requires ASAuthorizationController integration".to_string()),
-    })
+    let mut labels = 0;
+    let mut label_start = 0;
+    let mut tld_is_alphabetic = true;
+    let mut i = 0;
+    while i <= bytes.len() {
+        if i == bytes.len() || bytes[i] == b'.' {
+            let length = i - label_start;
+            if length == 0 || length > 63 || bytes[label_start] == b'-' || bytes[i - 1] == b'-' {
+                return false;
+            }
+            labels += 1;
+            tld_is_alphabetic = true;
+            let mut j = label_start;
+            while j < i {
+                if !bytes[j].is_ascii_lowercase() {
+                    tld_is_alphabetic = false;
+                }
+                j += 1;
+            }
+            label_start = i + 1;
+        } else if !(bytes[i].is_ascii_lowercase() || bytes[i].is_ascii_digit() || bytes[i] == b'-') {
+            return false;
+        }
+        i += 1;
+    }
+
+    labels >= 2 && tld_is_alphabetic
+}
+
+/// Environment variable that unlocks developer features such as `set_rp_id` in
+/// release builds (for CI)
+const DEVELOPER_MODE_ENV: &str = "SPARKTYPE_DEVELOPER_MODE";
+
+/// The relying party ceremonies run against
+pub struct RelyingParty {
+    /// RP ID of release builds
+    production: String,
+    /// RP ID set through `set_rp_id`, taking precedence over the built-in domain
+    overridden: RwLock<Option<String>>,
+}
+
+impl RelyingParty {
+    fn new(production: String) -> Self {
+        Self {
+            production,
+            overridden: RwLock::new(None),
+        }
+    }
+
+    /// Get the appropriate editing domain for WebAuthn authentication
+    /// Uses localhost in development, configured domain in production
+    ///
+    /// Mirrors the getEditingDomain() function from webauthn.service.ts
+    pub(crate) fn editing_domain(&self) -> String {
+        if let Some(rp_id) = self.overridden.read().ok().and_then(|rp_id| rp_id.clone()) {
+            return rp_id;
+        }
+
+        // In development, use localhost
+        if cfg!(debug_assertions) {
+            "localhost".to_string()
+        } else {
+            // Use configured editing domain for production
+            self.production.clone()
+        }
+    }
+
+    /// `editing_domain`, which release builds first check against the RP ID
+    /// the build was pinned to; what ceremonies run against
+    pub(crate) fn ceremony_domain(&self) -> Result<String, WebAuthnError> {
+        let domain = self.editing_domain();
+        if !cfg!(debug_assertions) {
+            rp_id_pin::check(&domain)?;
+        }
+        Ok(domain)
+    }
+}
+
+/// Whether developer features, such as overriding the RP ID, are unlocked:
+/// debug builds, or `SPARKTYPE_DEVELOPER_MODE=1`
+pub(crate) fn developer_mode() -> bool {
+    cfg!(debug_assertions) || std::env::var(DEVELOPER_MODE_ENV).is_ok_and(|value| value == "1")
+}
+
+/// Version of the webauthn plugin, which is the app's
+pub const PLUGIN_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short hash of the commit the app was built from; `unknown` when it was not
+/// built from a git checkout
+pub const GIT_SHA: &str = env!("SPARKTYPE_GIT_SHA");
+
+/// When the app was built, as an RFC 3339 UTC timestamp; `SOURCE_DATE_EPOCH`
+/// when the build set it
+pub const BUILD_TIMESTAMP: &str = env!("SPARKTYPE_BUILD_TIMESTAMP");
+
+/// Get the webauthn plugin's version, a semver string
+///
+/// The frontend checks it on startup, so an app too old for the credential
+/// format it finds asks to be updated instead of failing to deserialize.
+#[command]
+pub fn get_plugin_version<R: Runtime>(window: WebviewWindow<R>) -> Result<String, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    Ok(PLUGIN_VERSION.to_string())
+}
+
+/// Get the relying-party ID used for registration and authentication
+#[command]
+pub async fn get_rp_id<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+) -> Result<String, String> {
+    check_window(&window, CapabilityTier::ReadOnly).map_err(|error| error.to_string())?;
+    Ok(state.relying_party.editing_domain())
+}
+
+/// Override the relying-party ID without recompiling
+///
+/// Only available in debug builds or when `SPARKTYPE_DEVELOPER_MODE=1` is set,
+/// so integration tests and CI can target their own domain.
+#[command]
+pub async fn set_rp_id<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    domain: String,
+) -> Result<(), String> {
+    check_window(&window, CapabilityTier::Manage).map_err(|error| error.to_string())?;
+    if !developer_mode() {
+        return Err("Overriding the RP ID is only allowed in development builds".to_string());
+    }
+
+    let domain = domain.trim().to_ascii_lowercase();
+    if domain.is_empty() || domain.contains(|c: char| c == '/' || c == ':' || c.is_whitespace()) {
+        return Err(format!("Invalid RP ID: {:?} (expected a bare domain)", domain));
+    }
+
+    webauthn_log!(
+        Level::Warn,
+        WebAuthnLogEvent::new(LogOperation::RpIdOverride);
+        "Overriding WebAuthn RP ID with: {}",
+        domain
+    );
+    *state
+        .relying_party
+        .overridden
+        .write()
+        .map_err(|_| "RP ID override lock is poisoned".to_string())? = Some(domain);
+    Ok(())
+}
+
+/// Check if WebAuthn is available on the current platform
+#[command]
+pub async fn is_webauthn_available<R: Runtime>(window: WebviewWindow<R>) -> Result<bool, String> {
+    check_window(&window, CapabilityTier::ReadOnly).map_err(|error| error.to_string())?;
+    Ok(window.state::<Platform<R>>().is_available())
+}
+
+/// Describe the authenticators available, beyond the yes/no of `is_webauthn_available`
+#[command]
+pub async fn get_webauthn_capabilities<R: Runtime>(
+    window: WebviewWindow<R>,
+) -> Result<WebAuthnCapabilities, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    Ok(window.state::<Platform<R>>().capabilities())
 }
 
-/// Initialize the WebAuthn plugin
-pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
-    tauri::plugin::Builder::new("webauthn")
-        .invoke_handler(tauri::generate_handler![
-            is_webauthn_available,
-            authenticate_passkey,
-            register_passkey
-        ])
-        .build()
-}
\ No newline at end of file
+/// Authenticate user for site access using native WebAuthn
+///
+/// `site_id` must pass `validate_site_id`; other IDs fail with `InvalidInput`.
+/// `challenge` must come from `create_challenge`, for this site or for none,
+/// and be used within its TTL.
+/// A site whose `requiresAuth` is false succeeds at once, without a ceremony,
+/// unless the credential store holds it as requiring authentication.
+/// Any of the site's registered credentials is accepted; the one used has its
+/// `lastUsedAt` and signature counter refreshed in the credential store. A
+/// counter that fails to advance flags a possible clone instead of failing.
+/// `user_verification` defaults to `preferred`. `prompt_message` (at most 128
+/// characters) words the prompt where the platform allows it.
+///
+/// `authenticator_attachment` picks the platform authenticator, security keys
+/// or either; by default the platform authenticator where there is one. The
+/// result's `authenticatorAttachment` tells which the user asserted with.
+///
+/// `prf` asks the authenticator to evaluate the PRF extension, so a key can be
+/// derived from the passkey; the result's `prfOutput` carries the output when
+/// the authenticator supports it and is omitted otherwise.
+///
+/// `large_blob_read` and `large_blob_write` (base64url, up to about 2 KB) read
+/// or replace the data the authenticator keeps with the credential through the
+/// largeBlob extension; asking for both fails with `InvalidInput`. A read
+/// returns the data as `largeBlobData`. Where the authenticator does not support
+/// the extension the assertion succeeds without it.
+///
+/// Attempts are rate limited per site (5 a minute by default); beyond that the
+/// command fails with `RateLimited` before consuming the challenge. A challenge
+/// that already completed an authentication fails with `ReplayAttack`.
+///
+/// Failed user verification, an invalid signature and an unknown credential
+/// count against each passkey the ceremony offered; one that fails
+/// `lockout_threshold` times in a row (10 by default) is locked in the
+/// credential store and left out of later ceremonies until `unlock_credential`.
+/// When every passkey in `auth_config` is locked, the command fails with
+/// `CredentialLocked` before consuming the challenge.
+///
+/// `timeout_seconds` bounds the wait for the platform (60 by default, clamped
+/// to between 10 and 300). When it passes, the prompt is dismissed where the
+/// platform allows it and the command fails with `Timeout`.
+///
+/// Success opens a session for the site (30 minutes by default), which
+/// `get_site_auth_status` reports. It also unlocks the app if the app lock
+/// (see `configure_app_lock`) had locked it, reopening the sessions the lock
+/// ended; while locked with `requireBiometric`, the user must be verified.
+/// Within the session's first seconds, its grace period (10 by default, see
+/// `set_grace_period`), the command succeeds again without a ceremony and with
+/// `fromCache` set, provided neither `prf` nor a largeBlob operation is asked
+/// for. The challenge is still redeemed and the rate limit still applies.
+///
+/// How long each phase took is logged at debug level, and returned as
+/// `timings` in debug builds or with `SPARKTYPE_PERF_TRACE=1`.
+///
+/// Emits `webauthn://authentication-*` events as the ceremony progresses.
+#[command]
+#[allow(clippy::too_many_arguments)] // Each argument is a command parameter or managed state
+pub async fn authenticate_passkey<R: Runtime>(
+    window: WebviewWindow<R>,
+    platform: State<'_, Platform<R>>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    auth_config: SiteAuthConfig,
+    challenge: String,
+    user_verification: Option<UserVerification>,
+    prompt_message: Option<String>,
+    authenticator_attachment: Option<AuthenticatorAttachment>,
+    prf: Option<PrfExtensionInput>,
+    large_blob_write: Option<String>,
+    large_blob_read: Option<bool>,
+    timeout_seconds: Option<u64>,
+) -> Result<AuthenticationResult, WebAuthnError> {
+    let started = Instant::now();
+    let result = authenticate(
+        window,
+        platform,
+        state.clone(),
+        store.clone(),
+        site_id.clone(),
+        auth_config,
+        challenge,
+        user_verification,
+        prompt_message,
+        authenticator_attachment,
+        prf,
+        large_blob_write,
+        large_blob_read,
+        timeout_seconds,
+    )
+    .await;
+    state.audit_log.record(
+        &store,
+        AuditEvent::Authentication {
+            site_id,
+            credential_id: result.as_ref().ok().and_then(|result| result.credential_id.clone()),
+            duration_ms: as_millis(started.elapsed()),
+            outcome: audit_log::outcome(&result),
+        },
+    );
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn authenticate<R: Runtime>(
+    window: WebviewWindow<R>,
+    platform: State<'_, Platform<R>>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    mut auth_config: SiteAuthConfig,
+    challenge: String,
+    user_verification: Option<UserVerification>,
+    prompt_message: Option<String>,
+    authenticator_attachment: Option<AuthenticatorAttachment>,
+    prf: Option<PrfExtensionInput>,
+    large_blob_write: Option<String>,
+    large_blob_read: Option<bool>,
+    timeout_seconds: Option<u64>,
+) -> Result<AuthenticationResult, WebAuthnError> {
+    check_window(&window, CapabilityTier::Authenticate)?;
+    validate_site_id(&site_id)?;
+    let operation_id = OperationId::new();
+    let log_event = WebAuthnLogEvent::new(LogOperation::Authentication).site(&site_id);
+    let started = Instant::now();
+    let mut timings = OperationTimings::default();
+    if !auth_config.requires_auth {
+        // The frontend's copy could have been altered; the stored one has the last word
+        let stored = store.load_credential(&site_id)?;
+        if stored.as_ref().is_some_and(|config| config.requires_auth) {
+            webauthn_log!(
+                Level::Warn,
+                log_event,
+                operation_id:% = operation_id;
+                "Site {} requires authentication, although the given config says otherwise",
+                site_id
+            );
+        } else {
+            webauthn_log!(
+                Level::Debug,
+                log_event.succeeded().took(started.elapsed()),
+                operation_id:% = operation_id;
+                "Site {} does not require authentication",
+                site_id
+            );
+            return Ok(AuthenticationResult {
+                success: true,
+                credential_id: None,
+                site_name: stored.and_then(|config| config.site_name),
+                sign_count: 0,
+                possible_clone_detected: false,
+                authenticator_attachment: String::new(),
+                prf_output: None,
+                large_blob_data: None,
+                timings: None,
+                operation_id: operation_id.to_string(),
+                from_cache: false,
+                attempts: 0,
+            });
+        }
+    }
+    check_prompt_message(&prompt_message)?;
+    let prf = prf.map(|prf| prf.decode(&auth_config)).transpose()?;
+    let large_blob = large_blob_operation(large_blob_read.unwrap_or(false), large_blob_write.as_deref())?;
+    let timeout = authentication_timeout(timeout_seconds);
+    state.rate_limiter.check(&site_id).inspect_err(|_| state.metrics.rate_limit_hit())?;
+    if state.used_challenges.contains(&challenge) {
+        let error = WebAuthnError::ReplayAttack;
+        webauthn_log!(
+            Level::Warn,
+            log_event.failed(&error).took(started.elapsed()),
+            operation_id:% = operation_id;
+            "Rejected a replayed authentication for site {}: its challenge was already used",
+            site_id
+        );
+        return Err(error);
+    }
+    // The frontend's copy could have been altered; locks are only kept in the store
+    let locked: HashSet<String> = store
+        .load_credential(&site_id)?
+        .map(|config| config.credentials)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.locked)
+        .map(|entry| entry.credential_id)
+        .collect();
+    if !locked.is_empty() {
+        let offered = auth_config.credentials.len();
+        auth_config.credentials.retain(|entry| !locked.contains(&entry.credential_id));
+        if offered > 0 && auth_config.credentials.is_empty() {
+            let error = WebAuthnError::CredentialLocked;
+            webauthn_log!(
+                Level::Warn,
+                log_event.failed(&error).took(started.elapsed()),
+                operation_id:% = operation_id;
+                "Every passkey offered for site {} is locked",
+                site_id
+            );
+            return Err(error);
+        }
+    }
+    let phase = Instant::now();
+    state.challenges.redeem(&challenge, &site_id).inspect_err(|_| state.metrics.challenge_expired())?;
+    timings.challenge_gen_us = as_micros(phase.elapsed());
+    // The PRF and largeBlob extensions need the authenticator
+    if prf.is_none() && large_blob.is_none() {
+        if let Some(session) = state.sessions.in_grace_period(&site_id) {
+            webauthn_log!(
+                Level::Debug,
+                log_event.succeeded().took(started.elapsed()),
+                operation_id:% = operation_id;
+                "Site {} authenticated at {}, within its grace period; not prompting",
+                site_id,
+                session.authenticated_at
+            );
+            state.used_challenges.mark_used(&challenge);
+            let site_name = stored_site_name(&store, &site_id, operation_id);
+            WebAuthnEvent::AuthenticationCompleted {
+                site_id,
+                credential_id: None,
+                error: None,
+            }
+            .emit(&window);
+            return Ok(AuthenticationResult {
+                success: true,
+                credential_id: None,
+                site_name,
+                sign_count: 0,
+                possible_clone_detected: false,
+                authenticator_attachment: String::new(),
+                prf_output: None,
+                large_blob_data: None,
+                timings: None,
+                operation_id: operation_id.to_string(),
+                from_cache: true,
+                attempts: 0,
+            });
+        }
+    }
+    let editing_domain = state.relying_party.ceremony_domain()?;
+    state.metrics.authentication_started();
+    WebAuthnEvent::AuthenticationStarted {
+        site_id: site_id.clone(),
+    }
+    .emit(&window);
+
+    let result = authenticate_with_platform(
+        &**platform,
+        &window,
+        &site_id,
+        &auth_config,
+        &challenge,
+        state.app_lock.user_verification(user_verification.unwrap_or_default()),
+        prompt_message,
+        resolve_attachment(&**platform, authenticator_attachment),
+        prf,
+        large_blob,
+        timeout,
+        &state.retry,
+        &editing_domain,
+        operation_id,
+        &mut timings,
+    )
+    .await;
+    if let Err(error) = &result {
+        webauthn_log!(
+            Level::Warn,
+            log_event.failed(error).took(started.elapsed()),
+            operation_id:% = operation_id;
+            "Authentication failed for site {}: {}",
+            site_id,
+            error
+        );
+        if counts_toward_lockout(error) {
+            record_failure(&store, &site_id, &auth_config, state.lockout_threshold, log_event, operation_id);
+        }
+    }
+    state.metrics.authentication_finished(result.is_ok());
+    WebAuthnEvent::AuthenticationCompleted {
+        site_id: site_id.clone(),
+        credential_id: result.as_ref().ok().and_then(|result| result.credential_id.clone()),
+        error: result.as_ref().err().cloned(),
+    }
+    .emit(&window);
+    auth_notifications::notify(
+        &state.auth_notifications,
+        &site_id,
+        auth_config.user_display_name.as_deref(),
+        result.as_ref().map(|_| ()),
+    );
+    let mut result = result?;
+    state.used_challenges.mark_used(&challenge);
+
+    let phase = Instant::now();
+    if let Some(credential_id) = &result.credential_id {
+        let stored_count = match store.touch_credential(&site_id, credential_id, Utc::now(), result.sign_count) {
+            Ok(stored_count) => stored_count,
+            Err(error) => {
+                webauthn_log!(
+                    Level::Warn,
+                    log_event,
+                    operation_id:% = operation_id;
+                    "Failed to record passkey use for site {}: {}",
+                    site_id,
+                    error
+                );
+                auth_config.find_credential(credential_id).map(|entry| entry.sign_count)
+            }
+        };
+
+        // Authenticators without a counter always report 0
+        if let Some(stored_count) = stored_count.filter(|&count| count != 0 && result.sign_count != 0) {
+            if result.sign_count <= stored_count {
+                webauthn_log!(
+                    Level::Warn,
+                    log_event,
+                    operation_id:% = operation_id;
+                    "Signature counter for a passkey of site {} went from {} to {}; the authenticator may be cloned",
+                    site_id,
+                    stored_count,
+                    result.sign_count
+                );
+                result.possible_clone_detected = true;
+            }
+        }
+    }
+
+    result.site_name = stored_site_name(&store, &site_id, operation_id);
+
+    let grace_period_seconds = state.grace_period_seconds.load(Ordering::Relaxed);
+    let session = state.sessions.open(&site_id, grace_period_seconds);
+    state.app_lock.authenticated(&state.sessions, grace_period_seconds);
+    timings.session_write_us = as_micros(phase.elapsed());
+    webauthn_log!(
+        Level::Debug,
+        log_event,
+        operation_id:% = operation_id;
+        "Site {} authenticated until {}",
+        session.site_id,
+        session.expires_at()
+    );
+
+    timings.total_ms = as_millis(started.elapsed());
+    webauthn_log!(
+        Level::Debug,
+        log_event.succeeded().took(started.elapsed()),
+        operation_id:% = operation_id;
+        "Authentication timings: {}",
+        timings
+    );
+    result.timings = perf_trace_enabled().then_some(timings);
+    Ok(result)
+}
+
+/// Count a failed ceremony against the passkeys it offered, logging those it locks
+fn record_failure(
+    store: &CredentialStore,
+    site_id: &str,
+    auth_config: &SiteAuthConfig,
+    threshold: u32,
+    log_event: WebAuthnLogEvent<'_>,
+    operation_id: OperationId,
+) {
+    let offered = auth_config
+        .credentials
+        .iter()
+        .map(|entry| entry.credential_id.clone())
+        .collect::<Vec<_>>();
+    match store.record_failure(site_id, &offered, threshold) {
+        Ok(locked) => {
+            for credential_id in locked {
+                webauthn_log!(
+                    Level::Warn,
+                    log_event,
+                    operation_id:% = operation_id;
+                    "Locked passkey {} of site {} after {} failed attempts",
+                    Redacted(&credential_id),
+                    site_id,
+                    threshold
+                );
+            }
+        }
+        Err(error) => webauthn_log!(
+            Level::Warn,
+            log_event,
+            operation_id:% = operation_id;
+            "Failed to record the failed attempt for site {}: {}",
+            site_id,
+            error
+        ),
+    }
+}
+
+/// The name the credential store holds for a site, if it can be read
+fn stored_site_name(store: &CredentialStore, site_id: &str, operation_id: OperationId) -> Option<String> {
+    match store.load_credential(site_id) {
+        Ok(config) => config.and_then(|config| config.site_name),
+        Err(error) => {
+            log::warn!(operation_id:% = operation_id; "Could not read the name of site {}: {}", site_id, error);
+            None
+        }
+    }
+}
+
+/// Register a new WebAuthn credential using native WebAuthn
+///
+/// `site_id` must pass `validate_site_id`; other IDs fail with `InvalidInput`.
+/// The credential is added to the site's existing ones in the credential store,
+/// so each device the owner registers keeps working. All of a site's
+/// credentials share its `userId`, generated with the first one. Without a
+/// `device_name` the credential is labelled with this device's name as the OS
+/// reports it, leaving out the owner's name, and numbered when another of the
+/// site's passkeys already has it. `user_verification` defaults to `preferred`.
+/// `prompt_message` (at most 128 characters) words the prompt where the
+/// platform allows it. `resident_key` defaults to `preferred`; the result's
+/// `isResident` tells whether the credential ended up discoverable.
+/// `authenticator_attachment` picks the platform authenticator, security keys
+/// or either; by default the platform authenticator where there is one. The
+/// result's `authenticatorAttachment` tells which one created the credential.
+///
+/// `existing_config` imports credentials exported from another installation
+/// instead, without a ceremony: they are added to the site's stored ones and
+/// the result has `wasImported` set. Importing is only allowed in debug builds
+/// and builds with the `allow-credential-import` feature.
+///
+/// Sites whose `requiresAuth` is false are refused with `InvalidInput`;
+/// `upgrade_site_to_passkey` protects those. A call made while another is
+/// registering for the same site fails with `RegistrationInProgress`. For a
+/// site `should_reenroll` found to have lost its passkeys, the stale ones are
+/// deleted once the new one is stored; a ceremony that fails or is cancelled
+/// leaves them in place. A site that already has `max_credentials_per_site`
+/// passkeys (10 unless configured), stale ones included, is refused with
+/// `LimitExceeded` before any ceremony; imports are not limited.
+///
+/// With `no_sync` configured, the passkey must stay on this device. Where the
+/// authenticator syncs every passkey, e.g. the platform authenticator on Apple
+/// devices, the command fails with `SyncControlUnsupported` before any prompt.
+/// One that reports the new passkey as backup eligible, and so as able to
+/// sync, has it deleted again and the command fails the same way. Imports are
+/// not checked.
+///
+/// Ceremonies advance the onboarding state `get_onboarding_state` reports,
+/// checking the platform first if onboarding has not started.
+///
+/// Emits `webauthn://registration-*` events as the ceremony progresses.
+#[command]
+#[allow(clippy::too_many_arguments)] // Each argument is a command parameter or managed state
+pub async fn register_passkey<R: Runtime>(
+    window: WebviewWindow<R>,
+    platform: State<'_, Platform<R>>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    site_name: String,
+    user_display_name: Option<String>,
+    device_name: Option<String>,
+    user_verification: Option<UserVerification>,
+    prompt_message: Option<String>,
+    resident_key: Option<ResidentKeyRequirement>,
+    authenticator_attachment: Option<AuthenticatorAttachment>,
+    existing_config: Option<SiteAuthConfig>,
+) -> Result<RegistrationResult, WebAuthnError> {
+    let result = register(
+        window,
+        platform,
+        state.clone(),
+        store.clone(),
+        site_id.clone(),
+        site_name,
+        user_display_name,
+        device_name.clone(),
+        user_verification,
+        prompt_message,
+        resident_key,
+        authenticator_attachment,
+        existing_config,
+        false,
+    )
+    .await;
+    state.audit_log.record(
+        &store,
+        AuditEvent::Registration {
+            site_id,
+            device_name,
+            outcome: audit_log::outcome(&result),
+        },
+    );
+    result
+}
+
+/// Protect a site that was public, whose stored config has `requiresAuth`
+/// false, with a new passkey
+///
+/// Runs the ceremony `register_passkey` would, with the default options, and
+/// stores the new credential and `requiresAuth` true in the same store write,
+/// so a failed ceremony or a crash leaves the stored config as it was. A site
+/// with no stored config, or one already requiring auth, is refused with
+/// `InvalidInput`; `register_passkey` protects those.
+#[command]
+pub async fn upgrade_site_to_passkey<R: Runtime>(
+    window: WebviewWindow<R>,
+    platform: State<'_, Platform<R>>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    site_name: String,
+    user_display_name: Option<String>,
+) -> Result<RegistrationResult, WebAuthnError> {
+    let result = register(
+        window,
+        platform,
+        state.clone(),
+        store.clone(),
+        site_id.clone(),
+        site_name,
+        user_display_name,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        true,
+    )
+    .await;
+    state.audit_log.record(
+        &store,
+        AuditEvent::Registration {
+            site_id,
+            device_name: None,
+            outcome: audit_log::outcome(&result),
+        },
+    );
+    result
+}
+
+/// `upgrade` registers for a public site and makes it require auth, for
+/// `upgrade_site_to_passkey`
+#[allow(clippy::too_many_arguments)]
+async fn register<R: Runtime>(
+    window: WebviewWindow<R>,
+    platform: State<'_, Platform<R>>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    site_name: String,
+    user_display_name: Option<String>,
+    device_name: Option<String>,
+    user_verification: Option<UserVerification>,
+    prompt_message: Option<String>,
+    resident_key: Option<ResidentKeyRequirement>,
+    authenticator_attachment: Option<AuthenticatorAttachment>,
+    existing_config: Option<SiteAuthConfig>,
+    upgrade: bool,
+) -> Result<RegistrationResult, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    validate_site_id(&site_id)?;
+    let operation_id = OperationId::new();
+    let log_event = WebAuthnLogEvent::new(LogOperation::Registration).site(&site_id);
+    let started = Instant::now();
+    check_prompt_message(&prompt_message)?;
+    let _in_flight = state.registrations.begin(&site_id)?;
+    let stored = store.load_credential(&site_id)?;
+    let requires_auth = |config: &Option<SiteAuthConfig>| config.as_ref().map_or(true, |config| config.requires_auth);
+    if upgrade {
+        let Some(config) = &stored else {
+            return Err(WebAuthnError::InvalidInput(format!("Site {} has no stored config to upgrade", site_id)));
+        };
+        if config.requires_auth {
+            return Err(WebAuthnError::InvalidInput(format!("Site {} already requires auth", site_id)));
+        }
+    } else if !requires_auth(&stored) || !requires_auth(&existing_config) {
+        return Err(WebAuthnError::InvalidInput(
+            "cannot register credential for a site that does not require auth".to_string(),
+        ));
+    }
+    if let Some(existing_config) = existing_config {
+        state.metrics.registration_started();
+        let result = import_registration(&store, &site_id, existing_config, operation_id);
+        state.metrics.registration_finished(result.is_ok());
+        return result;
+    }
+    let editing_domain = state.relying_party.ceremony_domain()?;
+    let registered_before = stored.as_ref().map_or(0, |config| config.credentials.len());
+    let max = state.max_credentials_per_site;
+    if registered_before >= max {
+        webauthn_log!(
+            Level::Warn,
+            log_event,
+            operation_id:% = operation_id;
+            "Site {} already has {}/{} passkeys, refusing another",
+            site_id,
+            registered_before,
+            max
+        );
+        return Err(WebAuthnError::LimitExceeded { current: registered_before, max });
+    }
+    let reenrolling = state.registrations.take_reenrollment(&site_id);
+    let stale: Vec<String> = match &stored {
+        Some(config) if reenrolling => config.credentials.iter().map(|entry| entry.credential_id.clone()).collect(),
+        _ => Vec::new(),
+    };
+    let device_name = match device_name {
+        Some(device_name) => device_name,
+        None => {
+            let detected = tauri::async_runtime::spawn_blocking(get_device_name)
+                .await
+                .unwrap_or_else(|_| UNKNOWN_DEVICE.to_string());
+            let existing: Vec<&str> = match &stored {
+                Some(config) if !reenrolling => {
+                    config.credentials.iter().filter_map(|entry| entry.device_name.as_deref()).collect()
+                }
+                _ => Vec::new(),
+            };
+            make_unique_device_name(&detected, &existing)
+        }
+    };
+    let device_name = Some(device_name);
+
+    let user_id = match stored {
+        Some(config) if !config.user_id.is_empty() => config.user_id,
+        // Credentials registered before user handles were tracked used the site ID
+        Some(_) => URL_SAFE_NO_PAD.encode(site_id.as_bytes()),
+        None => generate_user_id(),
+    };
+
+    state.metrics.registration_started();
+    state.onboarding.registration_started(&store, || platform.is_available());
+    WebAuthnEvent::RegistrationStarted {
+        site_id: site_id.clone(),
+    }
+    .emit(&window);
+
+    let registered = register_with_platform(
+        &**platform,
+        &window,
+        &site_id,
+        &site_name,
+        &user_display_name,
+        &device_name,
+        &state.challenges.generate(),
+        &user_id,
+        user_verification.unwrap_or_default(),
+        resident_key.unwrap_or_default(),
+        prompt_message,
+        resolve_attachment(&**platform, authenticator_attachment),
+        state.no_sync,
+        &editing_domain,
+        operation_id,
+    )
+    .await;
+    let mut result = registered.and_then(|registered| {
+        save_registration(&store, &site_id, &site_name, &user_display_name, &user_id, registered, upgrade, operation_id)
+    });
+    if reenrolling {
+        match &mut result {
+            Ok(registration) => {
+                replace_stale_passkeys(&window, &site_id, &stale, registration, log_event, operation_id).await
+            }
+            // The stale passkeys stay until a registration replaces them
+            Err(_) => state.registrations.mark_reenrollment(&site_id),
+        }
+    }
+    match &result {
+        Ok(_) => webauthn_log!(
+            Level::Info,
+            log_event.succeeded().took(started.elapsed()),
+            operation_id:% = operation_id;
+            "Registered a passkey for site: {}",
+            site_id
+        ),
+        Err(error) => webauthn_log!(
+            Level::Warn,
+            log_event.failed(error).took(started.elapsed()),
+            operation_id:% = operation_id;
+            "Registration failed for site {}: {}",
+            site_id,
+            error
+        ),
+    }
+
+    let credential_id = result
+        .as_ref()
+        .ok()
+        .and_then(|result| result.auth_config.as_ref())
+        .and_then(|config| config.credentials.last())
+        .map(|entry| entry.credential_id.clone());
+    if let Some(credential_id) = &credential_id {
+        state.clipboard_guard.register_clipboard_sensitive(credential_id.clone());
+    }
+    state.metrics.registration_finished(result.is_ok());
+    state.onboarding.registration_finished(&store, result.is_ok());
+    WebAuthnEvent::RegistrationCompleted {
+        site_id: site_id.clone(),
+        credential_id,
+        error: result.as_ref().err().cloned(),
+    }
+    .emit(&window);
+    result
+}
+
+/// Delete the passkeys a re-enrolling site lost, now that `registration` stored
+/// the one replacing them, and drop them from its result
+async fn replace_stale_passkeys<R: Runtime>(
+    window: &WebviewWindow<R>,
+    site_id: &str,
+    stale: &[String],
+    registration: &mut RegistrationResult,
+    log_event: WebAuthnLogEvent<'_>,
+    operation_id: OperationId,
+) {
+    for credential_id in stale {
+        webauthn_log!(
+            Level::Info,
+            log_event,
+            operation_id:% = operation_id;
+            "Re-enrolled site {}, deleting passkey {}",
+            site_id,
+            Redacted(credential_id)
+        );
+        let deleted =
+            delete_passkey(window.clone(), window.state(), site_id.to_string(), credential_id.clone()).await;
+        match deleted {
+            Ok(()) => {
+                if let Some(config) = &mut registration.auth_config {
+                    config.credentials.retain(|entry| &entry.credential_id != credential_id);
+                }
+            }
+            // Only the code: CredentialNotFound would repeat the ID in full
+            Err(error) => webauthn_log!(
+                Level::Warn,
+                log_event,
+                operation_id:% = operation_id;
+                "Could not delete stale passkey {}: {}",
+                Redacted(credential_id),
+                error.code()
+            ),
+        }
+    }
+}
+
+/// Add a newly registered credential to the site's configuration in the store,
+/// making the site require auth when `upgrade` is set
+#[allow(clippy::too_many_arguments)]
+fn save_registration(
+    store: &CredentialStore,
+    site_id: &str,
+    site_name: &str,
+    user_display_name: &Option<String>,
+    user_id: &str,
+    registered: RegisteredCredential,
+    upgrade: bool,
+    operation_id: OperationId,
+) -> Result<RegistrationResult, WebAuthnError> {
+    let auth_config = store.modify_credential(site_id, |config| {
+        let config = config.get_or_insert_with(|| SiteAuthConfig {
+            credentials: Vec::new(),
+            requires_auth: true,
+            user_display_name: user_display_name.clone(),
+            user_id: user_id.to_string(),
+            site_name: None,
+        });
+        config.site_name = Some(site_name.to_string());
+        if upgrade {
+            config.requires_auth = true;
+        }
+        if user_display_name.is_some() {
+            config.user_display_name = user_display_name.clone();
+        }
+        if config.user_id.is_empty() {
+            config.user_id = user_id.to_string();
+        }
+        config.credentials.retain(|existing| existing.credential_id != registered.entry.credential_id);
+        config.credentials.push(registered.entry);
+        config.clone()
+    })?;
+
+    Ok(RegistrationResult {
+        success: true,
+        auth_config: Some(auth_config),
+        aaguid: registered.aaguid,
+        sign_count: registered.sign_count,
+        authenticator_attachment: registered.authenticator_attachment,
+        is_resident: registered.is_resident,
+        backup_eligible: registered.backup_eligible,
+        icloud_synced: registered.icloud_synced,
+        was_imported: false,
+        operation_id: operation_id.to_string(),
+    })
+}
+
+/// Whether `register_passkey`, `import_credentials` and `accept_sync_offer` may
+/// store credentials they did not see created: debug builds, or builds with the
+/// `allow-credential-import` feature
+pub(crate) fn credential_import_allowed() -> bool {
+    cfg!(any(debug_assertions, feature = "allow-credential-import"))
+}
+
+/// Add exported credentials to the site's configuration in the store, as a
+/// registration would
+fn import_registration(
+    store: &CredentialStore,
+    site_id: &str,
+    imported: SiteAuthConfig,
+    operation_id: OperationId,
+) -> Result<RegistrationResult, WebAuthnError> {
+    if !credential_import_allowed() {
+        return Err(WebAuthnError::InvalidInput(
+            "Importing credentials is not enabled in this build".to_string(),
+        ));
+    }
+    if imported.credentials.is_empty() {
+        return Err(WebAuthnError::InvalidInput("existing_config has no credentials".to_string()));
+    }
+    for entry in &imported.credentials {
+        check_imported_credential(entry)?;
+    }
+
+    let auth_config = store.modify_credential(site_id, |config| {
+        let config = config.get_or_insert_with(|| SiteAuthConfig {
+            credentials: Vec::new(),
+            requires_auth: imported.requires_auth,
+            user_display_name: imported.user_display_name.clone(),
+            user_id: imported.user_id.clone(),
+            site_name: imported.site_name.clone(),
+        });
+        if config.user_id.is_empty() {
+            config.user_id = imported.user_id.clone();
+        }
+        for entry in &imported.credentials {
+            config.credentials.retain(|existing| existing.credential_id != entry.credential_id);
+            config.credentials.push(entry.clone());
+        }
+        config.clone()
+    })?;
+    webauthn_log!(
+        Level::Info,
+        WebAuthnLogEvent::new(LogOperation::Registration).site(site_id).succeeded(),
+        operation_id:% = operation_id;
+        "Imported {} credentials for site: {}",
+        imported.credentials.len(),
+        site_id
+    );
+
+    Ok(RegistrationResult {
+        success: true,
+        sign_count: imported.credentials.last().map_or(0, |entry| entry.sign_count),
+        auth_config: Some(auth_config),
+        aaguid: None,
+        authenticator_attachment: String::new(),
+        is_resident: false,
+        backup_eligible: false,
+        icloud_synced: None,
+        was_imported: true,
+        operation_id: operation_id.to_string(),
+    })
+}
+
+/// Check an imported credential could be asserted with: a base64url credential
+/// ID of a length the spec allows and a base64url COSE public key
+fn check_imported_credential(entry: &CredentialEntry) -> Result<(), WebAuthnError> {
+    if entry.credential_id.trim().is_empty() {
+        return Err(WebAuthnError::InvalidInput("credentialId must not be empty".to_string()));
+    }
+    let credential_id = entry.parsed_credential_id()?;
+    log::debug!(
+        "Imported credential {} looks like it is from a {:?} authenticator",
+        Redacted(&entry.credential_id),
+        credential_id.authenticator_type()
+    );
+    let invalid_key = || {
+        WebAuthnError::InvalidInput(format!(
+            "publicKey of credential {} is not a base64url COSE key",
+            entry.credential_id
+        ))
+    };
+    let key = URL_SAFE_NO_PAD.decode(&entry.public_key).map_err(|_| invalid_key())?;
+    parse_cose_key(&key).map_err(|_| invalid_key())?;
+    Ok(())
+}
+
+/// Delete a passkey from the platform authenticator and the credential store
+///
+/// Fails with `CredentialNotFound` when the site has no such credential, so a
+/// repeated delete can be told apart from a real failure. The credential store
+/// entry is kept if the platform could not remove its copy.
+#[command]
+pub async fn delete_passkey<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    credential_id: String,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    validate_site_id(&site_id)?;
+    delete_credential(window.app_handle(), &store, &site_id, credential_id).await
+}
+
+/// `delete_passkey` without the window check, for tasks that run without a window
+pub(crate) async fn delete_credential<R: Runtime>(
+    app: &AppHandle<R>,
+    store: &CredentialStore,
+    site_id: &str,
+    credential_id: String,
+) -> Result<(), WebAuthnError> {
+    let result = remove_credential(app, store, site_id, credential_id.clone()).await;
+    app.state::<WebAuthnState>().audit_log.record(
+        store,
+        AuditEvent::PasskeyDeletion {
+            site_id: site_id.to_string(),
+            credential_id,
+            outcome: audit_log::outcome(&result),
+        },
+    );
+    result
+}
+
+async fn remove_credential<R: Runtime>(
+    app: &AppHandle<R>,
+    store: &CredentialStore,
+    site_id: &str,
+    credential_id: String,
+) -> Result<(), WebAuthnError> {
+    let config = store
+        .load_credential(site_id)?
+        .filter(|config| config.find_credential(&credential_id).is_some())
+        .ok_or_else(|| WebAuthnError::CredentialNotFound(credential_id.clone()))?;
+
+    let user_id = if config.user_id.is_empty() {
+        site_id.as_bytes().to_vec()
+    } else {
+        URL_SAFE_NO_PAD
+            .decode(&config.user_id)
+            .map_err(|_| WebAuthnError::InternalError(format!("Invalid user handle for site: {}", site_id)))?
+    };
+    let request = DeletionRequest {
+        rp_id: app.state::<WebAuthnState>().relying_party.ceremony_domain()?,
+        credential_id: URL_SAFE_NO_PAD
+            .decode(&credential_id)
+            .map_err(|_| WebAuthnError::CredentialNotFound(credential_id.clone()))?,
+        user_id,
+    };
+    app.state::<Platform<R>>().delete(request).await?;
+
+    let removed = store.modify_credential(site_id, |config| {
+        let Some(config) = config else {
+            return false;
+        };
+        let count = config.credentials.len();
+        config.credentials.retain(|entry| entry.credential_id != credential_id);
+        config.credentials.len() != count
+    })?;
+
+    if removed {
+        webauthn_log!(
+            Level::Info,
+            WebAuthnLogEvent::new(LogOperation::PasskeyDeletion).site(site_id).succeeded();
+            "Deleted passkey for site: {}",
+            site_id
+        );
+        Ok(())
+    } else {
+        Err(WebAuthnError::CredentialNotFound(credential_id))
+    }
+}
+
+/// Let a passkey locked after repeated failures authenticate again
+///
+/// Clears the passkey's failure count too. Unlocking a passkey that is not
+/// locked succeeds; one the site does not have fails with `CredentialNotFound`.
+#[command]
+pub async fn unlock_credential<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    credential_id: String,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    validate_site_id(&site_id)?;
+    let result = match store.unlock_credential(&site_id, &credential_id) {
+        Ok(true) => {
+            log::info!("Unlocked passkey {} of site {}", Redacted(&credential_id), site_id);
+            Ok(())
+        }
+        Ok(false) => Err(WebAuthnError::CredentialNotFound(credential_id.clone())),
+        Err(error) => Err(error.into()),
+    };
+    state.audit_log.record(
+        &store,
+        AuditEvent::PasskeyUnlock {
+            site_id,
+            credential_id,
+            outcome: audit_log::outcome(&result),
+        },
+    );
+    result
+}
+
+/// Change the user display name stored with a site's passkeys
+///
+/// The name is kept per site, so it changes for all of the site's passkeys;
+/// `credential_id` must be one of them, or the command fails with
+/// `CredentialNotFound`. The name must not be blank, longer than 128
+/// characters or contain control characters; such names fail with
+/// `InvalidInput`. Authenticators keep the name given at registration.
+#[command]
+pub async fn update_credential_display_name<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    credential_id: String,
+    display_name: String,
+) -> Result<(), WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    validate_site_id(&site_id)?;
+    check_display_name(&display_name)?;
+    if !store.set_user_display_name(&site_id, &credential_id, &display_name)? {
+        return Err(WebAuthnError::CredentialNotFound(credential_id));
+    }
+    log::info!("Updated the user display name of site {}", site_id);
+    Ok(())
+}
+
+/// Check whether a site's passkey is usable here without running a ceremony
+///
+/// Never prompts the user. Platforms that only reveal their credentials during
+/// a ceremony are trusted to still hold those in the credential store.
+#[command]
+pub async fn verify_stored_credential<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+) -> Result<CredentialStatus, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    validate_site_id(&site_id)?;
+    stored_credential_status(window.app_handle(), &store, &site_id).await
+}
+
+/// `verify_stored_credential` without the window check, for tasks that run without a window
+pub(crate) async fn stored_credential_status<R: Runtime>(
+    app: &AppHandle<R>,
+    store: &CredentialStore,
+    site_id: &str,
+) -> Result<CredentialStatus, WebAuthnError> {
+    let platform = app.state::<Platform<R>>();
+    let credentials = store
+        .load_credential(site_id)?
+        .map(|config| config.credentials)
+        .unwrap_or_default();
+
+    let rp_id = app.state::<WebAuthnState>().relying_party.ceremony_domain()?;
+    let mut exists_locally = false;
+    for entry in &credentials {
+        let Ok(credential_id) = URL_SAFE_NO_PAD.decode(&entry.credential_id) else {
+            continue;
+        };
+        if platform.holds_credential(&rp_id, &credential_id).await != Some(false) {
+            exists_locally = true;
+            break;
+        }
+    }
+
+    Ok(CredentialStatus {
+        exists_locally,
+        platform_available: platform.can_verify_user(),
+        last_verified_at: credentials.iter().filter_map(|entry| entry.last_used_at).max(),
+    })
+}
+
+/// When a site's passkey last authenticated, as an RFC 3339 timestamp
+///
+/// `None` for a passkey not used since it was registered. Fails with
+/// `CredentialNotFound` when the site has no passkey with that ID.
+#[command]
+pub async fn get_credential_last_used<R: Runtime>(
+    window: WebviewWindow<R>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+    credential_id: String,
+) -> Result<Option<String>, WebAuthnError> {
+    check_window(&window, CapabilityTier::ReadOnly)?;
+    validate_site_id(&site_id)?;
+    let entry = store
+        .load_credential(&site_id)?
+        .and_then(|config| config.credentials.into_iter().find(|entry| entry.credential_id == credential_id))
+        .ok_or(WebAuthnError::CredentialNotFound(credential_id))?;
+    Ok(entry.last_used_at.map(|used_at| used_at.to_rfc3339_opts(SecondsFormat::Millis, true)))
+}
+
+/// Check whether a site has to register a new passkey because the authenticator
+/// lost the stored ones, e.g. after a device wipe
+///
+/// Never prompts the user. When it returns true, `webauthn://reenrollment-required`
+/// is emitted and the site's next successful `register_passkey` call deletes
+/// the stale passkeys once its own is stored. Sites without stored passkeys have nothing
+/// to re-enroll.
+#[command]
+pub async fn should_reenroll<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    site_id: String,
+) -> Result<bool, WebAuthnError> {
+    check_window(&window, CapabilityTier::Manage)?;
+    validate_site_id(&site_id)?;
+    let has_credentials = store
+        .load_credential(&site_id)?
+        .is_some_and(|config| !config.credentials.is_empty());
+    if !has_credentials {
+        return Ok(false);
+    }
+    if stored_credential_status(window.app_handle(), &store, &site_id).await?.exists_locally {
+        return Ok(false);
+    }
+
+    webauthn_log!(
+        Level::Warn,
+        WebAuthnLogEvent::new(LogOperation::ReenrollmentCheck).site(&site_id);
+        "Passkeys for site {} are gone from the authenticator; re-enrollment required",
+        site_id
+    );
+    state.registrations.mark_reenrollment(&site_id);
+    WebAuthnEvent::ReenrollmentRequired { site_id }.emit(window.app_handle());
+    Ok(true)
+}
+
+/// Sign in with a discoverable credential, without naming one upfront
+///
+/// Runs an assertion with an empty allow list, so the authenticator offers every
+/// discoverable credential it holds for `rp_id` (the editing domain by default;
+/// other RP IDs are only accepted where `set_rp_id` is). The user picks one,
+/// which is returned with the site the credential store has it registered for.
+/// Platforms report a single credential per ceremony. Discoveries share one
+/// rate limit, and a credential its site has locked fails with
+/// `CredentialLocked` until `unlock_credential`.
+#[command]
+pub async fn discover_credentials<R: Runtime>(
+    window: WebviewWindow<R>,
+    platform: State<'_, Platform<R>>,
+    state: State<'_, WebAuthnState>,
+    store: State<'_, CredentialStore>,
+    rp_id: Option<String>,
+) -> Result<Vec<DiscoveredCredential>, WebAuthnError> {
+    check_window(&window, CapabilityTier::Authenticate)?;
+    let editing_domain = state.relying_party.ceremony_domain()?;
+    let rp_id = match rp_id.map(|rp_id| rp_id.trim().to_ascii_lowercase()) {
+        None => editing_domain,
+        Some(rp_id) if rp_id == editing_domain => rp_id,
+        Some(_) if !developer_mode() => {
+            return Err(WebAuthnError::InvalidInput(
+                "rp_id may only differ from the editing domain in development builds".to_string(),
+            ));
+        }
+        Some(rp_id) if rp_id.is_empty() || rp_id.contains(|c: char| c == '/' || c == ':' || c.is_whitespace()) => {
+            return Err(WebAuthnError::InvalidInput(format!("Invalid RP ID: {:?} (expected a bare domain)", rp_id)));
+        }
+        Some(rp_id) => rp_id,
+    };
+    webauthn_log!(
+        Level::Info,
+        WebAuthnLogEvent::new(LogOperation::CredentialDiscovery);
+        "Starting WebAuthn credential discovery for: {}",
+        rp_id
+    );
+
+    state
+        .rate_limiter
+        .check(DISCOVERY_RATE_LIMIT_KEY)
+        .inspect_err(|_| state.metrics.rate_limit_hit())?;
+    // The challenge never leaves the backend, so there is nothing to issue and
+    // redeem; once an assertion over it checks out it is logged as used
+    let challenge = state.challenges.generate();
+    let request = AuthenticationRequest {
+        rp_id: rp_id.clone(),
+        challenge: URL_SAFE_NO_PAD
+            .decode(&challenge)
+            .map_err(|_| WebAuthnError::InvalidChallenge)?,
+        allowed_credentials: Vec::new(),
+        user_verification: UserVerification::Preferred,
+        prompt_message: None,
+        authenticator_attachment: resolve_attachment(&**platform, None),
+        prf: None,
+        large_blob: None,
+        timeout: authentication_timeout(None),
+    };
+    WebAuthnEvent::BiometricPromptShown {
+        ceremony: Ceremony::Authentication,
+    }
+    .emit(&window);
+    let assertion = platform.authenticate(&window, request).await?;
+
+    let auth_data = parse_authenticator_data(&assertion.authenticator_data)?;
+    verify_scope(&auth_data, &rp_id, UserVerification::Preferred)?;
+    state.used_challenges.mark_used(&challenge);
+
+    let credential_id = URL_SAFE_NO_PAD.encode(&assertion.credential_id);
+    let user_handle = assertion.user_handle.as_ref().map(|user_handle| URL_SAFE_NO_PAD.encode(user_handle));
+    let site = store.load_all()?.into_iter().find_map(|(site_id, config)| {
+        let user_matches = user_handle
+            .as_ref()
+            .map_or(true, |user_handle| config.user_id.is_empty() || *user_handle == config.user_id);
+        let locked = config.find_credential(&credential_id).filter(|_| user_matches)?.locked;
+        Some((site_id, locked))
+    });
+    let site_id = match site {
+        Some((site_id, true)) => {
+            let error = WebAuthnError::CredentialLocked;
+            webauthn_log!(
+                Level::Warn,
+                WebAuthnLogEvent::new(LogOperation::CredentialDiscovery).site(&site_id).failed(&error);
+                "Discovered a locked passkey of site {}",
+                site_id
+            );
+            return Err(error);
+        }
+        Some((site_id, false)) => Some(site_id),
+        None => None,
+    };
+
+    if let Some(site_id) = &site_id {
+        if let Err(error) = store.touch_credential(site_id, &credential_id, Utc::now(), auth_data.sign_count) {
+            webauthn_log!(
+                Level::Warn,
+                WebAuthnLogEvent::new(LogOperation::CredentialDiscovery).site(site_id);
+                "Failed to record passkey use for site {}: {}",
+                site_id,
+                error
+            );
+        }
+    }
+    Ok(vec![DiscoveredCredential {
+        credential_id,
+        user_handle,
+        site_id,
+        sign_count: auth_data.sign_count,
+    }])
+}
+
+/// Run the assertion ceremony for `authenticate_passkey` and check what the
+/// authenticator returned
+///
+/// Platform failures that may pass are retried as `retry` says, within
+/// `timeout`. The raw PRF output is zeroed once it has been encoded for the result.
+#[allow(clippy::too_many_arguments)]
+async fn authenticate_with_platform<R: Runtime>(
+    platform: &dyn WebAuthnPlatform<R>,
+    window: &WebviewWindow<R>,
+    site_id: &str,
+    auth_config: &SiteAuthConfig,
+    challenge: &str,
+    user_verification: UserVerification,
+    prompt_message: Option<String>,
+    authenticator_attachment: AuthenticatorAttachment,
+    prf: Option<PrfInput>,
+    large_blob: Option<LargeBlobOperation>,
+    timeout: Duration,
+    retry: &RetryConfig,
+    editing_domain: &str,
+    operation_id: OperationId,
+    timings: &mut OperationTimings,
+) -> Result<AuthenticationResult, WebAuthnError> {
+    let log_event = WebAuthnLogEvent::new(LogOperation::Authentication).site(site_id);
+    webauthn_log!(
+        Level::Info,
+        log_event,
+        operation_id:% = operation_id;
+        "Starting native WebAuthn authentication for site: {}",
+        site_id
+    );
+    OperationContext::Challenge.scope(|| {
+        webauthn_log!(Level::Debug, log_event, operation_id:% = operation_id; "Challenge: {}", challenge)
+    });
+    webauthn_log!(Level::Debug, log_event, operation_id:% = operation_id; "Editing domain: {}", editing_domain);
+    webauthn_log!(
+        Level::Debug,
+        log_event,
+        operation_id:% = operation_id;
+        "Registered credentials: {}",
+        auth_config.credentials.len()
+    );
+
+    if auth_config.credentials.is_empty() {
+        return Err(WebAuthnError::CredentialNotFound(site_id.to_string()));
+    }
+
+    let challenge = URL_SAFE_NO_PAD
+        .decode(challenge)
+        .map_err(|_| WebAuthnError::InvalidChallenge)?;
+
+    // Offer every registered credential; the authenticator picks the one it holds
+    let allowed_credentials = auth_config
+        .credentials
+        .iter()
+        .map(|entry| {
+            URL_SAFE_NO_PAD
+                .decode(&entry.credential_id)
+                .map_err(|_| WebAuthnError::CredentialNotFound(entry.credential_id.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    check_authenticator_attachment(platform, authenticator_attachment)?;
+    check_user_verification(platform, user_verification)?;
+    let request = AuthenticationRequest {
+        rp_id: editing_domain.to_string(),
+        challenge,
+        allowed_credentials,
+        user_verification,
+        prompt_message,
+        authenticator_attachment,
+        prf,
+        large_blob,
+        timeout,
+    };
+    WebAuthnEvent::BiometricPromptShown {
+        ceremony: Ceremony::Authentication,
+    }
+    .emit(window);
+    let phase = Instant::now();
+    let attempts = platform_retry::with_retries(
+        retry,
+        || platform.authenticate(window, request.clone()),
+        |attempt, backoff, error| {
+            webauthn_log!(
+                Level::Debug,
+                log_event,
+                operation_id:% = operation_id;
+                "Attempt {} of {} failed for site {}, retrying in {} ms: {}",
+                attempt,
+                retry.max_attempts,
+                site_id,
+                backoff.as_millis(),
+                error
+            );
+        },
+    );
+    // Dropping the platform's future on expiry dismisses the prompt where it can
+    let assertion = tokio::time::timeout(timeout, attempts)
+        .await
+        .map(|(assertion, attempts)| assertion.map(|assertion| (assertion, attempts)))
+        .unwrap_or_else(|_| {
+            webauthn_log!(
+                Level::Warn,
+                log_event,
+                operation_id:% = operation_id;
+                "The platform did not answer within {} seconds for site {}",
+                timeout.as_secs(),
+                site_id
+            );
+            Err(WebAuthnError::Timeout)
+        });
+    timings.platform_api_ms = as_millis(phase.elapsed());
+    let (assertion, attempts) = assertion?;
+
+    let credential_id = URL_SAFE_NO_PAD.encode(&assertion.credential_id);
+    if auth_config.find_credential(&credential_id).is_none() {
+        webauthn_log!(
+            Level::Warn,
+            log_event,
+            operation_id:% = operation_id;
+            "Authenticator returned an unexpected credential for site: {}",
+            site_id
+        );
+        return Err(WebAuthnError::CredentialNotFound(credential_id));
+    }
+
+    // Authenticators only return the user handle for discoverable credentials
+    if let Some(user_handle) = &assertion.user_handle {
+        if !auth_config.user_id.is_empty() && URL_SAFE_NO_PAD.encode(user_handle) != auth_config.user_id {
+            webauthn_log!(
+                Level::Warn,
+                log_event,
+                operation_id:% = operation_id;
+                "Authenticator returned a credential for another user for site: {}",
+                site_id
+            );
+            return Err(WebAuthnError::CredentialNotFound(credential_id));
+        }
+    }
+
+    let auth_data = parse_authenticator_data(&assertion.authenticator_data)?;
+    verify_scope(&auth_data, editing_domain, user_verification)?;
+
+    Ok(AuthenticationResult {
+        success: true,
+        credential_id: Some(credential_id),
+        site_name: None,
+        sign_count: auth_data.sign_count,
+        possible_clone_detected: false,
+        authenticator_attachment: assertion.authenticator_attachment,
+        prf_output: assertion
+            .prf_output
+            .map(|output| URL_SAFE_NO_PAD.encode(Zeroizing::new(output))),
+        large_blob_data: assertion.large_blob_data.map(|data| URL_SAFE_NO_PAD.encode(data)),
+        timings: None,
+        operation_id: operation_id.to_string(),
+        from_cache: false,
+        attempts,
+    })
+}
+
+/// A credential created by `register_with_platform`, with what its authenticator reported
+struct RegisteredCredential {
+    entry: CredentialEntry,
+    aaguid: Option<String>,
+    sign_count: u32,
+    authenticator_attachment: String,
+    is_resident: bool,
+    backup_eligible: bool,
+    icloud_synced: Option<bool>,
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn register_with_platform<R: Runtime>(
+    platform: &dyn WebAuthnPlatform<R>,
+    window: &WebviewWindow<R>,
+    site_id: &str,
+    site_name: &str,
+    user_display_name: &Option<String>,
+    device_name: &Option<String>,
+    challenge: &str,
+    user_id: &str,
+    user_verification: UserVerification,
+    resident_key: ResidentKeyRequirement,
+    prompt_message: Option<String>,
+    authenticator_attachment: AuthenticatorAttachment,
+    device_bound: bool,
+    editing_domain: &str,
+    operation_id: OperationId,
+) -> Result<RegisteredCredential, WebAuthnError> {
+    let log_event = WebAuthnLogEvent::new(LogOperation::Registration).site(site_id);
+    webauthn_log!(
+        Level::Info,
+        log_event,
+        operation_id:% = operation_id;
+        "Starting native WebAuthn registration for site: {}",
+        site_id
+    );
+    OperationContext::Challenge.scope(|| {
+        webauthn_log!(Level::Debug, log_event, operation_id:% = operation_id; "Challenge: {}", challenge)
+    });
+    webauthn_log!(Level::Debug, log_event, operation_id:% = operation_id; "Site name: {}", site_name);
+    webauthn_log!(
+        Level::Debug,
+        log_event,
+        operation_id:% = operation_id;
+        "User display name: {:?}",
+        user_display_name
+    );
+    webauthn_log!(Level::Debug, log_event, operation_id:% = operation_id; "Editing domain: {}", editing_domain);
+
+    let registered_at = Utc::now();
+    if clock_is_skewed() {
+        webauthn_log!(
+            Level::Warn,
+            log_event,
+            operation_id:% = operation_id;
+            "The system clock reads {}; the passkey is recorded as registered then",
+            registered_at
+        );
+    }
+
+    let challenge = URL_SAFE_NO_PAD
+        .decode(challenge)
+        .map_err(|_| WebAuthnError::InvalidChallenge)?;
+    let user_id = URL_SAFE_NO_PAD
+        .decode(user_id)
+        .map_err(|_| WebAuthnError::InternalError(format!("Invalid user handle for site: {}", site_id)))?;
+    let request = RegistrationRequest {
+        rp_id: editing_domain.to_string(),
+        challenge,
+        user_name: format!("{} ({})", site_name, site_id),
+        user_id: user_id.clone(),
+        display_name: user_display_name.clone(),
+        user_verification,
+        resident_key,
+        prompt_message,
+        authenticator_attachment,
+        device_bound,
+    };
+    check_authenticator_attachment(platform, authenticator_attachment)?;
+    check_user_verification(platform, user_verification)?;
+    check_resident_key(platform, resident_key)?;
+    check_device_bound(platform, authenticator_attachment, device_bound)?;
+    WebAuthnEvent::BiometricPromptShown {
+        ceremony: Ceremony::Registration,
+    }
+    .emit(window);
+    let registration = platform.register(window, request).await?;
+
+    let auth_data = parse_authenticator_data(&registration.authenticator_data)?;
+    let public_key =
+        verify_registration(&auth_data, editing_domain, &registration.credential_id, user_verification)?;
+    if resident_key == ResidentKeyRequirement::Required && !registration.is_resident {
+        return Err(WebAuthnError::ResidentKeyNotSupported);
+    }
+    if device_bound && auth_data.backup_eligible() {
+        webauthn_log!(
+            Level::Warn,
+            log_event,
+            operation_id:% = operation_id;
+            "The authenticator created a passkey for site {} that may sync off the device; deleting it",
+            site_id
+        );
+        let deletion = DeletionRequest {
+            rp_id: editing_domain.to_string(),
+            credential_id: registration.credential_id,
+            user_id,
+        };
+        if let Err(error) = platform.delete(deletion).await {
+            webauthn_log!(
+                Level::Warn,
+                log_event,
+                operation_id:% = operation_id;
+                "Failed to delete the syncable passkey of site {}: {}",
+                site_id,
+                error
+            );
+        }
+        return Err(WebAuthnError::SyncControlUnsupported);
+    }
+
+    // Only platform passkeys on Apple devices sync through iCloud Keychain, if it is on
+    let icloud_synced = (cfg!(any(target_os = "macos", target_os = "ios"))
+        && registration.authenticator_attachment == "platform")
+        .then(|| auth_data.backed_up());
+    Ok(RegisteredCredential {
+        entry: CredentialEntry {
+            credential_id: URL_SAFE_NO_PAD.encode(&registration.credential_id),
+            public_key: URL_SAFE_NO_PAD.encode(&public_key.encoded),
+            registered_at,
+            device_name: device_name.clone(),
+            last_used_at: None,
+            sign_count: auth_data.sign_count,
+            failure_count: 0,
+            locked: false,
+        },
+        aaguid: auth_data.aaguid_string(),
+        sign_count: auth_data.sign_count,
+        authenticator_attachment: registration.authenticator_attachment,
+        is_resident: registration.is_resident,
+        backup_eligible: auth_data.backup_eligible(),
+        icloud_synced,
+    })
+}
+
+/// Answer the security key PIN prompt opened during a ceremony
+///
+/// Called by the PIN window with the entered PIN, or `null` when the user
+/// cancels. Ceremonies never take a PIN in their own payload. The PIN window
+/// needs no `window_capability` grant: the plugin opens it, and it is the only
+/// window whose PIN is taken.
+#[command]
+pub async fn submit_security_key_pin<R: Runtime>(
+    window: WebviewWindow<R>,
+    pin: Option<String>,
+) -> Result<(), WebAuthnError> {
+    #[cfg(target_os = "linux")]
+    {
+        // submit_pin refuses every other window by its label
+        if window.label() != webauthn_linux::PIN_WINDOW_LABEL {
+            check_window(&window, CapabilityTier::Authenticate)?;
+        }
+        webauthn_linux::submit_pin(&window, pin)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        check_window(&window, CapabilityTier::Authenticate)?;
+        let _ = (window, pin);
+        Err(WebAuthnError::PlatformNotSupported)
+    }
+}
+
+/// Configures the `webauthn` plugin, which sets up the state the passkey commands
+/// run with: the relying party, issued challenges, rate limits, sessions and the platform
+///
+/// The commands themselves are registered by the app (see `with_commands` in
+/// lib.rs), so the frontend keeps invoking them by their plain names.
+pub struct Builder<R: Runtime> {
+    rp_id: String,
+    challenge_ttl: Duration,
+    rate_limit: RateLimitConfig,
+    session_ttl: Duration,
+    capabilities: WebAuthnCapabilityFilter,
+    auth_notifications: AuthNotificationConfig,
+    idle_check_interval: Duration,
+    max_credentials_per_site: usize,
+    lockout_threshold: u32,
+    pin_cache_ttl: Duration,
+    sync_offer_ttl: Duration,
+    no_sync: bool,
+    retry: RetryConfig,
+    log: WebAuthnLogConfig,
+    platform: Option<Platform<R>>,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self {
+            rp_id: PRODUCTION_RP_ID.to_string(),
+            challenge_ttl: DEFAULT_CHALLENGE_TTL,
+            rate_limit: RateLimitConfig::default(),
+            session_ttl: DEFAULT_SESSION_TTL,
+            capabilities: WebAuthnCapabilityFilter::new(),
+            auth_notifications: AuthNotificationConfig::default(),
+            idle_check_interval: DEFAULT_IDLE_CHECK_INTERVAL,
+            max_credentials_per_site: DEFAULT_MAX_CREDENTIALS_PER_SITE,
+            lockout_threshold: DEFAULT_LOCKOUT_THRESHOLD,
+            pin_cache_ttl: DEFAULT_PIN_CACHE_TTL,
+            sync_offer_ttl: DEFAULT_SYNC_OFFER_TTL,
+            no_sync: false,
+            retry: RetryConfig::default(),
+            log: WebAuthnLogConfig::default(),
+            platform: None,
+        }
+    }
+}
+
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// RP ID of release builds, instead of the one from Cargo.toml
+    ///
+    /// Must be a registrable domain; the app fails to start otherwise.
+    /// Development builds keep using "localhost".
+    pub fn rp_id(mut self, rp_id: impl Into<String>) -> Self {
+        self.rp_id = rp_id.into();
+        self
+    }
+
+    /// How long an issued challenge stays valid
+    pub fn challenge_ttl(mut self, ttl: Duration) -> Self {
+        self.challenge_ttl = ttl;
+        self
+    }
+
+    /// How many authentication attempts a site gets, and over how long
+    pub fn rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limit = config;
+        self
+    }
+
+    /// How long a site stays authenticated after `authenticate_passkey` succeeds
+    pub fn session_ttl(mut self, ttl: Duration) -> Self {
+        self.session_ttl = ttl;
+        self
+    }
+
+    /// Let the window labelled `label` invoke the WebAuthn commands of `tier`
+    ///
+    /// Without any grant every window may invoke every command. With one,
+    /// windows that were not granted a tier are refused, except the security
+    /// key PIN window the plugin opens on Linux, which may always answer it.
+    pub fn window_capability(mut self, label: impl Into<String>, tier: CapabilityTier) -> Self {
+        self.capabilities.allow(label, tier);
+        self
+    }
+
+    /// Which `authenticate_passkey` outcomes post an OS alert; none by default
+    ///
+    /// Only macOS and iOS post alerts. When any are enabled, permission is
+    /// asked for as the plugin starts.
+    pub fn auth_notifications(mut self, config: AuthNotificationConfig) -> Self {
+        self.auth_notifications = config;
+        self
+    }
+
+    /// How often the app lock checks whether the user has been idle too long
+    pub fn idle_check_interval(mut self, interval: Duration) -> Self {
+        self.idle_check_interval = interval;
+        self
+    }
+
+    /// Refuse to register a passkey for a site that already has `max`, 10 by default
+    pub fn max_credentials_per_site(mut self, max: usize) -> Self {
+        self.max_credentials_per_site = max;
+        self
+    }
+
+    /// Lock a passkey once it fails to authenticate `threshold` times in a row, 10 by default
+    pub fn lockout_threshold(mut self, threshold: u32) -> Self {
+        self.lockout_threshold = threshold;
+        self
+    }
+
+    /// Keep a security key PIN that worked for `ttl`, 5 minutes by default;
+    /// `Duration::ZERO` asks for the PIN every time
+    pub fn pin_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.pin_cache_ttl = ttl;
+        self
+    }
+
+    /// How long a request made by `request_sync_offer` can be answered, 60
+    /// seconds by default
+    pub fn sync_offer_ttl(mut self, ttl: Duration) -> Self {
+        self.sync_offer_ttl = ttl;
+        self
+    }
+
+    /// Only register passkeys that stay on this device, never synced to iCloud
+    /// Keychain or a Google account; off by default
+    pub fn no_sync(mut self, no_sync: bool) -> Self {
+        self.no_sync = no_sync;
+        self
+    }
+
+    /// How `authenticate_passkey` retries platform failures that may pass;
+    /// 3 attempts, 100 ms apart and then twice as long each time, by default
+    pub fn retry(mut self, config: RetryConfig) -> Self {
+        self.retry = config;
+        self
+    }
+
+    /// Log levels of challenge, authentication and registration lines; `info`
+    /// for each, with failures kept down to `error`, by default
+    pub fn log_config(mut self, config: WebAuthnLogConfig) -> Self {
+        self.log = config;
+        self
+    }
+
+    /// Take what `config` sets in place of the values given so far
+    ///
+    /// Values the builder would not accept are logged and skipped: an RP ID
+    /// that is not a registrable domain, zero durations or attempts other
+    /// than the PIN cache's, and a retry table without attempts or whose
+    /// waits would shrink. The RP ID is only taken in development builds or
+    /// with developer mode, so the file cannot move a release build's
+    /// passkeys to another domain.
+    pub fn config(mut self, config: WebAuthnConfig) -> Self {
+        fn positive<T: Default + PartialEq>(key: &str, value: Option<T>) -> Option<T> {
+            match value {
+                Some(value) if value == T::default() => {
+                    log::warn!("Ignoring {} = 0 in the WebAuthn config", key);
+                    None
+                }
+                value => value,
+            }
+        }
+
+        match config.rp_id {
+            Some(rp_id) if !developer_mode() => log::warn!(
+                "Ignoring the WebAuthn config's rp_id {:?}: release builds keep the RP ID they were built with",
+                rp_id
+            ),
+            Some(rp_id) if is_registrable_domain(&rp_id.trim().to_ascii_lowercase()) => self.rp_id = rp_id,
+            Some(rp_id) => log::warn!("Ignoring the WebAuthn config's rp_id {:?}: not a registrable domain", rp_id),
+            None => {}
+        }
+        if let Some(seconds) = positive("challenge_ttl_seconds", config.challenge_ttl_seconds) {
+            self.challenge_ttl = Duration::from_secs(seconds);
+        }
+        if let Some(seconds) = positive("session_ttl_seconds", config.session_ttl_seconds) {
+            self.session_ttl = Duration::from_secs(seconds);
+        }
+        if let Some(max) = positive("max_credentials_per_site", config.max_credentials_per_site) {
+            self.max_credentials_per_site = max;
+        }
+        if let Some(threshold) = positive("lockout_threshold", config.lockout_threshold) {
+            self.lockout_threshold = threshold;
+        }
+        // Zero is allowed here: it turns the cache off
+        if let Some(seconds) = config.pin_cache_ttl_seconds {
+            self.pin_cache_ttl = Duration::from_secs(seconds);
+        }
+        if let Some(no_sync) = config.no_sync {
+            self.no_sync = no_sync;
+        }
+        if let Some(max_attempts) = positive("rate_limit_max_attempts", config.rate_limit_max_attempts) {
+            self.rate_limit.max_attempts = max_attempts;
+        }
+        if let Some(seconds) = positive("rate_limit_window_seconds", config.rate_limit_window_seconds) {
+            self.rate_limit.window = Duration::from_secs(seconds);
+        }
+        match config.retry {
+            Some(retry) if retry.is_valid() => self.retry = retry,
+            Some(retry) => log::warn!("Ignoring the WebAuthn config's retry table {:?}", retry),
+            None => {}
+        }
+        if let Some(log) = config.log {
+            self.log = log;
+        }
+        self
+    }
+
+    /// Take what the app's `webauthn.toml` sets, if it has one
+    fn with_config_file(self, app: &AppHandle<R>) -> Self {
+        match app.path().app_config_dir() {
+            Ok(dir) => match WebAuthnConfig::load(&dir.join(CONFIG_FILE_NAME)) {
+                Some(config) => self.config(config),
+                None => self,
+            },
+            Err(error) => {
+                log::debug!("No config directory to read the WebAuthn config from: {}", error);
+                self
+            }
+        }
+    }
+
+    /// Run ceremonies on `platform` instead of the platform of the build, e.g. a
+    /// `MockPlatform` in tests
+    pub fn mock_platform(mut self, platform: impl WebAuthnPlatform<R> + 'static) -> Self {
+        self.platform = Some(Platform::new(platform));
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("webauthn")
+            .setup(move |app, _api| {
+                let builder = self.with_config_file(app);
+                log_event::configure(builder.log);
+                let rp_id = builder.rp_id.trim().to_ascii_lowercase();
+                if !is_registrable_domain(&rp_id) {
+                    return Err(format!("Invalid WebAuthn RP ID: {:?} (expected a registrable domain)", rp_id).into());
+                }
+                log::info!(
+                    "Starting the WebAuthn plugin {} (commit {}, built {}) for RP ID {}",
+                    PLUGIN_VERSION,
+                    GIT_SHA,
+                    BUILD_TIMESTAMP,
+                    rp_id
+                );
+                if let Err(failure) = entropy_check::self_test() {
+                    // Failing setup stops the app before anything reads the unmanaged state
+                    return Err(format!("Refusing to start the WebAuthn plugin: {}", failure).into());
+                }
+
+                auth_notifications::request_permission(&builder.auth_notifications);
+                let clock_skew_detected = clock_is_skewed();
+                if clock_skew_detected {
+                    log::warn!("The system clock reads {}, before 2001; passkey timestamps will be wrong", Utc::now());
+                }
+                app.manage(WebAuthnState {
+                    relying_party: RelyingParty::new(rp_id),
+                    challenges: ChallengeStore::new(builder.challenge_ttl),
+                    used_challenges: UsedChallengeLog::new(),
+                    rate_limiter: RateLimiter::new(builder.rate_limit),
+                    sessions: SessionStore::new(builder.session_ttl),
+                    grace_period_seconds: AtomicU64::new(DEFAULT_GRACE_PERIOD_SECS),
+                    registrations: InFlightRegistry::new(),
+                    clipboard_guard: ClipboardGuard::new(),
+                    capabilities: builder.capabilities,
+                    auth_notifications: builder.auth_notifications,
+                    metrics: WebAuthnMetrics::new(),
+                    onboarding: Onboarding::new(),
+                    app_lock: AppLock::new(),
+                    max_credentials_per_site: builder.max_credentials_per_site,
+                    lockout_threshold: builder.lockout_threshold,
+                    pin_cache: PinCache::new(builder.pin_cache_ttl),
+                    no_sync: builder.no_sync,
+                    retry: builder.retry,
+                    audit_log: AuditLog::new(),
+                    hybrid_auth: HybridAuthSessions::new(),
+                    sync_requests: SyncRequests::new(builder.sync_offer_ttl),
+                    clock_skew_detected,
+                });
+                app.manage(builder.platform.unwrap_or_else(Platform::for_build));
+                panic_hook::install(app.clone());
+                challenge_store::spawn_eviction(app.clone());
+                app_lock::spawn_idle_check(app.clone(), builder.idle_check_interval);
+                if clock_skew_detected {
+                    WebAuthnEvent::ClockSkewDetected.emit(app);
+                }
+                Ok(())
+            })
+            .on_event(|app, event| {
+                if let RunEvent::Exit = event {
+                    shutdown::on_exit(app);
+                }
+            })
+            .build()
+    }
+}
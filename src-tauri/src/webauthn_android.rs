@@ -0,0 +1,625 @@
+// src-tauri/src/webauthn_android.rs
+
+//! Native passkey ceremonies backed by the androidx Credential Manager.
+//!
+//! Credential Manager reports back through Kotlin callbacks, which cannot be
+//! implemented from JNI alone, so a small bridge class
+//! (`org.sparktype.sparktype.PasskeyBridge`, in the Android app module) starts the
+//! ceremony on the UI thread and hands the response JSON back through the native
+//! `PasskeyBridge.deliver` method below. Each ceremony is keyed by a handle whose
+//! `tokio::sync::oneshot` sender waits in `PENDING` until the callback fires on
+//! the Dalvik main thread.
+//!
+//! The app module needs `androidx.credentials:credentials` and
+//! `androidx.credentials:credentials-play-services-auth` (for Android 9-13) on
+//! its classpath.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use jni::objects::{JClass, JObject, JString, JValue};
+use jni::sys::jlong;
+use jni::{JNIEnv, JavaVM};
+use serde::Deserialize;
+use tokio::sync::oneshot;
+
+use crate::webauthn::{
+    AuthenticatorAttachment, ResidentKeyRequirement, UserVerification, WebAuthnCapabilities, WebAuthnError,
+};
+use crate::webauthn_platform::{LargeBlobOperation, PrfInput};
+
+/// Fully qualified name of the Kotlin bridge, as seen by the app class loader
+const BRIDGE_CLASS: &str = "org.sparktype.sparktype.PasskeyBridge";
+
+/// Credential Manager falls back to Play Services FIDO2 from Android 9 (API 28)
+const MIN_SDK_VERSION: i32 = 28;
+
+/// `PackageManager.FEATURE_CREDENTIALS`, present from Android 14 (API 34)
+const FEATURE_CREDENTIALS: &str = "android.software.credentials";
+
+const PLAY_SERVICES_PACKAGE: &str = "com.google.android.gms";
+
+/// Alias of the throwaway key `hardware_backed` creates to inspect
+const PROBE_KEY_ALIAS: &str = "sparktype-hardware-probe";
+
+/// `KeyProperties.PURPOSE_SIGN`
+const PURPOSE_SIGN: i32 = 4;
+
+/// Default time the system sheet stays up before giving up
+const CEREMONY_TIMEOUT_MS: u32 = 60_000;
+
+/// Raw output of a successful registration ceremony
+pub(crate) struct NativeRegistration {
+    pub credential_id: Vec<u8>,
+    pub attestation_object: Vec<u8>,
+    pub authenticator_attachment: String,
+    pub is_resident: bool,
+}
+
+/// Raw output of a successful assertion ceremony
+pub(crate) struct NativeAssertion {
+    pub credential_id: Vec<u8>,
+    pub user_handle: Option<Vec<u8>>,
+    pub authenticator_data: Vec<u8>,
+    pub authenticator_attachment: String,
+    pub prf_output: Option<Vec<u8>>,
+    pub large_blob_data: Option<Vec<u8>>,
+}
+
+type CeremonySender = oneshot::Sender<Result<String, WebAuthnError>>;
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+static PENDING: Mutex<Option<HashMap<i64, CeremonySender>>> = Mutex::new(None);
+
+/// `registrationResponseJson` / `authenticationResponseJson` as returned by Credential Manager
+#[derive(Deserialize)]
+struct CredentialResponseJson {
+    #[serde(rename = "rawId")]
+    raw_id: String,
+    #[serde(rename = "authenticatorAttachment")]
+    authenticator_attachment: Option<String>,
+    response: AuthenticatorResponseJson,
+    #[serde(rename = "clientExtensionResults", default)]
+    client_extension_results: ClientExtensionResultsJson,
+}
+
+#[derive(Default, Deserialize)]
+struct ClientExtensionResultsJson {
+    #[serde(rename = "credProps")]
+    cred_props: Option<CredPropsJson>,
+    prf: Option<PrfOutputJson>,
+    #[serde(rename = "largeBlob")]
+    large_blob: Option<LargeBlobOutputJson>,
+}
+
+/// Output of the `credProps` extension
+#[derive(Deserialize)]
+struct CredPropsJson {
+    rk: Option<bool>,
+}
+
+/// Output of the `prf` extension
+#[derive(Deserialize)]
+struct PrfOutputJson {
+    results: Option<PrfResultsJson>,
+}
+
+#[derive(Deserialize)]
+struct PrfResultsJson {
+    first: String,
+}
+
+/// Output of the `largeBlob` extension
+#[derive(Deserialize)]
+struct LargeBlobOutputJson {
+    blob: Option<String>,
+    written: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct AuthenticatorResponseJson {
+    #[serde(rename = "attestationObject")]
+    attestation_object: Option<String>,
+    #[serde(rename = "userHandle")]
+    user_handle: Option<String>,
+    #[serde(rename = "authenticatorData")]
+    authenticator_data: Option<String>,
+}
+
+/// `Build.MODEL`, the end-user-visible name of the device model
+pub(crate) fn device_model() -> Option<String> {
+    let model = with_activity(|env, _| {
+        let model = env.get_static_field("android/os/Build", "MODEL", "Ljava/lang/String;")?.l()?;
+        Ok(read_string(env, &JString::from(model)))
+    });
+    match model {
+        Ok(model) => model,
+        Err(error) => {
+            log::warn!("Could not read the device model: {}", error);
+            None
+        }
+    }
+}
+
+/// Whether this device can run passkey ceremonies through Credential Manager
+pub(crate) fn is_available() -> bool {
+    with_activity(|env, activity| {
+        let sdk_version = env.get_static_field("android/os/Build$VERSION", "SDK_INT", "I")?.i()?;
+        if sdk_version < MIN_SDK_VERSION {
+            return Ok(false);
+        }
+
+        let package_manager = env
+            .call_method(activity, "getPackageManager", "()Landroid/content/pm/PackageManager;", &[])?
+            .l()?;
+        let feature = env.new_string(FEATURE_CREDENTIALS)?;
+        let has_credentials = env
+            .call_method(
+                &package_manager,
+                "hasSystemFeature",
+                "(Ljava/lang/String;)Z",
+                &[(&feature).into()],
+            )?
+            .z()?;
+        if has_credentials {
+            return Ok(true);
+        }
+
+        // Older releases rely on the Play Services FIDO2 provider
+        let package = env.new_string(PLAY_SERVICES_PACKAGE)?;
+        let installed = env.call_method(
+            &package_manager,
+            "getPackageInfo",
+            "(Ljava/lang/String;I)Landroid/content/pm/PackageInfo;",
+            &[(&package).into(), JValue::Int(0)],
+        );
+        if installed.is_err() {
+            // NameNotFoundException: Play Services is not installed
+            env.exception_clear()?;
+        }
+        Ok(installed.is_ok())
+    })
+    .unwrap_or_else(|error| {
+        log::warn!("Failed to query passkey support: {}", error);
+        false
+    })
+}
+
+/// Credential Manager passkeys are discoverable and verified with the screen lock
+///
+/// Credential Manager also offers security keys over USB, NFC or Bluetooth.
+pub(crate) fn capabilities() -> WebAuthnCapabilities {
+    let available = is_available();
+    WebAuthnCapabilities {
+        platform_authenticator: available,
+        roaming_authenticator: available,
+        resident_key_support: available,
+        user_verification_support: available,
+        hardware_backed: hardware_backed(),
+        min_os_version_required: None,
+    }
+}
+
+/// Whether the Android keystore keeps keys in a TEE or StrongBox, found by
+/// creating a test key and asking `KeyInfo.isInsideSecureHardware`
+///
+/// The answer cannot change while the app runs, so the key is only created once.
+fn hardware_backed() -> Option<bool> {
+    static HARDWARE_BACKED: OnceLock<Option<bool>> = OnceLock::new();
+    *HARDWARE_BACKED.get_or_init(|| {
+        let probe = with_activity(|env, _| {
+            let provider = env.new_string("AndroidKeyStore")?;
+            let alias = env.new_string(PROBE_KEY_ALIAS)?;
+
+            let builder = env.new_object(
+                "android/security/keystore/KeyGenParameterSpec$Builder",
+                "(Ljava/lang/String;I)V",
+                &[(&alias).into(), JValue::Int(PURPOSE_SIGN)],
+            )?;
+            let digest = env.new_string("SHA-256")?;
+            let digests = env.new_object_array(1, "java/lang/String", &digest)?;
+            env.call_method(
+                &builder,
+                "setDigests",
+                "([Ljava/lang/String;)Landroid/security/keystore/KeyGenParameterSpec$Builder;",
+                &[(&digests).into()],
+            )?;
+            let spec = env
+                .call_method(&builder, "build", "()Landroid/security/keystore/KeyGenParameterSpec;", &[])?
+                .l()?;
+
+            let algorithm = env.new_string("EC")?;
+            let generator = env
+                .call_static_method(
+                    "java/security/KeyPairGenerator",
+                    "getInstance",
+                    "(Ljava/lang/String;Ljava/lang/String;)Ljava/security/KeyPairGenerator;",
+                    &[(&algorithm).into(), (&provider).into()],
+                )?
+                .l()?;
+            env.call_method(
+                &generator,
+                "initialize",
+                "(Ljava/security/spec/AlgorithmParameterSpec;)V",
+                &[(&spec).into()],
+            )?;
+            let key_pair = env
+                .call_method(&generator, "generateKeyPair", "()Ljava/security/KeyPair;", &[])?
+                .l()?;
+            let private_key = env
+                .call_method(&key_pair, "getPrivate", "()Ljava/security/PrivateKey;", &[])?
+                .l()?;
+
+            let factory = env
+                .call_static_method(
+                    "java/security/KeyFactory",
+                    "getInstance",
+                    "(Ljava/lang/String;Ljava/lang/String;)Ljava/security/KeyFactory;",
+                    &[(&algorithm).into(), (&provider).into()],
+                )?
+                .l()?;
+            let key_info_class = env.find_class("android/security/keystore/KeyInfo")?;
+            let key_info = env
+                .call_method(
+                    &factory,
+                    "getKeySpec",
+                    "(Ljava/security/Key;Ljava/lang/Class;)Ljava/security/spec/KeySpec;",
+                    &[(&private_key).into(), (&key_info_class).into()],
+                )?
+                .l()?;
+            let inside_secure_hardware = env.call_method(&key_info, "isInsideSecureHardware", "()Z", &[])?.z()?;
+
+            let key_store = env
+                .call_static_method(
+                    "java/security/KeyStore",
+                    "getInstance",
+                    "(Ljava/lang/String;)Ljava/security/KeyStore;",
+                    &[(&provider).into()],
+                )?
+                .l()?;
+            env.call_method(
+                &key_store,
+                "load",
+                "(Ljava/security/KeyStore$LoadStoreParameter;)V",
+                &[(&JObject::null()).into()],
+            )?;
+            env.call_method(&key_store, "deleteEntry", "(Ljava/lang/String;)V", &[(&alias).into()])?;
+            Ok(inside_secure_hardware)
+        });
+        match probe {
+            Ok(inside_secure_hardware) => Some(inside_secure_hardware),
+            Err(error) => {
+                log::warn!("Failed to check for a hardware-backed keystore: {}", error);
+                None
+            }
+        }
+    })
+}
+
+/// Create a new credential through the Credential Manager sheet
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn register(
+    rp_id: &str,
+    challenge: &[u8],
+    user_name: &str,
+    user_id: &[u8],
+    display_name: Option<&str>,
+    user_verification: UserVerification,
+    resident_key: ResidentKeyRequirement,
+    authenticator_attachment: AuthenticatorAttachment,
+    device_bound: bool,
+) -> Result<NativeRegistration, WebAuthnError> {
+    let mut request = serde_json::json!({
+        "challenge": URL_SAFE_NO_PAD.encode(challenge),
+        "rp": { "id": rp_id, "name": "Sparktype" },
+        "user": {
+            "id": URL_SAFE_NO_PAD.encode(user_id),
+            "name": user_name,
+            "displayName": display_name.unwrap_or(user_name),
+        },
+        "pubKeyCredParams": [
+            { "type": "public-key", "alg": -7 },
+            { "type": "public-key", "alg": -257 },
+        ],
+        "timeout": CEREMONY_TIMEOUT_MS,
+        "attestation": "none",
+        "authenticatorSelection": {
+            "residentKey": resident_key,
+            "requireResidentKey": resident_key == ResidentKeyRequirement::Required,
+            "userVerification": user_verification,
+        },
+        // Reports whether the provider stored the credential as discoverable
+        "extensions": { "credProps": true },
+    });
+    // Left out to let the user pick
+    if let Some(attachment) = attachment_option(authenticator_attachment) {
+        request["authenticatorSelection"]["authenticatorAttachment"] = attachment.into();
+    }
+    // credProtect level 3: the credential needs user verification and is not
+    // to be exported. Google Password Manager syncs regardless, and reports
+    // the passkey as backup eligible, which `register_passkey` refuses.
+    if device_bound {
+        request["authenticatorSelection"]["requireResidentKey"] = false.into();
+        request["extensions"]["credentialProtectionPolicy"] = "userVerificationRequired".into();
+        request["extensions"]["enforceCredentialProtectionPolicy"] = true.into();
+    }
+
+    let response = perform_ceremony("create", &request.to_string()).await?;
+    let response: CredentialResponseJson = serde_json::from_str(&response)
+        .map_err(|e| WebAuthnError::InternalError(format!("Invalid registration response: {}", e)))?;
+    let attestation_object = response
+        .response
+        .attestation_object
+        .ok_or_else(|| WebAuthnError::InternalError("Platform did not return an attestation object".to_string()))?;
+
+    Ok(NativeRegistration {
+        credential_id: decode_field(&response.raw_id, "rawId")?,
+        attestation_object: decode_field(&attestation_object, "attestationObject")?,
+        authenticator_attachment: reported_attachment(response.authenticator_attachment, authenticator_attachment),
+        // Providers that ignore `credProps` still honour a required discoverable credential
+        is_resident: response
+            .client_extension_results
+            .cred_props
+            .and_then(|cred_props| cred_props.rk)
+            .unwrap_or(resident_key == ResidentKeyRequirement::Required),
+    })
+}
+
+/// Get an assertion for one of the given credentials through the Credential Manager sheet
+///
+/// WebAuthn assertion requests take no attachment, so Credential Manager offers
+/// every authenticator that may hold one of the credentials; the result reports
+/// the one the user picked. Providers that do not support the PRF or largeBlob
+/// extension leave its output out.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn authenticate(
+    rp_id: &str,
+    challenge: &[u8],
+    allowed_credentials: &[Vec<u8>],
+    user_verification: UserVerification,
+    authenticator_attachment: AuthenticatorAttachment,
+    prf: Option<&PrfInput>,
+    large_blob: Option<&LargeBlobOperation>,
+    timeout: Duration,
+) -> Result<NativeAssertion, WebAuthnError> {
+    let allow_credentials: Vec<_> = allowed_credentials
+        .iter()
+        .map(|credential_id| serde_json::json!({ "type": "public-key", "id": URL_SAFE_NO_PAD.encode(credential_id) }))
+        .collect();
+    let mut request = serde_json::json!({
+        "challenge": URL_SAFE_NO_PAD.encode(challenge),
+        "rpId": rp_id,
+        "allowCredentials": allow_credentials,
+        "timeout": timeout.as_millis() as u64,
+        "userVerification": user_verification,
+    });
+    let mut extensions = serde_json::Map::new();
+    if let Some(prf) = prf {
+        let eval_by_credential: serde_json::Map<_, _> = prf
+            .eval_by_credential
+            .iter()
+            .map(|(credential_id, input)| {
+                (
+                    URL_SAFE_NO_PAD.encode(credential_id),
+                    serde_json::json!({ "first": URL_SAFE_NO_PAD.encode(input) }),
+                )
+            })
+            .collect();
+        extensions.insert(
+            "prf".to_string(),
+            serde_json::json!({
+                "eval": { "first": URL_SAFE_NO_PAD.encode(&prf.eval) },
+                "evalByCredential": eval_by_credential,
+            }),
+        );
+    }
+    match large_blob {
+        Some(LargeBlobOperation::Read) => {
+            extensions.insert("largeBlob".to_string(), serde_json::json!({ "read": true }));
+        }
+        Some(LargeBlobOperation::Write(data)) => {
+            extensions.insert(
+                "largeBlob".to_string(),
+                serde_json::json!({ "write": URL_SAFE_NO_PAD.encode(data) }),
+            );
+        }
+        None => {}
+    }
+    if !extensions.is_empty() {
+        request["extensions"] = serde_json::Value::Object(extensions);
+    }
+
+    let response = perform_ceremony("get", &request.to_string()).await?;
+    let response: CredentialResponseJson = serde_json::from_str(&response)
+        .map_err(|e| WebAuthnError::InternalError(format!("Invalid authentication response: {}", e)))?;
+    let authenticator_data = response
+        .response
+        .authenticator_data
+        .ok_or_else(|| WebAuthnError::InternalError("Platform did not return authenticator data".to_string()))?;
+    let large_blob_output = response.client_extension_results.large_blob;
+    if matches!(large_blob, Some(LargeBlobOperation::Write(_)))
+        && !large_blob_output.as_ref().is_some_and(|output| output.written == Some(true))
+    {
+        log::warn!("The credential provider did not store the largeBlob data");
+    }
+
+    Ok(NativeAssertion {
+        credential_id: decode_field(&response.raw_id, "rawId")?,
+        user_handle: response
+            .response
+            .user_handle
+            .filter(|user_handle| !user_handle.is_empty())
+            .map(|user_handle| decode_field(&user_handle, "userHandle"))
+            .transpose()?,
+        authenticator_data: decode_field(&authenticator_data, "authenticatorData")?,
+        authenticator_attachment: reported_attachment(response.authenticator_attachment, authenticator_attachment),
+        prf_output: response
+            .client_extension_results
+            .prf
+            .and_then(|prf| prf.results)
+            .map(|results| decode_field(&results.first, "prf.results.first"))
+            .transpose()?,
+        large_blob_data: large_blob_output
+            .and_then(|output| output.blob)
+            .map(|blob| decode_field(&blob, "largeBlob.blob"))
+            .transpose()?,
+    })
+}
+
+fn attachment_option(authenticator_attachment: AuthenticatorAttachment) -> Option<&'static str> {
+    match authenticator_attachment {
+        AuthenticatorAttachment::Platform => Some("platform"),
+        AuthenticatorAttachment::CrossPlatform => Some("cross-platform"),
+        AuthenticatorAttachment::Any => None,
+    }
+}
+
+/// The attachment the provider reported, or the requested one for older
+/// providers that leave it out
+fn reported_attachment(reported: Option<String>, requested: AuthenticatorAttachment) -> String {
+    reported.unwrap_or_else(|| {
+        match requested {
+            AuthenticatorAttachment::CrossPlatform => "cross-platform",
+            AuthenticatorAttachment::Platform | AuthenticatorAttachment::Any => "platform",
+        }
+        .to_string()
+    })
+}
+
+/// Start a ceremony through the bridge and wait for its callback
+///
+/// `method` is the static bridge entry point (`create` or `get`), which takes the
+/// activity, the WebAuthn request JSON and the handle to report back with.
+async fn perform_ceremony(method: &str, request_json: &str) -> Result<String, WebAuthnError> {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = oneshot::channel();
+    PENDING
+        .lock()
+        .map_err(|_| WebAuthnError::InternalError("Passkey bridge state is poisoned".to_string()))?
+        .get_or_insert_with(HashMap::new)
+        .insert(handle, sender);
+
+    let started = with_activity(|env, activity| {
+        let class = bridge_class(env, activity)?;
+        let request_json = env.new_string(request_json)?;
+        env.call_static_method(
+            &class,
+            method,
+            "(Landroid/app/Activity;Ljava/lang/String;J)V",
+            &[activity.into(), (&request_json).into(), JValue::Long(handle)],
+        )?;
+        Ok(())
+    });
+    if let Err(error) = started {
+        take_pending(handle);
+        return Err(WebAuthnError::InternalError(format!(
+            "Failed to start the passkey request: {}",
+            error
+        )));
+    }
+
+    receiver
+        .await
+        .map_err(|_| WebAuthnError::InternalError("Credential Manager went away before completing".to_string()))?
+}
+
+/// Called by `PasskeyBridge` on the main thread once Credential Manager reports back
+#[no_mangle]
+pub extern "system" fn Java_org_sparktype_sparktype_PasskeyBridge_deliver<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    response_json: JString<'local>,
+    error_type: JString<'local>,
+    error_message: JString<'local>,
+) {
+    let Some(sender) = take_pending(handle) else {
+        log::warn!("Passkey response for unknown request {}", handle);
+        return;
+    };
+
+    let result = match read_string(&mut env, &response_json) {
+        Some(response) => Ok(response),
+        None => Err(describe_error(
+            read_string(&mut env, &error_type).as_deref(),
+            read_string(&mut env, &error_message).as_deref(),
+        )),
+    };
+    // The receiver is gone if the command future was dropped; nothing to report to.
+    let _ = sender.send(result);
+}
+
+fn take_pending(handle: i64) -> Option<CeremonySender> {
+    PENDING.lock().ok()?.as_mut()?.remove(&handle)
+}
+
+/// Run `call` with a JNI environment attached to this thread and the app's activity
+fn with_activity<T>(
+    call: impl FnOnce(&mut JNIEnv, &JObject) -> jni::errors::Result<T>,
+) -> Result<T, String> {
+    let context = ndk_context::android_context();
+    let vm = unsafe { JavaVM::from_raw(context.vm().cast()) }.map_err(|e| e.to_string())?;
+    let mut env = vm.attach_current_thread().map_err(|e| e.to_string())?;
+    let activity = unsafe { JObject::from_raw(context.context().cast()) };
+
+    call(&mut env, &activity).map_err(|error| {
+        if env.exception_check().unwrap_or(false) {
+            let _ = env.exception_describe();
+            let _ = env.exception_clear();
+        }
+        error.to_string()
+    })
+}
+
+/// Load the bridge through the app class loader (`FindClass` on a native thread
+/// only sees system classes)
+fn bridge_class<'local>(env: &mut JNIEnv<'local>, activity: &JObject) -> jni::errors::Result<JClass<'local>> {
+    let loader = env
+        .call_method(activity, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])?
+        .l()?;
+    let name = env.new_string(BRIDGE_CLASS)?;
+    let class = env
+        .call_method(&loader, "loadClass", "(Ljava/lang/String;)Ljava/lang/Class;", &[(&name).into()])?
+        .l()?;
+    Ok(JClass::from(class))
+}
+
+fn read_string(env: &mut JNIEnv, value: &JString) -> Option<String> {
+    if value.is_null() {
+        return None;
+    }
+    env.get_string(value).ok().map(Into::into)
+}
+
+fn decode_field(value: &str, field: &str) -> Result<Vec<u8>, WebAuthnError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| WebAuthnError::InternalError(format!("Credential Manager returned an invalid {}", field)))
+}
+
+/// Map a Credential Manager exception type (`CreateCredentialException.type` /
+/// `GetCredentialException.type`) to a `WebAuthnError`
+fn describe_error(error_type: Option<&str>, message: Option<&str>) -> WebAuthnError {
+    let error_type = error_type.unwrap_or_default();
+    let message = message.unwrap_or("unknown error");
+    if error_type.ends_with("TYPE_USER_CANCELED") {
+        WebAuthnError::UserCancelled
+    } else if error_type.ends_with("TYPE_NO_CREDENTIAL") {
+        WebAuthnError::CredentialNotFound(message.to_string())
+    } else if error_type.ends_with("TYPE_NO_CREATE_OPTIONS") {
+        WebAuthnError::PlatformNotSupported
+    } else if error_type.ends_with("TYPE_INTERRUPTED") {
+        // Credential Manager documents these as worth retrying
+        WebAuthnError::PlatformUnavailable(message.to_string())
+    } else if error_type.contains("TimeoutError") {
+        WebAuthnError::Timeout
+    } else if error_type.contains("NotAllowedError") {
+        // The DOM error raised when the user could not be verified
+        WebAuthnError::BiometricFailed
+    } else {
+        WebAuthnError::InternalError(format!("Credential Manager error ({}): {}", error_type, message))
+    }
+}
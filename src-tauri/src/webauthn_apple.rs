@@ -0,0 +1,898 @@
+// src-tauri/src/webauthn_apple.rs
+
+//! Native passkey ceremonies backed by AuthenticationServices on macOS and iOS.
+//!
+//! `ASAuthorizationController` must be created, presented and called back on the
+//! main thread. The ceremony is therefore started from `WebviewWindow::with_webview`
+//! (which runs on the main thread) and the delegate hands the outcome back to the
+//! awaiting Tauri command through a `tokio::sync::oneshot` channel. Only plain Rust
+//! data crosses the thread boundary, never Objective-C objects.
+//!
+//! Dropping the future of `perform_ceremony`, as a command that times out does,
+//! cancels its controller so the system sheet does not outlive the command. The
+//! channel is watched (see `ceremony_watchdog`), so a delegate that never
+//! reports back fails the ceremony with `Timeout` and has its sheet cancelled
+//! the same way. As the app exits, `cancel_all_ceremonies` cancels every sheet
+//! still up and detaches the delegates, so none calls back into a runtime that
+//! is shutting down.
+
+use std::cell::{Cell, RefCell};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use block2::RcBlock;
+use objc2::rc::{Allocated, Retained};
+use objc2::runtime::{AnyClass, AnyObject, Bool, NSObject, NSObjectProtocol, ProtocolObject};
+use objc2::{declare_class, msg_send, msg_send_id, mutability, sel, ClassType, DeclaredClass};
+use objc2_authentication_services::{
+    ASAuthorization, ASAuthorizationAllSupportedPublicKeyCredentialDescriptorTransports, ASAuthorizationController,
+    ASAuthorizationControllerDelegate,
+    ASAuthorizationControllerPresentationContextProviding, ASAuthorizationError,
+    ASAuthorizationPlatformPublicKeyCredentialAssertion, ASAuthorizationPlatformPublicKeyCredentialAssertionRequest,
+    ASAuthorizationPlatformPublicKeyCredentialDescriptor,
+    ASAuthorizationPlatformPublicKeyCredentialProvider,
+    ASAuthorizationPlatformPublicKeyCredentialRegistration, ASAuthorizationPublicKeyCredentialAssertion,
+    ASAuthorizationPublicKeyCredentialAssertionRequest, ASAuthorizationPublicKeyCredentialLargeBlobAssertionInput,
+    ASAuthorizationPublicKeyCredentialLargeBlobAssertionOperation, ASAuthorizationPublicKeyCredentialParameters,
+    ASAuthorizationPublicKeyCredentialRegistration, ASAuthorizationPublicKeyCredentialRegistrationRequest,
+    ASAuthorizationPublicKeyCredentialResidentKeyPreference,
+    ASAuthorizationPublicKeyCredentialResidentKeyPreferenceDiscouraged,
+    ASAuthorizationPublicKeyCredentialResidentKeyPreferencePreferred,
+    ASAuthorizationPublicKeyCredentialResidentKeyPreferenceRequired,
+    ASAuthorizationPublicKeyCredentialUserVerificationPreference,
+    ASAuthorizationPublicKeyCredentialUserVerificationPreferenceDiscouraged,
+    ASAuthorizationPublicKeyCredentialUserVerificationPreferencePreferred,
+    ASAuthorizationPublicKeyCredentialUserVerificationPreferenceRequired, ASAuthorizationRequest,
+    ASAuthorizationSecurityKeyPublicKeyCredentialAssertion, ASAuthorizationSecurityKeyPublicKeyCredentialDescriptor,
+    ASAuthorizationSecurityKeyPublicKeyCredentialProvider, ASAuthorizationSecurityKeyPublicKeyCredentialRegistration,
+    ASCOSEAlgorithmIdentifierES256,
+    ASCredentialIdentity, ASCredentialIdentityStore, ASCredentialIdentityStoreState, ASCredentialIdentityTypes,
+    ASPasskeyCredentialIdentity, ASPublicKeyCredential,
+};
+use objc2_foundation::{
+    MainThreadMarker, NSArray, NSData, NSDictionary, NSError, NSInteger, NSProcessInfo, NSString,
+};
+use objc2_local_authentication::{LAContext, LAPolicy};
+use tauri::{Manager, Runtime, WebviewWindow};
+use tokio::sync::oneshot;
+
+use crate::ceremony_watchdog::{self, CeremonyReply};
+use crate::webauthn::{
+    AuthenticatorAttachment, ResidentKeyRequirement, UserVerification, WebAuthnCapabilities, WebAuthnError,
+};
+use crate::webauthn_platform::{LargeBlobOperation, PrfInput};
+
+/// The OS the build runs on, as `min_os_version_required` names it
+#[cfg(target_os = "macos")]
+const OS_NAME: &str = "macOS";
+#[cfg(target_os = "ios")]
+const OS_NAME: &str = "iOS";
+
+/// First major release with the AuthenticationServices passkey and security
+/// key providers; older ones cannot run a ceremony at all
+#[cfg(target_os = "macos")]
+const PROVIDERS_SINCE: NSInteger = 12;
+#[cfg(target_os = "ios")]
+const PROVIDERS_SINCE: NSInteger = 15;
+
+/// First major release whose platform provider works in every app; before it,
+/// platform passkeys needed a developer setting turned on
+#[cfg(target_os = "macos")]
+const PLATFORM_PASSKEYS_SINCE: NSInteger = 13;
+#[cfg(target_os = "ios")]
+const PLATFORM_PASSKEYS_SINCE: NSInteger = 16;
+
+/// The major version of the running macOS or iOS
+fn os_major_version() -> NSInteger {
+    NSProcessInfo::processInfo().operatingSystemVersion().majorVersion
+}
+
+/// Whether the OS has the passkey providers, so security keys at least can be used
+pub(crate) fn is_available() -> bool {
+    os_major_version() >= PROVIDERS_SINCE
+}
+
+/// Probe LocalAuthentication for what the platform authenticator can do
+///
+/// Passkeys require a device passcode, with biometrics used for verification when
+/// enrolled, so both are covered by `LAPolicyDeviceOwnerAuthentication`. Security
+/// keys cannot be detected before a ceremony, but can always be offered where
+/// the OS has the providers. Below macOS 13 and iOS 16 the platform
+/// authenticator is not offered, and `min_os_version_required` names the
+/// release to upgrade to.
+pub(crate) fn capabilities() -> WebAuthnCapabilities {
+    let platform_passkeys = os_major_version() >= PLATFORM_PASSKEYS_SINCE;
+    let device_owner = platform_passkeys && {
+        let context = unsafe { LAContext::new() };
+        unsafe { context.canEvaluatePolicy_error(LAPolicy::DeviceOwnerAuthentication) }.is_ok()
+    };
+
+    WebAuthnCapabilities {
+        platform_authenticator: device_owner,
+        // Over USB, NFC or Bluetooth, from macOS 12 and iOS 15
+        roaming_authenticator: is_available(),
+        // iCloud Keychain passkeys are always discoverable
+        resident_key_support: device_owner,
+        user_verification_support: device_owner,
+        // Passkey private keys are protected by the Secure Enclave
+        hardware_backed: Some(true),
+        min_os_version_required: (!platform_passkeys).then(|| format!("{} {}", OS_NAME, PLATFORM_PASSKEYS_SINCE)),
+    }
+}
+
+/// Whether Touch ID or Face ID is set up, so a passkey prompt verifies the user
+/// biometrically rather than falling back to the device password
+pub(crate) fn biometrics_enrolled() -> bool {
+    let context = unsafe { LAContext::new() };
+    unsafe { context.canEvaluatePolicy_error(LAPolicy::DeviceOwnerAuthenticationWithBiometrics) }.is_ok()
+}
+
+/// A ceremony to run against the platform authenticator, security keys or both
+///
+/// Asking for security keys still offers the platform authenticator alongside
+/// them, so a user without their key at hand can dismiss the key prompt and use
+/// a passkey instead; the response reports which one was used.
+pub(crate) enum CeremonyRequest {
+    Register {
+        rp_id: String,
+        challenge: Vec<u8>,
+        user_name: String,
+        user_id: Vec<u8>,
+        display_name: Option<String>,
+        user_verification: UserVerification,
+        resident_key: ResidentKeyRequirement,
+        authenticator_attachment: AuthenticatorAttachment,
+    },
+    Authenticate {
+        rp_id: String,
+        challenge: Vec<u8>,
+        allowed_credentials: Vec<Vec<u8>>,
+        user_verification: UserVerification,
+        authenticator_attachment: AuthenticatorAttachment,
+        /// Only the platform authenticator is asked to evaluate it
+        prf: Option<PrfInput>,
+        /// Likewise only asked of the platform authenticator
+        large_blob: Option<LargeBlobOperation>,
+        /// How long the delegate has to report back
+        timeout: Duration,
+    },
+}
+
+impl CeremonyRequest {
+    fn watchdog_timeout(&self) -> Duration {
+        match self {
+            CeremonyRequest::Register { .. } => REGISTRATION_WATCHDOG_TIMEOUT,
+            CeremonyRequest::Authenticate { timeout, .. } => *timeout,
+        }
+    }
+
+    fn offers_security_keys(&self) -> bool {
+        let (CeremonyRequest::Register {
+            authenticator_attachment,
+            ..
+        }
+        | CeremonyRequest::Authenticate {
+            authenticator_attachment,
+            ..
+        }) = self;
+        *authenticator_attachment != AuthenticatorAttachment::Platform
+    }
+}
+
+/// Raw output of a successful registration ceremony
+pub(crate) struct NativeRegistration {
+    pub credential_id: Vec<u8>,
+    pub attestation_object: Option<Vec<u8>>,
+    /// `platform` for an iCloud Keychain passkey, `cross-platform` for a security key
+    pub authenticator_attachment: String,
+    pub is_resident: bool,
+}
+
+/// Raw output of a successful assertion ceremony
+pub(crate) struct NativeAssertion {
+    pub credential_id: Vec<u8>,
+    pub user_handle: Option<Vec<u8>>,
+    pub authenticator_data: Vec<u8>,
+    /// See `NativeRegistration::authenticator_attachment`
+    pub authenticator_attachment: String,
+    pub prf_output: Option<Vec<u8>>,
+    pub large_blob_data: Option<Vec<u8>>,
+}
+
+/// Outcome reported by the controller delegate
+pub(crate) enum CeremonyResponse {
+    Registration(NativeRegistration),
+    Assertion(NativeAssertion),
+}
+
+type CeremonySender = CeremonyReply<CeremonyResponse>;
+
+/// How long the watchdog gives a registration, which has no timeout of its own;
+/// as long as the longest authentication
+const REGISTRATION_WATCHDOG_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// `NSXPCConnectionInterrupted` and `NSXPCConnectionInvalid`, in `NSCocoaErrorDomain`
+const NS_XPC_CONNECTION_INTERRUPTED: isize = 4097;
+const NS_XPC_CONNECTION_INVALID: isize = 4099;
+
+pub(crate) struct DelegateIvars {
+    anchor: Retained<AnyObject>,
+    sender: CeremonySender,
+    /// The registration required a discoverable credential, so a security key that
+    /// completed it stored one
+    resident_key_required: bool,
+}
+
+declare_class!(
+    // SAFETY:
+    // - NSObject has no subclassing requirements.
+    // - The delegate is only ever touched on the main thread.
+    // - `CeremonyDelegate` does not implement `Drop`.
+    pub(crate) struct CeremonyDelegate;
+
+    unsafe impl ClassType for CeremonyDelegate {
+        type Super = NSObject;
+        type Mutability = mutability::MainThreadOnly;
+        const NAME: &'static str = "SparktypeWebAuthnCeremonyDelegate";
+    }
+
+    impl DeclaredClass for CeremonyDelegate {
+        type Ivars = DelegateIvars;
+    }
+
+    unsafe impl NSObjectProtocol for CeremonyDelegate {}
+
+    unsafe impl ASAuthorizationControllerDelegate for CeremonyDelegate {
+        #[method(authorizationController:didCompleteWithAuthorization:)]
+        fn did_complete_with_authorization(
+            &self,
+            _controller: &ASAuthorizationController,
+            authorization: &ASAuthorization,
+        ) {
+            let resident_key_required = self.ivars().resident_key_required;
+            self.finish(unsafe { read_authorization(authorization, resident_key_required) });
+        }
+
+        #[method(authorizationController:didCompleteWithError:)]
+        fn did_complete_with_error(&self, _controller: &ASAuthorizationController, error: &NSError) {
+            self.finish(Err(describe_error(error)));
+        }
+    }
+
+    unsafe impl ASAuthorizationControllerPresentationContextProviding for CeremonyDelegate {
+        #[method_id(presentationAnchorForAuthorizationController:)]
+        fn presentation_anchor(&self, _controller: &ASAuthorizationController) -> Retained<AnyObject> {
+            self.ivars().anchor.clone()
+        }
+    }
+);
+
+impl CeremonyDelegate {
+    fn new(
+        mtm: MainThreadMarker,
+        anchor: Retained<AnyObject>,
+        sender: CeremonySender,
+        resident_key_required: bool,
+    ) -> Retained<Self> {
+        let this = mtm.alloc::<Self>().set_ivars(DelegateIvars {
+            anchor,
+            sender,
+            resident_key_required,
+        });
+        unsafe { msg_send_id![super(this), init] }
+    }
+
+    fn finish(&self, result: Result<CeremonyResponse, WebAuthnError>) {
+        self.ivars().sender.send(result);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.ivars().sender.has_fired()
+    }
+}
+
+/// A presented ceremony, with the ID of its `perform_ceremony` call
+struct InFlightCeremony {
+    id: u64,
+    controller: Retained<ASAuthorizationController>,
+    delegate: Retained<CeremonyDelegate>,
+}
+
+thread_local! {
+    /// Controllers only hold their delegate weakly, so both are kept alive here
+    /// until the delegate has reported back. Finished entries are swept lazily
+    /// rather than from inside the delegate callback that is still running.
+    static IN_FLIGHT: RefCell<Vec<InFlightCeremony>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Source of the IDs that tie `IN_FLIGHT` entries to their `perform_ceremony` call
+static NEXT_CEREMONY_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Run a passkey ceremony presented over the given window
+///
+/// Await this from the async command; the platform UI is driven from the main
+/// thread while the caller stays on Tauri's background thread pool.
+pub(crate) async fn perform_ceremony<R: Runtime>(
+    window: &WebviewWindow<R>,
+    request: CeremonyRequest,
+) -> Result<CeremonyResponse, WebAuthnError> {
+    let (sender, receiver) = ceremony_watchdog::watched_channel(request.watchdog_timeout());
+    let id = NEXT_CEREMONY_ID.fetch_add(1, Ordering::Relaxed);
+
+    window
+        .with_webview(move |webview| unsafe {
+            start_ceremony(webview.inner().cast(), id, request, sender);
+        })
+        .map_err(|e| WebAuthnError::InternalError(format!("Failed to reach the webview: {}", e)))?;
+
+    // Runs only if this future is dropped before the delegate reports back
+    let app = window.app_handle().clone();
+    let cancel_on_drop = scopeguard::guard((), move |()| {
+        if let Err(e) = app.run_on_main_thread(move || unsafe { cancel_ceremony(id) }) {
+            log::warn!("Failed to cancel an abandoned passkey ceremony: {}", e);
+        }
+    });
+    let response = receiver.await;
+    // A watchdog timeout leaves the sheet up, so the guard dismisses it
+    if !matches!(response, Ok(Err(WebAuthnError::Timeout))) {
+        scopeguard::ScopeGuard::into_inner(cancel_on_drop);
+    }
+
+    response
+        .map_err(|_| WebAuthnError::InternalError("Authorization controller went away before completing".to_string()))?
+}
+
+/// Dismiss the sheet of ceremony `id` if it is still up. Must be called on the main thread.
+unsafe fn cancel_ceremony(id: u64) {
+    let controller = IN_FLIGHT.with(|in_flight| {
+        in_flight
+            .borrow()
+            .iter()
+            .find(|ceremony| ceremony.id == id && !ceremony.delegate.is_finished())
+            .map(|ceremony| ceremony.controller.clone())
+    });
+    let Some(controller) = controller else {
+        return;
+    };
+    // `cancel` is only there from macOS 13 and iOS 16; earlier systems leave the
+    // sheet up until the user dismisses it
+    if !controller.respondsToSelector(sel!(cancel)) {
+        return;
+    }
+    log::debug!("Cancelling an abandoned passkey ceremony");
+    controller.cancel();
+}
+
+/// Cancel every ceremony whose sheet is still up and let go of its controller
+/// and delegate, as the app exits. Does nothing off the main thread.
+pub(crate) fn cancel_all_ceremonies() {
+    if MainThreadMarker::new().is_none() {
+        log::warn!("Passkey ceremonies can only be cancelled from the main thread");
+        return;
+    }
+    let ceremonies = IN_FLIGHT.with(|in_flight| in_flight.take());
+    for ceremony in ceremonies.iter().filter(|ceremony| !ceremony.delegate.is_finished()) {
+        unsafe {
+            // Detached first, so the cancellation is not reported to a delegate about to go away
+            ceremony.controller.setDelegate(None);
+            ceremony.controller.setPresentationContextProvider(None);
+            if ceremony.controller.respondsToSelector(sel!(cancel)) {
+                ceremony.controller.cancel();
+            }
+        }
+    }
+    if !ceremonies.is_empty() {
+        log::debug!("Cancelled {} passkey ceremonies on exit", ceremonies.len());
+    }
+}
+
+/// Remove a passkey from the system credential identity store
+///
+/// The store feeds the passkey suggestions AutoFill offers for the RP. It is
+/// only enabled for apps set up as a credential provider; when it is not, there
+/// is nothing to remove. Identities are matched on the RP and credential ID.
+pub(crate) async fn remove_credential_identity(
+    rp_id: &str,
+    credential_id: &[u8],
+    user_handle: &[u8],
+) -> Result<(), WebAuthnError> {
+    let (sender, receiver) = oneshot::channel();
+
+    unsafe { start_identity_removal(rp_id, credential_id, user_handle, sender) };
+
+    receiver
+        .await
+        .map_err(|_| WebAuthnError::InternalError("Credential identity store went away before completing".to_string()))?
+}
+
+unsafe fn start_identity_removal(
+    rp_id: &str,
+    credential_id: &[u8],
+    user_handle: &[u8],
+    sender: oneshot::Sender<Result<(), WebAuthnError>>,
+) {
+    let store = ASCredentialIdentityStore::sharedStore();
+    // Passkey identities arrived with macOS 14 and iOS 17
+    if !store.respondsToSelector(sel!(removeCredentialIdentityEntries:completion:)) {
+        let _ = sender.send(Ok(()));
+        return;
+    }
+
+    let identity =
+        ASPasskeyCredentialIdentity::identityWithRelyingPartyIdentifier_userName_credentialID_userHandle_recordIdentifier(
+            &NSString::from_str(rp_id),
+            &NSString::new(),
+            &NSData::with_bytes(credential_id),
+            &NSData::with_bytes(user_handle),
+            None,
+        );
+    let identities = NSArray::from_vec(vec![ProtocolObject::<dyn ASCredentialIdentity>::from_retained(identity)]);
+
+    // Completion handlers are `Fn` blocks called once, so the sender is taken out of a cell
+    let sender = Cell::new(Some(sender));
+    let removal_store = store.clone();
+    let on_state = RcBlock::new(move |state: NonNull<ASCredentialIdentityStoreState>| {
+        let Some(sender) = sender.take() else {
+            return;
+        };
+        if !state.as_ref().isEnabled() {
+            let _ = sender.send(Ok(()));
+            return;
+        }
+
+        let sender = Cell::new(Some(sender));
+        let on_removed = RcBlock::new(move |removed: Bool, error: *mut NSError| {
+            let Some(sender) = sender.take() else {
+                return;
+            };
+            let result = if removed.as_bool() {
+                Ok(())
+            } else {
+                let reason = error.as_ref().map(|error| error.localizedDescription().to_string());
+                Err(WebAuthnError::InternalError(format!(
+                    "Failed to remove the passkey from the credential identity store: {}",
+                    reason.unwrap_or_else(|| "unknown error".to_string())
+                )))
+            };
+            let _ = sender.send(result);
+        });
+        removal_store.removeCredentialIdentityEntries_completion(&identities, Some(&on_removed));
+    });
+    store.getCredentialIdentityStoreStateWithCompletion(&on_state);
+}
+
+/// Whether the system credential identity store still lists a passkey
+///
+/// Only answers where the store can be listed (macOS 15 and iOS 18) and is
+/// enabled, which needs the app to be set up as a credential provider; `None`
+/// otherwise. Never prompts the user.
+pub(crate) async fn credential_identity_exists(rp_id: &str, credential_id: &[u8]) -> Option<bool> {
+    let (sender, receiver) = oneshot::channel();
+
+    unsafe { start_identity_lookup(rp_id.to_string(), credential_id.to_vec(), sender) };
+
+    receiver.await.ok().flatten()
+}
+
+unsafe fn start_identity_lookup(rp_id: String, credential_id: Vec<u8>, sender: oneshot::Sender<Option<bool>>) {
+    let store = ASCredentialIdentityStore::sharedStore();
+    if !store.respondsToSelector(sel!(getCredentialIdentitiesForService:credentialIdentityTypes:completionHandler:)) {
+        let _ = sender.send(None);
+        return;
+    }
+
+    let sender = Cell::new(Some(sender));
+    let lookup_store = store.clone();
+    let on_state = RcBlock::new(move |state: NonNull<ASCredentialIdentityStoreState>| {
+        let Some(sender) = sender.take() else {
+            return;
+        };
+        if !state.as_ref().isEnabled() {
+            let _ = sender.send(None);
+            return;
+        }
+
+        let sender = Cell::new(Some(sender));
+        let rp_id = rp_id.clone();
+        let credential_id = credential_id.clone();
+        let on_identities = RcBlock::new(move |identities: NonNull<NSArray<ProtocolObject<dyn ASCredentialIdentity>>>| {
+            let Some(sender) = sender.take() else {
+                return;
+            };
+            let found = identities.as_ref().iter().any(|identity| {
+                if !identity.isKindOfClass(ASPasskeyCredentialIdentity::class()) {
+                    return false;
+                }
+                // Checked to be a passkey identity just above
+                let passkey = &*(identity as *const ProtocolObject<dyn ASCredentialIdentity>)
+                    .cast::<ASPasskeyCredentialIdentity>();
+                passkey.relyingPartyIdentifier().to_string() == rp_id && passkey.credentialID().bytes() == credential_id
+            });
+            let _ = sender.send(Some(found));
+        });
+        lookup_store.getCredentialIdentitiesForService_credentialIdentityTypes_completionHandler(
+            None,
+            ASCredentialIdentityTypes::Passkey,
+            &on_identities,
+        );
+    });
+    store.getCredentialIdentityStoreStateWithCompletion(&on_state);
+}
+
+/// Build the authorization request and present it. Must be called on the main thread.
+unsafe fn start_ceremony(webview: *mut AnyObject, id: u64, request: CeremonyRequest, sender: CeremonySender) {
+    let Some(mtm) = MainThreadMarker::new() else {
+        sender.send(Err(WebAuthnError::InternalError(
+            "Passkey ceremony must start on the main thread".to_string(),
+        )));
+        return;
+    };
+
+    IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().retain(|ceremony| !ceremony.delegate.is_finished()));
+
+    // The WKWebView's window (NSWindow / UIWindow) is the presentation anchor.
+    let anchor: Option<Retained<AnyObject>> = match webview.as_ref() {
+        Some(webview) => msg_send_id![webview, window],
+        None => None,
+    };
+    let Some(anchor) = anchor else {
+        sender.send(Err(WebAuthnError::InternalError(
+            "Webview is not attached to a window".to_string(),
+        )));
+        return;
+    };
+
+    let mut authorization_requests = vec![build_platform_request(&request)];
+    if request.offers_security_keys() {
+        authorization_requests.push(build_security_key_request(&request));
+    }
+    let requests = NSArray::from_vec(authorization_requests);
+    let controller =
+        ASAuthorizationController::initWithAuthorizationRequests(ASAuthorizationController::alloc(), &requests);
+
+    let resident_key_required = matches!(
+        request,
+        CeremonyRequest::Register {
+            resident_key: ResidentKeyRequirement::Required,
+            ..
+        }
+    );
+    let delegate = CeremonyDelegate::new(mtm, anchor, sender, resident_key_required);
+    controller.setDelegate(Some(ProtocolObject::from_ref(&*delegate)));
+    controller.setPresentationContextProvider(Some(ProtocolObject::from_ref(&*delegate)));
+    controller.performRequests();
+
+    let ceremony = InFlightCeremony {
+        id,
+        controller,
+        delegate,
+    };
+    IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().push(ceremony));
+}
+
+unsafe fn build_platform_request(request: &CeremonyRequest) -> Retained<ASAuthorizationRequest> {
+    match request {
+        CeremonyRequest::Register {
+            rp_id,
+            challenge,
+            user_name,
+            user_id,
+            display_name,
+            user_verification,
+            ..
+        } => {
+            let provider = platform_provider(rp_id);
+            let registration = provider.createCredentialRegistrationRequestWithChallenge_name_userID(
+                &NSData::with_bytes(challenge),
+                &NSString::from_str(user_name),
+                &NSData::with_bytes(user_id),
+            );
+            if let Some(display_name) = display_name {
+                registration.setDisplayName(Some(&NSString::from_str(display_name)));
+            }
+            if let Some(preference) = user_verification_preference(*user_verification) {
+                registration.setUserVerificationPreference(preference);
+            }
+            Retained::into_super(registration)
+        }
+        CeremonyRequest::Authenticate {
+            rp_id,
+            challenge,
+            allowed_credentials,
+            user_verification,
+            prf,
+            large_blob,
+            ..
+        } => {
+            let provider = platform_provider(rp_id);
+            let assertion = provider.createCredentialAssertionRequestWithChallenge(&NSData::with_bytes(challenge));
+            let descriptors = allowed_credentials
+                .iter()
+                .map(|credential_id| {
+                    ASAuthorizationPlatformPublicKeyCredentialDescriptor::initWithCredentialID(
+                        ASAuthorizationPlatformPublicKeyCredentialDescriptor::alloc(),
+                        &NSData::with_bytes(credential_id),
+                    )
+                })
+                .collect();
+            assertion.setAllowedCredentials(&NSArray::from_vec(descriptors));
+            if let Some(preference) = user_verification_preference(*user_verification) {
+                assertion.setUserVerificationPreference(preference);
+            }
+            if let Some(prf) = prf {
+                set_prf_input(&assertion, prf);
+            }
+            if let Some(large_blob) = large_blob {
+                set_large_blob_input(&assertion, large_blob);
+            }
+            Retained::into_super(assertion)
+        }
+    }
+}
+
+/// Ask the platform authenticator to evaluate the PRF extension
+///
+/// AuthenticationServices gained PRF with macOS 15 and iOS 18, after the
+/// bindings were generated, so its classes are looked up at runtime. Earlier
+/// releases assert without evaluating the inputs.
+unsafe fn set_prf_input(assertion: &ASAuthorizationPlatformPublicKeyCredentialAssertionRequest, prf: &PrfInput) {
+    let (Some(input_class), Some(values_class)) = (
+        AnyClass::get("ASAuthorizationPublicKeyCredentialPRFAssertionInput"),
+        AnyClass::get("ASAuthorizationPublicKeyCredentialPRFAssertionInputValues"),
+    ) else {
+        log::warn!("AuthenticationServices does not support the PRF extension before macOS 15 and iOS 18");
+        return;
+    };
+    let input_values = |input: &[u8]| -> Retained<AnyObject> {
+        let values: Allocated<AnyObject> = msg_send_id![values_class, alloc];
+        msg_send_id![values, initWithSaltInput1: &*NSData::with_bytes(input), saltInput2: None::<&NSData>]
+    };
+
+    let credential_ids: Vec<_> = prf
+        .eval_by_credential
+        .iter()
+        .map(|(credential_id, _)| NSData::with_bytes(credential_id))
+        .collect();
+    let per_credential = (!credential_ids.is_empty()).then(|| {
+        NSDictionary::<NSData, AnyObject>::from_vec(
+            &credential_ids.iter().map(|credential_id| &**credential_id).collect::<Vec<_>>(),
+            prf.eval_by_credential.iter().map(|(_, input)| input_values(input)).collect(),
+        )
+    });
+    let input: Allocated<AnyObject> = msg_send_id![input_class, alloc];
+    let input: Retained<AnyObject> = msg_send_id![
+        input,
+        initWithInputValues: &*input_values(&prf.eval),
+        perCredentialInputValues: per_credential.as_deref()
+    ];
+    let _: () = msg_send![assertion, setPrf: &*input];
+}
+
+/// The PRF output of a platform assertion, where AuthenticationServices reports one
+unsafe fn read_prf_output(assertion: &ASAuthorizationPlatformPublicKeyCredentialAssertion) -> Option<Vec<u8>> {
+    if !assertion.respondsToSelector(sel!(prf)) {
+        return None;
+    }
+    let output: Option<Retained<AnyObject>> = msg_send_id![assertion, prf];
+    let first: Option<Retained<NSData>> = msg_send_id![&*output?, first];
+    first.map(|first| first.bytes().to_vec())
+}
+
+/// Ask the platform authenticator to read or write the credential's largeBlob
+///
+/// Supported from macOS 14 and iOS 17; earlier releases assert without it.
+unsafe fn set_large_blob_input(
+    assertion: &ASAuthorizationPlatformPublicKeyCredentialAssertionRequest,
+    large_blob: &LargeBlobOperation,
+) {
+    if !assertion.respondsToSelector(sel!(setLargeBlob:)) {
+        log::warn!("AuthenticationServices does not support the largeBlob extension before macOS 14 and iOS 17");
+        return;
+    }
+    let input = match large_blob {
+        LargeBlobOperation::Read => ASAuthorizationPublicKeyCredentialLargeBlobAssertionInput::initWithOperation(
+            ASAuthorizationPublicKeyCredentialLargeBlobAssertionInput::alloc(),
+            ASAuthorizationPublicKeyCredentialLargeBlobAssertionOperation::Read,
+        ),
+        LargeBlobOperation::Write(data) => {
+            let input = ASAuthorizationPublicKeyCredentialLargeBlobAssertionInput::initWithOperation(
+                ASAuthorizationPublicKeyCredentialLargeBlobAssertionInput::alloc(),
+                ASAuthorizationPublicKeyCredentialLargeBlobAssertionOperation::Write,
+            );
+            input.setDataToWrite(Some(&NSData::with_bytes(data)));
+            input
+        }
+    };
+    assertion.setLargeBlob(Some(&input));
+}
+
+/// The largeBlob data a platform assertion read, where AuthenticationServices reports some
+unsafe fn read_large_blob_output(assertion: &ASAuthorizationPlatformPublicKeyCredentialAssertion) -> Option<Vec<u8>> {
+    if !assertion.respondsToSelector(sel!(largeBlob)) {
+        return None;
+    }
+    assertion.largeBlob()?.readData().map(|data| data.bytes().to_vec())
+}
+
+/// The same ceremony for a security key over USB, NFC or Bluetooth
+unsafe fn build_security_key_request(request: &CeremonyRequest) -> Retained<ASAuthorizationRequest> {
+    match request {
+        CeremonyRequest::Register {
+            rp_id,
+            challenge,
+            user_name,
+            user_id,
+            display_name,
+            user_verification,
+            resident_key,
+            ..
+        } => {
+            let provider = security_key_provider(rp_id);
+            let registration = provider.createCredentialRegistrationRequestWithChallenge_displayName_name_userID(
+                &NSData::with_bytes(challenge),
+                &NSString::from_str(display_name.as_deref().unwrap_or(user_name)),
+                &NSString::from_str(user_name),
+                &NSData::with_bytes(user_id),
+            );
+            // Security keys are only asked for the algorithms listed; every CTAP2 key supports ES256
+            let parameters = ASAuthorizationPublicKeyCredentialParameters::initWithAlgorithm(
+                ASAuthorizationPublicKeyCredentialParameters::alloc(),
+                ASCOSEAlgorithmIdentifierES256,
+            );
+            registration.setCredentialParameters(&NSArray::from_vec(vec![parameters]));
+            if let Some(preference) = resident_key_preference(*resident_key) {
+                registration.setResidentKeyPreference(preference);
+            }
+            if let Some(preference) = user_verification_preference(*user_verification) {
+                registration.setUserVerificationPreference(preference);
+            }
+            Retained::into_super(registration)
+        }
+        CeremonyRequest::Authenticate {
+            rp_id,
+            challenge,
+            allowed_credentials,
+            user_verification,
+            ..
+        } => {
+            let provider = security_key_provider(rp_id);
+            let assertion = provider.createCredentialAssertionRequestWithChallenge(&NSData::with_bytes(challenge));
+            let transports = ASAuthorizationAllSupportedPublicKeyCredentialDescriptorTransports();
+            let descriptors = allowed_credentials
+                .iter()
+                .map(|credential_id| {
+                    ASAuthorizationSecurityKeyPublicKeyCredentialDescriptor::initWithCredentialID_transports(
+                        ASAuthorizationSecurityKeyPublicKeyCredentialDescriptor::alloc(),
+                        &NSData::with_bytes(credential_id),
+                        transports.as_ref(),
+                    )
+                })
+                .collect();
+            assertion.setAllowedCredentials(&NSArray::from_vec(descriptors));
+            if let Some(preference) = user_verification_preference(*user_verification) {
+                assertion.setUserVerificationPreference(preference);
+            }
+            Retained::into_super(assertion)
+        }
+    }
+}
+
+unsafe fn resident_key_preference(
+    resident_key: ResidentKeyRequirement,
+) -> Option<&'static ASAuthorizationPublicKeyCredentialResidentKeyPreference> {
+    match resident_key {
+        ResidentKeyRequirement::Required => ASAuthorizationPublicKeyCredentialResidentKeyPreferenceRequired,
+        ResidentKeyRequirement::Preferred => ASAuthorizationPublicKeyCredentialResidentKeyPreferencePreferred,
+        ResidentKeyRequirement::Discouraged => ASAuthorizationPublicKeyCredentialResidentKeyPreferenceDiscouraged,
+    }
+}
+
+unsafe fn user_verification_preference(
+    user_verification: UserVerification,
+) -> Option<&'static ASAuthorizationPublicKeyCredentialUserVerificationPreference> {
+    match user_verification {
+        UserVerification::Required => ASAuthorizationPublicKeyCredentialUserVerificationPreferenceRequired,
+        UserVerification::Preferred => ASAuthorizationPublicKeyCredentialUserVerificationPreferencePreferred,
+        UserVerification::Discouraged => ASAuthorizationPublicKeyCredentialUserVerificationPreferenceDiscouraged,
+    }
+}
+
+unsafe fn platform_provider(rp_id: &str) -> Retained<ASAuthorizationPlatformPublicKeyCredentialProvider> {
+    ASAuthorizationPlatformPublicKeyCredentialProvider::initWithRelyingPartyIdentifier(
+        ASAuthorizationPlatformPublicKeyCredentialProvider::alloc(),
+        &NSString::from_str(rp_id),
+    )
+}
+
+unsafe fn security_key_provider(rp_id: &str) -> Retained<ASAuthorizationSecurityKeyPublicKeyCredentialProvider> {
+    ASAuthorizationSecurityKeyPublicKeyCredentialProvider::initWithRelyingPartyIdentifier(
+        ASAuthorizationSecurityKeyPublicKeyCredentialProvider::alloc(),
+        &NSString::from_str(rp_id),
+    )
+}
+
+/// Copy the credential returned by the controller into plain Rust data
+///
+/// Whichever of the offered requests the user completed decides the attachment:
+/// dismissing the security key prompt for the platform authenticator yields a
+/// platform credential.
+unsafe fn read_authorization(
+    authorization: &ASAuthorization,
+    resident_key_required: bool,
+) -> Result<CeremonyResponse, WebAuthnError> {
+    let credential = authorization.credential();
+    let object = Retained::as_ptr(&credential);
+
+    if credential.isKindOfClass(ASAuthorizationPlatformPublicKeyCredentialRegistration::class()) {
+        let registration = &*object.cast::<ASAuthorizationPlatformPublicKeyCredentialRegistration>();
+        return Ok(CeremonyResponse::Registration(NativeRegistration {
+            credential_id: registration.credentialID().bytes().to_vec(),
+            attestation_object: registration.rawAttestationObject().map(|data| data.bytes().to_vec()),
+            authenticator_attachment: "platform".to_string(),
+            // iCloud Keychain only stores discoverable passkeys
+            is_resident: true,
+        }));
+    }
+
+    if credential.isKindOfClass(ASAuthorizationSecurityKeyPublicKeyCredentialRegistration::class()) {
+        let registration = &*object.cast::<ASAuthorizationSecurityKeyPublicKeyCredentialRegistration>();
+        return Ok(CeremonyResponse::Registration(NativeRegistration {
+            credential_id: registration.credentialID().bytes().to_vec(),
+            attestation_object: registration.rawAttestationObject().map(|data| data.bytes().to_vec()),
+            authenticator_attachment: "cross-platform".to_string(),
+            // AuthenticationServices does not report whether a preferred one was stored
+            is_resident: resident_key_required,
+        }));
+    }
+
+    if credential.isKindOfClass(ASAuthorizationPlatformPublicKeyCredentialAssertion::class()) {
+        let assertion = &*object.cast::<ASAuthorizationPlatformPublicKeyCredentialAssertion>();
+        let user_handle = assertion.userID().bytes().to_vec();
+        return Ok(CeremonyResponse::Assertion(NativeAssertion {
+            credential_id: assertion.credentialID().bytes().to_vec(),
+            user_handle: (!user_handle.is_empty()).then_some(user_handle),
+            authenticator_data: assertion.rawAuthenticatorData().bytes().to_vec(),
+            authenticator_attachment: "platform".to_string(),
+            prf_output: read_prf_output(assertion),
+            large_blob_data: read_large_blob_output(assertion),
+        }));
+    }
+
+    if credential.isKindOfClass(ASAuthorizationSecurityKeyPublicKeyCredentialAssertion::class()) {
+        let assertion = &*object.cast::<ASAuthorizationSecurityKeyPublicKeyCredentialAssertion>();
+        let user_handle = assertion.userID().bytes().to_vec();
+        return Ok(CeremonyResponse::Assertion(NativeAssertion {
+            credential_id: assertion.credentialID().bytes().to_vec(),
+            user_handle: (!user_handle.is_empty()).then_some(user_handle),
+            authenticator_data: assertion.rawAuthenticatorData().bytes().to_vec(),
+            authenticator_attachment: "cross-platform".to_string(),
+            // Security key assertion requests take no PRF or largeBlob inputs
+            prf_output: None,
+            large_blob_data: None,
+        }));
+    }
+
+    Err(WebAuthnError::InternalError(
+        "Unexpected credential type returned by the platform authenticator".to_string(),
+    ))
+}
+
+fn describe_error(error: &NSError) -> WebAuthnError {
+    // The connection to the passkey daemon dropped, e.g. while it restarted
+    if error.domain().to_string() == "NSCocoaErrorDomain"
+        && matches!(error.code(), NS_XPC_CONNECTION_INTERRUPTED | NS_XPC_CONNECTION_INVALID)
+    {
+        return WebAuthnError::PlatformUnavailable(error.localizedDescription().to_string());
+    }
+    let message = match ASAuthorizationError(error.code()) {
+        ASAuthorizationError::Canceled => return WebAuthnError::UserCancelled,
+        // Raised when Touch ID / Face ID or the device passcode was not accepted
+        ASAuthorizationError::Failed => return WebAuthnError::BiometricFailed,
+        ASAuthorizationError::InvalidResponse => "The authenticator returned an invalid response".to_string(),
+        ASAuthorizationError::NotHandled => "The authorization request was not handled".to_string(),
+        ASAuthorizationError::NotInteractive => "The authorization request requires user interaction".to_string(),
+        _ => format!("Authorization error ({}): {}", error.code(), error.localizedDescription()),
+    };
+    WebAuthnError::InternalError(message)
+}
@@ -0,0 +1,350 @@
+// src-tauri/src/webauthn_cbor.rs
+
+//! Decoding the binary structures authenticators hand back.
+//!
+//! Authenticator data has a fixed binary layout, but the credential public key
+//! it carries on registration is a COSE key, which is CBOR, and so is the
+//! attestation object some platforms wrap it in. Fields the commands do not
+//! check, such as attestation statements and extension outputs, are left
+//! undecoded.
+//!
+//! Every platform is asked for ES256 credentials, and Windows and Android for
+//! RS256 as well, so those are the only keys accepted. `CoseKey::to_pem` turns
+//! one into the PEM form a server verifying assertions can load.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use bitflags::bitflags;
+use ciborium::Value;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::webauthn::WebAuthnError;
+
+bitflags! {
+    /// The flags byte of authenticator data
+    ///
+    /// Bits the spec reserves are kept as they came.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct AuthenticatorDataFlags: u8 {
+        /// UP: the user was present
+        const USER_PRESENT = 0x01;
+        /// UV: the user was verified
+        const USER_VERIFIED = 0x04;
+        /// BE: the credential may be synced to other devices
+        const BACKUP_ELIGIBLE = 0x08;
+        /// BS: the credential is currently synced to other devices
+        const BACKED_UP = 0x10;
+        /// AT: attested credential data follows the signature counter
+        const ATTESTED_CREDENTIAL_DATA = 0x40;
+        /// ED: extension outputs come last
+        const EXTENSION_DATA = 0x80;
+    }
+}
+
+/// Authenticator data returned with a registration or an assertion
+#[derive(Debug, Clone)]
+pub struct AuthenticatorData {
+    /// SHA-256 of the RP ID the credential is scoped to
+    pub rp_id_hash: [u8; 32],
+    pub flags: AuthenticatorDataFlags,
+    /// Signature counter, or 0 for authenticators that don't keep one
+    pub sign_count: u32,
+    /// The AAGUID, credential ID and key are present on registration (the AT flag)
+    pub aaguid: Option<[u8; 16]>,
+    pub credential_id: Option<Vec<u8>>,
+    pub cose_key: Option<CoseKey>,
+}
+
+/// A credential public key in COSE_Key form (RFC 8152)
+///
+/// Its key material is zeroed when it is dropped.
+#[derive(Debug, Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct CoseKey {
+    /// COSE algorithm identifier: -7 for ES256 or -257 for RS256
+    pub algorithm: i64,
+    pub parameters: CoseKeyParameters,
+    /// The key as the authenticator encoded it, which is what gets stored
+    pub encoded: Vec<u8>,
+}
+
+/// The key material of a COSE key, by key type
+#[derive(Debug, Clone, PartialEq, Eq, Zeroize)]
+pub enum CoseKeyParameters {
+    /// A P-256 key (kty 2, crv 1), by its coordinates
+    Ec2 { x: [u8; 32], y: [u8; 32] },
+    /// An RSA key (kty 3), by its big-endian modulus and exponent
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+}
+
+/// rpIdHash (32) | flags (1) | signCount (4)
+const FIXED_LENGTH: usize = 37;
+
+// COSE_Key labels and values; the negative labels depend on the key type
+const LABEL_KTY: i64 = 1;
+const LABEL_ALG: i64 = 3;
+const KTY_EC2: i64 = 2;
+const KTY_RSA: i64 = 3;
+const CRV_P256: i64 = 1;
+const ALG_ES256: i64 = -7;
+const ALG_RS256: i64 = -257;
+
+/// SubjectPublicKeyInfo up to the point, for a P-256 key: the id-ecPublicKey
+/// and prime256v1 OIDs, then the header of the bit string holding the point
+const P256_SPKI_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce,
+    0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// AlgorithmIdentifier for rsaEncryption, with its NULL parameters
+const RSA_ALGORITHM_IDENTIFIER: [u8; 15] = [
+    0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00,
+];
+
+fn malformed(message: impl Into<String>) -> WebAuthnError {
+    WebAuthnError::InternalError(message.into())
+}
+
+fn truncated() -> WebAuthnError {
+    malformed("Authenticator data is truncated")
+}
+
+/// Parse raw authenticator data
+///
+/// Layout: rpIdHash (32) | flags (1) | signCount (4) followed, when the AT flag
+/// is set, by aaguid (16) | credentialIdLength (2) | credentialId |
+/// credentialPublicKey (a COSE key), and when the ED flag is set by extension
+/// outputs.
+pub fn parse_authenticator_data(raw: &[u8]) -> Result<AuthenticatorData, WebAuthnError> {
+    if raw.len() < FIXED_LENGTH {
+        return Err(truncated());
+    }
+    let mut rp_id_hash = [0u8; 32];
+    rp_id_hash.copy_from_slice(&raw[..32]);
+    let flags = AuthenticatorDataFlags::from_bits_retain(raw[32]);
+    let sign_count = u32::from_be_bytes([raw[33], raw[34], raw[35], raw[36]]);
+    let mut auth_data = AuthenticatorData {
+        rp_id_hash,
+        flags,
+        sign_count,
+        aaguid: None,
+        credential_id: None,
+        cose_key: None,
+    };
+    if !flags.contains(AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA) {
+        return Ok(auth_data);
+    }
+
+    let attested = &raw[FIXED_LENGTH..];
+    let aaguid: [u8; 16] = attested
+        .get(..16)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(truncated)?;
+    let id_length = attested
+        .get(16..18)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+        .ok_or_else(truncated)?;
+    let credential_id = attested.get(18..18 + id_length).ok_or_else(truncated)?;
+    // Extension outputs may follow the key
+    let (cose_key, _) = parse_cose_key_prefix(&attested[18 + id_length..])?;
+
+    auth_data.aaguid = Some(aaguid);
+    auth_data.credential_id = Some(credential_id.to_vec());
+    auth_data.cose_key = Some(cose_key);
+    Ok(auth_data)
+}
+
+impl AuthenticatorData {
+    pub fn user_present(&self) -> bool {
+        self.flags.contains(AuthenticatorDataFlags::USER_PRESENT)
+    }
+
+    pub fn user_verified(&self) -> bool {
+        self.flags.contains(AuthenticatorDataFlags::USER_VERIFIED)
+    }
+
+    pub fn backup_eligible(&self) -> bool {
+        self.flags.contains(AuthenticatorDataFlags::BACKUP_ELIGIBLE)
+    }
+
+    pub fn backed_up(&self) -> bool {
+        self.flags.contains(AuthenticatorDataFlags::BACKED_UP)
+    }
+
+    /// The AAGUID as a UUID string, or `None` when the authenticator withheld it
+    ///
+    /// Authenticators report an all-zero AAGUID with "none" attestation, which is
+    /// what every platform authenticator here is asked for.
+    pub fn aaguid_string(&self) -> Option<String> {
+        let aaguid = self.aaguid.filter(|aaguid| *aaguid != [0u8; 16])?;
+        let hex: String = aaguid.iter().map(|byte| format!("{:02x}", byte)).collect();
+        Some(format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        ))
+    }
+}
+
+/// Decode a COSE key that makes up the whole of `data`
+///
+/// Only ES256 keys on P-256 and RS256 keys are accepted.
+pub fn parse_cose_key(data: &[u8]) -> Result<CoseKey, WebAuthnError> {
+    let (key, length) = parse_cose_key_prefix(data)?;
+    if length != data.len() {
+        return Err(malformed("Credential public key is followed by stray bytes"));
+    }
+    Ok(key)
+}
+
+/// Decode the COSE key `data` starts with, and tell how many bytes it took
+fn parse_cose_key_prefix(data: &[u8]) -> Result<(CoseKey, usize), WebAuthnError> {
+    let mut remaining = data;
+    let value: Value = ciborium::de::from_reader(&mut remaining)
+        .map_err(|e| malformed(format!("Invalid credential public key: {}", e)))?;
+    let length = data.len() - remaining.len();
+    let Value::Map(entries) = value else {
+        return Err(malformed("Credential public key is not a COSE key"));
+    };
+
+    let get = |label: i64| {
+        entries
+            .iter()
+            .find(|(key, _)| key.as_integer().is_some_and(|key| i128::from(key) == i128::from(label)))
+            .map(|(_, value)| value)
+    };
+    let integer = |label: i64| {
+        get(label)
+            .and_then(Value::as_integer)
+            .and_then(|value| i64::try_from(value).ok())
+            .ok_or_else(|| malformed(format!("COSE key has no integer parameter {}", label)))
+    };
+    let bytes_of = |label: i64| {
+        get(label)
+            .and_then(Value::as_bytes)
+            .cloned()
+            .ok_or_else(|| malformed(format!("COSE key has no byte string parameter {}", label)))
+    };
+    let coordinate = |label: i64| {
+        <[u8; 32]>::try_from(bytes_of(label)?)
+            .map_err(|_| malformed(format!("COSE key parameter {} is not a P-256 coordinate", label)))
+    };
+
+    let key_type = integer(LABEL_KTY)?;
+    let algorithm = integer(LABEL_ALG)?;
+    let parameters = match (key_type, algorithm) {
+        (KTY_EC2, ALG_ES256) => {
+            let curve = integer(-1)?;
+            if curve != CRV_P256 {
+                return Err(malformed(format!("Unsupported COSE curve: {}", curve)));
+            }
+            CoseKeyParameters::Ec2 {
+                x: coordinate(-2)?,
+                y: coordinate(-3)?,
+            }
+        }
+        (KTY_RSA, ALG_RS256) => CoseKeyParameters::Rsa {
+            n: bytes_of(-1)?,
+            e: bytes_of(-2)?,
+        },
+        _ => {
+            return Err(malformed(format!(
+                "Unsupported COSE key type {} with algorithm {}",
+                key_type, algorithm
+            )))
+        }
+    };
+    let key = CoseKey {
+        algorithm,
+        parameters,
+        encoded: data[..length].to_vec(),
+    };
+    Ok((key, length))
+}
+
+impl CoseKey {
+    /// The key as a DER-encoded SubjectPublicKeyInfo (RFC 5280)
+    pub fn to_spki_der(&self) -> Vec<u8> {
+        match &self.parameters {
+            CoseKeyParameters::Ec2 { x, y } => {
+                // An uncompressed point: 0x04 | x | y
+                let mut der = P256_SPKI_PREFIX.to_vec();
+                der.push(0x04);
+                der.extend_from_slice(x);
+                der.extend_from_slice(y);
+                der
+            }
+            CoseKeyParameters::Rsa { n, e } => {
+                // RSAPublicKey (RFC 8017) inside the bit string
+                let mut rsa_public_key = der_unsigned_integer(n);
+                rsa_public_key.extend(der_unsigned_integer(e));
+                let mut bit_string = vec![0x00];
+                bit_string.extend(der_element(0x30, &rsa_public_key));
+
+                let mut spki = RSA_ALGORITHM_IDENTIFIER.to_vec();
+                spki.extend(der_element(0x03, &bit_string));
+                der_element(0x30, &spki)
+            }
+        }
+    }
+
+    /// The key as a PEM `PUBLIC KEY` block, as `openssl` and most verification
+    /// libraries load it
+    pub fn to_pem(&self) -> String {
+        let encoded = STANDARD.encode(self.to_spki_der());
+        let mut pem = String::from("-----BEGIN PUBLIC KEY-----\n");
+        // The base64 alphabet is ASCII, so every chunk is valid UTF-8
+        for line in encoded.as_bytes().chunks(64) {
+            pem.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+            pem.push('\n');
+        }
+        pem.push_str("-----END PUBLIC KEY-----\n");
+        pem
+    }
+}
+
+/// A DER element with the given tag around `contents`
+fn der_element(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+    let length = contents.len();
+    if length < 0x80 {
+        element.push(length as u8);
+    } else {
+        let length_bytes: Vec<u8> = length.to_be_bytes().into_iter().skip_while(|byte| *byte == 0).collect();
+        element.push(0x80 | length_bytes.len() as u8);
+        element.extend(length_bytes);
+    }
+    element.extend_from_slice(contents);
+    element
+}
+
+/// A DER INTEGER for the unsigned big-endian number `bytes`
+fn der_unsigned_integer(bytes: &[u8]) -> Vec<u8> {
+    let mut contents: Vec<u8> = bytes.iter().copied().skip_while(|byte| *byte == 0).collect();
+    // A leading 1 bit would make the number negative
+    if contents.first().map_or(true, |byte| byte & 0x80 != 0) {
+        contents.insert(0, 0x00);
+    }
+    der_element(0x02, &contents)
+}
+
+/// Extract the raw authenticator data from a CBOR attestation object
+///
+/// The attestation object is a CBOR map whose `authData` entry holds the
+/// authenticator data, next to the attestation format and statement.
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "android"))]
+pub(crate) fn authenticator_data_from_attestation(attestation_object: &[u8]) -> Result<Vec<u8>, WebAuthnError> {
+    let value: Value = ciborium::de::from_reader(attestation_object)
+        .map_err(|e| malformed(format!("Invalid attestation object: {}", e)))?;
+    value
+        .as_map()
+        .and_then(|entries| {
+            entries
+                .iter()
+                .find(|(key, _)| key.as_text() == Some("authData"))
+                .and_then(|(_, value)| value.as_bytes())
+        })
+        .cloned()
+        .ok_or_else(|| malformed("Attestation object has no authData"))
+}
@@ -0,0 +1,103 @@
+// src-tauri/src/webauthn_config.rs
+
+//! Plugin settings read from a file as the plugin starts.
+//!
+//! The plugin looks for `webauthn.toml` in the app's config directory. What
+//! the file sets replaces what the `Builder` was given, and keys it leaves out
+//! keep the builder's values. Every key is optional:
+//!
+//! ```toml
+//! rp_id = "edit.example.com"
+//! challenge_ttl_seconds = 120
+//! session_ttl_seconds = 3600
+//! max_credentials_per_site = 10
+//! lockout_threshold = 10
+//! pin_cache_ttl_seconds = 300
+//! no_sync = false
+//! rate_limit_max_attempts = 5
+//! rate_limit_window_seconds = 60
+//!
+//! [retry]
+//! max_attempts = 3
+//! initial_backoff_ms = 100
+//! backoff_multiplier = 2.0
+//!
+//! [log]
+//! challenge_level = "info"
+//! authentication_level = "info"
+//! registration_level = "info"
+//! error_level = "error"
+//! ```
+//!
+//! Having no file is the usual case. A file that cannot be read or parsed is
+//! logged and ignored, and so is a value the builder would not accept, so a
+//! typo cannot keep the app from starting.
+
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::log_event::WebAuthnLogConfig;
+use crate::platform_retry::RetryConfig;
+
+/// Name of the file in the app's config directory
+pub const CONFIG_FILE_NAME: &str = "webauthn.toml";
+
+/// Settings for the `webauthn` plugin, mirroring its `Builder`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebAuthnConfig {
+    /// RP ID of release builds; development builds keep using "localhost".
+    /// Only read in debug builds or with `SPARKTYPE_DEVELOPER_MODE=1`
+    pub rp_id: Option<String>,
+    /// How long an issued challenge stays valid
+    pub challenge_ttl_seconds: Option<u64>,
+    /// How long a site stays authenticated after `authenticate_passkey` succeeds
+    pub session_ttl_seconds: Option<u64>,
+    /// Most passkeys `register_passkey` lets a site have, 10 by default
+    pub max_credentials_per_site: Option<usize>,
+    /// Failed authentications in a row that lock a passkey, 10 by default
+    pub lockout_threshold: Option<u32>,
+    /// How long a security key PIN that worked is kept; 0 asks every time
+    pub pin_cache_ttl_seconds: Option<u64>,
+    /// Only register passkeys that stay on this device, false by default
+    pub no_sync: Option<bool>,
+    /// Authentication attempts a site gets per window
+    pub rate_limit_max_attempts: Option<usize>,
+    pub rate_limit_window_seconds: Option<u64>,
+    /// How `authenticate_passkey` retries platform failures that may pass;
+    /// keys the table leaves out keep their defaults
+    pub retry: Option<RetryConfig>,
+    /// Log levels of challenge, authentication and registration lines; keys
+    /// the table leaves out keep their defaults
+    pub log: Option<WebAuthnLogConfig>,
+}
+
+impl WebAuthnConfig {
+    /// Read the config file at `path`, or `None` when there is none or it
+    /// cannot be used
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                log::debug!("No WebAuthn config at {}, using the defaults", path.display());
+                return None;
+            }
+            Err(error) => {
+                log::warn!("Could not read the WebAuthn config at {}, using the defaults: {}", path.display(), error);
+                return None;
+            }
+        };
+        match toml::from_str(&contents) {
+            Ok(config) => {
+                log::info!("Loaded the WebAuthn config from {}", path.display());
+                Some(config)
+            }
+            Err(error) => {
+                log::warn!("Could not parse the WebAuthn config at {}, using the defaults: {}", path.display(), error);
+                None
+            }
+        }
+    }
+}
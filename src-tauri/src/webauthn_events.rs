@@ -0,0 +1,125 @@
+// src-tauri/src/webauthn_events.rs
+
+//! Progress events emitted while a passkey ceremony runs.
+//!
+//! The commands only answer once the ceremony is over, so the frontend listens
+//! for these to show what is happening in the meantime. The background
+//! credential refresh, re-enrollment checks, logouts, the clipboard guard,
+//! deep links, the clock check at startup, the app lock and emergency wipes
+//! report through the same channel. Each event goes out under its own `webauthn://` name, with a
+//! payload tagged by `type`.
+
+use serde::Serialize;
+use tauri::{Emitter, Runtime};
+
+use crate::emergency_wipe::WipeReport;
+use crate::webauthn::WebAuthnError;
+
+/// Which ceremony an event belongs to
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ceremony {
+    Registration,
+    Authentication,
+}
+
+/// How serious a reported problem is
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+/// A step in a passkey ceremony
+/// Matches the WebAuthnEvent union in webauthn.service.ts
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum WebAuthnEvent {
+    RegistrationStarted {
+        site_id: String,
+    },
+    /// The system passkey sheet or security key prompt is up, waiting for the user
+    BiometricPromptShown {
+        ceremony: Ceremony,
+    },
+    RegistrationCompleted {
+        site_id: String,
+        credential_id: Option<String>,
+        error: Option<WebAuthnError>,
+    },
+    AuthenticationStarted {
+        site_id: String,
+    },
+    AuthenticationCompleted {
+        site_id: String,
+        credential_id: Option<String>,
+        error: Option<WebAuthnError>,
+    },
+    /// The authenticator no longer holds any of the site's passkeys, as found by
+    /// the credential refresh at launch
+    CredentialStale {
+        site_id: String,
+    },
+    /// None of the site's stored passkeys are on the authenticator any more, as
+    /// found by `should_reenroll`; the next registration replaces them
+    ReenrollmentRequired {
+        site_id: String,
+    },
+    /// The site's session was ended by `logout_site` or `logout_all_sites`
+    SessionInvalidated {
+        site_id: String,
+    },
+    /// `check_clipboard_write` refused text containing a credential ID
+    ClipboardBlocked {
+        severity: Severity,
+    },
+    /// A deep link asked to sign in to the site with this passkey; the
+    /// authentication events follow
+    DeepLinkReceived {
+        site_id: String,
+        credential_id: String,
+    },
+    /// The system clock read a time before 2001 as the plugin started, so
+    /// passkey timestamps will be wrong; `webauthn_health_check` reports it too
+    ClockSkewDetected,
+    /// The user was idle past the app lock's timeout, so every session was
+    /// ended; the next authentication reopens them
+    AppLocked,
+    /// `emergency_wipe` deleted the passkeys and ended the sessions in `report`
+    EmergencyWipeCompleted {
+        severity: Severity,
+        report: WipeReport,
+    },
+}
+
+impl WebAuthnEvent {
+    /// Event name the payload is emitted under
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebAuthnEvent::RegistrationStarted { .. } => "webauthn://registration-started",
+            WebAuthnEvent::BiometricPromptShown { .. } => "webauthn://biometric-prompt-shown",
+            WebAuthnEvent::RegistrationCompleted { .. } => "webauthn://registration-completed",
+            WebAuthnEvent::AuthenticationStarted { .. } => "webauthn://authentication-started",
+            WebAuthnEvent::AuthenticationCompleted { .. } => "webauthn://authentication-completed",
+            WebAuthnEvent::CredentialStale { .. } => "webauthn://credential-stale",
+            WebAuthnEvent::ReenrollmentRequired { .. } => "webauthn://reenrollment-required",
+            WebAuthnEvent::SessionInvalidated { .. } => "webauthn://session-invalidated",
+            WebAuthnEvent::ClipboardBlocked { .. } => "webauthn://clipboard-blocked",
+            WebAuthnEvent::DeepLinkReceived { .. } => "webauthn://deep-link-received",
+            WebAuthnEvent::ClockSkewDetected => "webauthn://clock-skew-detected",
+            WebAuthnEvent::AppLocked => "webauthn://app-locked",
+            WebAuthnEvent::EmergencyWipeCompleted { .. } => "webauthn://emergency-wipe-completed",
+        }
+    }
+
+    /// Broadcast the event to every webview
+    ///
+    /// Progress is informational, so a failed emit is logged rather than
+    /// failing the ceremony.
+    pub fn emit<R: Runtime>(self, emitter: &impl Emitter<R>) {
+        if let Err(error) = emitter.emit(self.name(), &self) {
+            log::warn!("Failed to emit {}: {}", self.name(), error);
+        }
+    }
+}
@@ -0,0 +1,929 @@
+// src-tauri/src/webauthn_linux.rs
+
+//! Native passkey ceremonies with FIDO2 security keys, through libfido2.
+//!
+//! libfido2 is loaded at runtime rather than linked, so the app still starts on
+//! systems without it and simply reports passkeys as unavailable. The
+//! `fido_dev_*` calls block until the key is touched, so they run on a blocking
+//! worker rather than on the async runtime.
+//!
+//! User verification is "preferred": keys with built-in verification (e.g. a
+//! fingerprint reader) use it, and keys protected by a PIN get one through the
+//! PIN window (`security-key-pin.html`), which answers through
+//! `submit_security_key_pin`. The PIN therefore never travels in the ceremony
+//! payloads, and is wiped from memory once the ceremony is over or, if it got
+//! the ceremony through, once the `PinCache` lets go of it.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use libloading::{Library, Symbol};
+use tauri::{Manager, Runtime, WebviewUrl, WebviewWindow, WebviewWindowBuilder, WindowEvent};
+use tokio::sync::oneshot;
+use zeroize::Zeroizing;
+
+use crate::client_data::{app_origin, ClientDataJson};
+use crate::pin_cache::{PinCache, SecretPin};
+use crate::webauthn::{ResidentKeyRequirement, UserVerification, WebAuthnCapabilities, WebAuthnError};
+use crate::webauthn_state::WebAuthnState;
+
+/// Shared object names tried in order, the versioned one being what distributions ship
+const LIBRARY_NAMES: &[&str] = &["libfido2.so.1", "libfido2.so"];
+
+/// Upper bound on the number of HID devices enumerated
+const MAX_DEVICES: usize = 64;
+
+/// Label of the window that asks for the security key PIN
+pub(crate) const PIN_WINDOW_LABEL: &str = "security-key-pin";
+
+/// Page served from the frontend's public directory
+const PIN_WINDOW_PAGE: &str = "security-key-pin.html";
+
+/// CTAP2 PINs are 4 to 63 bytes of UTF-8
+const PIN_LENGTH: std::ops::RangeInclusive<usize> = 4..=63;
+
+const COSE_ES256: c_int = -7;
+
+// fido_opt_t
+const FIDO_OPT_OMIT: c_int = 0;
+const FIDO_OPT_TRUE: c_int = 2;
+
+// Status codes from <fido/err.h>
+const FIDO_OK: c_int = 0x00;
+const FIDO_ERR_CHANNEL_BUSY: c_int = 0x06;
+const FIDO_ERR_INVALID_CBOR: c_int = 0x12;
+const FIDO_ERR_CREDENTIAL_EXCLUDED: c_int = 0x19;
+const FIDO_ERR_INVALID_CREDENTIAL: c_int = 0x22;
+const FIDO_ERR_OPERATION_DENIED: c_int = 0x27;
+const FIDO_ERR_KEY_STORE_FULL: c_int = 0x28;
+const FIDO_ERR_UNSUPPORTED_OPTION: c_int = 0x2b;
+const FIDO_ERR_KEEPALIVE_CANCEL: c_int = 0x2d;
+const FIDO_ERR_NO_CREDENTIALS: c_int = 0x2e;
+const FIDO_ERR_USER_ACTION_TIMEOUT: c_int = 0x2f;
+const FIDO_ERR_NOT_ALLOWED: c_int = 0x30;
+const FIDO_ERR_PIN_INVALID: c_int = 0x31;
+const FIDO_ERR_PIN_BLOCKED: c_int = 0x32;
+const FIDO_ERR_PIN_AUTH_INVALID: c_int = 0x33;
+const FIDO_ERR_PIN_AUTH_BLOCKED: c_int = 0x34;
+const FIDO_ERR_PIN_REQUIRED: c_int = 0x36;
+const FIDO_ERR_ACTION_TIMEOUT: c_int = 0x3a;
+const FIDO_ERR_UV_BLOCKED: c_int = 0x3c;
+const FIDO_ERR_UV_INVALID: c_int = 0x3f;
+const FIDO_ERR_TX: c_int = -1;
+const FIDO_ERR_RX: c_int = -2;
+const FIDO_ERR_INTERNAL: c_int = -9;
+
+/// Raw output of a successful registration ceremony
+pub(crate) struct NativeRegistration {
+    pub credential_id: Vec<u8>,
+    pub authenticator_data: Vec<u8>,
+    pub is_resident: bool,
+}
+
+/// Raw output of a successful assertion ceremony
+pub(crate) struct NativeAssertion {
+    pub credential_id: Vec<u8>,
+    pub user_handle: Option<Vec<u8>>,
+    pub authenticator_data: Vec<u8>,
+}
+
+#[repr(C)]
+struct FidoDevInfo {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct FidoDev {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct FidoCred {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct FidoAssert {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct FidoCborInfo {
+    _private: [u8; 0],
+}
+
+/// The subset of the libfido2 API used here, resolved from the shared object
+struct Fido2 {
+    strerr: unsafe extern "C" fn(c_int) -> *const c_char,
+    dev_info_new: unsafe extern "C" fn(usize) -> *mut FidoDevInfo,
+    dev_info_free: unsafe extern "C" fn(*mut *mut FidoDevInfo, usize),
+    dev_info_manifest: unsafe extern "C" fn(*mut FidoDevInfo, usize, *mut usize) -> c_int,
+    dev_info_ptr: unsafe extern "C" fn(*const FidoDevInfo, usize) -> *const FidoDevInfo,
+    dev_info_path: unsafe extern "C" fn(*const FidoDevInfo) -> *const c_char,
+    dev_new: unsafe extern "C" fn() -> *mut FidoDev,
+    dev_free: unsafe extern "C" fn(*mut *mut FidoDev),
+    dev_open: unsafe extern "C" fn(*mut FidoDev, *const c_char) -> c_int,
+    dev_close: unsafe extern "C" fn(*mut FidoDev) -> c_int,
+    dev_has_pin: unsafe extern "C" fn(*const FidoDev) -> bool,
+    dev_has_uv: unsafe extern "C" fn(*const FidoDev) -> bool,
+    dev_is_fido2: unsafe extern "C" fn(*const FidoDev) -> bool,
+    dev_get_cbor_info: unsafe extern "C" fn(*mut FidoDev, *mut FidoCborInfo) -> c_int,
+    dev_make_cred: unsafe extern "C" fn(*mut FidoDev, *mut FidoCred, *const c_char) -> c_int,
+    dev_get_assert: unsafe extern "C" fn(*mut FidoDev, *mut FidoAssert, *const c_char) -> c_int,
+    cred_new: unsafe extern "C" fn() -> *mut FidoCred,
+    cred_free: unsafe extern "C" fn(*mut *mut FidoCred),
+    cred_set_type: unsafe extern "C" fn(*mut FidoCred, c_int) -> c_int,
+    cred_set_clientdata_hash: unsafe extern "C" fn(*mut FidoCred, *const u8, usize) -> c_int,
+    cred_set_rp: unsafe extern "C" fn(*mut FidoCred, *const c_char, *const c_char) -> c_int,
+    cred_set_user: unsafe extern "C" fn(
+        *mut FidoCred,
+        *const u8,
+        usize,
+        *const c_char,
+        *const c_char,
+        *const c_char,
+    ) -> c_int,
+    cred_set_rk: unsafe extern "C" fn(*mut FidoCred, c_int) -> c_int,
+    cred_set_uv: unsafe extern "C" fn(*mut FidoCred, c_int) -> c_int,
+    cred_id_ptr: unsafe extern "C" fn(*const FidoCred) -> *const u8,
+    cred_id_len: unsafe extern "C" fn(*const FidoCred) -> usize,
+    cred_authdata_raw_ptr: unsafe extern "C" fn(*const FidoCred) -> *const u8,
+    cred_authdata_raw_len: unsafe extern "C" fn(*const FidoCred) -> usize,
+    assert_new: unsafe extern "C" fn() -> *mut FidoAssert,
+    assert_free: unsafe extern "C" fn(*mut *mut FidoAssert),
+    assert_set_rp: unsafe extern "C" fn(*mut FidoAssert, *const c_char) -> c_int,
+    assert_set_clientdata_hash: unsafe extern "C" fn(*mut FidoAssert, *const u8, usize) -> c_int,
+    assert_allow_cred: unsafe extern "C" fn(*mut FidoAssert, *const u8, usize) -> c_int,
+    assert_set_uv: unsafe extern "C" fn(*mut FidoAssert, c_int) -> c_int,
+    assert_count: unsafe extern "C" fn(*const FidoAssert) -> usize,
+    assert_id_ptr: unsafe extern "C" fn(*const FidoAssert, usize) -> *const u8,
+    assert_id_len: unsafe extern "C" fn(*const FidoAssert, usize) -> usize,
+    assert_user_id_ptr: unsafe extern "C" fn(*const FidoAssert, usize) -> *const u8,
+    assert_user_id_len: unsafe extern "C" fn(*const FidoAssert, usize) -> usize,
+    assert_authdata_ptr: unsafe extern "C" fn(*const FidoAssert, usize) -> *const u8,
+    assert_authdata_len: unsafe extern "C" fn(*const FidoAssert, usize) -> usize,
+    cbor_info_new: unsafe extern "C" fn() -> *mut FidoCborInfo,
+    cbor_info_free: unsafe extern "C" fn(*mut *mut FidoCborInfo),
+    cbor_info_options_name_ptr: unsafe extern "C" fn(*const FidoCborInfo) -> *const *const c_char,
+    cbor_info_options_value_ptr: unsafe extern "C" fn(*const FidoCborInfo) -> *const bool,
+    cbor_info_options_len: unsafe extern "C" fn(*const FidoCborInfo) -> usize,
+    // Keeps the symbols above valid
+    _library: Library,
+}
+
+impl Fido2 {
+    fn load() -> Result<Self, libloading::Error> {
+        let mut library = Err(libloading::Error::DlOpenUnknown);
+        for name in LIBRARY_NAMES {
+            library = unsafe { Library::new(name) };
+            if library.is_ok() {
+                break;
+            }
+        }
+        let library = library?;
+
+        macro_rules! symbol {
+            ($name:literal) => {{
+                let symbol: Symbol<_> = unsafe { library.get(concat!($name, "\0").as_bytes())? };
+                *symbol
+            }};
+        }
+
+        let init: unsafe extern "C" fn(c_int) = symbol!("fido_init");
+        unsafe { init(0) };
+
+        Ok(Self {
+            strerr: symbol!("fido_strerr"),
+            dev_info_new: symbol!("fido_dev_info_new"),
+            dev_info_free: symbol!("fido_dev_info_free"),
+            dev_info_manifest: symbol!("fido_dev_info_manifest"),
+            dev_info_ptr: symbol!("fido_dev_info_ptr"),
+            dev_info_path: symbol!("fido_dev_info_path"),
+            dev_new: symbol!("fido_dev_new"),
+            dev_free: symbol!("fido_dev_free"),
+            dev_open: symbol!("fido_dev_open"),
+            dev_close: symbol!("fido_dev_close"),
+            dev_has_pin: symbol!("fido_dev_has_pin"),
+            dev_has_uv: symbol!("fido_dev_has_uv"),
+            dev_is_fido2: symbol!("fido_dev_is_fido2"),
+            dev_get_cbor_info: symbol!("fido_dev_get_cbor_info"),
+            dev_make_cred: symbol!("fido_dev_make_cred"),
+            dev_get_assert: symbol!("fido_dev_get_assert"),
+            cred_new: symbol!("fido_cred_new"),
+            cred_free: symbol!("fido_cred_free"),
+            cred_set_type: symbol!("fido_cred_set_type"),
+            cred_set_clientdata_hash: symbol!("fido_cred_set_clientdata_hash"),
+            cred_set_rp: symbol!("fido_cred_set_rp"),
+            cred_set_user: symbol!("fido_cred_set_user"),
+            cred_set_rk: symbol!("fido_cred_set_rk"),
+            cred_set_uv: symbol!("fido_cred_set_uv"),
+            cred_id_ptr: symbol!("fido_cred_id_ptr"),
+            cred_id_len: symbol!("fido_cred_id_len"),
+            cred_authdata_raw_ptr: symbol!("fido_cred_authdata_raw_ptr"),
+            cred_authdata_raw_len: symbol!("fido_cred_authdata_raw_len"),
+            assert_new: symbol!("fido_assert_new"),
+            assert_free: symbol!("fido_assert_free"),
+            assert_set_rp: symbol!("fido_assert_set_rp"),
+            assert_set_clientdata_hash: symbol!("fido_assert_set_clientdata_hash"),
+            assert_allow_cred: symbol!("fido_assert_allow_cred"),
+            assert_set_uv: symbol!("fido_assert_set_uv"),
+            assert_count: symbol!("fido_assert_count"),
+            assert_id_ptr: symbol!("fido_assert_id_ptr"),
+            assert_id_len: symbol!("fido_assert_id_len"),
+            assert_user_id_ptr: symbol!("fido_assert_user_id_ptr"),
+            assert_user_id_len: symbol!("fido_assert_user_id_len"),
+            assert_authdata_ptr: symbol!("fido_assert_authdata_ptr"),
+            assert_authdata_len: symbol!("fido_assert_authdata_len"),
+            cbor_info_new: symbol!("fido_cbor_info_new"),
+            cbor_info_free: symbol!("fido_cbor_info_free"),
+            cbor_info_options_name_ptr: symbol!("fido_cbor_info_options_name_ptr"),
+            cbor_info_options_value_ptr: symbol!("fido_cbor_info_options_value_ptr"),
+            cbor_info_options_len: symbol!("fido_cbor_info_options_len"),
+            _library: library,
+        })
+    }
+
+    /// Human-readable description of a libfido2 status code
+    fn describe(&self, status: c_int) -> String {
+        let message = unsafe { (self.strerr)(status) };
+        if message.is_null() {
+            return format!("error {}", status);
+        }
+        unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+    }
+}
+
+/// libfido2, loaded on first use; `None` when it is not installed
+fn library() -> Option<&'static Fido2> {
+    static FIDO2: OnceLock<Option<Fido2>> = OnceLock::new();
+    FIDO2
+        .get_or_init(|| {
+            Fido2::load()
+                .map_err(|error| log::info!("libfido2 is not available: {}", error))
+                .ok()
+        })
+        .as_ref()
+}
+
+/// A non-zero libfido2 status code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FidoError(c_int);
+
+fn check(status: c_int) -> Result<(), FidoError> {
+    if status == FIDO_OK {
+        Ok(())
+    } else {
+        Err(FidoError(status))
+    }
+}
+
+/// How the user is verified on a given key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verification {
+    /// The key verifies the user itself (fingerprint reader, on-device PIN pad)
+    BuiltIn,
+    /// The key has a client PIN set, entered through the PIN window
+    Pin,
+    /// Neither is available; a touch proves user presence only
+    PresenceOnly,
+}
+
+/// A NUL-terminated client PIN, wiped from memory on drop
+struct Pin(Zeroizing<Vec<u8>>);
+
+impl Pin {
+    fn new(pin: &SecretPin) -> Self {
+        let pin = pin.expose();
+        let mut bytes = Zeroizing::new(Vec::with_capacity(pin.len() + 1));
+        bytes.extend_from_slice(pin.as_bytes());
+        bytes.push(0);
+        Self(bytes)
+    }
+
+    fn as_ptr(&self) -> *const c_char {
+        self.0.as_ptr().cast()
+    }
+}
+
+/// An open FIDO2 device
+struct Device {
+    fido: &'static Fido2,
+    handle: *mut FidoDev,
+    path: CString,
+}
+
+// A libfido2 device may be used from any thread as long as it is not used from
+// two at once, which owning it guarantees
+unsafe impl Send for Device {}
+
+impl Device {
+    /// Open the first security key found
+    fn open_first(fido: &'static Fido2) -> Result<Self, WebAuthnError> {
+        let paths = device_paths(fido).map_err(|error| describe_error(fido, error))?;
+        let path = paths
+            .first()
+            .ok_or_else(|| WebAuthnError::InternalError("No FIDO2 security key is connected".to_string()))?;
+        Self::open(fido, path).map_err(|error| describe_error(fido, error))
+    }
+
+    fn open(fido: &'static Fido2, path: &CStr) -> Result<Self, FidoError> {
+        let handle = unsafe { (fido.dev_new)() };
+        if handle.is_null() {
+            return Err(FidoError(FIDO_ERR_INTERNAL));
+        }
+        let device = Self {
+            fido,
+            handle,
+            path: path.to_owned(),
+        };
+        check(unsafe { (fido.dev_open)(device.handle, path.as_ptr()) })?;
+        Ok(device)
+    }
+
+    /// Whether the key is a CTAP2 key advertising the `rk` option
+    fn supports_resident_keys(&self) -> bool {
+        let fido = self.fido;
+        if !unsafe { (fido.dev_is_fido2)(self.handle) } {
+            return false;
+        }
+
+        unsafe {
+            let mut info = (fido.cbor_info_new)();
+            if info.is_null() {
+                return false;
+            }
+            let supported = check((fido.dev_get_cbor_info)(self.handle, info)).is_ok() && {
+                let names = (fido.cbor_info_options_name_ptr)(info);
+                let values = (fido.cbor_info_options_value_ptr)(info);
+                !names.is_null()
+                    && !values.is_null()
+                    && (0..(fido.cbor_info_options_len)(info))
+                        .any(|index| CStr::from_ptr(*names.add(index)).to_bytes() == b"rk" && *values.add(index))
+            };
+            (fido.cbor_info_free)(&mut info);
+            supported
+        }
+    }
+
+    /// What the `PinCache` keeps the key's PIN under
+    ///
+    /// libfido2 reports no serial number, so this is the HID path, which holds
+    /// for as long as the key stays plugged in.
+    fn cache_key(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+
+    fn verification(&self) -> Verification {
+        if unsafe { (self.fido.dev_has_uv)(self.handle) } {
+            Verification::BuiltIn
+        } else if unsafe { (self.fido.dev_has_pin)(self.handle) } {
+            Verification::Pin
+        } else {
+            Verification::PresenceOnly
+        }
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        unsafe {
+            (self.fido.dev_close)(self.handle);
+            (self.fido.dev_free)(&mut self.handle);
+        }
+    }
+}
+
+/// A `fido_cred_t`, freed on drop
+struct Credential {
+    fido: &'static Fido2,
+    handle: *mut FidoCred,
+}
+
+impl Drop for Credential {
+    fn drop(&mut self) {
+        unsafe { (self.fido.cred_free)(&mut self.handle) };
+    }
+}
+
+/// A `fido_assert_t`, freed on drop
+struct Assertion {
+    fido: &'static Fido2,
+    handle: *mut FidoAssert,
+}
+
+impl Drop for Assertion {
+    fn drop(&mut self) {
+        unsafe { (self.fido.assert_free)(&mut self.handle) };
+    }
+}
+
+/// Paths of the FIDO2 HID devices currently connected
+fn device_paths(fido: &Fido2) -> Result<Vec<CString>, FidoError> {
+    unsafe {
+        let mut list = (fido.dev_info_new)(MAX_DEVICES);
+        if list.is_null() {
+            return Err(FidoError(FIDO_ERR_INTERNAL));
+        }
+
+        let mut found = 0;
+        let status = (fido.dev_info_manifest)(list, MAX_DEVICES, &mut found);
+        let paths = (0..found)
+            .filter_map(|index| {
+                let path = (fido.dev_info_path)((fido.dev_info_ptr)(list, index));
+                (!path.is_null()).then(|| CStr::from_ptr(path).to_owned())
+            })
+            .collect();
+        (fido.dev_info_free)(&mut list, MAX_DEVICES);
+
+        check(status)?;
+        Ok(paths)
+    }
+}
+
+/// Whether libfido2 is installed and at least one FIDO2 device is connected
+pub(crate) fn is_available() -> bool {
+    let Some(fido) = library() else {
+        return false;
+    };
+    match device_paths(fido) {
+        Ok(paths) => !paths.is_empty(),
+        Err(error) => {
+            log::warn!("Failed to enumerate FIDO2 devices: {}", fido.describe(error.0));
+            false
+        }
+    }
+}
+
+/// What the connected security keys support, from their CTAP2 info
+pub(crate) fn capabilities() -> WebAuthnCapabilities {
+    let mut capabilities = WebAuthnCapabilities::default();
+    let Some(fido) = library() else {
+        return capabilities;
+    };
+    let paths = match device_paths(fido) {
+        Ok(paths) => paths,
+        Err(error) => {
+            log::warn!("Failed to enumerate FIDO2 devices: {}", fido.describe(error.0));
+            return capabilities;
+        }
+    };
+
+    for path in paths {
+        let device = match Device::open(fido, &path) {
+            Ok(device) => device,
+            Err(error) => {
+                log::warn!("Failed to open FIDO2 device {:?}: {}", path, fido.describe(error.0));
+                continue;
+            }
+        };
+        capabilities.roaming_authenticator = true;
+        // Security keys keep their credentials on the device itself
+        capabilities.hardware_backed = Some(true);
+        capabilities.resident_key_support |= device.supports_resident_keys();
+        capabilities.user_verification_support |= device.verification() != Verification::PresenceOnly;
+    }
+    capabilities
+}
+
+/// Everything `fido_dev_make_cred` needs, owned so it can move to a blocking worker
+struct RegistrationRequest {
+    rp_id: CString,
+    rp_name: CString,
+    client_data_hash: [u8; 32],
+    user_id: Vec<u8>,
+    user_name: CString,
+    display_name: CString,
+    resident_key: ResidentKeyRequirement,
+}
+
+/// Everything `fido_dev_get_assert` needs, owned so it can move to a blocking worker
+struct AssertionRequest {
+    rp_id: CString,
+    client_data_hash: [u8; 32],
+    allowed_credentials: Vec<Vec<u8>>,
+}
+
+/// Create a new credential on the connected security key
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn register<R: Runtime>(
+    window: &WebviewWindow<R>,
+    rp_id: &str,
+    challenge: &[u8],
+    user_name: &str,
+    user_id: &[u8],
+    display_name: Option<&str>,
+    user_verification: UserVerification,
+    resident_key: ResidentKeyRequirement,
+    prompt_message: Option<&str>,
+) -> Result<NativeRegistration, WebAuthnError> {
+    let request = RegistrationRequest {
+        rp_id: c_string(rp_id)?,
+        rp_name: c_string("Sparktype")?,
+        client_data_hash: ClientDataJson::new_create(&URL_SAFE_NO_PAD.encode(challenge), &app_origin(rp_id)).hash(),
+        user_id: user_id.to_vec(),
+        user_name: c_string(user_name)?,
+        display_name: c_string(display_name.unwrap_or(user_name))?,
+        resident_key,
+    };
+
+    run_ceremony(window, user_verification, prompt_message, move |device, verification, pin| {
+        make_credential(device, &request, verification, pin)
+    })
+    .await
+}
+
+/// Get an assertion for one of the given credentials from the connected security key
+///
+/// libfido2 cannot be interrupted, so when the command stops waiting the call
+/// goes on until the key is touched or gives up by itself.
+pub(crate) async fn authenticate<R: Runtime>(
+    window: &WebviewWindow<R>,
+    rp_id: &str,
+    challenge: &[u8],
+    allowed_credentials: &[Vec<u8>],
+    user_verification: UserVerification,
+    prompt_message: Option<&str>,
+) -> Result<NativeAssertion, WebAuthnError> {
+    let request = AssertionRequest {
+        rp_id: c_string(rp_id)?,
+        client_data_hash: ClientDataJson::new_get(&URL_SAFE_NO_PAD.encode(challenge), &app_origin(rp_id)).hash(),
+        allowed_credentials: allowed_credentials.to_vec(),
+    };
+
+    run_ceremony(window, user_verification, prompt_message, move |device, verification, pin| {
+        get_assertion(device, &request, verification, pin)
+    })
+    .await
+}
+
+/// Open the security key and run `ceremony` on it with the requested user verification
+///
+/// Keys with a PIN set get the PIN up front, unless verification is discouraged. Keys with built-in verification that
+/// still insist on a PIN (for instance after too many failed fingerprint reads)
+/// report `FIDO_ERR_PIN_REQUIRED`, in which case the PIN is requested and the
+/// ceremony retried once. The PIN window is titled with `prompt_message`, if given.
+///
+/// A PIN the `PinCache` keeps for the key is supplied instead of asking. If the
+/// key rejects it, it is evicted and the ceremony retried once with a PIN from
+/// the window; a PIN from the window that gets the ceremony through is cached.
+async fn run_ceremony<R, T, F>(
+    window: &WebviewWindow<R>,
+    user_verification: UserVerification,
+    prompt_message: Option<&str>,
+    ceremony: F,
+) -> Result<T, WebAuthnError>
+where
+    R: Runtime,
+    T: Send + 'static,
+    F: Fn(&Device, Verification, Option<&Pin>) -> Result<T, FidoError> + Send + Sync + 'static,
+{
+    let fido = library().ok_or(WebAuthnError::PlatformNotSupported)?;
+    let ceremony = Arc::new(ceremony);
+
+    let device = run_blocking(move || Device::open_first(fido)).await??;
+    let verification = match (user_verification, device.verification()) {
+        (UserVerification::Required, Verification::PresenceOnly) => {
+            return Err(WebAuthnError::UserVerificationFailed);
+        }
+        (UserVerification::Discouraged, _) => Verification::PresenceOnly,
+        (_, verification) => verification,
+    };
+    let state = window.state::<WebAuthnState>();
+    let cache = &state.pin_cache;
+    let authenticator = device.cache_key();
+    let pin = match verification {
+        Verification::Pin => Some(known_pin(window, prompt_message, cache, &authenticator).await?),
+        _ => None,
+    };
+
+    let (device, result) = attempt(&ceremony, device, verification, pin.as_ref()).await?;
+    let (pin, result) = match result {
+        Err(FidoError(FIDO_ERR_PIN_INVALID | FIDO_ERR_PIN_AUTH_INVALID))
+            if pin.as_ref().is_some_and(|pin| pin.cached) =>
+        {
+            log::info!("Security key {} rejected its cached PIN", authenticator);
+            cache.evict(&authenticator);
+            let pin = SuppliedPin::entered(request_pin(window, prompt_message).await?);
+            let (_, result) = attempt(&ceremony, device, Verification::Pin, Some(&pin)).await?;
+            (Some(pin), result)
+        }
+        Err(FidoError(FIDO_ERR_PIN_REQUIRED)) if verification != Verification::Pin => {
+            let pin = known_pin(window, prompt_message, cache, &authenticator).await?;
+            let (_, result) = attempt(&ceremony, device, Verification::Pin, Some(&pin)).await?;
+            (Some(pin), result)
+        }
+        result => (pin, result),
+    };
+    if let Some(pin) = pin {
+        match &result {
+            Ok(_) if !pin.cached => cache.insert(&authenticator, pin.pin),
+            Err(FidoError(
+                FIDO_ERR_PIN_INVALID | FIDO_ERR_PIN_AUTH_INVALID | FIDO_ERR_PIN_BLOCKED | FIDO_ERR_PIN_AUTH_BLOCKED,
+            )) => cache.evict(&authenticator),
+            _ => {}
+        }
+    }
+    result.map_err(|error| describe_error(fido, error))
+}
+
+/// A PIN for a ceremony, and whether it came from the `PinCache`
+struct SuppliedPin {
+    pin: SecretPin,
+    cached: bool,
+}
+
+impl SuppliedPin {
+    fn entered(pin: SecretPin) -> Self {
+        Self { pin, cached: false }
+    }
+}
+
+/// The PIN cached for `authenticator`, or else one asked for in the PIN window
+async fn known_pin<R: Runtime>(
+    window: &WebviewWindow<R>,
+    prompt_message: Option<&str>,
+    cache: &PinCache,
+    authenticator: &str,
+) -> Result<SuppliedPin, WebAuthnError> {
+    if let Some(pin) = cache.get(authenticator) {
+        log::debug!("Using the cached PIN of security key {}", authenticator);
+        return Ok(SuppliedPin { pin, cached: true });
+    }
+    Ok(SuppliedPin::entered(request_pin(window, prompt_message).await?))
+}
+
+/// Run one ceremony attempt on a blocking worker, handing the device back for a retry
+async fn attempt<T, F>(
+    ceremony: &Arc<F>,
+    device: Device,
+    verification: Verification,
+    pin: Option<&SuppliedPin>,
+) -> Result<(Device, Result<T, FidoError>), WebAuthnError>
+where
+    T: Send + 'static,
+    F: Fn(&Device, Verification, Option<&Pin>) -> Result<T, FidoError> + Send + Sync + 'static,
+{
+    let ceremony = Arc::clone(ceremony);
+    let pin = pin.map(|pin| Pin::new(&pin.pin));
+    run_blocking(move || {
+        let result = ceremony(&device, verification, pin.as_ref());
+        (device, result)
+    })
+    .await
+}
+
+/// Run a blocking libfido2 call off the async runtime
+async fn run_blocking<T, F>(call: F) -> Result<T, WebAuthnError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(call)
+        .await
+        .map_err(|e| WebAuthnError::InternalError(format!("Security key worker failed: {}", e)))
+}
+
+fn make_credential(
+    device: &Device,
+    request: &RegistrationRequest,
+    verification: Verification,
+    pin: Option<&Pin>,
+) -> Result<NativeRegistration, FidoError> {
+    let fido = device.fido;
+    let credential = Credential {
+        fido,
+        handle: unsafe { (fido.cred_new)() },
+    };
+    if credential.handle.is_null() {
+        return Err(FidoError(FIDO_ERR_INTERNAL));
+    }
+
+    unsafe {
+        check((fido.cred_set_type)(credential.handle, COSE_ES256))?;
+        check((fido.cred_set_clientdata_hash)(
+            credential.handle,
+            request.client_data_hash.as_ptr(),
+            request.client_data_hash.len(),
+        ))?;
+        check((fido.cred_set_rp)(credential.handle, request.rp_id.as_ptr(), request.rp_name.as_ptr()))?;
+        check((fido.cred_set_user)(
+            credential.handle,
+            request.user_id.as_ptr(),
+            request.user_id.len(),
+            request.user_name.as_ptr(),
+            request.display_name.as_ptr(),
+            std::ptr::null(),
+        ))?;
+        // Only keys advertising `rk` are asked to spend one of their few slots on a preferred one
+        let is_resident = match request.resident_key {
+            ResidentKeyRequirement::Required => true,
+            ResidentKeyRequirement::Preferred => device.supports_resident_keys(),
+            ResidentKeyRequirement::Discouraged => false,
+        };
+        let rk = if is_resident { FIDO_OPT_TRUE } else { FIDO_OPT_OMIT };
+        check((fido.cred_set_rk)(credential.handle, rk))?;
+        check((fido.cred_set_uv)(credential.handle, uv_option(verification)))?;
+        check((fido.dev_make_cred)(device.handle, credential.handle, pin_ptr(pin)))?;
+
+        Ok(NativeRegistration {
+            credential_id: copy_bytes((fido.cred_id_ptr)(credential.handle), (fido.cred_id_len)(credential.handle)),
+            authenticator_data: copy_bytes(
+                (fido.cred_authdata_raw_ptr)(credential.handle),
+                (fido.cred_authdata_raw_len)(credential.handle),
+            ),
+            is_resident,
+        })
+    }
+}
+
+fn get_assertion(
+    device: &Device,
+    request: &AssertionRequest,
+    verification: Verification,
+    pin: Option<&Pin>,
+) -> Result<NativeAssertion, FidoError> {
+    let fido = device.fido;
+    let assertion = Assertion {
+        fido,
+        handle: unsafe { (fido.assert_new)() },
+    };
+    if assertion.handle.is_null() {
+        return Err(FidoError(FIDO_ERR_INTERNAL));
+    }
+
+    unsafe {
+        check((fido.assert_set_rp)(assertion.handle, request.rp_id.as_ptr()))?;
+        check((fido.assert_set_clientdata_hash)(
+            assertion.handle,
+            request.client_data_hash.as_ptr(),
+            request.client_data_hash.len(),
+        ))?;
+        for credential_id in &request.allowed_credentials {
+            check((fido.assert_allow_cred)(assertion.handle, credential_id.as_ptr(), credential_id.len()))?;
+        }
+        check((fido.assert_set_uv)(assertion.handle, uv_option(verification)))?;
+        check((fido.dev_get_assert)(device.handle, assertion.handle, pin_ptr(pin)))?;
+
+        if (fido.assert_count)(assertion.handle) == 0 {
+            return Err(FidoError(FIDO_ERR_NO_CREDENTIALS));
+        }
+        let mut credential_id = copy_bytes(
+            (fido.assert_id_ptr)(assertion.handle, 0),
+            (fido.assert_id_len)(assertion.handle, 0),
+        );
+        // CTAP2 lets the key omit the credential when the allow list has a single entry
+        if credential_id.is_empty() && request.allowed_credentials.len() == 1 {
+            credential_id = request.allowed_credentials[0].clone();
+        }
+
+        // Only returned for discoverable credentials
+        let user_handle = copy_bytes(
+            (fido.assert_user_id_ptr)(assertion.handle, 0),
+            (fido.assert_user_id_len)(assertion.handle, 0),
+        );
+
+        // libfido2 hands out the authenticator data wrapped in a CBOR byte string
+        let authenticator_data = copy_bytes(
+            (fido.assert_authdata_ptr)(assertion.handle, 0),
+            (fido.assert_authdata_len)(assertion.handle, 0),
+        );
+        let authenticator_data = match ciborium::de::from_reader(authenticator_data.as_slice()) {
+            Ok(ciborium::Value::Bytes(bytes)) => bytes,
+            _ => return Err(FidoError(FIDO_ERR_INVALID_CBOR)),
+        };
+
+        Ok(NativeAssertion {
+            credential_id,
+            user_handle: (!user_handle.is_empty()).then_some(user_handle),
+            authenticator_data,
+        })
+    }
+}
+
+/// `uv` option for a ceremony: only asked of keys that verify the user themselves,
+/// since a PIN already implies verification and other keys would reject it
+fn uv_option(verification: Verification) -> c_int {
+    match verification {
+        Verification::BuiltIn => FIDO_OPT_TRUE,
+        Verification::Pin | Verification::PresenceOnly => FIDO_OPT_OMIT,
+    }
+}
+
+fn pin_ptr(pin: Option<&Pin>) -> *const c_char {
+    pin.map_or(std::ptr::null(), Pin::as_ptr)
+}
+
+fn c_string(value: &str) -> Result<CString, WebAuthnError> {
+    CString::new(value).map_err(|_| WebAuthnError::InternalError(format!("Unexpected NUL byte in {:?}", value)))
+}
+
+unsafe fn copy_bytes(data: *const u8, len: usize) -> Vec<u8> {
+    if data.is_null() || len == 0 {
+        return Vec::new();
+    }
+    std::slice::from_raw_parts(data, len).to_vec()
+}
+
+fn describe_error(fido: &Fido2, error: FidoError) -> WebAuthnError {
+    match error.0 {
+        FIDO_ERR_OPERATION_DENIED | FIDO_ERR_KEEPALIVE_CANCEL | FIDO_ERR_NOT_ALLOWED => WebAuthnError::UserCancelled,
+        FIDO_ERR_USER_ACTION_TIMEOUT | FIDO_ERR_ACTION_TIMEOUT => WebAuthnError::Timeout,
+        FIDO_ERR_NO_CREDENTIALS | FIDO_ERR_INVALID_CREDENTIAL => {
+            WebAuthnError::CredentialNotFound("No registered passkey is on this security key".to_string())
+        }
+        FIDO_ERR_PIN_INVALID
+        | FIDO_ERR_PIN_BLOCKED
+        | FIDO_ERR_PIN_AUTH_INVALID
+        | FIDO_ERR_PIN_AUTH_BLOCKED
+        | FIDO_ERR_PIN_REQUIRED
+        | FIDO_ERR_UV_BLOCKED
+        | FIDO_ERR_UV_INVALID => WebAuthnError::BiometricFailed,
+        FIDO_ERR_KEY_STORE_FULL | FIDO_ERR_UNSUPPORTED_OPTION => WebAuthnError::ResidentKeyNotSupported,
+        // The key is talking to another client, or a packet got lost on the way
+        FIDO_ERR_CHANNEL_BUSY | FIDO_ERR_TX | FIDO_ERR_RX => {
+            WebAuthnError::PlatformUnavailable(format!("Security key error: {}", fido.describe(error.0)))
+        }
+        FIDO_ERR_CREDENTIAL_EXCLUDED => {
+            WebAuthnError::InternalError("A passkey for this site already exists on this security key".to_string())
+        }
+        status => WebAuthnError::InternalError(format!("Security key error: {}", fido.describe(status))),
+    }
+}
+
+type PinSender = oneshot::Sender<Option<Zeroizing<String>>>;
+
+/// The prompt currently waiting for a PIN; only one ceremony can ask at a time
+static PIN_REQUEST: Mutex<Option<PinSender>> = Mutex::new(None);
+
+fn take_pin_request() -> Option<PinSender> {
+    PIN_REQUEST.lock().ok()?.take()
+}
+
+/// Ask for the security key PIN in a dedicated window over `window`
+///
+/// Closing the window or pressing cancel fails the ceremony with `UserCancelled`.
+async fn request_pin<R: Runtime>(window: &WebviewWindow<R>, title: Option<&str>) -> Result<SecretPin, WebAuthnError> {
+    let (sender, receiver) = oneshot::channel();
+    {
+        let mut pending = PIN_REQUEST
+            .lock()
+            .map_err(|_| WebAuthnError::InternalError("PIN prompt state is poisoned".to_string()))?;
+        if pending.is_some() {
+            return Err(WebAuthnError::InternalError("A security key PIN prompt is already open".to_string()));
+        }
+        *pending = Some(sender);
+    }
+
+    let url = WebviewUrl::App(PIN_WINDOW_PAGE.into());
+    let prompt = WebviewWindowBuilder::new(window.app_handle(), PIN_WINDOW_LABEL, url)
+        .title(title.unwrap_or("Security key PIN"))
+        .inner_size(380.0, 220.0)
+        .resizable(false)
+        .minimizable(false)
+        .maximizable(false)
+        .always_on_top(true)
+        .center()
+        .parent(window)
+        .and_then(|builder| builder.build());
+    let prompt = match prompt {
+        Ok(prompt) => prompt,
+        Err(error) => {
+            take_pin_request();
+            return Err(WebAuthnError::InternalError(format!("Failed to open the PIN prompt: {}", error)));
+        }
+    };
+    prompt.on_window_event(|event| {
+        if let WindowEvent::Destroyed = event {
+            if let Some(sender) = take_pin_request() {
+                let _ = sender.send(None);
+            }
+        }
+    });
+
+    let pin = receiver
+        .await
+        .map_err(|_| WebAuthnError::InternalError("The PIN prompt went away".to_string()))?;
+    // Already gone if the user closed it
+    let _ = prompt.close();
+
+    let pin = pin.ok_or(WebAuthnError::UserCancelled)?;
+    if !PIN_LENGTH.contains(&pin.len()) || pin.contains('\0') {
+        return Err(WebAuthnError::BiometricFailed);
+    }
+    Ok(SecretPin::new(pin))
+}
+
+/// Hand the PIN entered in the PIN window (or `None` on cancel) to the waiting ceremony
+pub(crate) fn submit_pin<R: Runtime>(window: &WebviewWindow<R>, pin: Option<String>) -> Result<(), WebAuthnError> {
+    if window.label() != PIN_WINDOW_LABEL {
+        return Err(WebAuthnError::InternalError(
+            "Security key PINs can only be entered in the PIN prompt".to_string(),
+        ));
+    }
+    let sender = take_pin_request()
+        .ok_or_else(|| WebAuthnError::InternalError("No security key PIN was requested".to_string()))?;
+    // The receiver is gone if the ceremony was dropped; nothing to report to.
+    let _ = sender.send(pin.map(Zeroizing::new));
+    Ok(())
+}
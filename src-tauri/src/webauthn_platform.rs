@@ -0,0 +1,834 @@
+// src-tauri/src/webauthn_platform.rs
+
+//! The authenticator behind the passkey commands.
+//!
+//! Commands never call the OS passkey APIs directly; they go through the
+//! `WebAuthnPlatform` managed as Tauri state. `NativePlatform` runs the real
+//! ceremony for the target OS, while `MockPlatform` answers immediately with
+//! deterministic credentials, so the command layer can be exercised without a
+//! system sheet or any artificial delay.
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use tauri::{Runtime, WebviewWindow};
+
+use crate::webauthn::{
+    AuthenticatorAttachment, ResidentKeyRequirement, UserVerification, WebAuthnCapabilities, WebAuthnError,
+};
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "android"))]
+use crate::webauthn_cbor::authenticator_data_from_attestation;
+#[cfg(target_os = "android")]
+use crate::webauthn_android;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use crate::webauthn_apple::{self, CeremonyRequest, CeremonyResponse};
+#[cfg(target_os = "linux")]
+use crate::webauthn_linux;
+#[cfg(target_os = "windows")]
+use crate::webauthn_windows;
+
+/// A credential to create for a site
+#[derive(Debug, Clone)]
+pub struct RegistrationRequest {
+    pub rp_id: String,
+    pub challenge: Vec<u8>,
+    pub user_name: String,
+    pub user_id: Vec<u8>,
+    pub display_name: Option<String>,
+    pub user_verification: UserVerification,
+    /// Platform passkeys on Apple devices are always discoverable; only security
+    /// keys there honour it
+    pub resident_key: ResidentKeyRequirement,
+    /// Reason shown while the authenticator waits for the user
+    ///
+    /// Only the security key PIN window shows it. AuthenticationServices, Windows
+    /// Hello and Credential Manager word their sheets themselves from the RP ID
+    /// and app name, and take no reason string for passkey ceremonies.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub prompt_message: Option<String>,
+    /// Which kind of authenticator to offer; security keys are the only kind on Linux
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub authenticator_attachment: AuthenticatorAttachment,
+    /// The credential must stay on the authenticator; only Credential Manager
+    /// is asked for that, other platforms are held to it by the backup
+    /// eligible flag `register_passkey` checks
+    #[cfg_attr(not(target_os = "android"), allow(dead_code))]
+    pub device_bound: bool,
+}
+
+/// An assertion to get for one of a site's credentials
+#[derive(Debug, Clone)]
+pub struct AuthenticationRequest {
+    pub rp_id: String,
+    pub challenge: Vec<u8>,
+    pub allowed_credentials: Vec<Vec<u8>>,
+    pub user_verification: UserVerification,
+    /// See `RegistrationRequest::prompt_message`
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub prompt_message: Option<String>,
+    /// See `RegistrationRequest::authenticator_attachment`
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub authenticator_attachment: AuthenticatorAttachment,
+    /// PRF extension inputs; libfido2 is not asked for them, so Linux returns no output
+    pub prf: Option<PrfInput>,
+    /// largeBlob extension operation; likewise left out on Linux
+    pub large_blob: Option<LargeBlobOperation>,
+    /// How long the prompt may stay up; Windows and Android close theirs after
+    /// it, macOS and iOS stop watching for the delegate, and the command stops
+    /// waiting for every platform
+    #[cfg_attr(
+        not(any(target_os = "windows", target_os = "android", target_os = "macos", target_os = "ios")),
+        allow(dead_code)
+    )]
+    pub timeout: Duration,
+}
+
+/// What to do with the data the authenticator stores alongside the credential
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LargeBlobOperation {
+    Read,
+    /// Replace the stored data with these bytes
+    Write(Vec<u8>),
+}
+
+/// Decoded PRF extension inputs, as the platforms pass them on
+#[derive(Debug, Clone)]
+pub struct PrfInput {
+    /// Evaluated for a credential without an input of its own
+    pub eval: Vec<u8>,
+    /// (credential ID, input) pairs
+    pub eval_by_credential: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl PrfInput {
+    /// The input to evaluate for a credential
+    pub fn for_credential(&self, credential_id: &[u8]) -> &[u8] {
+        self.eval_by_credential
+            .iter()
+            .find(|(id, _)| id == credential_id)
+            .map_or(&self.eval, |(_, input)| input)
+    }
+}
+
+/// A credential to remove from the authenticator that holds it
+///
+/// Only the AuthenticationServices identity store needs every field; other
+/// platforms delete by credential ID or keep nothing to delete.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(any(target_os = "macos", target_os = "ios")), allow(dead_code))]
+pub struct DeletionRequest {
+    pub rp_id: String,
+    pub credential_id: Vec<u8>,
+    pub user_id: Vec<u8>,
+}
+
+/// A newly created credential
+#[derive(Debug, Clone)]
+pub struct PlatformRegistration {
+    pub credential_id: Vec<u8>,
+    /// Raw authenticator data, including the attested credential
+    pub authenticator_data: Vec<u8>,
+    /// `platform` or `cross-platform`, as in `PublicKeyCredential.authenticatorAttachment`
+    pub authenticator_attachment: String,
+    /// Whether the authenticator stored the credential as discoverable
+    pub is_resident: bool,
+}
+
+/// The credential an assertion was made with
+#[derive(Debug, Clone)]
+pub struct PlatformAssertion {
+    pub credential_id: Vec<u8>,
+    /// The user handle stored with the credential, if the authenticator returned one
+    pub user_handle: Option<Vec<u8>>,
+    /// Raw authenticator data the assertion signature covers
+    pub authenticator_data: Vec<u8>,
+    /// `platform` or `cross-platform`, as in `PublicKeyCredential.authenticatorAttachment`
+    pub authenticator_attachment: String,
+    /// PRF extension output for `first`, when inputs were given and the
+    /// authenticator evaluated them; never to be logged
+    pub prf_output: Option<Vec<u8>>,
+    /// Data the authenticator stores with the credential, for a largeBlob read
+    pub large_blob_data: Option<Vec<u8>>,
+}
+
+/// Something that can run passkey ceremonies over a window
+#[async_trait]
+pub trait WebAuthnPlatform<R: Runtime>: Send + Sync {
+    /// Whether ceremonies can currently be run
+    fn is_available(&self) -> bool;
+
+    /// Which kinds of authenticators ceremonies can use
+    fn capabilities(&self) -> WebAuthnCapabilities;
+
+    async fn register(
+        &self,
+        window: &WebviewWindow<R>,
+        request: RegistrationRequest,
+    ) -> Result<PlatformRegistration, WebAuthnError>;
+
+    async fn authenticate(
+        &self,
+        window: &WebviewWindow<R>,
+        request: AuthenticationRequest,
+    ) -> Result<PlatformAssertion, WebAuthnError>;
+
+    /// Remove a credential from the authenticator
+    ///
+    /// Succeeds when the authenticator no longer holds the credential, including
+    /// when it never kept any state for it.
+    async fn delete(&self, request: DeletionRequest) -> Result<(), WebAuthnError>;
+
+    /// Whether a ceremony can verify the user right now
+    ///
+    /// Defaults to the capabilities' user verification support.
+    fn can_verify_user(&self) -> bool {
+        self.is_available() && self.capabilities().user_verification_support
+    }
+
+    /// Whether the authenticators of `authenticator_attachment` can create a
+    /// credential that never leaves them
+    ///
+    /// Defaults to true, leaving it to the backup eligible flag of the new
+    /// credential.
+    fn creates_device_bound(&self, authenticator_attachment: AuthenticatorAttachment) -> bool {
+        let _ = authenticator_attachment;
+        true
+    }
+
+    /// Whether the authenticator still holds a credential, without prompting
+    ///
+    /// `None` when the platform has no way to tell.
+    async fn holds_credential(&self, rp_id: &str, credential_id: &[u8]) -> Option<bool>;
+
+    /// Dismiss the ceremonies still waiting on the user, as the app exits;
+    /// called on the main thread
+    ///
+    /// Defaults to doing nothing, for platforms whose ceremonies end with the
+    /// futures awaiting them.
+    fn cancel_ceremonies(&self) {}
+}
+
+/// The `WebAuthnPlatform` managed as Tauri state
+pub struct Platform<R: Runtime>(Box<dyn WebAuthnPlatform<R>>);
+
+impl<R: Runtime> Platform<R> {
+    pub fn new(platform: impl WebAuthnPlatform<R> + 'static) -> Self {
+        Self(Box::new(platform))
+    }
+
+    /// The platform the app runs with
+    ///
+    /// The Apple and Android platform authenticators only accept RP IDs associated
+    /// with the app, never "localhost", so development builds there simulate
+    /// ceremonies instead of presenting the system sheet. So do builds of any
+    /// profile run in test mode.
+    pub fn for_build() -> Self {
+        if test_mode_enabled() {
+            log::warn!("Test mode: passkey ceremonies are simulated");
+            Self::new(MockPlatform::default())
+        } else if cfg!(all(debug_assertions, any(target_os = "macos", target_os = "ios", target_os = "android"))) {
+            log::warn!("Development mode: passkey ceremonies are simulated");
+            Self::new(MockPlatform::default())
+        } else {
+            Self::new(NativePlatform)
+        }
+    }
+}
+
+/// Environment variable that simulates ceremonies in builds with the `test-mode`
+/// feature, so CI can run release builds without an authenticator
+#[cfg(feature = "test-mode")]
+const TEST_MODE_ENV: &str = "SPARKTYPE_WEBAUTHN_TEST_MODE";
+
+/// Whether `TEST_MODE_ENV` is set to "1"; always false without the `test-mode`
+/// feature, which leaves the variable out of the binary
+fn test_mode_enabled() -> bool {
+    #[cfg(feature = "test-mode")]
+    {
+        std::env::var(TEST_MODE_ENV).is_ok_and(|value| value == "1")
+    }
+    #[cfg(not(feature = "test-mode"))]
+    {
+        false
+    }
+}
+
+impl<R: Runtime> Deref for Platform<R> {
+    type Target = dyn WebAuthnPlatform<R>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+/// The OS passkey APIs for the target platform
+pub struct NativePlatform;
+
+#[async_trait]
+impl<R: Runtime> WebAuthnPlatform<R> for NativePlatform {
+    fn is_available(&self) -> bool {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            webauthn_apple::is_available() // The passkey providers ship with macOS 12+ and iOS 15+
+        }
+        #[cfg(target_os = "windows")]
+        {
+            webauthn_windows::is_available() // webauthn.dll API v1 ships with Windows 10 1903+
+        }
+        #[cfg(target_os = "android")]
+        {
+            webauthn_android::is_available() // Credential Manager via Play Services on Android 9+
+        }
+        #[cfg(target_os = "linux")]
+        {
+            webauthn_linux::is_available() // A FIDO2 security key is plugged in and libfido2 is installed
+        }
+        #[cfg(not(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "windows",
+            target_os = "android",
+            target_os = "linux"
+        )))]
+        {
+            false
+        }
+    }
+
+    fn capabilities(&self) -> WebAuthnCapabilities {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            webauthn_apple::capabilities()
+        }
+        #[cfg(target_os = "windows")]
+        {
+            webauthn_windows::capabilities()
+        }
+        #[cfg(target_os = "android")]
+        {
+            webauthn_android::capabilities()
+        }
+        #[cfg(target_os = "linux")]
+        {
+            webauthn_linux::capabilities()
+        }
+        #[cfg(not(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "windows",
+            target_os = "android",
+            target_os = "linux"
+        )))]
+        {
+            WebAuthnCapabilities::default()
+        }
+    }
+
+    async fn register(
+        &self,
+        window: &WebviewWindow<R>,
+        request: RegistrationRequest,
+    ) -> Result<PlatformRegistration, WebAuthnError> {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            let request = CeremonyRequest::Register {
+                rp_id: request.rp_id,
+                challenge: request.challenge,
+                user_name: request.user_name,
+                user_id: request.user_id,
+                display_name: request.display_name,
+                user_verification: request.user_verification,
+                resident_key: request.resident_key,
+                authenticator_attachment: request.authenticator_attachment,
+            };
+            let registration = match webauthn_apple::perform_ceremony(window, request).await? {
+                CeremonyResponse::Registration(registration) => registration,
+                CeremonyResponse::Assertion(_) => {
+                    return Err(WebAuthnError::InternalError(
+                        "Platform returned an assertion for a registration request".to_string(),
+                    ))
+                }
+            };
+
+            let attestation_object = registration.attestation_object.ok_or_else(|| {
+                WebAuthnError::InternalError("Platform did not return an attestation object".to_string())
+            })?;
+            Ok(PlatformRegistration {
+                credential_id: registration.credential_id,
+                authenticator_data: authenticator_data_from_attestation(&attestation_object)?,
+                authenticator_attachment: registration.authenticator_attachment,
+                is_resident: registration.is_resident,
+            })
+        }
+        #[cfg(target_os = "windows")]
+        {
+            let registration = webauthn_windows::register(
+                window,
+                &request.rp_id,
+                &request.challenge,
+                &request.user_name,
+                &request.user_id,
+                request.display_name.as_deref(),
+                request.user_verification,
+                request.resident_key,
+                request.authenticator_attachment,
+            )
+            .await?;
+            Ok(PlatformRegistration {
+                credential_id: registration.credential_id,
+                authenticator_data: registration.authenticator_data,
+                authenticator_attachment: registration.authenticator_attachment,
+                is_resident: registration.is_resident,
+            })
+        }
+        #[cfg(target_os = "linux")]
+        {
+            let registration = webauthn_linux::register(
+                window,
+                &request.rp_id,
+                &request.challenge,
+                &request.user_name,
+                &request.user_id,
+                request.display_name.as_deref(),
+                request.user_verification,
+                request.resident_key,
+                request.prompt_message.as_deref(),
+            )
+            .await?;
+            Ok(PlatformRegistration {
+                credential_id: registration.credential_id,
+                authenticator_data: registration.authenticator_data,
+                authenticator_attachment: "cross-platform".to_string(),
+                is_resident: registration.is_resident,
+            })
+        }
+        // Credential Manager presents over the current activity rather than a given window
+        #[cfg(target_os = "android")]
+        {
+            let _ = window;
+            let registration = webauthn_android::register(
+                &request.rp_id,
+                &request.challenge,
+                &request.user_name,
+                &request.user_id,
+                request.display_name.as_deref(),
+                request.user_verification,
+                request.resident_key,
+                request.authenticator_attachment,
+                request.device_bound,
+            )
+            .await?;
+            Ok(PlatformRegistration {
+                authenticator_data: authenticator_data_from_attestation(&registration.attestation_object)?,
+                credential_id: registration.credential_id,
+                authenticator_attachment: registration.authenticator_attachment,
+                is_resident: registration.is_resident,
+            })
+        }
+        #[cfg(not(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "windows",
+            target_os = "android",
+            target_os = "linux"
+        )))]
+        {
+            let _ = (window, request);
+            Err(WebAuthnError::PlatformNotSupported)
+        }
+    }
+
+    async fn authenticate(
+        &self,
+        window: &WebviewWindow<R>,
+        request: AuthenticationRequest,
+    ) -> Result<PlatformAssertion, WebAuthnError> {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            let request = CeremonyRequest::Authenticate {
+                rp_id: request.rp_id,
+                challenge: request.challenge,
+                allowed_credentials: request.allowed_credentials,
+                user_verification: request.user_verification,
+                authenticator_attachment: request.authenticator_attachment,
+                prf: request.prf,
+                large_blob: request.large_blob,
+                timeout: request.timeout,
+            };
+            match webauthn_apple::perform_ceremony(window, request).await? {
+                CeremonyResponse::Assertion(assertion) => Ok(PlatformAssertion {
+                    credential_id: assertion.credential_id,
+                    user_handle: assertion.user_handle,
+                    authenticator_data: assertion.authenticator_data,
+                    authenticator_attachment: assertion.authenticator_attachment,
+                    prf_output: assertion.prf_output,
+                    large_blob_data: assertion.large_blob_data,
+                }),
+                CeremonyResponse::Registration(_) => Err(WebAuthnError::InternalError(
+                    "Platform returned a registration for an authentication request".to_string(),
+                )),
+            }
+        }
+        #[cfg(target_os = "windows")]
+        {
+            let assertion = webauthn_windows::authenticate(
+                window,
+                &request.rp_id,
+                &request.challenge,
+                &request.allowed_credentials,
+                request.user_verification,
+                request.authenticator_attachment,
+                request.prf.as_ref(),
+                request.large_blob.as_ref(),
+                request.timeout,
+            )
+            .await?;
+            Ok(PlatformAssertion {
+                credential_id: assertion.credential_id,
+                user_handle: assertion.user_handle,
+                authenticator_data: assertion.authenticator_data,
+                authenticator_attachment: assertion.authenticator_attachment,
+                prf_output: assertion.prf_output,
+                large_blob_data: assertion.large_blob_data,
+            })
+        }
+        #[cfg(target_os = "linux")]
+        {
+            let assertion = webauthn_linux::authenticate(
+                window,
+                &request.rp_id,
+                &request.challenge,
+                &request.allowed_credentials,
+                request.user_verification,
+                request.prompt_message.as_deref(),
+            )
+            .await?;
+            Ok(PlatformAssertion {
+                credential_id: assertion.credential_id,
+                user_handle: assertion.user_handle,
+                authenticator_data: assertion.authenticator_data,
+                authenticator_attachment: "cross-platform".to_string(),
+                prf_output: None,
+                large_blob_data: None,
+            })
+        }
+        #[cfg(target_os = "android")]
+        {
+            let _ = window;
+            let assertion = webauthn_android::authenticate(
+                &request.rp_id,
+                &request.challenge,
+                &request.allowed_credentials,
+                request.user_verification,
+                request.authenticator_attachment,
+                request.prf.as_ref(),
+                request.large_blob.as_ref(),
+                request.timeout,
+            )
+            .await?;
+            Ok(PlatformAssertion {
+                credential_id: assertion.credential_id,
+                user_handle: assertion.user_handle,
+                authenticator_data: assertion.authenticator_data,
+                authenticator_attachment: assertion.authenticator_attachment,
+                prf_output: assertion.prf_output,
+                large_blob_data: assertion.large_blob_data,
+            })
+        }
+        #[cfg(not(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "windows",
+            target_os = "android",
+            target_os = "linux"
+        )))]
+        {
+            let _ = (window, request);
+            Err(WebAuthnError::PlatformNotSupported)
+        }
+    }
+
+    async fn delete(&self, request: DeletionRequest) -> Result<(), WebAuthnError> {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            webauthn_apple::remove_credential_identity(&request.rp_id, &request.credential_id, &request.user_id).await
+        }
+        #[cfg(target_os = "windows")]
+        {
+            webauthn_windows::delete_credential(&request.credential_id)
+        }
+        // Credential Manager offers no way to delete a passkey; the user removes it
+        // from their password manager
+        #[cfg(target_os = "android")]
+        {
+            let _ = request;
+            Ok(())
+        }
+        // Discoverable security key credentials stay on the key until removed with
+        // the key's own management tool, which needs its PIN
+        #[cfg(target_os = "linux")]
+        {
+            let _ = request;
+            Ok(())
+        }
+        #[cfg(not(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "windows",
+            target_os = "android",
+            target_os = "linux"
+        )))]
+        {
+            let _ = request;
+            Err(WebAuthnError::PlatformNotSupported)
+        }
+    }
+
+    /// On macOS and iOS the user must have enrolled biometrics
+    fn can_verify_user(&self) -> bool {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            webauthn_apple::biometrics_enrolled()
+        }
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+        {
+            <Self as WebAuthnPlatform<R>>::is_available(self)
+                && <Self as WebAuthnPlatform<R>>::capabilities(self).user_verification_support
+        }
+    }
+
+    /// iCloud Keychain syncs every platform passkey on macOS and iOS
+    fn creates_device_bound(&self, authenticator_attachment: AuthenticatorAttachment) -> bool {
+        let apple = cfg!(any(target_os = "macos", target_os = "ios"));
+        !(apple && authenticator_attachment == AuthenticatorAttachment::Platform)
+    }
+
+    async fn holds_credential(&self, rp_id: &str, credential_id: &[u8]) -> Option<bool> {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            webauthn_apple::credential_identity_exists(rp_id, credential_id).await
+        }
+        // Windows, Credential Manager and security keys only reveal credentials
+        // during a ceremony
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+        {
+            let _ = (rp_id, credential_id);
+            None
+        }
+    }
+
+    /// Only AuthenticationServices calls a delegate back once its sheet is gone
+    fn cancel_ceremonies(&self) {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        webauthn_apple::cancel_all_ceremonies();
+    }
+}
+
+/// A stand-in authenticator that answers immediately, for development builds and tests
+///
+/// Registration derives the credential ID from the challenge and reports it in
+/// well-formed authenticator data, and authentication asserts with the first
+/// allowed credential. Discovery asserts with the credential registered last,
+/// shared between clones, and finds none before the first registration.
+/// PRF inputs are answered with SHA-256 of the credential ID and the input.
+/// largeBlob writes are kept in memory, shared between clones, and read back.
+/// Ceremonies report a security key when only cross-platform authenticators are
+/// asked for, the platform authenticator otherwise. Credentials are device-bound
+/// unless `synced` is set, and reported held unless `lost` is.
+///
+/// Configure the responses fluently: `MockPlatform::new().fail_with(WebAuthnError::UserCancelled)`.
+#[derive(Debug, Clone, Default)]
+pub struct MockPlatform {
+    /// When set, every ceremony fails with this error
+    pub failure: Option<WebAuthnError>,
+    /// User handle returned with every assertion, as a discoverable credential would
+    pub user_handle: Option<Vec<u8>>,
+    /// largeBlob data written so far, keyed by credential ID
+    pub large_blobs: Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>>,
+    /// New credentials are reported as backup eligible and backed up
+    pub synced: bool,
+    /// Assertions still to fail with `PlatformUnavailable`, shared between clones
+    pub transient_failures: Arc<AtomicUsize>,
+    /// The credential registered last, which discovery asserts with
+    pub discoverable: Arc<Mutex<Option<Vec<u8>>>>,
+    /// Stored credentials are reported gone, as after a device wipe
+    pub lost: bool,
+}
+
+impl MockPlatform {
+    /// A mock whose ceremonies all succeed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail every ceremony with `error`
+    pub fn fail_with(mut self, error: WebAuthnError) -> Self {
+        self.failure = Some(error);
+        self
+    }
+
+    /// Return `user_handle` with every assertion
+    pub fn user_handle(mut self, user_handle: impl Into<Vec<u8>>) -> Self {
+        self.user_handle = Some(user_handle.into());
+        self
+    }
+
+    /// Report new credentials as synced, as a passkey provider that syncs would
+    pub fn synced(mut self) -> Self {
+        self.synced = true;
+        self
+    }
+
+    /// Report every stored credential gone from the authenticator
+    pub fn lost_credentials(mut self) -> Self {
+        self.lost = true;
+        self
+    }
+
+    /// Fail the next `times` assertions with `PlatformUnavailable`
+    pub fn fail_transiently(self, times: usize) -> Self {
+        self.transient_failures.store(times, Ordering::SeqCst);
+        self
+    }
+}
+
+#[async_trait]
+impl<R: Runtime> WebAuthnPlatform<R> for MockPlatform {
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> WebAuthnCapabilities {
+        WebAuthnCapabilities {
+            platform_authenticator: true,
+            roaming_authenticator: true,
+            resident_key_support: true,
+            user_verification_support: true,
+            hardware_backed: Some(true),
+            min_os_version_required: None,
+        }
+    }
+
+    async fn register(
+        &self,
+        _window: &WebviewWindow<R>,
+        request: RegistrationRequest,
+    ) -> Result<PlatformRegistration, WebAuthnError> {
+        if let Some(error) = &self.failure {
+            return Err(error.clone());
+        }
+
+        let credential_id = [b"mock-credential-".as_slice(), &request.challenge].concat();
+        if let Ok(mut discoverable) = self.discoverable.lock() {
+            *discoverable = Some(credential_id.clone());
+        }
+        Ok(PlatformRegistration {
+            authenticator_data: mock_authenticator_data(&request.rp_id, Some(&credential_id), self.synced),
+            credential_id,
+            authenticator_attachment: mock_attachment(request.authenticator_attachment),
+            is_resident: true,
+        })
+    }
+
+    async fn authenticate(
+        &self,
+        _window: &WebviewWindow<R>,
+        request: AuthenticationRequest,
+    ) -> Result<PlatformAssertion, WebAuthnError> {
+        if let Some(error) = &self.failure {
+            return Err(error.clone());
+        }
+        let transient = self
+            .transient_failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1));
+        if transient.is_ok() {
+            return Err(WebAuthnError::PlatformUnavailable("Mock platform is busy".to_string()));
+        }
+
+        let discovered = || self.discoverable.lock().ok().and_then(|discoverable| discoverable.clone());
+        let credential_id = match request.allowed_credentials.into_iter().next() {
+            Some(credential_id) => credential_id,
+            None => discovered().ok_or_else(|| WebAuthnError::CredentialNotFound(request.rp_id.clone()))?,
+        };
+        let large_blob_data = match request.large_blob {
+            Some(operation) => {
+                let mut large_blobs = self
+                    .large_blobs
+                    .lock()
+                    .map_err(|_| WebAuthnError::InternalError("Mock largeBlob lock is poisoned".to_string()))?;
+                match operation {
+                    LargeBlobOperation::Read => large_blobs.get(&credential_id).cloned(),
+                    LargeBlobOperation::Write(data) => {
+                        large_blobs.insert(credential_id.clone(), data);
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        Ok(PlatformAssertion {
+            prf_output: request
+                .prf
+                .map(|prf| Sha256::new().chain_update(&credential_id).chain_update(prf.for_credential(&credential_id)))
+                .map(|hasher| hasher.finalize().to_vec()),
+            large_blob_data,
+            credential_id,
+            user_handle: self.user_handle.clone(),
+            authenticator_data: mock_authenticator_data(&request.rp_id, None, self.synced),
+            authenticator_attachment: mock_attachment(request.authenticator_attachment),
+        })
+    }
+
+    async fn delete(&self, _request: DeletionRequest) -> Result<(), WebAuthnError> {
+        match &self.failure {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    async fn holds_credential(&self, _rp_id: &str, _credential_id: &[u8]) -> Option<bool> {
+        self.lost.then_some(false)
+    }
+}
+
+fn mock_attachment(requested: AuthenticatorAttachment) -> String {
+    match requested {
+        AuthenticatorAttachment::CrossPlatform => "cross-platform",
+        AuthenticatorAttachment::Platform | AuthenticatorAttachment::Any => "platform",
+    }
+    .to_string()
+}
+
+/// Mock authenticator data: user present and verified, backup eligible and
+/// backed up when `synced`, no signature counter and, for a new credential, a
+/// zero AAGUID and a P-256 COSE key with placeholder coordinates
+fn mock_authenticator_data(rp_id: &str, new_credential_id: Option<&[u8]>, synced: bool) -> Vec<u8> {
+    let mut auth_data = Sha256::digest(rp_id.as_bytes()).to_vec();
+    let flags = if new_credential_id.is_some() { 0x45 } else { 0x05 }; // UP | UV (| AT)
+    auth_data.push(if synced { flags | 0x18 } else { flags }); // (| BE | BS)
+    auth_data.extend_from_slice(&0u32.to_be_bytes());
+
+    if let Some(credential_id) = new_credential_id {
+        let cose_key = ciborium::Value::Map(vec![
+            (1.into(), 2.into()),    // kty: EC2
+            (3.into(), (-7).into()), // alg: ES256
+            ((-1).into(), 1.into()), // crv: P-256
+            ((-2).into(), ciborium::Value::Bytes(vec![0x01; 32])),
+            ((-3).into(), ciborium::Value::Bytes(vec![0x02; 32])),
+        ]);
+        let mut public_key = Vec::new();
+        ciborium::ser::into_writer(&cose_key, &mut public_key).expect("serializing to a Vec cannot fail");
+
+        auth_data.extend_from_slice(&[0u8; 16]);
+        auth_data.extend_from_slice(&(credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(credential_id);
+        auth_data.extend_from_slice(&public_key);
+    }
+    auth_data
+}
@@ -0,0 +1,72 @@
+// src-tauri/src/webauthn_state.rs
+
+//! The state shared by the WebAuthn commands.
+//!
+//! The webauthn plugin's `setup` manages a single `WebAuthnState`, built from
+//! its `Builder`, and the commands reach every piece through it, either as a
+//! `State<'_, WebAuthnState>` parameter or with `app.state::<WebAuthnState>()`.
+//! Each piece does its own locking, so commands only contend on the pieces they
+//! use. Two pieces are managed on their own: the `Platform`, which is generic
+//! over the runtime, and the `CredentialStore`, which the app creates once it
+//! knows its data directory.
+
+use std::sync::atomic::AtomicU64;
+
+use crate::app_lock::AppLock;
+use crate::audit_log::AuditLog;
+use crate::auth_notifications::AuthNotificationConfig;
+use crate::capability_filter::WebAuthnCapabilityFilter;
+use crate::challenge_store::{ChallengeStore, UsedChallengeLog};
+use crate::clipboard_guard::ClipboardGuard;
+use crate::credential_sync::SyncRequests;
+use crate::hybrid_auth::HybridAuthSessions;
+use crate::in_flight::InFlightRegistry;
+use crate::metrics::WebAuthnMetrics;
+use crate::onboarding::Onboarding;
+use crate::pin_cache::PinCache;
+use crate::platform_retry::RetryConfig;
+use crate::rate_limiter::RateLimiter;
+use crate::session_store::SessionStore;
+use crate::webauthn::RelyingParty;
+
+/// Everything the WebAuthn subsystem keeps between commands, managed as Tauri state
+pub struct WebAuthnState {
+    pub relying_party: RelyingParty,
+    pub challenges: ChallengeStore,
+    /// Challenges that completed an authentication, to detect replays
+    pub used_challenges: UsedChallengeLog,
+    pub rate_limiter: RateLimiter,
+    pub sessions: SessionStore,
+    /// Seconds after a ceremony during which `authenticate_passkey` does not prompt
+    pub grace_period_seconds: AtomicU64,
+    /// Registrations in progress and sites due to be re-enrolled
+    pub registrations: InFlightRegistry,
+    pub clipboard_guard: ClipboardGuard,
+    /// Which windows may invoke which commands
+    pub capabilities: WebAuthnCapabilityFilter,
+    pub auth_notifications: AuthNotificationConfig,
+    /// Ceremony counts for `get_webauthn_metrics`
+    pub metrics: WebAuthnMetrics,
+    /// How far the first passkey setup got, saved next to the credential store
+    pub onboarding: Onboarding,
+    /// Ends every session once the user has been idle too long
+    pub app_lock: AppLock,
+    /// Most passkeys `register_passkey` lets a site have
+    pub max_credentials_per_site: usize,
+    /// Failed authentications in a row that lock a passkey
+    pub lockout_threshold: u32,
+    /// Security key PINs that worked recently
+    pub pin_cache: PinCache,
+    /// Registered passkeys must stay on this device
+    pub no_sync: bool,
+    /// How `authenticate_passkey` retries platform failures that may pass
+    pub retry: RetryConfig,
+    /// Where registrations, authentications and other passkey changes are recorded
+    pub audit_log: AuditLog,
+    /// Sign-ins waiting for a passkey on another device
+    pub hybrid_auth: HybridAuthSessions,
+    /// Sync offers this device asked for and has not accepted yet
+    pub sync_requests: SyncRequests,
+    /// The system clock read a time before 2001 when the plugin started
+    pub clock_skew_detected: bool,
+}
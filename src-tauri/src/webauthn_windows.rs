@@ -0,0 +1,444 @@
+// src-tauri/src/webauthn_windows.rs
+
+//! Native passkey ceremonies backed by Windows Hello (`webauthn.dll`).
+//!
+//! The `WebAuthNAuthenticator*` calls block until the system dialog is dismissed,
+//! so they run on a blocking worker rather than on the async runtime. Windows acts
+//! as a pass-through for the client data, so the `clientDataJSON` is built by
+//! `ClientDataJson`.
+
+use std::time::Duration;
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use tauri::{Runtime, WebviewWindow};
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{ERROR_BUSY, ERROR_TIMEOUT, HWND, NTE_NOT_FOUND, RPC_E_SERVERCALL_RETRYLATER};
+use windows::Win32::Networking::WindowsWebServices::*;
+
+use crate::client_data::{app_origin, ClientDataJson};
+use crate::webauthn::{
+    AuthenticatorAttachment, ResidentKeyRequirement, UserVerification, WebAuthnCapabilities, WebAuthnError,
+};
+use crate::webauthn_platform::{LargeBlobOperation, PrfInput};
+
+/// Default time the system dialog stays up before giving up
+const CEREMONY_TIMEOUT_MS: u32 = 60_000;
+
+/// Raw output of a successful registration ceremony
+pub(crate) struct NativeRegistration {
+    pub credential_id: Vec<u8>,
+    pub authenticator_data: Vec<u8>,
+    /// `platform` for Windows Hello, `cross-platform` for a security key or phone
+    pub authenticator_attachment: String,
+    pub is_resident: bool,
+}
+
+/// Raw output of a successful assertion ceremony
+pub(crate) struct NativeAssertion {
+    pub credential_id: Vec<u8>,
+    pub user_handle: Option<Vec<u8>>,
+    pub authenticator_data: Vec<u8>,
+    /// See `NativeRegistration::authenticator_attachment`
+    pub authenticator_attachment: String,
+    pub prf_output: Option<Vec<u8>>,
+    pub large_blob_data: Option<Vec<u8>>,
+}
+
+/// Version of the WebAuthn API exposed by `webauthn.dll`
+///
+/// Version 1 shipped with Windows 10 1903, the first release with a stable API.
+pub(crate) fn api_version() -> u32 {
+    unsafe { WebAuthNGetApiVersionNumber() }
+}
+
+pub(crate) fn is_available() -> bool {
+    api_version() >= WEBAUTHN_API_VERSION_1
+}
+
+/// Whether Windows Hello is set up, as reported by `webauthn.dll`
+///
+/// Security keys over USB, NFC or Bluetooth can be used with any API version.
+pub(crate) fn capabilities() -> WebAuthnCapabilities {
+    let windows_hello = is_available()
+        && unsafe { WebAuthNIsUserVerifyingPlatformAuthenticatorAvailable() }.is_ok_and(|available| available.as_bool());
+
+    WebAuthnCapabilities {
+        platform_authenticator: windows_hello,
+        roaming_authenticator: is_available(),
+        resident_key_support: windows_hello,
+        user_verification_support: windows_hello,
+        // Windows Hello falls back to software keys without a TPM and does not say which it used
+        hardware_backed: None,
+        min_os_version_required: None,
+    }
+}
+
+/// Create a new credential, presenting Windows Hello or the security key dialog
+/// over the given window
+///
+/// Preferring a discoverable credential needs API version 3 or later; older
+/// versions only create one when it is required.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn register<R: Runtime>(
+    window: &WebviewWindow<R>,
+    rp_id: &str,
+    challenge: &[u8],
+    user_name: &str,
+    user_id: &[u8],
+    display_name: Option<&str>,
+    user_verification: UserVerification,
+    resident_key: ResidentKeyRequirement,
+    authenticator_attachment: AuthenticatorAttachment,
+) -> Result<NativeRegistration, WebAuthnError> {
+    let hwnd = parent_window(window)?;
+    let prefer_resident_key = api_version() >= WEBAUTHN_API_VERSION_3;
+    let rp_id = rp_id.to_string();
+    let mut client_data =
+        ClientDataJson::new_create(&URL_SAFE_NO_PAD.encode(challenge), &app_origin(&rp_id)).to_json_bytes();
+    let user_name = user_name.to_string();
+    let mut user_id = user_id.to_vec();
+    let display_name = display_name.unwrap_or(&user_name).to_string();
+
+    run_blocking(hwnd, move |hwnd| unsafe {
+        let rp_id_w = HSTRING::from(rp_id.as_str());
+        let rp_name_w = HSTRING::from("Sparktype");
+        let rp = WEBAUTHN_RP_ENTITY_INFORMATION {
+            dwVersion: WEBAUTHN_RP_ENTITY_INFORMATION_CURRENT_VERSION,
+            pwszId: PCWSTR(rp_id_w.as_ptr()),
+            pwszName: PCWSTR(rp_name_w.as_ptr()),
+            pwszIcon: PCWSTR::null(),
+        };
+
+        let user_name_w = HSTRING::from(user_name.as_str());
+        let display_name_w = HSTRING::from(display_name.as_str());
+        let user = WEBAUTHN_USER_ENTITY_INFORMATION {
+            dwVersion: WEBAUTHN_USER_ENTITY_INFORMATION_CURRENT_VERSION,
+            cbId: user_id.len() as u32,
+            pbId: user_id.as_mut_ptr(),
+            pwszName: PCWSTR(user_name_w.as_ptr()),
+            pwszIcon: PCWSTR::null(),
+            pwszDisplayName: PCWSTR(display_name_w.as_ptr()),
+        };
+
+        let mut parameters = [
+            cose_parameter(WEBAUTHN_COSE_ALGORITHM_ECDSA_P256_WITH_SHA256),
+            cose_parameter(WEBAUTHN_COSE_ALGORITHM_RSASSA_PKCS1_V1_5_WITH_SHA256),
+        ];
+        let parameters = WEBAUTHN_COSE_CREDENTIAL_PARAMETERS {
+            cCredentialParameters: parameters.len() as u32,
+            pCredentialParameters: parameters.as_mut_ptr(),
+        };
+
+        let client_data = client_data_struct(&mut client_data);
+        let options = WEBAUTHN_AUTHENTICATOR_MAKE_CREDENTIAL_OPTIONS {
+            // `bPreferResidentKey` first appears in version 4 of the options
+            dwVersion: if prefer_resident_key {
+                WEBAUTHN_AUTHENTICATOR_MAKE_CREDENTIAL_OPTIONS_VERSION_4
+            } else {
+                WEBAUTHN_AUTHENTICATOR_MAKE_CREDENTIAL_OPTIONS_VERSION_1
+            },
+            dwTimeoutMilliseconds: CEREMONY_TIMEOUT_MS,
+            dwAuthenticatorAttachment: attachment_option(authenticator_attachment),
+            bRequireResidentKey: (resident_key == ResidentKeyRequirement::Required).into(),
+            bPreferResidentKey: (prefer_resident_key && resident_key == ResidentKeyRequirement::Preferred).into(),
+            dwUserVerificationRequirement: user_verification_requirement(user_verification),
+            dwAttestationConveyancePreference: WEBAUTHN_ATTESTATION_CONVEYANCE_PREFERENCE_NONE,
+            ..Default::default()
+        };
+
+        let attestation = WebAuthNAuthenticatorMakeCredential(
+            hwnd,
+            &rp,
+            &user,
+            &parameters,
+            &client_data,
+            Some(&options),
+        )
+        .map_err(describe_error)?;
+
+        let result = NativeRegistration {
+            credential_id: copy_bytes((*attestation).pbCredentialId, (*attestation).cbCredentialId),
+            authenticator_data: copy_bytes(
+                (*attestation).pbAuthenticatorData,
+                (*attestation).cbAuthenticatorData,
+            ),
+            // Reported from version 3 of the attestation
+            authenticator_attachment: used_attachment(
+                ((*attestation).dwVersion >= WEBAUTHN_CREDENTIAL_ATTESTATION_VERSION_3)
+                    .then_some((*attestation).dwUsedTransport),
+                authenticator_attachment,
+            ),
+            // Reported from version 4 of the attestation; before that only a required one is certain
+            is_resident: if (*attestation).dwVersion >= WEBAUTHN_CREDENTIAL_ATTESTATION_VERSION_4 {
+                (*attestation).bResidentKey.as_bool()
+            } else {
+                resident_key == ResidentKeyRequirement::Required
+            },
+        };
+        WebAuthNFreeCredentialAttestation(Some(attestation));
+        Ok(result)
+    })
+    .await
+}
+
+/// Get an assertion for one of the given credentials, presenting Windows Hello or
+/// the security key dialog over the given window
+///
+/// PRF inputs are passed as `pHmacSecretSaltValues`, which arrived with API
+/// version 3; Windows hashes them into `hmac-secret` salts itself. Older
+/// releases assert without evaluating them. The same goes for largeBlob
+/// operations, which Windows only carries out when a single credential is
+/// allowed.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn authenticate<R: Runtime>(
+    window: &WebviewWindow<R>,
+    rp_id: &str,
+    challenge: &[u8],
+    allowed_credentials: &[Vec<u8>],
+    user_verification: UserVerification,
+    authenticator_attachment: AuthenticatorAttachment,
+    prf: Option<&PrfInput>,
+    large_blob: Option<&LargeBlobOperation>,
+    timeout: Duration,
+) -> Result<NativeAssertion, WebAuthnError> {
+    let hwnd = parent_window(window)?;
+    let rp_id = rp_id.to_string();
+    let mut client_data =
+        ClientDataJson::new_get(&URL_SAFE_NO_PAD.encode(challenge), &app_origin(&rp_id)).to_json_bytes();
+    let mut allowed_credentials = allowed_credentials.to_vec();
+    let mut prf = match prf {
+        Some(_) if api_version() < WEBAUTHN_API_VERSION_3 => {
+            log::warn!("Windows Hello cannot evaluate PRF inputs before API version 3; asserting without them");
+            None
+        }
+        prf => prf.cloned(),
+    };
+    let mut large_blob = match large_blob {
+        Some(_) if api_version() < WEBAUTHN_API_VERSION_3 => {
+            log::warn!("Windows Hello cannot use largeBlob before API version 3; asserting without it");
+            None
+        }
+        large_blob => large_blob.cloned(),
+    };
+
+    run_blocking(hwnd, move |hwnd| unsafe {
+        let rp_id_w = HSTRING::from(rp_id.as_str());
+        let mut allowed: Vec<WEBAUTHN_CREDENTIAL> = allowed_credentials
+            .iter_mut()
+            .map(|credential_id| WEBAUTHN_CREDENTIAL {
+                dwVersion: WEBAUTHN_CREDENTIAL_CURRENT_VERSION,
+                cbId: credential_id.len() as u32,
+                pbId: credential_id.as_mut_ptr(),
+                pwszCredentialType: WEBAUTHN_CREDENTIAL_TYPE_PUBLIC_KEY,
+            })
+            .collect();
+
+        // The options point into these, so they live until the call returns
+        let mut global_salt = prf.as_mut().map(|prf| hmac_secret_salt(&mut prf.eval));
+        let mut credential_salts: Vec<WEBAUTHN_HMAC_SECRET_SALT> = prf
+            .iter_mut()
+            .flat_map(|prf| prf.eval_by_credential.iter_mut())
+            .map(|(_, input)| hmac_secret_salt(input))
+            .collect();
+        let mut credentials_with_salts: Vec<WEBAUTHN_CRED_WITH_HMAC_SECRET_SALT> = prf
+            .iter_mut()
+            .flat_map(|prf| prf.eval_by_credential.iter_mut())
+            .zip(credential_salts.iter_mut())
+            .map(|((credential_id, _), salt)| WEBAUTHN_CRED_WITH_HMAC_SECRET_SALT {
+                cbCredID: credential_id.len() as u32,
+                pbCredID: credential_id.as_mut_ptr(),
+                pHmacSecretSalt: salt,
+            })
+            .collect();
+        let mut salt_values = global_salt.as_mut().map(|global_salt| WEBAUTHN_HMAC_SECRET_SALT_VALUES {
+            pGlobalHmacSalt: global_salt,
+            cCredWithHmacSecretSaltList: credentials_with_salts.len() as u32,
+            pCredWithHmacSecretSaltList: credentials_with_salts.as_mut_ptr(),
+        });
+
+        let (large_blob_operation, large_blob_data) = match large_blob.as_mut() {
+            Some(LargeBlobOperation::Read) => (WEBAUTHN_CRED_LARGE_BLOB_OPERATION_GET, &mut [][..]),
+            Some(LargeBlobOperation::Write(data)) => (WEBAUTHN_CRED_LARGE_BLOB_OPERATION_SET, &mut data[..]),
+            None => (WEBAUTHN_CRED_LARGE_BLOB_OPERATION_NONE, &mut [][..]),
+        };
+
+        let client_data = client_data_struct(&mut client_data);
+        let options = WEBAUTHN_AUTHENTICATOR_GET_ASSERTION_OPTIONS {
+            // The largeBlob fields are read from version 4 of the options,
+            // `pHmacSecretSaltValues` from version 5
+            dwVersion: if salt_values.is_some() || large_blob_operation != WEBAUTHN_CRED_LARGE_BLOB_OPERATION_NONE {
+                WEBAUTHN_AUTHENTICATOR_GET_ASSERTION_OPTIONS_VERSION_5
+            } else {
+                WEBAUTHN_AUTHENTICATOR_GET_ASSERTION_OPTIONS_VERSION_1
+            },
+            dwTimeoutMilliseconds: timeout.as_millis().try_into().unwrap_or(u32::MAX),
+            CredentialList: WEBAUTHN_CREDENTIALS {
+                cCredentials: allowed.len() as u32,
+                pCredentials: allowed.as_mut_ptr(),
+            },
+            dwAuthenticatorAttachment: attachment_option(authenticator_attachment),
+            dwUserVerificationRequirement: user_verification_requirement(user_verification),
+            dwCredLargeBlobOperation: large_blob_operation,
+            cbCredLargeBlob: large_blob_data.len() as u32,
+            pbCredLargeBlob: large_blob_data.as_mut_ptr(),
+            pHmacSecretSaltValues: salt_values.as_mut().map_or(std::ptr::null_mut(), |values| values as *mut _),
+            ..Default::default()
+        };
+
+        let assertion = WebAuthNAuthenticatorGetAssertion(hwnd, &rp_id_w, &client_data, Some(&options))
+            .map_err(describe_error)?;
+
+        let user_handle = copy_bytes((*assertion).pbUserId, (*assertion).cbUserId);
+        // Reported from version 2 of the assertion
+        let large_blob_status = if (*assertion).dwVersion >= WEBAUTHN_ASSERTION_VERSION_2 {
+            (*assertion).dwCredLargeBlobStatus
+        } else {
+            WEBAUTHN_CRED_LARGE_BLOB_STATUS_NONE
+        };
+        if large_blob_operation != WEBAUTHN_CRED_LARGE_BLOB_OPERATION_NONE
+            && large_blob_status != WEBAUTHN_CRED_LARGE_BLOB_STATUS_SUCCESS
+        {
+            log::warn!("Windows Hello did not carry out the largeBlob operation, status {}", large_blob_status);
+        }
+        let result = NativeAssertion {
+            credential_id: copy_bytes((*assertion).Credential.pbId, (*assertion).Credential.cbId),
+            user_handle: (!user_handle.is_empty()).then_some(user_handle),
+            authenticator_data: copy_bytes((*assertion).pbAuthenticatorData, (*assertion).cbAuthenticatorData),
+            // Reported from version 4 of the assertion
+            authenticator_attachment: used_attachment(
+                ((*assertion).dwVersion >= WEBAUTHN_ASSERTION_VERSION_4).then_some((*assertion).dwUsedTransport),
+                authenticator_attachment,
+            ),
+            // Reported from version 3 of the assertion
+            prf_output: ((*assertion).dwVersion >= WEBAUTHN_ASSERTION_VERSION_3 && !(*assertion).pHmacSecret.is_null())
+                .then(|| copy_bytes((*(*assertion).pHmacSecret).pbFirst, (*(*assertion).pHmacSecret).cbFirst))
+                .filter(|output| !output.is_empty()),
+            large_blob_data: (large_blob_operation == WEBAUTHN_CRED_LARGE_BLOB_OPERATION_GET
+                && large_blob_status == WEBAUTHN_CRED_LARGE_BLOB_STATUS_SUCCESS)
+                .then(|| copy_bytes((*assertion).pbCredLargeBlob, (*assertion).cbCredLargeBlob)),
+        };
+        WebAuthNFreeAssertion(assertion);
+        Ok(result)
+    })
+    .await
+}
+
+/// Remove a Windows Hello passkey
+///
+/// Deletion arrived with API version 4 (Windows 11 22H2); on older releases the
+/// user removes passkeys from Settings instead. A credential Windows no longer
+/// has is treated as already deleted.
+pub(crate) fn delete_credential(credential_id: &[u8]) -> Result<(), WebAuthnError> {
+    if api_version() < WEBAUTHN_API_VERSION_4 {
+        log::warn!("Windows Hello cannot delete passkeys before API version 4; leaving it in place");
+        return Ok(());
+    }
+
+    match unsafe { WebAuthNDeletePlatformCredential(credential_id) } {
+        Err(error) if error.code() != NTE_NOT_FOUND => Err(describe_error(error)),
+        _ => Ok(()),
+    }
+}
+
+/// Window handle used as the owner of the Windows Hello dialog
+fn parent_window<R: Runtime>(window: &WebviewWindow<R>) -> Result<isize, WebAuthnError> {
+    window
+        .hwnd()
+        .map(|hwnd| hwnd.0 as isize)
+        .map_err(|e| WebAuthnError::InternalError(format!("Failed to get the window handle: {}", e)))
+}
+
+/// Run a blocking webauthn.dll call off the async runtime
+///
+/// The handle travels as an integer because `HWND` is not `Send`.
+async fn run_blocking<T, F>(hwnd: isize, call: F) -> Result<T, WebAuthnError>
+where
+    T: Send + 'static,
+    F: FnOnce(HWND) -> Result<T, WebAuthnError> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || call(HWND(hwnd as *mut _)))
+        .await
+        .map_err(|e| WebAuthnError::InternalError(format!("Windows Hello worker failed: {}", e)))?
+}
+
+fn client_data_struct(client_data: &mut [u8]) -> WEBAUTHN_CLIENT_DATA {
+    WEBAUTHN_CLIENT_DATA {
+        dwVersion: WEBAUTHN_CLIENT_DATA_CURRENT_VERSION,
+        cbClientDataJSON: client_data.len() as u32,
+        pbClientDataJSON: client_data.as_mut_ptr(),
+        pwszHashAlgId: WEBAUTHN_HASH_ALGORITHM_SHA_256,
+    }
+}
+
+fn attachment_option(authenticator_attachment: AuthenticatorAttachment) -> u32 {
+    match authenticator_attachment {
+        AuthenticatorAttachment::Platform => WEBAUTHN_AUTHENTICATOR_ATTACHMENT_PLATFORM,
+        AuthenticatorAttachment::CrossPlatform => WEBAUTHN_AUTHENTICATOR_ATTACHMENT_CROSS_PLATFORM,
+        AuthenticatorAttachment::Any => WEBAUTHN_AUTHENTICATOR_ATTACHMENT_ANY,
+    }
+}
+
+/// The attachment a ceremony ended up using, from the transport Windows reports
+///
+/// Without a reported transport, only a ceremony restricted to security keys is
+/// known to have used one.
+fn used_attachment(used_transport: Option<u32>, requested: AuthenticatorAttachment) -> String {
+    let cross_platform = match used_transport.filter(|&transport| transport != 0) {
+        Some(transport) => transport & WEBAUTHN_CTAP_TRANSPORT_INTERNAL == 0,
+        None => requested == AuthenticatorAttachment::CrossPlatform,
+    };
+    if cross_platform { "cross-platform" } else { "platform" }.to_string()
+}
+
+/// A PRF input with no second one, as webauthn.dll takes it
+fn hmac_secret_salt(input: &mut [u8]) -> WEBAUTHN_HMAC_SECRET_SALT {
+    WEBAUTHN_HMAC_SECRET_SALT {
+        cbFirst: input.len() as u32,
+        pbFirst: input.as_mut_ptr(),
+        ..Default::default()
+    }
+}
+
+fn user_verification_requirement(user_verification: UserVerification) -> u32 {
+    match user_verification {
+        UserVerification::Required => WEBAUTHN_USER_VERIFICATION_REQUIREMENT_REQUIRED,
+        UserVerification::Preferred => WEBAUTHN_USER_VERIFICATION_REQUIREMENT_PREFERRED,
+        UserVerification::Discouraged => WEBAUTHN_USER_VERIFICATION_REQUIREMENT_DISCOURAGED,
+    }
+}
+
+fn cose_parameter(algorithm: i32) -> WEBAUTHN_COSE_CREDENTIAL_PARAMETER {
+    WEBAUTHN_COSE_CREDENTIAL_PARAMETER {
+        dwVersion: WEBAUTHN_COSE_CREDENTIAL_PARAMETER_CURRENT_VERSION,
+        pwszCredentialType: WEBAUTHN_CREDENTIAL_TYPE_PUBLIC_KEY,
+        lAlg: algorithm,
+    }
+}
+
+unsafe fn copy_bytes(data: *const u8, len: u32) -> Vec<u8> {
+    if data.is_null() || len == 0 {
+        return Vec::new();
+    }
+    std::slice::from_raw_parts(data, len as usize).to_vec()
+}
+
+fn describe_error(error: windows::core::Error) -> WebAuthnError {
+    if error.code() == ERROR_TIMEOUT.to_hresult() {
+        return WebAuthnError::Timeout;
+    }
+    // The WebAuthn service is handling another request
+    if error.code() == ERROR_BUSY.to_hresult() || error.code() == RPC_E_SERVERCALL_RETRYLATER {
+        return WebAuthnError::PlatformUnavailable(error.message());
+    }
+
+    let name = unsafe { WebAuthNGetErrorName(error.code()).to_string() }.unwrap_or_default();
+    match name.as_str() {
+        // Raised for user cancellation as well as expired dialogs, per the WebAuthn spec
+        "NotAllowedError" => WebAuthnError::UserCancelled,
+        "InvalidStateError" => {
+            WebAuthnError::InternalError("A passkey for this site already exists on this device".to_string())
+        }
+        "NotSupportedError" => WebAuthnError::PlatformNotSupported,
+        _ => WebAuthnError::InternalError(format!("Windows Hello error ({}): {}", name, error.message())),
+    }
+}
@@ -0,0 +1,242 @@
+// src-tauri/tests/authenticator_data.rs
+
+//! `parse_authenticator_data` and `parse_cose_key` against sample data.
+//!
+//! The samples are laid out byte by byte as in section 6.1 of the WebAuthn
+//! spec, with COSE keys encoded as in its section 6.5.1.1, so that each field
+//! can be checked against a known value. The P-256 key is the one of RFC 8152
+//! appendix C.7.1.
+
+use app_lib::{parse_authenticator_data, parse_cose_key, AuthenticatorDataFlags, CoseKeyParameters, WebAuthnError};
+use sha2::{Digest, Sha256};
+
+/// AAGUID of a real authenticator, so that its string form can be checked
+const AAGUID: [u8; 16] = [
+    0xad, 0xce, 0x00, 0x02, 0x35, 0xbc, 0xc6, 0x0a, 0x64, 0x8b, 0x0b, 0x25, 0xf1, 0xf0, 0x55, 0x03,
+];
+
+const CREDENTIAL_ID: [u8; 16] = [
+    0x8f, 0x3a, 0x11, 0x52, 0x07, 0xe4, 0x9b, 0x6d, 0x20, 0xc1, 0x5e, 0x77, 0xa8, 0x04, 0xd3, 0x69,
+];
+
+/// The fixed part of authenticator data for example.com
+fn header(flags: u8, sign_count: u32) -> Vec<u8> {
+    let mut data = Sha256::digest(b"example.com").to_vec();
+    data.push(flags);
+    data.extend_from_slice(&sign_count.to_be_bytes());
+    data
+}
+
+/// Authenticator data for a new credential with the given COSE key
+fn registration(flags: u8, aaguid: [u8; 16], cose_key: &[u8]) -> Vec<u8> {
+    let mut data = header(flags, 0);
+    data.extend_from_slice(&aaguid);
+    data.extend_from_slice(&(CREDENTIAL_ID.len() as u16).to_be_bytes());
+    data.extend_from_slice(&CREDENTIAL_ID);
+    data.extend_from_slice(cose_key);
+    data
+}
+
+/// {1: 2, 3: -7, -1: 1, -2: x, -3: y}, a P-256 key for ES256
+fn ec2_key() -> Vec<u8> {
+    let mut key = vec![0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58, 0x20];
+    key.extend_from_slice(&[0x11; 32]);
+    key.extend_from_slice(&[0x22, 0x58, 0x20]);
+    key.extend_from_slice(&[0x22; 32]);
+    key
+}
+
+/// x and y of the P-256 key in RFC 8152 appendix C.7.1
+const RFC_8152_X: &str = "65eda5a12577c2bae829437fe338701a10aaa375e1bb5b5de108de439c08551d";
+const RFC_8152_Y: &str = "1e52ed75701163f7f9e40ddf9f341b3dc9ba860af7e0ca7ca7e9eecd0084d19c";
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// The RFC 8152 key as it appears there, {-1: 1, -2: x, -3: y, 1: 2, 2: kid},
+/// with the ES256 algorithm added as WebAuthn requires
+fn rfc_8152_key() -> Vec<u8> {
+    let mut key = vec![0xa6, 0x20, 0x01, 0x21, 0x58, 0x20];
+    key.extend(from_hex(RFC_8152_X));
+    key.extend_from_slice(&[0x22, 0x58, 0x20]);
+    key.extend(from_hex(RFC_8152_Y));
+    key.extend_from_slice(&[0x01, 0x02, 0x02, 0x58, 0x24]);
+    key.extend_from_slice(b"meriadoc.brandybuck@buckland.example");
+    key.extend_from_slice(&[0x03, 0x26]);
+    key
+}
+
+/// {1: 1, 3: -8, -1: 6, -2: x}, an Ed25519 key for EdDSA
+fn okp_key() -> Vec<u8> {
+    let mut key = vec![0xa4, 0x01, 0x01, 0x03, 0x27, 0x20, 0x06, 0x21, 0x58, 0x20];
+    key.extend_from_slice(&[0x33; 32]);
+    key
+}
+
+/// {1: 3, 3: -257, -1: n, -2: 65537}, a 2048-bit key for RS256
+fn rsa_key() -> Vec<u8> {
+    let mut key = vec![0xa4, 0x01, 0x03, 0x03, 0x39, 0x01, 0x00, 0x20, 0x59, 0x01, 0x00];
+    key.extend_from_slice(&[0xc3; 256]);
+    key.extend_from_slice(&[0x21, 0x43, 0x01, 0x00, 0x01]);
+    key
+}
+
+fn assert_malformed(result: Result<impl std::fmt::Debug, WebAuthnError>) {
+    match result {
+        Err(WebAuthnError::InternalError(_)) => {}
+        other => panic!("expected malformed data to be rejected, got {:?}", other),
+    }
+}
+
+#[test]
+fn assertion_data_is_parsed() {
+    // UP | UV
+    let auth_data = parse_authenticator_data(&header(0x05, 42)).expect("parsing failed");
+    assert_eq!(auth_data.rp_id_hash[..], Sha256::digest(b"example.com")[..]);
+    assert_eq!(
+        auth_data.flags,
+        AuthenticatorDataFlags::USER_PRESENT | AuthenticatorDataFlags::USER_VERIFIED
+    );
+    assert!(auth_data.user_present() && auth_data.user_verified());
+    assert!(!auth_data.backup_eligible() && !auth_data.backed_up());
+    assert_eq!(auth_data.sign_count, 42);
+    assert_eq!(auth_data.aaguid, None);
+    assert_eq!(auth_data.credential_id, None);
+    assert_eq!(auth_data.cose_key, None);
+    assert_eq!(auth_data.aaguid_string(), None);
+}
+
+#[test]
+fn registration_data_carries_the_credential() {
+    // UP | UV | BE | BS | AT
+    let key = ec2_key();
+    let auth_data = parse_authenticator_data(&registration(0x5d, AAGUID, &key)).expect("parsing failed");
+    assert!(auth_data.backup_eligible() && auth_data.backed_up());
+    assert!(auth_data.flags.contains(AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA));
+    assert_eq!(auth_data.aaguid, Some(AAGUID));
+    assert_eq!(auth_data.aaguid_string().as_deref(), Some("adce0002-35bc-c60a-648b-0b25f1f05503"));
+    assert_eq!(auth_data.credential_id.as_deref(), Some(&CREDENTIAL_ID[..]));
+
+    let cose_key = auth_data.cose_key.expect("no COSE key");
+    assert_eq!(cose_key.algorithm, -7);
+    assert_eq!(
+        cose_key.parameters,
+        CoseKeyParameters::Ec2 {
+            x: [0x11; 32],
+            y: [0x22; 32],
+        }
+    );
+    assert_eq!(cose_key.encoded, key);
+}
+
+#[test]
+fn zero_aaguid_is_reported_as_withheld() {
+    let auth_data = parse_authenticator_data(&registration(0x45, [0; 16], &ec2_key())).expect("parsing failed");
+    assert_eq!(auth_data.aaguid, Some([0; 16]));
+    assert_eq!(auth_data.aaguid_string(), None);
+}
+
+#[test]
+fn extension_outputs_are_not_part_of_the_key() {
+    // UP | UV | AT | ED, then {"credProtect": 2}
+    let key = ec2_key();
+    let mut data = registration(0xc5, AAGUID, &key);
+    data.extend_from_slice(&[0xa1, 0x6b]);
+    data.extend_from_slice(b"credProtect");
+    data.push(0x02);
+
+    let auth_data = parse_authenticator_data(&data).expect("parsing failed");
+    assert!(auth_data.flags.contains(AuthenticatorDataFlags::EXTENSION_DATA));
+    assert_eq!(auth_data.cose_key.expect("no COSE key").encoded, key);
+}
+
+#[test]
+fn reserved_flag_bits_are_kept() {
+    // UP and the two reserved bits
+    let auth_data = parse_authenticator_data(&header(0x23, 0)).expect("parsing failed");
+    assert_eq!(auth_data.flags.bits(), 0x23);
+    assert!(auth_data.user_present());
+    assert!(!auth_data.user_verified());
+}
+
+#[test]
+fn rfc_8152_key_is_parsed_and_exported() {
+    let key = parse_cose_key(&rfc_8152_key()).expect("parsing failed");
+    assert_eq!(key.algorithm, -7);
+    let CoseKeyParameters::Ec2 { x, y } = key.parameters else {
+        panic!("expected an EC2 key, got {:?}", key.parameters);
+    };
+    assert_eq!(x[..], from_hex(RFC_8152_X)[..]);
+    assert_eq!(y[..], from_hex(RFC_8152_Y)[..]);
+    assert_eq!(
+        key.to_pem(),
+        "-----BEGIN PUBLIC KEY-----\n\
+         MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEZe2loSV3wrroKUN/4zhwGhCqo3Xh\n\
+         u1td4QjeQ5wIVR0eUu11cBFj9/nkDd+fNBs9ybqGCvfgynyn6e7NAITRnA==\n\
+         -----END PUBLIC KEY-----\n"
+    );
+}
+
+#[test]
+fn rsa_key_is_parsed_and_exported() {
+    let key = parse_cose_key(&rsa_key()).expect("parsing failed");
+    assert_eq!(key.algorithm, -257);
+    assert_eq!(
+        key.parameters,
+        CoseKeyParameters::Rsa {
+            n: vec![0xc3; 256],
+            e: vec![0x01, 0x00, 0x01],
+        }
+    );
+
+    // A 2048-bit modulus with its top bit set gets a leading zero byte
+    let der = key.to_spki_der();
+    assert_eq!(der.len(), 294);
+    assert_eq!(der[28..34], [0x02, 0x82, 0x01, 0x01, 0x00, 0xc3]);
+    assert!(der.ends_with(&[0x02, 0x03, 0x01, 0x00, 0x01]));
+    assert!(key
+        .to_pem()
+        .starts_with("-----BEGIN PUBLIC KEY-----\nMIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAw8PD"));
+}
+
+#[test]
+fn truncated_data_is_rejected() {
+    let data = registration(0x45, AAGUID, &ec2_key());
+    for length in 0..data.len() {
+        assert_malformed(parse_authenticator_data(&data[..length]));
+    }
+}
+
+#[test]
+fn malformed_keys_are_rejected() {
+    // A byte string rather than a map
+    assert_malformed(parse_cose_key(&[0x43, 0x01, 0x02, 0x03]));
+    // {1: 4, 3: 5}, a symmetric key
+    assert_malformed(parse_cose_key(&[0xa2, 0x01, 0x04, 0x03, 0x05]));
+    // Ed25519 is not asked for
+    assert_malformed(parse_cose_key(&okp_key()));
+    // An EC2 key without an algorithm
+    let mut without_algorithm = ec2_key();
+    without_algorithm[0] = 0xa4;
+    without_algorithm.drain(3..5);
+    assert_malformed(parse_cose_key(&without_algorithm));
+    // An EC2 key on P-384 (crv 2)
+    let mut p384 = ec2_key();
+    p384[6] = 0x02;
+    assert_malformed(parse_cose_key(&p384));
+    // A coordinate one byte short
+    let mut short_coordinate = ec2_key();
+    short_coordinate[9] = 0x1f;
+    short_coordinate.remove(10);
+    assert_malformed(parse_cose_key(&short_coordinate));
+    // Stray bytes after the key
+    let mut trailing = ec2_key();
+    trailing.push(0x00);
+    assert_malformed(parse_cose_key(&trailing));
+    // AT set over a key that is not a COSE key
+    assert_malformed(parse_authenticator_data(&registration(0x45, AAGUID, &[0x43, 0x01, 0x02, 0x03])));
+}
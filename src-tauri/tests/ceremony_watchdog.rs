@@ -0,0 +1,38 @@
+// src-tauri/tests/ceremony_watchdog.rs
+
+//! `watched_channel` with a platform callback that reports back and one that
+//! never does.
+
+use std::time::{Duration, Instant};
+
+use app_lib::{watched_channel, WebAuthnError};
+
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How late past `TIMEOUT` the watchdog may fire
+const SLACK: Duration = Duration::from_millis(200);
+
+#[test]
+fn stuck_delegate_times_out() {
+    tauri::async_runtime::block_on(async {
+        // Held but never used, like a delegate that is never called back
+        let (_reply, receiver) = watched_channel::<u32>(TIMEOUT);
+        let started = Instant::now();
+        let result = receiver.await.expect("the watchdog dropped the sender");
+        let elapsed = started.elapsed();
+
+        assert!(matches!(result, Err(WebAuthnError::Timeout)), "got {:?}", result);
+        assert!(elapsed >= TIMEOUT, "the watchdog fired after {:?}", elapsed);
+        assert!(elapsed < TIMEOUT + SLACK, "the watchdog fired after {:?}", elapsed);
+    });
+}
+
+#[test]
+fn delegate_reply_wins() {
+    tauri::async_runtime::block_on(async {
+        let (reply, receiver) = watched_channel(TIMEOUT);
+        reply.send(Ok(7));
+        assert!(reply.has_fired());
+        assert_eq!(receiver.await.expect("the reply was lost").ok(), Some(7));
+    });
+}
@@ -0,0 +1,76 @@
+// src-tauri/tests/challenge_zeroing.rs
+
+//! `generate_challenge` zeroing its random bytes before it frees them.
+//!
+//! The global allocator of this test binary keeps an eye on freed blocks of
+//! the challenge's length while it records, so what the bytes read when they
+//! went back to the allocator can be checked. That needs the binary to itself:
+//! other tests allocating at the same time could free blocks of that length.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+
+use app_lib::{generate_challenge, ChallengeConfig, ChallengeRng};
+
+/// A challenge length that nothing else allocates during the test
+const LENGTH: usize = 37;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU8 = AtomicU8::new(0);
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+/// `LENGTH`-byte blocks freed while recording
+static FREED_BLOCKS: AtomicUsize = AtomicUsize::new(0);
+/// Every bit set in any of those blocks when it was freed
+static FREED_BITS: [AtomicU8; LENGTH] = [ZERO; LENGTH];
+
+/// `System`, noting what is freed while recording
+struct RecordingAllocator;
+
+unsafe impl GlobalAlloc for RecordingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if layout.size() == LENGTH && RECORDING.load(Ordering::SeqCst) {
+            for (i, bits) in FREED_BITS.iter().enumerate() {
+                bits.fetch_or(*ptr.add(i), Ordering::SeqCst);
+            }
+            FREED_BLOCKS.fetch_add(1, Ordering::SeqCst);
+        }
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: RecordingAllocator = RecordingAllocator;
+
+/// How many `LENGTH`-byte blocks `run` freed, and whether any held a set bit
+fn record(run: impl FnOnce()) -> (usize, bool) {
+    FREED_BLOCKS.store(0, Ordering::SeqCst);
+    for bits in &FREED_BITS {
+        bits.store(0, Ordering::SeqCst);
+    }
+    RECORDING.store(true, Ordering::SeqCst);
+    run();
+    RECORDING.store(false, Ordering::SeqCst);
+    let dirty = FREED_BITS.iter().any(|bits| bits.load(Ordering::SeqCst) != 0);
+    (FREED_BLOCKS.load(Ordering::SeqCst), dirty)
+}
+
+#[test]
+fn challenge_bytes_are_zeroed_before_they_are_freed() {
+    // Without zeroing, the freed block still holds what was written to it
+    let (freed, dirty) = record(|| drop(std::hint::black_box(vec![0xa5u8; LENGTH])));
+    assert_eq!(freed, 1);
+    assert!(dirty, "a block freed without zeroing read as zeros");
+
+    let rng = ChallengeRng::new();
+    let config = ChallengeConfig { length_bytes: LENGTH };
+    let mut challenge = String::new();
+    let (freed, dirty) = record(|| challenge = generate_challenge(&rng, &config));
+    assert_eq!(freed, 1, "the challenge bytes were not freed as one block");
+    assert!(!dirty, "the challenge bytes were freed without being zeroed");
+    assert!(!challenge.is_empty());
+}
@@ -0,0 +1,89 @@
+// src-tauri/tests/client_data.rs
+
+//! `ClientDataJson` serialized as WebAuthn Level 2, 5.8.1.1 specifies.
+
+use app_lib::ClientDataJson;
+use sha2::{Digest, Sha256};
+
+#[test]
+fn assertions_serialize_their_members_in_spec_order() {
+    let client_data = ClientDataJson::new_get("Y2hhbGxlbmdl", "https://sparktype.org");
+    assert_eq!(
+        client_data.to_json_bytes(),
+        br#"{"type":"webauthn.get","challenge":"Y2hhbGxlbmdl","origin":"https://sparktype.org","crossOrigin":false}"#
+    );
+}
+
+#[test]
+fn registrations_are_of_type_webauthn_create() {
+    let client_data = ClientDataJson::new_create("AAECAw", "https://sparktype.org");
+    assert_eq!(
+        client_data.to_json_bytes(),
+        br#"{"type":"webauthn.create","challenge":"AAECAw","origin":"https://sparktype.org","crossOrigin":false}"#
+    );
+}
+
+#[test]
+fn cross_origin_ceremonies_say_so() {
+    let client_data = ClientDataJson::new_get("AAECAw", "https://sparktype.org").cross_origin(true);
+    assert!(client_data.to_json_bytes().ends_with(br#","crossOrigin":true}"#));
+}
+
+#[test]
+fn the_prefix_matches_the_limited_verification_algorithm() {
+    // 5.8.1.2 checks the bytes up to the challenge without parsing the JSON
+    let client_data = ClientDataJson::new_get("AAECAw", "https://sparktype.org").to_json_bytes();
+    assert!(client_data.starts_with(br#"{"type":"webauthn.get","challenge":"AAECAw","origin":""#));
+}
+
+#[test]
+fn only_quotes_backslashes_and_control_characters_are_escaped() {
+    let client_data = ClientDataJson::new_get("AAECAw", "https://a\"b\\c\nd.org/é").to_json_bytes();
+    assert_eq!(
+        String::from_utf8(client_data).unwrap(),
+        r#"{"type":"webauthn.get","challenge":"AAECAw","origin":"https://a\"b\\c\u000ad.org/é","crossOrigin":false}"#
+    );
+}
+
+#[test]
+fn the_serialization_is_valid_json() {
+    let client_data = ClientDataJson::new_get("AAECAw", "https://ex\"a\\m\nple.org").to_json_bytes();
+    let parsed: serde_json::Value = serde_json::from_slice(&client_data).unwrap();
+    assert_eq!(parsed["type"], "webauthn.get");
+    assert_eq!(parsed["origin"], "https://ex\"a\\m\nple.org");
+    assert_eq!(parsed["crossOrigin"], false);
+}
+
+#[test]
+fn the_hash_is_the_sha256_of_the_serialized_bytes() {
+    let client_data = ClientDataJson::new_create("AAECAw", "https://sparktype.org");
+    assert_eq!(client_data.hash()[..], Sha256::digest(client_data.to_json_bytes())[..]);
+}
+
+/// The assertion client data of the WebAuthn Level 3 `none.ES256` test vector
+/// (section 16, `#sctn-test-vectors-none-es256`), as the spec publishes it in hex
+const SPEC_ASSERTION_CLIENT_DATA: &str = concat!(
+    "7b2274797065223a22776562617574686e2e676574222c226368616c6c656e6765223a224f63446e55685158756c5455506f334a5558",
+    "543049393770767a7a59425039745a63685879617630314167222c226f726967696e223a2268747470733a2f2f6578616d706c652e6f",
+    "7267222c2263726f73734f726967696e223a66616c73657d",
+);
+
+/// SHA-256 of `SPEC_ASSERTION_CLIENT_DATA`, which the vector's signature covers
+const SPEC_ASSERTION_CLIENT_DATA_HASH: &str = "676a6e380fd31ea5710705c7be1ac4889b037895d0a25291f51251b7d7b1df02";
+
+fn hex(text: &str) -> Vec<u8> {
+    (0..text.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(&text[at..at + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn the_spec_test_vector_serializes_byte_for_byte() {
+    let client_data = ClientDataJson::new_get("OcDnUhQXulTUPo3JUXT0I97pvzzYBP9tZchXyav01Ag", "https://example.org");
+    assert_eq!(
+        String::from_utf8(client_data.to_json_bytes()).unwrap(),
+        String::from_utf8(hex(SPEC_ASSERTION_CLIENT_DATA)).unwrap()
+    );
+    assert_eq!(client_data.hash()[..], hex(SPEC_ASSERTION_CLIENT_DATA_HASH)[..]);
+}
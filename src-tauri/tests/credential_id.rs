@@ -0,0 +1,46 @@
+// src-tauri/tests/credential_id.rs
+
+//! `CredentialId` against IDs of the lengths authenticators produce.
+
+use app_lib::{AuthenticatorType, CredentialId, WebAuthnError};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+fn credential_id(length: usize) -> Result<CredentialId, WebAuthnError> {
+    CredentialId::try_from(URL_SAFE_NO_PAD.encode(vec![0xa5; length]).as_str())
+}
+
+fn assert_invalid(result: Result<CredentialId, WebAuthnError>) {
+    match result {
+        Err(WebAuthnError::InvalidInput(_)) => {}
+        other => panic!("expected the credential ID to be refused, got {:?}", other),
+    }
+}
+
+#[test]
+fn ids_within_the_spec_limits_are_decoded() {
+    for length in [16, 32, 64, 1023] {
+        let id = credential_id(length).expect("a valid credential ID was refused");
+        assert_eq!(id.as_bytes(), &vec![0xa5; length][..]);
+    }
+}
+
+#[test]
+fn ids_outside_the_spec_limits_are_refused() {
+    assert_invalid(credential_id(0));
+    assert_invalid(credential_id(15));
+    assert_invalid(credential_id(1024));
+    assert_invalid(CredentialId::try_from("not base64url!"));
+    // Standard base64 with padding
+    assert_invalid(CredentialId::try_from("q6urq6urq6urq6urq6urqw=="));
+}
+
+#[test]
+fn authenticator_type_follows_the_id_length() {
+    let authenticator_type = |length| credential_id(length).unwrap().authenticator_type();
+    assert_eq!(authenticator_type(64), AuthenticatorType::SecurityKey);
+    assert_eq!(authenticator_type(32), AuthenticatorType::Platform);
+    assert_eq!(authenticator_type(65), AuthenticatorType::Platform);
+    assert_eq!(authenticator_type(96), AuthenticatorType::Platform);
+    assert_eq!(authenticator_type(16), AuthenticatorType::Unknown);
+    assert_eq!(authenticator_type(128), AuthenticatorType::Unknown);
+}
@@ -0,0 +1,34 @@
+// src-tauri/tests/device_name.rs
+
+//! `make_unique_device_name` numbering device names a site's passkeys share.
+
+use app_lib::make_unique_device_name;
+
+#[test]
+fn names_no_passkey_has_are_kept() {
+    assert_eq!(make_unique_device_name("MacBook Pro", &[]), "MacBook Pro");
+    assert_eq!(make_unique_device_name("MacBook Pro", &["iPhone", "MacBook Air"]), "MacBook Pro");
+}
+
+#[test]
+fn names_already_taken_are_numbered_from_2() {
+    assert_eq!(make_unique_device_name("MacBook Pro", &["MacBook Pro"]), "MacBook Pro (2)");
+    assert_eq!(
+        make_unique_device_name("MacBook Pro", &["MacBook Pro", "MacBook Pro (2)"]),
+        "MacBook Pro (3)"
+    );
+    // A gap left by a deleted passkey is filled
+    assert_eq!(
+        make_unique_device_name("MacBook Pro", &["MacBook Pro", "MacBook Pro (3)"]),
+        "MacBook Pro (2)"
+    );
+}
+
+#[test]
+fn names_are_compared_ignoring_case() {
+    assert_eq!(make_unique_device_name("Unknown Device", &["unknown device"]), "Unknown Device (2)");
+    assert_eq!(
+        make_unique_device_name("ThinkPad", &["THINKPAD", "thinkpad (2)"]),
+        "ThinkPad (3)"
+    );
+}
@@ -0,0 +1,34 @@
+// src-tauri/tests/entropy_check.rs
+
+//! The chi-squared test the entropy self-test runs on samples from the OS.
+
+use app_lib::{byte_frequency_chi_squared, is_plausibly_random};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+#[test]
+fn evenly_spread_bytes_score_zero() {
+    let bytes: Vec<u8> = (0..2048).map(|i| i as u8).collect();
+    assert_eq!(byte_frequency_chi_squared(&bytes), 0.0);
+    assert!(is_plausibly_random(0.0));
+}
+
+#[test]
+fn constant_bytes_are_not_plausibly_random() {
+    let chi_squared = byte_frequency_chi_squared(&[0u8; 2048]);
+    assert_eq!(chi_squared, 522240.0);
+    assert!(!is_plausibly_random(chi_squared));
+}
+
+#[test]
+fn bytes_from_a_seeded_generator_are_plausibly_random() {
+    let mut bytes = [0u8; 2048];
+    ChaCha20Rng::seed_from_u64(1).fill_bytes(&mut bytes);
+    assert!(is_plausibly_random(byte_frequency_chi_squared(&bytes)));
+}
+
+#[test]
+fn bytes_from_half_the_values_are_not_plausibly_random() {
+    let bytes: Vec<u8> = (0..2048).map(|i| (i % 128) as u8).collect();
+    assert!(!is_plausibly_random(byte_frequency_chi_squared(&bytes)));
+}
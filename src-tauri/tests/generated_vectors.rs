@@ -0,0 +1,249 @@
+// src-tauri/tests/generated_vectors.rs
+
+//! The authenticator data parser, COSE key extraction and signature check
+//! against the generated vectors in `fixtures/generated_webauthn_vectors.json`.
+//!
+//! These are extras beside the W3C test vectors of `w3c_vectors.rs`, covering
+//! what those leave out: RS256 keys, discoverable credentials and assertions
+//! that must fail. They were generated with fresh ES256 and RS256 keys to the
+//! formats of WebAuthn Level 2: authenticator data
+//! as in its section 6.1, COSE keys as in 6.5.1.1, client data serialized as in
+//! 5.8.1.1 and signatures made as in 6.3.3. Each one is a registration and an
+//! assertion by the same credential; some assertions were altered after signing
+//! and must fail to verify. Every vector is checked and every mismatch
+//! reported, by vector name, with the expected and actual values.
+
+use std::fmt::Debug;
+
+use app_lib::{
+    parse_authenticator_data, parse_cose_key, verify_signature, AuthenticatorData, AuthenticatorDataFlags,
+    ClientDataJson, CoseKey, CoseKeyParameters,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+const FIXTURE: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/generated_webauthn_vectors.json"
+));
+
+/// Mismatches found so far, one line each
+#[derive(Default)]
+struct Failures(Vec<String>);
+
+impl Failures {
+    fn check<T: PartialEq + Debug>(&mut self, vector: &str, field: &str, expected: T, actual: T) {
+        if expected != actual {
+            self.0.push(format!(
+                "{}: {}: expected {:?}, got {:?}",
+                vector, field, expected, actual
+            ));
+        }
+    }
+
+    fn fail(&mut self, vector: &str, message: String) {
+        self.0.push(format!("{}: {}", vector, message));
+    }
+}
+
+fn fixture() -> Value {
+    serde_json::from_str(FIXTURE).expect("the vector fixture is not JSON")
+}
+
+fn vectors(fixture: &Value) -> &[Value] {
+    fixture["vectors"].as_array().expect("the fixture has no vectors")
+}
+
+fn decode(value: &Value) -> Vec<u8> {
+    URL_SAFE_NO_PAD
+        .decode(value.as_str().expect("expected a base64url string"))
+        .expect("expected a base64url string")
+}
+
+fn str_field(value: &Value) -> &str {
+    value.as_str().expect("expected a string")
+}
+
+/// The flags named by their WebAuthn abbreviations
+fn flags(names: &Value) -> AuthenticatorDataFlags {
+    names
+        .as_array()
+        .expect("expected a list of flags")
+        .iter()
+        .map(|name| match str_field(name) {
+            "UP" => AuthenticatorDataFlags::USER_PRESENT,
+            "UV" => AuthenticatorDataFlags::USER_VERIFIED,
+            "BE" => AuthenticatorDataFlags::BACKUP_ELIGIBLE,
+            "BS" => AuthenticatorDataFlags::BACKED_UP,
+            "AT" => AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA,
+            "ED" => AuthenticatorDataFlags::EXTENSION_DATA,
+            other => panic!("unknown flag {}", other),
+        })
+        .collect()
+}
+
+/// Parse one of a vector's authenticator data, checking what both kinds share
+fn parse(failures: &mut Failures, name: &str, rp_id_hash: &[u8], ceremony: &Value) -> Option<AuthenticatorData> {
+    let expected = &ceremony["expected"];
+    let auth_data = match parse_authenticator_data(&decode(&ceremony["authenticatorData"])) {
+        Ok(auth_data) => auth_data,
+        Err(error) => {
+            failures.fail(name, format!("authenticator data does not parse: {}", error));
+            return None;
+        }
+    };
+    failures.check(name, "rpIdHash", rp_id_hash, &auth_data.rp_id_hash[..]);
+    failures.check(name, "flags", flags(&expected["flags"]), auth_data.flags);
+    failures.check(
+        name,
+        "signCount",
+        expected["signCount"].as_u64().map(|count| count as u32),
+        Some(auth_data.sign_count),
+    );
+    Some(auth_data)
+}
+
+/// The registered key, or `None` after reporting why there is none
+fn registered_key(failures: &mut Failures, name: &str, rp_id_hash: &[u8], vector: &Value) -> Option<CoseKey> {
+    let registration = &vector["registration"];
+    let expected = &registration["expected"];
+    let auth_data = parse(failures, name, rp_id_hash, registration)?;
+
+    failures.check(
+        name,
+        "aaguid",
+        expected["aaguid"].as_str().map(String::from),
+        auth_data.aaguid_string(),
+    );
+    failures.check(
+        name,
+        "credentialId",
+        Some(decode(&expected["credentialId"])),
+        auth_data.credential_id,
+    );
+    let Some(cose_key) = auth_data.cose_key else {
+        failures.fail(name, "registration has no credential public key".to_string());
+        return None;
+    };
+
+    let encoded = decode(&registration["credentialPublicKey"]);
+    failures.check(name, "credentialPublicKey", &encoded, &cose_key.encoded);
+    match parse_cose_key(&encoded) {
+        Ok(parsed) => failures.check(name, "parse_cose_key", &cose_key, &parsed),
+        Err(error) => failures.fail(name, format!("credentialPublicKey does not parse: {}", error)),
+    }
+    failures.check(
+        name,
+        "algorithm",
+        expected["algorithm"].as_i64(),
+        Some(cose_key.algorithm),
+    );
+    let public_key = &expected["publicKey"];
+    match &cose_key.parameters {
+        CoseKeyParameters::Ec2 { x, y } => {
+            failures.check(name, "publicKey.x", decode(&public_key["x"]), x.to_vec());
+            failures.check(name, "publicKey.y", decode(&public_key["y"]), y.to_vec());
+        }
+        CoseKeyParameters::Rsa { n, e } => {
+            failures.check(name, "publicKey.n", &decode(&public_key["n"]), n);
+            failures.check(name, "publicKey.e", &decode(&public_key["e"]), e);
+        }
+    }
+    Some(cose_key)
+}
+
+#[test]
+fn the_fixture_covers_each_algorithm_and_kind_of_credential() {
+    let fixture = fixture();
+    let vectors = vectors(&fixture);
+    assert!(vectors.len() >= 20, "only {} vectors", vectors.len());
+
+    let count = |pattern: &dyn Fn(&Value) -> bool| vectors.iter().filter(|vector| pattern(vector)).count();
+    let algorithm = |vector: &Value| vector["registration"]["expected"]["algorithm"].as_i64();
+    let assertion_flags = |vector: &Value| flags(&vector["assertion"]["expected"]["flags"]);
+    assert!(count(&|vector| algorithm(vector) == Some(-7)) > 0, "no ES256 vectors");
+    assert!(count(&|vector| algorithm(vector) == Some(-257)) > 0, "no RS256 vectors");
+    assert!(
+        count(&|vector| assertion_flags(vector) == AuthenticatorDataFlags::USER_PRESENT) > 0,
+        "no vectors with only user presence"
+    );
+    assert!(
+        count(&|vector| assertion_flags(vector).contains(AuthenticatorDataFlags::USER_VERIFIED)) > 0,
+        "no user verified vectors"
+    );
+    assert!(
+        count(&|vector| !vector["assertion"]["userHandle"].is_null()) > 0,
+        "no resident key vectors"
+    );
+    assert!(
+        count(&|vector| vector["assertion"]["expected"]["signatureValid"] == false) > 0,
+        "no vectors that must fail to verify"
+    );
+}
+
+#[test]
+fn registrations_parse_to_the_expected_credential() {
+    let fixture = fixture();
+    let rp_id_hash = Sha256::digest(str_field(&fixture["rpId"]).as_bytes());
+    let mut failures = Failures::default();
+    for vector in vectors(&fixture) {
+        registered_key(&mut failures, str_field(&vector["name"]), &rp_id_hash, vector);
+    }
+    assert!(failures.0.is_empty(), "\n{}", failures.0.join("\n"));
+}
+
+#[test]
+fn assertions_parse_to_the_expected_authenticator_data() {
+    let fixture = fixture();
+    let origin = str_field(&fixture["origin"]);
+    let rp_id_hash = Sha256::digest(str_field(&fixture["rpId"]).as_bytes());
+    let mut failures = Failures::default();
+    for vector in vectors(&fixture) {
+        let name = str_field(&vector["name"]);
+        let assertion = &vector["assertion"];
+        let expected = &assertion["expected"];
+        if let Some(auth_data) = parse(&mut failures, name, &rp_id_hash, assertion) {
+            // Only registrations carry a credential
+            failures.check(name, "credentialId", None, auth_data.credential_id);
+        }
+
+        // The client data is serialized the way ClientDataJson serializes it
+        let client_data = ClientDataJson::new_get(str_field(&expected["challenge"]), origin)
+            .cross_origin(expected["crossOrigin"].as_bool().expect("crossOrigin is not a boolean"));
+        failures.check(
+            name,
+            "clientDataJSON",
+            String::from_utf8(client_data.to_json_bytes()).unwrap(),
+            String::from_utf8(decode(&assertion["clientDataJSON"])).unwrap_or_default(),
+        );
+    }
+    assert!(failures.0.is_empty(), "\n{}", failures.0.join("\n"));
+}
+
+#[test]
+fn signatures_verify_only_when_expected_to() {
+    let fixture = fixture();
+    let rp_id_hash = Sha256::digest(str_field(&fixture["rpId"]).as_bytes());
+    let mut failures = Failures::default();
+    for vector in vectors(&fixture) {
+        let name = str_field(&vector["name"]);
+        let Some(public_key) = registered_key(&mut failures, name, &rp_id_hash, vector) else {
+            continue;
+        };
+        let assertion = &vector["assertion"];
+        let verified = verify_signature(
+            &public_key,
+            &decode(&assertion["authenticatorData"]),
+            &decode(&assertion["clientDataJSON"]),
+            &decode(&assertion["signature"]),
+        );
+        failures.check(
+            name,
+            "signatureValid",
+            assertion["expected"]["signatureValid"].as_bool(),
+            Some(verified.is_ok()),
+        );
+    }
+    assert!(failures.0.is_empty(), "\n{}", failures.0.join("\n"));
+}
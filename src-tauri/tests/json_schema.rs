@@ -0,0 +1,71 @@
+// src-tauri/tests/json_schema.rs
+
+//! The checked-in WebAuthn schema kept in step with the Rust types.
+//!
+//! Run with `--features schema-export`; set `UPDATE_WEBAUTHN_SCHEMA=1` to
+//! rewrite the schema after changing a type.
+
+#![cfg(feature = "schema-export")]
+
+use std::fs;
+use std::path::Path;
+
+use app_lib::webauthn_json_schema;
+
+/// Where the frontend reads the schema from
+const SCHEMA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../src/core/types/webauthn-schema.json");
+
+#[test]
+fn the_checked_in_schema_is_up_to_date() {
+    let schema = webauthn_json_schema().expect("failed to export the schema") + "\n";
+    if std::env::var("UPDATE_WEBAUTHN_SCHEMA").is_ok_and(|value| value == "1") {
+        fs::write(SCHEMA_PATH, &schema).expect("failed to write the schema");
+        return;
+    }
+
+    let checked_in = fs::read_to_string(Path::new(SCHEMA_PATH)).unwrap_or_default();
+    assert!(
+        checked_in == schema,
+        "{} is stale; run `UPDATE_WEBAUTHN_SCHEMA=1 cargo test --features schema-export --test json_schema`",
+        SCHEMA_PATH
+    );
+}
+
+#[test]
+fn every_command_type_is_defined() {
+    let schema: serde_json::Value =
+        serde_json::from_str(&webauthn_json_schema().expect("failed to export the schema")).unwrap();
+    for name in [
+        "SiteAuthConfig",
+        "CredentialEntry",
+        "AuthenticationResult",
+        "RegistrationResult",
+        "WebAuthnError",
+        "SiteCredentialSummary",
+        "LoginHint",
+        "SiteAuthStatus",
+        "HealthReport",
+        "RateLimitEntry",
+        "WipeReport",
+        "PurgeReport",
+        "SyncRequest",
+        "SyncOffer",
+        "SyncAcceptResult",
+        "ExportResult",
+        "ImportResult",
+        "MigrationReport",
+        "MigrationError",
+    ] {
+        assert!(schema["definitions"][name].is_object(), "{} is missing from the schema", name);
+    }
+    // Timestamps are RFC 3339 strings on the wire
+    assert_eq!(
+        schema["definitions"]["CredentialEntry"]["properties"]["registeredAt"]["type"],
+        "string"
+    );
+    assert_eq!(schema["definitions"]["SyncRequest"]["properties"]["expiresAt"]["type"], "string");
+    assert_eq!(
+        schema["definitions"]["RegistrationResult"]["properties"]["authConfig"]["anyOf"][0]["$ref"],
+        "#/definitions/SiteAuthConfig"
+    );
+}
@@ -0,0 +1,91 @@
+// src-tauri/tests/log_config.rs
+
+//! `WebAuthnLogConfig` filtering log lines by their operation, and the
+//! `OperationContext` that says which operation a line belongs to.
+
+use app_lib::{OperationContext, WebAuthnConfig, WebAuthnLogConfig};
+use log::{Level, LevelFilter};
+
+#[test]
+fn operations_log_at_info_and_failures_at_error_by_default() {
+    let config = WebAuthnLogConfig::default();
+    assert_eq!(config.authentication_level, LevelFilter::Info);
+    assert_eq!(config.registration_level, LevelFilter::Info);
+    assert_eq!(config.challenge_level, LevelFilter::Info);
+    assert_eq!(config.error_level, LevelFilter::Error);
+
+    let authentication = Some(OperationContext::Authentication);
+    assert!(config.enabled(authentication, Level::Info, false));
+    assert!(config.enabled(authentication, Level::Warn, true));
+    assert!(!config.enabled(authentication, Level::Debug, false));
+    // Lines of other operations are not filtered
+    assert!(config.enabled(None, Level::Trace, false));
+}
+
+#[test]
+fn each_operation_has_its_own_level() {
+    let config = WebAuthnLogConfig {
+        authentication_level: LevelFilter::Debug,
+        registration_level: LevelFilter::Off,
+        ..Default::default()
+    };
+    assert!(config.enabled(Some(OperationContext::Authentication), Level::Debug, false));
+    assert!(!config.enabled(Some(OperationContext::Registration), Level::Error, false));
+    // Failures at least as severe as error_level get through a silenced operation
+    assert!(config.enabled(Some(OperationContext::Registration), Level::Error, true));
+    assert!(!config.enabled(Some(OperationContext::Registration), Level::Warn, true));
+}
+
+#[test]
+fn challenges_log_at_debug_in_development_builds() {
+    let config = WebAuthnLogConfig {
+        challenge_level: LevelFilter::Off,
+        ..Default::default()
+    };
+    let expected = if cfg!(debug_assertions) {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Off
+    };
+    assert_eq!(config.level_for(OperationContext::Challenge), expected);
+    assert!(!config.enabled(Some(OperationContext::Challenge), Level::Trace, false));
+}
+
+#[test]
+fn contexts_nest_and_end_with_their_scope() {
+    assert_eq!(OperationContext::current(), None);
+    OperationContext::Authentication.scope(|| {
+        assert_eq!(OperationContext::current(), Some(OperationContext::Authentication));
+        OperationContext::Challenge.scope(|| {
+            assert_eq!(OperationContext::current(), Some(OperationContext::Challenge));
+        });
+        assert_eq!(OperationContext::current(), Some(OperationContext::Authentication));
+    });
+    assert_eq!(OperationContext::current(), None);
+
+    // Another thread starts outside any context
+    OperationContext::Registration.scope(|| {
+        let elsewhere = std::thread::spawn(OperationContext::current).join().unwrap();
+        assert_eq!(elsewhere, None);
+    });
+}
+
+#[test]
+fn the_log_table_of_the_config_file_sets_the_levels() {
+    let config: WebAuthnConfig = toml::from_str(
+        r#"
+        [log]
+        authentication_level = "debug"
+        error_level = "WARN"
+        "#,
+    )
+    .unwrap();
+    let log = config.log.unwrap();
+    assert_eq!(log.authentication_level, LevelFilter::Debug);
+    assert_eq!(log.error_level, LevelFilter::Warn);
+    // Keys the table leaves out keep their defaults
+    assert_eq!(log.registration_level, LevelFilter::Info);
+
+    assert!(toml::from_str::<WebAuthnConfig>("[log]\nsigning_level = \"debug\"").is_err());
+    assert!(toml::from_str::<WebAuthnConfig>("[log]\nerror_level = \"loud\"").is_err());
+}
@@ -0,0 +1,39 @@
+// src-tauri/tests/panic_hook.rs
+
+//! The plugin's panic hook recording panics in the audit log.
+//!
+//! The hook is process-wide and installed once, by the first app that sets up
+//! the plugin, so the test needs the binary to itself.
+
+use std::thread;
+
+use app_lib::{verify_audit_log, CredentialStore, MockPlatform, WebAuthnBuilder};
+use serde_json::Value;
+use tauri::test::{mock_builder, mock_context, noop_assets};
+
+#[test]
+fn panics_are_recorded_in_the_audit_log() {
+    let data_dir = tempfile::tempdir().expect("failed to create the credential store directory");
+    let _app = mock_builder()
+        .plugin(WebAuthnBuilder::new().mock_platform(MockPlatform::new()).build())
+        .manage(CredentialStore::with_key(data_dir.path().to_path_buf(), [7; 32]))
+        .build(mock_context(noop_assets()))
+        .expect("failed to build the test app");
+
+    let panicked = thread::Builder::new()
+        .name("ceremony".to_string())
+        .spawn(|| panic!("authenticator went away"))
+        .unwrap()
+        .join();
+    assert!(panicked.is_err());
+
+    let contents = std::fs::read_to_string(data_dir.path().join("webauthn_audit.jsonl"))
+        .expect("the panic was not recorded");
+    let record: Value = serde_json::from_str(contents.lines().next().unwrap()).unwrap();
+    assert_eq!(record["event"], "panic");
+    assert_eq!(record["thread"], "ceremony");
+    assert_eq!(record["message"], "authenticator went away");
+    assert!(record["location"].as_str().unwrap().contains("panic_hook.rs:"));
+    let store = CredentialStore::with_key(data_dir.path().to_path_buf(), [7; 32]);
+    assert_eq!(verify_audit_log(&store).expect("the audit log did not verify"), 1);
+}
@@ -0,0 +1,49 @@
+// src-tauri/tests/pin_cache.rs
+
+//! `PinCache` keeping security key PINs per key until they expire or are evicted.
+
+use std::thread;
+use std::time::Duration;
+
+use app_lib::{PinCache, SecretPin};
+use zeroize::Zeroizing;
+
+fn pin(value: &str) -> SecretPin {
+    SecretPin::new(Zeroizing::new(value.to_string()))
+}
+
+#[test]
+fn pins_are_kept_per_key_until_evicted() {
+    let cache = PinCache::new(Duration::from_secs(60));
+    assert!(cache.get("/dev/hidraw0").is_none());
+
+    cache.insert("/dev/hidraw0", pin("1234"));
+    cache.insert("/dev/hidraw1", pin("5678"));
+    assert_eq!(cache.get("/dev/hidraw0").unwrap().expose(), "1234");
+    assert_eq!(cache.get("/dev/hidraw1").unwrap().expose(), "5678");
+
+    cache.evict("/dev/hidraw0");
+    assert!(cache.get("/dev/hidraw0").is_none());
+    assert!(cache.get("/dev/hidraw1").is_some());
+
+    assert_eq!(cache.clear(), 1);
+    assert!(cache.get("/dev/hidraw1").is_none());
+}
+
+#[test]
+fn pins_expire() {
+    let cache = PinCache::new(Duration::from_millis(50));
+    cache.insert("/dev/hidraw0", pin("1234"));
+    assert!(cache.get("/dev/hidraw0").is_some());
+    thread::sleep(Duration::from_millis(100));
+    assert!(cache.get("/dev/hidraw0").is_none());
+
+    let disabled = PinCache::new(Duration::ZERO);
+    disabled.insert("/dev/hidraw0", pin("1234"));
+    assert!(disabled.get("/dev/hidraw0").is_none());
+}
+
+#[test]
+fn pins_are_not_debug_printed() {
+    assert_eq!(format!("{:?}", pin("1234")), "SecretPin([REDACTED])");
+}
@@ -0,0 +1,59 @@
+// src-tauri/tests/qr_code.rs
+
+//! `QrCode` payloads surviving a round trip through the rendered PNG and a
+//! QR decoder, from a short link to the most a sync offer can carry.
+
+use app_lib::{QrCode, WebAuthnError};
+
+/// Decode the PNG `QrCode::render_png` drew back into its payload
+fn decode(png: &[u8]) -> Vec<u8> {
+    let mut reader = png::Decoder::new(png).read_info().expect("not a PNG");
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut pixels).expect("the PNG does not decode");
+    assert_eq!(frame.color_type, png::ColorType::Grayscale);
+    let (width, height) = (frame.width as usize, frame.height as usize);
+
+    let mut image = rqrr::PreparedImage::prepare_from_greyscale(width, height, |x, y| pixels[y * width + x]);
+    let grids = image.detect_grids();
+    assert_eq!(grids.len(), 1, "expected one QR code in the PNG");
+    let mut payload = Vec::new();
+    grids[0].decode_to(&mut payload).expect("the QR code does not decode");
+    payload
+}
+
+/// A payload of `len` bytes mixing the characters links and offers use
+fn payload(len: usize) -> Vec<u8> {
+    let alphabet = b"sparktype-sync:2:ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+    (0..len).map(|at| alphabet[(at * 7 + at / 3) % alphabet.len()]).collect()
+}
+
+#[test]
+fn payloads_decode_to_what_was_encoded() {
+    for len in [1, 17, 64, 200, 500, 1000, 1500, 2331] {
+        let data = payload(len);
+        let code = QrCode::encode(&data).unwrap_or_else(|error| panic!("{} bytes: {}", len, error));
+        assert_eq!(decode(&code.render_png().expect("failed to render")), data, "{} bytes", len);
+    }
+}
+
+#[test]
+fn binary_payloads_decode_to_what_was_encoded() {
+    let data: Vec<u8> = (0..=255).collect();
+    let code = QrCode::encode(&data).expect("failed to encode");
+    assert_eq!(decode(&code.render_png().expect("failed to render")), data);
+}
+
+#[test]
+fn the_finder_patterns_are_in_the_corners() {
+    let code = QrCode::encode(b"https://example.com").expect("failed to encode");
+    for (x, y) in [(0, 0), (6, 0), (0, 6), (6, 6), (2, 2), (4, 4)] {
+        assert!(code.is_dark(x, y), "module ({}, {}) of the top-left finder is light", x, y);
+    }
+    assert!(!code.is_dark(7, 7), "the separator of the top-left finder is dark");
+}
+
+#[test]
+fn payloads_too_long_for_any_version_are_refused() {
+    let error = QrCode::encode(&[0x80; 4000]).err().expect("4000 bytes were encoded");
+    assert!(matches!(error, WebAuthnError::InvalidInput(_)), "failed with {}", error);
+}
@@ -0,0 +1,31 @@
+// src-tauri/tests/redacted.rs
+
+//! `Redacted` keeping credential IDs out of the logs.
+
+use app_lib::Redacted;
+
+#[test]
+fn long_values_keep_their_first_8_and_last_4_characters() {
+    let credential_id = "AbCdEfGhIjKlMnOpQrStUvwxyz";
+    assert_eq!(Redacted(credential_id).to_string(), "AbCdEfGh****wxyz");
+    assert_eq!(format!("{:?}", Redacted(credential_id)), "AbCdEfGh****wxyz");
+    assert_eq!(Redacted(&credential_id.to_string()).to_string(), "AbCdEfGh****wxyz");
+}
+
+#[test]
+fn values_shorter_than_12_characters_are_hidden_entirely() {
+    assert_eq!(Redacted("").to_string(), "****");
+    assert_eq!(Redacted("AbCdEfGhIjK").to_string(), "****");
+    assert_eq!(format!("{:?}", Redacted("AbCdEfGhIjK")), "****");
+}
+
+#[test]
+fn values_of_exactly_12_characters_are_split_into_prefix_and_suffix() {
+    assert_eq!(Redacted("AbCdEfGhwxyz").to_string(), "AbCdEfGh****wxyz");
+}
+
+#[test]
+fn characters_are_counted_rather_than_bytes() {
+    assert_eq!(Redacted("ééééééééxyzw").to_string(), "éééééééé****xyzw");
+    assert_eq!(Redacted("éééééééééé").to_string(), "****");
+}
@@ -0,0 +1,79 @@
+// src-tauri/tests/site_id.rs
+
+//! `validate_site_id` accepting domain names and site slugs, and nothing else.
+
+use app_lib::{validate_site_id, WebAuthnError};
+use proptest::prelude::*;
+
+/// A label of the characters site IDs may hold, without dots
+fn label() -> impl Strategy<Value = String> {
+    "[A-Za-z0-9_-]{1,63}"
+}
+
+/// A valid site ID: labels joined by single dots, at most 253 characters
+fn site_id() -> impl Strategy<Value = String> {
+    prop::collection::vec(label(), 1..5)
+        .prop_map(|labels| labels.join("."))
+        .prop_filter("longer than 253 characters", |id| id.len() <= 253)
+}
+
+fn is_rejected(id: &str) -> bool {
+    matches!(validate_site_id(id), Err(WebAuthnError::InvalidInput(_)))
+}
+
+proptest! {
+    #[test]
+    fn labels_joined_by_dots_are_accepted(id in site_id()) {
+        prop_assert!(validate_site_id(&id).is_ok(), "{:?} was rejected", id);
+    }
+
+    #[test]
+    fn ids_of_up_to_253_characters_are_accepted(id in "[a-z0-9]{1,253}") {
+        prop_assert!(validate_site_id(&id).is_ok(), "{} characters were rejected", id.len());
+    }
+
+    #[test]
+    fn ids_over_253_characters_are_rejected(id in "[a-z0-9]{254,400}") {
+        prop_assert!(is_rejected(&id), "{} characters were accepted", id.len());
+    }
+
+    #[test]
+    fn dots_at_either_end_are_rejected(id in site_id()) {
+        prop_assert!(is_rejected(&format!(".{}", id)), "leading dot accepted on {:?}", id);
+        prop_assert!(is_rejected(&format!("{}.", id)), "trailing dot accepted on {:?}", id);
+    }
+
+    #[test]
+    fn consecutive_dots_are_rejected(first in label(), second in label(), dots in 2..5usize) {
+        let id = format!("{}{}{}", first, ".".repeat(dots), second);
+        prop_assert!(is_rejected(&id), "{:?} was accepted", id);
+    }
+
+    #[test]
+    fn urls_are_rejected(scheme in "[a-z][a-z0-9+.-]{0,9}", rest in "[ -~]{0,40}") {
+        let id = format!("{}://{}", scheme, rest);
+        prop_assert!(is_rejected(&id), "{:?} was accepted", id);
+    }
+
+    #[test]
+    fn characters_outside_labels_are_rejected(
+        id in site_id(),
+        c in any::<char>().prop_filter("allowed in site IDs", |c| {
+            !(c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))
+        }),
+        at in any::<prop::sample::Index>(),
+    ) {
+        let mut id = id;
+        id.insert(at.index(id.len() + 1), c);
+        prop_assert!(is_rejected(&id), "{:?} was accepted", id);
+    }
+}
+
+#[test]
+fn the_limit_is_253_characters() {
+    let longest = format!("{}.{}.{}.{}", "a".repeat(63), "b".repeat(63), "c".repeat(63), "d".repeat(61));
+    assert_eq!(longest.len(), 253);
+    assert!(validate_site_id(&longest).is_ok());
+    assert!(is_rejected(&format!("{}d", longest)));
+    assert!(is_rejected(""));
+}
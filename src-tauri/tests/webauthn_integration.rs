@@ -0,0 +1,2014 @@
+// src-tauri/tests/webauthn_integration.rs
+
+//! The passkey commands end to end on a mock app, with `MockPlatform` standing
+//! in for the authenticator.
+//!
+//! Every test stands up its own app, with an empty credential store in a
+//! temporary directory sealed with a fixed key, and calls the commands through
+//! the IPC handler the way the frontend does.
+
+use std::thread;
+use std::time::Duration;
+
+use app_lib::{
+    verify_audit_log, CapabilityTier, CredentialStore, MockPlatform, RateLimitConfig, Redacted, RetryConfig,
+    SiteAuthConfig, WebAuthnBuilder, WebAuthnConfig, WebAuthnError,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::ipc::{CallbackFn, InvokeBody};
+use tauri::test::{get_ipc_response, mock_builder, mock_context, noop_assets, MockRuntime, INVOKE_KEY};
+use tauri::webview::InvokeRequest;
+use tauri::{App, Manager, WebviewWindow, WebviewWindowBuilder};
+use tempfile::TempDir;
+
+/// A mock app running the commands, and the window they are invoked from
+struct TestApp {
+    _app: App<MockRuntime>,
+    webview: WebviewWindow<MockRuntime>,
+    _data_dir: TempDir,
+}
+
+/// Fluent setup for a `TestApp`; anything not configured uses the app's defaults
+struct TestAppBuilder {
+    webauthn: WebAuthnBuilder<MockRuntime>,
+    window_label: &'static str,
+}
+
+impl TestAppBuilder {
+    fn platform(mut self, platform: MockPlatform) -> Self {
+        self.webauthn = self.webauthn.mock_platform(platform);
+        self
+    }
+
+    fn challenge_ttl(mut self, ttl: Duration) -> Self {
+        self.webauthn = self.webauthn.challenge_ttl(ttl);
+        self
+    }
+
+    fn rate_limit(mut self, max_attempts: usize, window: Duration) -> Self {
+        self.webauthn = self.webauthn.rate_limit(RateLimitConfig { max_attempts, window });
+        self
+    }
+
+    fn session_ttl(mut self, ttl: Duration) -> Self {
+        self.webauthn = self.webauthn.session_ttl(ttl);
+        self
+    }
+
+    fn idle_check_interval(mut self, interval: Duration) -> Self {
+        self.webauthn = self.webauthn.idle_check_interval(interval);
+        self
+    }
+
+    fn sync_offer_ttl(mut self, ttl: Duration) -> Self {
+        self.webauthn = self.webauthn.sync_offer_ttl(ttl);
+        self
+    }
+
+    /// Label the test window `label` instead of "main"
+    fn window_label(mut self, label: &'static str) -> Self {
+        self.window_label = label;
+        self
+    }
+
+    /// Grant the test window, labelled "main", the commands of `tier`
+    fn main_window_capability(mut self, tier: CapabilityTier) -> Self {
+        self.webauthn = self.webauthn.window_capability("main", tier);
+        self
+    }
+
+    /// Grant the window labelled `label`, which the test never opens, the commands of `tier`
+    fn window_capability(mut self, label: &str, tier: CapabilityTier) -> Self {
+        self.webauthn = self.webauthn.window_capability(label, tier);
+        self
+    }
+
+    fn max_credentials_per_site(mut self, max: usize) -> Self {
+        self.webauthn = self.webauthn.max_credentials_per_site(max);
+        self
+    }
+
+    fn lockout_threshold(mut self, threshold: u32) -> Self {
+        self.webauthn = self.webauthn.lockout_threshold(threshold);
+        self
+    }
+
+    /// Apply settings as if read from `webauthn.toml`
+    fn config(mut self, config: WebAuthnConfig) -> Self {
+        self.webauthn = self.webauthn.config(config);
+        self
+    }
+
+    fn build(self) -> TestApp {
+        let data_dir = tempfile::tempdir().expect("failed to create the credential store directory");
+
+        let app = app_lib::with_commands(mock_builder())
+            .plugin(self.webauthn.build())
+            .manage(CredentialStore::with_key(data_dir.path().to_path_buf(), [7; 32]))
+            .build(mock_context(noop_assets()))
+            .expect("failed to build the test app");
+        let webview = WebviewWindowBuilder::new(&app, self.window_label, Default::default())
+            .build()
+            .expect("failed to open the test window");
+
+        TestApp {
+            _app: app,
+            webview,
+            _data_dir: data_dir,
+        }
+    }
+}
+
+impl TestApp {
+    fn builder() -> TestAppBuilder {
+        TestAppBuilder {
+            webauthn: WebAuthnBuilder::new().mock_platform(MockPlatform::new()),
+            window_label: "main",
+        }
+    }
+
+    /// Invoke a command, returning its JSON result or the JSON of its error
+    fn invoke(&self, cmd: &str, args: Value) -> Result<Value, Value> {
+        let request = InvokeRequest {
+            cmd: cmd.to_string(),
+            callback: CallbackFn(0),
+            error: CallbackFn(1),
+            url: "http://tauri.localhost".parse().unwrap(),
+            body: InvokeBody::Json(args),
+            headers: Default::default(),
+            invoke_key: INVOKE_KEY.to_string(),
+        };
+        get_ipc_response(&self.webview, request)
+            .map(|body| body.deserialize().expect("command returned invalid JSON"))
+    }
+
+    /// Store a site's config as the backend would, including passkeys
+    /// `save_site_credential` refuses to add
+    fn seed_site(&self, site_id: &str, auth_config: &Value) {
+        let auth_config: SiteAuthConfig = serde_json::from_value(auth_config.clone()).expect("invalid site config");
+        self.webview
+            .state::<CredentialStore>()
+            .save_credential(site_id, &auth_config)
+            .expect("failed to store the site");
+    }
+
+    fn register(&self, site_id: &str) -> Result<Value, Value> {
+        self.invoke(
+            "register_passkey",
+            json!({
+                "siteId": site_id,
+                "siteName": "My Blog",
+                "userDisplayName": "Alice",
+            }),
+        )
+    }
+
+    /// Authenticate with a freshly issued challenge
+    fn authenticate(&self, site_id: &str, auth_config: &Value) -> Result<Value, Value> {
+        let challenge = self.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+        self.authenticate_with(site_id, auth_config, &challenge)
+    }
+
+    fn authenticate_with(&self, site_id: &str, auth_config: &Value, challenge: &Value) -> Result<Value, Value> {
+        self.invoke(
+            "authenticate_passkey",
+            json!({
+                "siteId": site_id,
+                "authConfig": auth_config,
+                "challenge": challenge,
+            }),
+        )
+    }
+}
+
+/// Assert an object has exactly these keys
+fn assert_keys(value: &Value, expected: &[&str]) {
+    let mut keys: Vec<&str> = value
+        .as_object()
+        .unwrap_or_else(|| panic!("expected an object, got {}", value))
+        .keys()
+        .map(String::as_str)
+        .collect();
+    keys.sort_unstable();
+    let mut expected = expected.to_vec();
+    expected.sort_unstable();
+    assert_eq!(keys, expected);
+}
+
+fn error_json(error: WebAuthnError) -> Value {
+    serde_json::to_value(error).unwrap()
+}
+
+/// A site configuration with one credential the mock will assert with
+fn auth_config_with_credential(credential_id: &str) -> Value {
+    json!({
+        "credentials": [{
+            "credentialId": credential_id,
+            "publicKey": "bW9jay1wdWJsaWMta2V5",
+            "registeredAt": "2025-01-01T00:00:00.000Z",
+            "deviceName": null,
+        }],
+        "requiresAuth": true,
+        "userDisplayName": null,
+        "userId": "dXNlcg",
+    })
+}
+
+#[test]
+fn registration_succeeds() {
+    let app = TestApp::builder().build();
+
+    let result = app.register("site-1").expect("registration failed");
+
+    assert_keys(
+        &result,
+        &[
+            "success",
+            "authConfig",
+            "aaguid",
+            "signCount",
+            "authenticatorAttachment",
+            "isResident",
+            "backupEligible",
+            "icloudSynced",
+            "wasImported",
+            "operationId",
+        ],
+    );
+    assert_eq!(result["success"], true);
+    assert_eq!(result["aaguid"], Value::Null);
+    assert_eq!(result["signCount"], 0);
+    assert_eq!(result["authenticatorAttachment"], "platform");
+    assert_eq!(result["isResident"], true);
+    assert_eq!(result["backupEligible"], false);
+    let icloud_synced = if cfg!(any(target_os = "macos", target_os = "ios")) {
+        json!(false)
+    } else {
+        Value::Null
+    };
+    assert_eq!(result["icloudSynced"], icloud_synced);
+    assert_eq!(result["wasImported"], false);
+    assert!(result["operationId"].as_str().is_some_and(|id| !id.is_empty()));
+
+    let auth_config = &result["authConfig"];
+    assert_keys(auth_config, &["credentials", "requiresAuth", "userDisplayName", "userId", "siteName"]);
+    assert_eq!(auth_config["siteName"], "My Blog");
+    assert_eq!(auth_config["requiresAuth"], true);
+    assert_eq!(auth_config["userDisplayName"], "Alice");
+    assert!(auth_config["userId"].as_str().is_some_and(|id| !id.is_empty()));
+
+    let credentials = auth_config["credentials"].as_array().unwrap();
+    assert_eq!(credentials.len(), 1);
+    assert_keys(
+        &credentials[0],
+        &[
+            "credentialId",
+            "publicKey",
+            "registeredAt",
+            "deviceName",
+            "lastUsedAt",
+            "signCount",
+            "failureCount",
+            "locked",
+        ],
+    );
+    assert_eq!(credentials[0]["failureCount"], 0);
+    assert_eq!(credentials[0]["locked"], false);
+    // Labelled with the test machine's name, as no deviceName was given
+    assert!(credentials[0]["deviceName"].as_str().is_some_and(|name| !name.is_empty() && name.chars().count() <= 64));
+    assert_eq!(credentials[0]["lastUsedAt"], Value::Null);
+    assert_eq!(credentials[0]["signCount"], 0);
+}
+
+#[test]
+fn result_keys_are_camel_case() {
+    let app = TestApp::builder().build();
+    app.register("site-1").expect("registration failed");
+
+    let capabilities = app
+        .invoke("get_webauthn_capabilities", json!({}))
+        .expect("failed to get the capabilities");
+    assert_keys(
+        &capabilities,
+        &[
+            "platformAuthenticator",
+            "roamingAuthenticator",
+            "residentKeySupport",
+            "userVerificationSupport",
+            "hardwareBacked",
+            "minOsVersionRequired",
+        ],
+    );
+    assert_eq!(capabilities["minOsVersionRequired"], Value::Null);
+
+    let status = app
+        .invoke("verify_stored_credential", json!({ "siteId": "site-1" }))
+        .expect("failed to verify the stored credential");
+    assert_keys(&status, &["existsLocally", "platformAvailable", "lastVerifiedAt"]);
+}
+
+#[test]
+fn authentication_succeeds() {
+    let app = TestApp::builder().build();
+    let registration = app.register("site-1").expect("registration failed");
+    let auth_config = &registration["authConfig"];
+
+    let result = app.authenticate("site-1", auth_config).expect("authentication failed");
+
+    assert_keys(
+        &result,
+        &[
+            "success",
+            "credentialId",
+            "siteName",
+            "signCount",
+            "possibleCloneDetected",
+            "authenticatorAttachment",
+            "timings",
+            "operationId",
+            "fromCache",
+            "attempts",
+        ],
+    );
+    assert_eq!(result["success"], true);
+    assert_eq!(result["fromCache"], false);
+    assert_eq!(result["attempts"], 1);
+    assert_eq!(result["credentialId"], auth_config["credentials"][0]["credentialId"]);
+    assert_eq!(result["siteName"], "My Blog");
+    assert_eq!(result["signCount"], 0);
+    assert_eq!(result["possibleCloneDetected"], false);
+    assert_eq!(result["authenticatorAttachment"], "platform");
+    assert_ne!(result["operationId"], registration["operationId"]);
+    // Tests are debug builds, which always report timings
+    assert_keys(
+        &result["timings"],
+        &["challengeGenUs", "platformApiMs", "sessionWriteUs", "totalMs"],
+    );
+
+    let stored = app
+        .invoke("load_site_credential", json!({ "siteId": "site-1" }))
+        .expect("failed to load the site");
+    assert!(stored["credentials"][0]["lastUsedAt"].is_string());
+}
+
+#[test]
+fn public_sites_skip_webauthn() {
+    let app = TestApp::builder()
+        .platform(MockPlatform::new().fail_with(WebAuthnError::UserCancelled))
+        .build();
+    let mut public_config = auth_config_with_credential("Y3JlZC0x");
+    public_config["requiresAuth"] = json!(false);
+
+    // No challenge was issued and the platform would fail
+    let result = app
+        .authenticate_with("site-1", &public_config, &json!("bm90LWEtY2hhbGxlbmdl"))
+        .expect("authentication failed");
+    assert_eq!(result["success"], true);
+    assert_eq!(result["credentialId"], Value::Null);
+    // The store holds nothing for the site
+    assert_eq!(result["siteName"], Value::Null);
+
+    app.seed_site("site-1", &public_config);
+    let error = app.register("site-1").expect_err("registration succeeded");
+    assert_eq!(error["code"], "InvalidInput");
+}
+
+#[test]
+fn public_sites_are_upgraded_to_require_auth() {
+    let app = TestApp::builder().build();
+    let upgrade = || {
+        app.invoke(
+            "upgrade_site_to_passkey",
+            json!({ "siteId": "site-1", "siteName": "My Blog", "userDisplayName": "Alice" }),
+        )
+    };
+    let error = upgrade().expect_err("upgraded a site with no stored config");
+    assert_eq!(error["code"], "InvalidInput");
+
+    // Public sites are stored without passkeys
+    app.invoke(
+        "save_site_credential",
+        json!({ "siteId": "site-1", "authConfig": { "credentials": [], "requiresAuth": false } }),
+    )
+    .expect("failed to save the public site");
+    let result = upgrade().expect("upgrade failed");
+    assert_eq!(result["success"], true);
+    assert_eq!(result["authConfig"]["requiresAuth"], true);
+    assert_eq!(result["authConfig"]["credentials"].as_array().unwrap().len(), 1);
+    let stored = app
+        .invoke("load_site_credential", json!({ "siteId": "site-1" }))
+        .expect("failed to load the site");
+    assert_eq!(stored, result["authConfig"]);
+
+    let error = upgrade().expect_err("upgraded a site that already requires auth");
+    assert_eq!(error["code"], "InvalidInput");
+}
+
+#[test]
+fn failed_upgrades_leave_the_stored_config_unchanged() {
+    let app = TestApp::builder()
+        .platform(MockPlatform::new().fail_with(WebAuthnError::UserCancelled))
+        .build();
+    app.seed_site("site-1", &json!({ "credentials": [], "requiresAuth": false }));
+    let before = app
+        .invoke("load_site_credential", json!({ "siteId": "site-1" }))
+        .expect("failed to load the site");
+
+    let error = app
+        .invoke("upgrade_site_to_passkey", json!({ "siteId": "site-1", "siteName": "My Blog" }))
+        .expect_err("upgrade succeeded");
+    assert_eq!(error, error_json(WebAuthnError::UserCancelled));
+    let after = app
+        .invoke("load_site_credential", json!({ "siteId": "site-1" }))
+        .expect("failed to load the site");
+    assert_eq!(after, before);
+    assert_eq!(after["requiresAuth"], false);
+}
+
+#[test]
+fn security_key_attachment_is_reported() {
+    let app = TestApp::builder().build();
+
+    let registration = app
+        .invoke(
+            "register_passkey",
+            json!({
+                "siteId": "site-1",
+                "siteName": "My Blog",
+                "authenticatorAttachment": "cross-platform",
+            }),
+        )
+        .expect("registration failed");
+    assert_eq!(registration["authenticatorAttachment"], "cross-platform");
+    // Security keys never sync through iCloud Keychain
+    assert_eq!(registration["icloudSynced"], Value::Null);
+
+    let challenge = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+    let result = app
+        .invoke(
+            "authenticate_passkey",
+            json!({
+                "siteId": "site-1",
+                "authConfig": registration["authConfig"],
+                "challenge": challenge,
+                "authenticatorAttachment": "cross-platform",
+            }),
+        )
+        .expect("authentication failed");
+    assert_eq!(result["authenticatorAttachment"], "cross-platform");
+}
+
+#[test]
+fn prf_output_is_returned_when_requested() {
+    let app = TestApp::builder().build();
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let credential_id = auth_config["credentials"][0]["credentialId"].clone();
+    let authenticate_with_prf = |prf: Value| {
+        let challenge = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+        app.invoke(
+            "authenticate_passkey",
+            json!({
+                "siteId": "site-1",
+                "authConfig": auth_config,
+                "challenge": challenge,
+                "prf": prf,
+            }),
+        )
+    };
+
+    let result = authenticate_with_prf(json!({ "eval": { "first": "c2FsdC0x" } })).expect("authentication failed");
+    // 32 bytes, base64url without padding
+    assert!(result["prfOutput"].as_str().is_some_and(|output| output.len() == 43));
+
+    let repeated = authenticate_with_prf(json!({ "eval": { "first": "c2FsdC0x" } })).expect("authentication failed");
+    assert_eq!(repeated["prfOutput"], result["prfOutput"]);
+
+    let by_credential = authenticate_with_prf(json!({
+        "eval": { "first": "c2FsdC0x" },
+        "evalByCredential": { credential_id.as_str().unwrap(): { "first": "c2FsdC0y" } },
+    }))
+    .expect("authentication failed");
+    assert_ne!(by_credential["prfOutput"], result["prfOutput"]);
+
+    let error = authenticate_with_prf(json!({
+        "eval": { "first": "c2FsdC0x" },
+        "evalByCredential": { "dW5rbm93bg": { "first": "c2FsdC0y" } },
+    }))
+    .expect_err("authentication succeeded");
+    assert_eq!(error["code"], "InvalidInput");
+}
+
+#[test]
+fn exported_config_is_imported_without_a_ceremony() {
+    let exported = TestApp::builder().build().register("site-1").expect("registration failed")["authConfig"].clone();
+    let app = TestApp::builder().build();
+    let import = |config: &Value| {
+        app.invoke(
+            "register_passkey",
+            json!({
+                "siteId": "site-1",
+                "siteName": "My Blog",
+                "existingConfig": config,
+            }),
+        )
+    };
+
+    let imported = import(&exported).expect("import failed");
+    assert_eq!(imported["wasImported"], true);
+    assert_eq!(imported["authConfig"]["credentials"], exported["credentials"]);
+    app.authenticate("site-1", &imported["authConfig"]).expect("authentication failed");
+
+    let mut malformed = exported.clone();
+    malformed["credentials"][0]["publicKey"] = json!("bm90LWNib3I");
+    let error = import(&malformed).expect_err("import succeeded");
+    assert_eq!(error["code"], "InvalidInput");
+}
+
+#[test]
+fn configs_without_optional_fields_are_imported() {
+    let exported = TestApp::builder().build().register("site-1").expect("registration failed")["authConfig"].clone();
+    let credential = &exported["credentials"][0];
+    let minimal = json!({
+        "credentials": [{
+            "credentialId": credential["credentialId"],
+            "publicKey": credential["publicKey"],
+            "registeredAt": credential["registeredAt"],
+        }],
+        "requiresAuth": true,
+    });
+    let app = TestApp::builder().build();
+
+    let imported = app
+        .invoke(
+            "register_passkey",
+            json!({ "siteId": "site-1", "siteName": "My Blog", "existingConfig": minimal }),
+        )
+        .expect("import failed");
+    let config = &imported["authConfig"];
+    assert_eq!(config["userDisplayName"], Value::Null);
+    assert_eq!(config["userId"], "");
+    assert_eq!(config["credentials"][0]["deviceName"], Value::Null);
+    assert_eq!(config["credentials"][0]["lastUsedAt"], Value::Null);
+    assert_eq!(config["credentials"][0]["signCount"], 0);
+}
+
+#[test]
+fn large_blob_is_written_then_read_back() {
+    let app = TestApp::builder().build();
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let authenticate_with = |large_blob: Value| {
+        let challenge = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+        let mut args = json!({
+            "siteId": "site-1",
+            "authConfig": auth_config,
+            "challenge": challenge,
+        });
+        args.as_object_mut().unwrap().extend(large_blob.as_object().unwrap().clone());
+        app.invoke("authenticate_passkey", args)
+    };
+
+    let written = authenticate_with(json!({ "largeBlobWrite": "c2V0dGluZ3M" })).expect("authentication failed");
+    assert!(written.get("largeBlobData").is_none());
+
+    let read = authenticate_with(json!({ "largeBlobRead": true })).expect("authentication failed");
+    assert_eq!(read["largeBlobData"], "c2V0dGluZ3M");
+
+    let error = authenticate_with(json!({ "largeBlobRead": true, "largeBlobWrite": "c2V0dGluZ3M" }))
+        .expect_err("authentication succeeded");
+    assert_eq!(error["code"], "InvalidInput");
+}
+
+#[test]
+fn out_of_range_timeouts_are_clamped() {
+    let app = TestApp::builder().build();
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    for timeout_seconds in [1, 100_000] {
+        let challenge = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+        app.invoke(
+            "authenticate_passkey",
+            json!({
+                "siteId": "site-1",
+                "authConfig": auth_config,
+                "challenge": challenge,
+                "timeoutSeconds": timeout_seconds,
+            }),
+        )
+        .expect("authentication failed");
+    }
+}
+
+#[test]
+fn site_auth_status_follows_authentication() {
+    let app = TestApp::builder().session_ttl(Duration::from_millis(200)).build();
+    let status = |site_id: &str| {
+        app.invoke("get_site_auth_status", json!({ "siteId": site_id }))
+            .expect("failed to get the site's status")
+    };
+
+    assert_eq!(
+        status("site-1"),
+        json!({ "hasCredential": false, "isAuthenticated": false, "authenticatedAt": null, "expiresAt": null })
+    );
+
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let registered = status("site-1");
+    assert_eq!(registered["hasCredential"], true);
+    assert_eq!(registered["isAuthenticated"], false);
+
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    let authenticated = status("site-1");
+    assert_keys(&authenticated, &["hasCredential", "isAuthenticated", "authenticatedAt", "expiresAt"]);
+    assert_eq!(authenticated["isAuthenticated"], true);
+    assert!(authenticated["authenticatedAt"].is_string());
+    assert!(authenticated["expiresAt"].as_str() > authenticated["authenticatedAt"].as_str());
+    assert_eq!(status("site-2")["isAuthenticated"], false);
+
+    thread::sleep(Duration::from_millis(300));
+    let expired = status("site-1");
+    assert_eq!(expired["isAuthenticated"], false);
+    assert_eq!(expired["expiresAt"], Value::Null);
+}
+
+#[test]
+fn logout_ends_sessions() {
+    let app = TestApp::builder().build();
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    let is_authenticated = |site_id: &str| {
+        app.invoke("get_site_auth_status", json!({ "siteId": site_id }))
+            .expect("failed to get the site's status")["isAuthenticated"]
+            .clone()
+    };
+    assert_eq!(is_authenticated("site-1"), true);
+
+    app.invoke("logout_site", json!({ "siteId": "site-1" })).expect("logout failed");
+    assert_eq!(is_authenticated("site-1"), false);
+    // Logging out again is not an error
+    app.invoke("logout_site", json!({ "siteId": "site-1" })).expect("repeated logout failed");
+
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    assert_eq!(app.invoke("logout_all_sites", json!({})), Ok(json!(1)));
+    assert_eq!(is_authenticated("site-1"), false);
+    assert_eq!(app.invoke("logout_all_sites", json!({})), Ok(json!(0)));
+}
+
+#[test]
+fn logout_drops_the_challenges_issued_for_the_site() {
+    let app = TestApp::builder().build();
+    let site_1 = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let site_2 = app.register("site-2").expect("registration failed")["authConfig"].clone();
+    let issue = |site_id: &str| {
+        app.invoke("create_challenge", json!({ "siteId": site_id }))
+            .expect("failed to issue a challenge")
+    };
+
+    // A site's challenge is not another site's
+    let challenge = issue("site-1");
+    let error = app.authenticate_with("site-2", &site_2, &challenge).expect_err("another site redeemed it");
+    assert_eq!(error["code"], "Timeout");
+    app.authenticate_with("site-1", &site_1, &challenge).expect("authentication failed");
+
+    let revoked = issue("site-1");
+    let kept = issue("site-2");
+    let unbound = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+    app.invoke("logout_site", json!({ "siteId": "site-1" })).expect("logout failed");
+    let error = app.authenticate_with("site-1", &site_1, &revoked).expect_err("a revoked challenge was redeemed");
+    assert_eq!(error["code"], "Timeout");
+    app.authenticate_with("site-2", &site_2, &kept).expect("authentication failed");
+    app.authenticate_with("site-1", &site_1, &unbound).expect("authentication failed");
+
+    let revoked = issue("site-2");
+    app.invoke("logout_all_sites", json!({})).expect("logout failed");
+    let error = app.authenticate_with("site-2", &site_2, &revoked).expect_err("a revoked challenge was redeemed");
+    assert_eq!(error["code"], "Timeout");
+}
+
+#[test]
+fn unlocking_does_not_reopen_sessions_logged_out_of() {
+    let app = TestApp::builder().idle_check_interval(Duration::from_millis(50)).build();
+    let site_1 = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let site_2 = app.register("site-2").expect("registration failed")["authConfig"].clone();
+    app.authenticate("site-1", &site_1).expect("authentication failed");
+    app.authenticate("site-2", &site_2).expect("authentication failed");
+    let is_authenticated = |site_id: &str| {
+        app.invoke("get_site_auth_status", json!({ "siteId": site_id }))
+            .expect("failed to get the site's status")["isAuthenticated"]
+            .clone()
+    };
+    app.invoke("configure_app_lock", json!({ "config": { "idleTimeoutSeconds": 1 } }))
+        .expect("failed to configure the app lock");
+    thread::sleep(Duration::from_millis(2200));
+    assert_eq!(is_authenticated("site-1"), false);
+
+    app.invoke("logout_site", json!({ "siteId": "site-2" })).expect("logout failed");
+    app.authenticate("site-1", &site_1).expect("authentication failed");
+    assert_eq!(is_authenticated("site-1"), true);
+    assert_eq!(is_authenticated("site-2"), false);
+}
+
+#[test]
+fn emergency_wipe_clears_every_store() {
+    let app = TestApp::builder().build();
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    app.register("site-2").expect("registration failed");
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    let pending = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+
+    let error = app
+        .invoke("emergency_wipe", json!({ "confirmation": "yes" }))
+        .expect_err("a wipe ran without the confirmation");
+    assert_eq!(error["code"], "InvalidInput");
+    assert_eq!(
+        app.invoke("list_protected_sites", json!({})).expect("failed to list the sites").as_array().unwrap().len(),
+        2
+    );
+
+    let report = app
+        .invoke("emergency_wipe", json!({ "confirmation": "CONFIRM_WIPE" }))
+        .expect("wipe failed");
+    assert_eq!(report["credentialsDeleted"], 2);
+    assert_eq!(report["sessionsCleared"], 1);
+    assert_eq!(report["errors"], json!([]));
+    let files_removed = report["filesRemoved"].as_array().unwrap();
+    assert_eq!(files_removed.len(), 1);
+    assert!(files_removed[0].as_str().unwrap().ends_with("site-credentials.json"));
+
+    assert_eq!(app.invoke("list_protected_sites", json!({})), Ok(json!([])));
+    let health = app.invoke("webauthn_health_check", json!({})).expect("health check failed");
+    assert_eq!(health["activeSessions"], 0);
+    assert_eq!(health["storedCredentialSites"], json!([]));
+    assert_eq!(health["rateLimitStates"], json!([]));
+    let error = app
+        .authenticate_with("site-1", &auth_config, &pending)
+        .expect_err("a challenge issued before the wipe was accepted");
+    assert_eq!(error["code"], "Timeout");
+}
+
+#[test]
+fn emergency_wipe_errors_redact_the_passkey() {
+    let app = TestApp::builder().build();
+    // Not base64url, so the platform cannot be asked to delete it
+    let credential_id = "bm90LWJhc2U2NC!!!";
+    app.seed_site("site-1", &auth_config_with_credential(credential_id));
+
+    let report = app
+        .invoke("emergency_wipe", json!({ "confirmation": "CONFIRM_WIPE" }))
+        .expect("wipe failed");
+    assert_eq!(report["credentialsDeleted"], 0);
+    let errors = report["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    let error = errors[0].as_str().unwrap();
+    assert!(error.contains(&Redacted(credential_id).to_string()), "{}", error);
+    assert!(!error.contains(credential_id), "{}", error);
+}
+
+#[test]
+fn idle_app_locks_until_authenticated() {
+    let app = TestApp::builder().idle_check_interval(Duration::from_millis(50)).build();
+    let site_1 = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let site_2 = app.register("site-2").expect("registration failed")["authConfig"].clone();
+    app.authenticate("site-1", &site_1).expect("authentication failed");
+    app.authenticate("site-2", &site_2).expect("authentication failed");
+    let is_authenticated = |site_id: &str| {
+        app.invoke("get_site_auth_status", json!({ "siteId": site_id }))
+            .expect("failed to get the site's status")["isAuthenticated"]
+            .clone()
+    };
+
+    let error = app
+        .invoke("configure_app_lock", json!({ "config": { "idleTimeoutSeconds": 100_000 } }))
+        .expect_err("an over-long idle timeout was accepted");
+    assert_eq!(error["code"], "InvalidInput");
+    app.invoke("configure_app_lock", json!({ "config": { "idleTimeoutSeconds": 1 } }))
+        .expect("failed to configure the app lock");
+    app.invoke("record_activity", json!({})).expect("failed to record activity");
+    assert_eq!(is_authenticated("site-1"), true);
+
+    thread::sleep(Duration::from_millis(2200));
+    assert_eq!(is_authenticated("site-1"), false);
+    assert_eq!(is_authenticated("site-2"), false);
+
+    // Authenticating for one site brings back both sessions
+    app.authenticate("site-1", &site_1).expect("authentication failed");
+    assert_eq!(is_authenticated("site-1"), true);
+    assert_eq!(is_authenticated("site-2"), true);
+}
+
+#[test]
+fn clipboard_writes_of_credential_ids_are_blocked() {
+    let app = TestApp::builder().build();
+    let registered = app.register("site-1").expect("registration failed");
+    let credential_id = registered["authConfig"]["credentials"][0]["credentialId"]
+        .as_str()
+        .expect("no credential ID")
+        .to_string();
+    let allowed = |text: String| app.invoke("check_clipboard_write", json!({ "text": text }));
+
+    assert_eq!(allowed(format!("Passkey {} for site-1", credential_id)), Ok(json!(false)));
+    assert_eq!(allowed("site-1".to_string()), Ok(json!(true)));
+}
+
+#[test]
+fn window_capabilities_are_enforced() {
+    let app = TestApp::builder().main_window_capability(CapabilityTier::ReadOnly).build();
+
+    assert_eq!(app.invoke("is_webauthn_available", json!({})), Ok(json!(true)));
+    let error = app.register("site-1").expect_err("a read-only window registered a passkey");
+    assert_eq!(error, error_json(WebAuthnError::Unauthorized));
+    let error = app
+        .authenticate_with("site-1", &auth_config_with_credential("Y3JlZGVudGlhbA"), &json!("Y2hhbGxlbmdl"))
+        .expect_err("a read-only window authenticated");
+    assert_eq!(error, error_json(WebAuthnError::Unauthorized));
+
+    let app = TestApp::builder().main_window_capability(CapabilityTier::Manage).build();
+    app.register("site-1").expect("registration failed");
+}
+
+/// Commands besides the ceremonies, with arguments they accept, and the tier each needs
+fn tiered_commands() -> Vec<(&'static str, Value, CapabilityTier)> {
+    let site = json!({ "siteId": "site-1" });
+    let credential = json!({ "siteId": "site-1", "credentialId": "Y3JlZGVudGlhbA" });
+    vec![
+        ("get_webauthn_capabilities", json!({}), CapabilityTier::ReadOnly),
+        ("get_plugin_version", json!({}), CapabilityTier::ReadOnly),
+        ("get_rp_id", json!({}), CapabilityTier::ReadOnly),
+        ("load_site_credential", site.clone(), CapabilityTier::ReadOnly),
+        ("list_site_credentials", site.clone(), CapabilityTier::ReadOnly),
+        ("list_protected_sites", json!({}), CapabilityTier::ReadOnly),
+        ("get_login_hints", json!({}), CapabilityTier::ReadOnly),
+        ("verify_stored_credential", site.clone(), CapabilityTier::ReadOnly),
+        ("webauthn_health_check", json!({}), CapabilityTier::ReadOnly),
+        ("get_webauthn_metrics", json!({}), CapabilityTier::ReadOnly),
+        ("get_onboarding_state", json!({}), CapabilityTier::ReadOnly),
+        ("check_clipboard_write", json!({ "text": "hello" }), CapabilityTier::ReadOnly),
+        (
+            "verify_assertion",
+            json!({
+                "credentialId": "Y3JlZGVudGlhbA",
+                "clientDataJson": "e30",
+                "authenticatorData": "AA",
+                "signature": "AA",
+                "publicKey": "AA",
+            }),
+            CapabilityTier::ReadOnly,
+        ),
+        ("create_challenge", json!({}), CapabilityTier::Authenticate),
+        ("discover_credentials", json!({}), CapabilityTier::Authenticate),
+        ("submit_security_key_pin", json!({ "pin": null }), CapabilityTier::Authenticate),
+        ("poll_hybrid_auth", json!({ "sessionToken": "unknown" }), CapabilityTier::Authenticate),
+        (
+            "save_site_credential",
+            json!({ "siteId": "site-1", "authConfig": auth_config_with_credential("Y3JlZGVudGlhbA") }),
+            CapabilityTier::Manage,
+        ),
+        ("delete_site_credential", site.clone(), CapabilityTier::Manage),
+        ("remove_site_credential", credential, CapabilityTier::Manage),
+        (
+            "import_credentials",
+            json!({ "inputPath": "/nonexistent/backup.json", "passphrase": "correct horse battery staple" }),
+            CapabilityTier::Manage,
+        ),
+        ("migrate_credentials_from_frontend", json!({ "configs": [] }), CapabilityTier::Manage),
+        ("request_sync_offer", json!({}), CapabilityTier::Manage),
+        ("should_reenroll", site.clone(), CapabilityTier::Manage),
+        ("set_rp_id", json!({ "domain": "example.com" }), CapabilityTier::Manage),
+        ("configure_challenge", json!({ "config": { "lengthBytes": 32 } }), CapabilityTier::Manage),
+        ("rotate_challenge_seed", json!({}), CapabilityTier::Manage),
+        ("logout_site", site.clone(), CapabilityTier::Manage),
+        ("logout_all_sites", json!({}), CapabilityTier::Manage),
+        ("set_site_session_ttl", json!({ "siteId": "site-1", "ttlSeconds": 60 }), CapabilityTier::Manage),
+        ("set_grace_period", json!({ "seconds": 0 }), CapabilityTier::Manage),
+        ("configure_app_lock", json!({ "config": { "idleTimeoutSeconds": 0 } }), CapabilityTier::Manage),
+        ("record_activity", json!({}), CapabilityTier::Manage),
+        ("clear_pin_cache", json!({}), CapabilityTier::Manage),
+        ("cancel_credential_refresh", json!({}), CapabilityTier::Manage),
+        ("reset_webauthn_metrics", json!({}), CapabilityTier::Manage),
+        ("reset_onboarding", json!({}), CapabilityTier::Manage),
+    ]
+}
+
+/// Whether a command's error is `Unauthorized`; the RP ID commands fail with its message
+fn is_unauthorized(error: &Value) -> bool {
+    *error == error_json(WebAuthnError::Unauthorized) || *error == json!(WebAuthnError::Unauthorized.to_string())
+}
+
+#[test]
+fn every_command_refuses_windows_without_its_tier() {
+    let app = TestApp::builder()
+        .window_capability("settings", CapabilityTier::Manage)
+        .build();
+    for (command, args, _) in tiered_commands() {
+        let error = app
+            .invoke(command, args)
+            .expect_err(&format!("{} ran for a window without a tier", command));
+        assert!(is_unauthorized(&error), "{} failed with {}", command, error);
+    }
+
+    for granted in [CapabilityTier::ReadOnly, CapabilityTier::Authenticate] {
+        let app = TestApp::builder().main_window_capability(granted).build();
+        for (command, args, required) in tiered_commands() {
+            let refused = app.invoke(command, args).is_err_and(|error| is_unauthorized(&error));
+            assert_eq!(refused, required > granted, "{} for a {:?} window", command, granted);
+        }
+    }
+}
+
+#[test]
+fn the_pin_window_submits_pins_without_a_grant() {
+    let app = TestApp::builder()
+        .window_label("security-key-pin")
+        .window_capability("settings", CapabilityTier::Manage)
+        .build();
+
+    let error = app
+        .invoke("submit_security_key_pin", json!({ "pin": "1234" }))
+        .expect_err("a PIN was taken without a ceremony asking for one");
+    // Past the tier check, no ceremony is waiting for a PIN. Only Linux opens a PIN window
+    let expected = if cfg!(target_os = "linux") {
+        WebAuthnError::InternalError("No security key PIN was requested".to_string())
+    } else {
+        WebAuthnError::Unauthorized
+    };
+    assert_eq!(error, error_json(expected));
+
+    // Other windows still need the tier
+    let app = TestApp::builder()
+        .window_capability("settings", CapabilityTier::Manage)
+        .build();
+    let error = app
+        .invoke("submit_security_key_pin", json!({ "pin": "1234" }))
+        .expect_err("a window without a tier submitted a PIN");
+    assert!(is_unauthorized(&error), "failed with {}", error);
+}
+
+#[test]
+fn malformed_site_ids_are_rejected() {
+    let app = TestApp::builder().build();
+
+    let longest = format!("{}.{}.{}.{}", "a".repeat(63), "b".repeat(63), "c".repeat(63), "d".repeat(61));
+    for site_id in ["a", "my_site-1a2b3c4d", "docs.example.com", "-untitled-1a2b3c4d", longest.as_str()] {
+        app.register(site_id).unwrap_or_else(|error| panic!("site ID {:?} was rejected: {}", site_id, error));
+    }
+
+    let too_long = format!("{}a", longest);
+    for site_id in [
+        "",
+        too_long.as_str(),
+        "https://example.com",
+        "example.com/path",
+        "my site",
+        "site\n",
+        ".example.com",
+        "example.com.",
+        "example..com",
+        "caf\u{e9}",
+    ] {
+        let error = app.register(site_id).expect_err("a malformed site ID was accepted");
+        assert_eq!(error["code"], "InvalidInput", "site ID {:?}", site_id);
+        let error = app
+            .authenticate(site_id, &auth_config_with_credential("Y3JlZGVudGlhbA"))
+            .expect_err("a malformed site ID was accepted");
+        assert_eq!(error["code"], "InvalidInput", "site ID {:?}", site_id);
+        for (command, args) in [
+            ("create_challenge", json!({ "siteId": site_id })),
+            ("delete_passkey", json!({ "siteId": site_id, "credentialId": "Y3JlZGVudGlhbA" })),
+            ("verify_stored_credential", json!({ "siteId": site_id })),
+            ("should_reenroll", json!({ "siteId": site_id })),
+            (
+                "save_site_credential",
+                json!({ "siteId": site_id, "authConfig": auth_config_with_credential("Y3JlZGVudGlhbA") }),
+            ),
+            ("get_site_auth_status", json!({ "siteId": site_id })),
+            ("logout_site", json!({ "siteId": site_id })),
+            ("set_site_session_ttl", json!({ "siteId": site_id, "ttlSeconds": 600 })),
+        ] {
+            let error = app.invoke(command, args).expect_err("a malformed site ID was accepted");
+            assert_eq!(error["code"], "InvalidInput", "{} with site ID {:?}", command, site_id);
+        }
+
+        let mut config = auth_config_with_credential("Y3JlZGVudGlhbA");
+        config["siteId"] = json!(site_id);
+        let report = app
+            .invoke("migrate_credentials_from_frontend", json!({ "configs": [config] }))
+            .expect("migration failed");
+        assert_eq!(report["migrated"], 0, "site ID {:?}", site_id);
+        assert_eq!(report["errors"][0]["siteId"], site_id);
+        assert_eq!(report["errors"][0]["credentialId"], Value::Null);
+    }
+}
+
+#[test]
+fn site_session_ttl_is_validated() {
+    let app = TestApp::builder().build();
+    let set_ttl = |ttl_seconds: u64| {
+        app.invoke("set_site_session_ttl", json!({ "siteId": "site-1", "ttlSeconds": ttl_seconds }))
+    };
+
+    for ttl_seconds in [59, 86401] {
+        let error = set_ttl(ttl_seconds).expect_err("an out-of-range TTL was accepted");
+        assert_eq!(error["code"], "InvalidInput");
+    }
+    set_ttl(60).expect("the shortest TTL was refused");
+
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    let status = app
+        .invoke("get_site_auth_status", json!({ "siteId": "site-1" }))
+        .expect("failed to get the site's status");
+    let timestamp = |field: &str| {
+        chrono::DateTime::parse_from_rfc3339(status[field].as_str().unwrap()).expect("invalid timestamp")
+    };
+    assert_eq!((timestamp("expiresAt") - timestamp("authenticatedAt")).num_seconds(), 60);
+}
+
+#[test]
+fn recent_authentication_skips_the_prompt() {
+    // Authentications that skip the prompt still count against the limit
+    let app = TestApp::builder().rate_limit(10, Duration::from_secs(60)).build();
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let attempts = || {
+        app.invoke("get_webauthn_metrics", json!({})).expect("failed to get the metrics")["authenticationAttempts"]
+            .clone()
+    };
+
+    let result = app.authenticate("site-1", &auth_config).expect("authentication failed");
+    assert_eq!(result["fromCache"], false);
+    let cached = app.authenticate("site-1", &auth_config).expect("authentication failed");
+    assert_eq!(cached["success"], true);
+    assert_eq!(cached["fromCache"], true);
+    assert_eq!(cached["credentialId"], Value::Null);
+    assert_eq!(attempts(), 1);
+
+    // The largeBlob extension needs the authenticator
+    let challenge = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+    let result = app
+        .invoke(
+            "authenticate_passkey",
+            json!({ "siteId": "site-1", "authConfig": auth_config, "challenge": challenge, "largeBlobRead": true }),
+        )
+        .expect("authentication failed");
+    assert_eq!(result["fromCache"], false);
+
+    let error = app
+        .invoke("set_grace_period", json!({ "seconds": 61 }))
+        .expect_err("an out-of-range grace period was accepted");
+    assert_eq!(error["code"], "InvalidInput");
+    app.invoke("set_grace_period", json!({ "seconds": 0 })).expect("failed to set the grace period");
+    // The session opened by the largeBlob read keeps its grace period
+    assert_eq!(app.authenticate("site-1", &auth_config).expect("authentication failed")["fromCache"], true);
+    app.invoke("logout_site", json!({ "siteId": "site-1" })).expect("logout failed");
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    assert_eq!(app.authenticate("site-1", &auth_config).expect("authentication failed")["fromCache"], false);
+    assert_eq!(attempts(), 4);
+}
+
+#[test]
+fn plugin_version_is_semver() {
+    let app = TestApp::builder().build();
+
+    let version = app
+        .invoke("get_plugin_version", json!({}))
+        .expect("failed to get the plugin version");
+    let version = version.as_str().expect("the version is not a string");
+    semver::Version::parse(version).expect("the version is not semver");
+    assert_eq!(version, env!("CARGO_PKG_VERSION"));
+}
+
+#[test]
+fn health_check_reports_state() {
+    let app = TestApp::builder().challenge_ttl(Duration::from_secs(90)).build();
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+
+    let report = app.invoke("webauthn_health_check", json!({})).expect("health check failed");
+    // Tests are debug builds, so the developer-only fields are included
+    assert_keys(
+        &report,
+        &[
+            "platform",
+            "rpId",
+            "challengeTtlSeconds",
+            "activeSessions",
+            "storedCredentialSites",
+            "rateLimitStates",
+            "lastCredentialRefresh",
+            "pluginVersion",
+            "gitSha",
+            "buildTimestamp",
+            "clockSkewDetected",
+        ],
+    );
+    assert_eq!(report["rpId"], "localhost");
+    assert!(report["gitSha"].as_str().is_some_and(|sha| !sha.is_empty()));
+    let built = report["buildTimestamp"].as_str().unwrap();
+    assert!(chrono::DateTime::parse_from_rfc3339(built).is_ok(), "{} is not RFC 3339", built);
+    assert_eq!(report["challengeTtlSeconds"], 90);
+    assert_eq!(report["activeSessions"], 1);
+    assert_eq!(report["storedCredentialSites"], json!(["site-1"]));
+    assert_eq!(
+        report["rateLimitStates"],
+        json!([{ "siteId": "site-1", "attempts": 1, "retryAfterSeconds": null }])
+    );
+    assert_eq!(report["lastCredentialRefresh"], Value::Null);
+    assert_eq!(report["clockSkewDetected"], false);
+}
+
+#[test]
+fn metrics_count_ceremonies() {
+    let app = TestApp::builder().rate_limit(2, Duration::from_secs(60)).build();
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    app.authenticate_with("site-1", &auth_config, &json!("bm90LWlzc3VlZA"))
+        .expect_err("an unissued challenge was accepted");
+    app.authenticate("site-1", &auth_config).expect_err("the rate limit was not enforced");
+
+    let metrics = app.invoke("get_webauthn_metrics", json!({})).expect("failed to get the metrics");
+    assert_eq!(
+        metrics,
+        json!({
+            "registrationAttempts": 1,
+            "registrationSuccesses": 1,
+            "registrationFailures": 0,
+            "authenticationAttempts": 1,
+            "authenticationSuccesses": 1,
+            "authenticationFailures": 0,
+            "rateLimitHits": 1,
+            "challengeExpirations": 1,
+        })
+    );
+
+    app.invoke("reset_webauthn_metrics", json!({})).expect("failed to reset the metrics");
+    let metrics = app.invoke("get_webauthn_metrics", json!({})).expect("failed to get the metrics");
+    assert!(metrics.as_object().unwrap().values().all(|count| count == 0));
+}
+
+#[test]
+fn registration_advances_onboarding() {
+    let onboarding_state = |app: &TestApp| {
+        app.invoke("get_onboarding_state", json!({}))
+            .expect("failed to get the onboarding state")
+    };
+
+    let app = TestApp::builder().build();
+    assert_eq!(onboarding_state(&app), "not-started");
+    app.register("site-1").expect("registration failed");
+    assert_eq!(onboarding_state(&app), "complete");
+    app.invoke("reset_onboarding", json!({})).expect("failed to reset onboarding");
+    assert_eq!(onboarding_state(&app), "not-started");
+
+    // A failed ceremony leaves the platform check done
+    let app = TestApp::builder()
+        .platform(MockPlatform::new().fail_with(WebAuthnError::UserCancelled))
+        .build();
+    app.register("site-1").expect_err("registration succeeded");
+    assert_eq!(onboarding_state(&app), "platform-check-done");
+}
+
+#[test]
+fn user_cancellation_is_reported() {
+    let app = TestApp::builder()
+        .platform(MockPlatform::new().fail_with(WebAuthnError::UserCancelled))
+        .build();
+
+    let error = app
+        .authenticate("site-1", &auth_config_with_credential("Y3JlZGVudGlhbA"))
+        .expect_err("authentication succeeded");
+    assert_eq!(error, json!({ "code": "UserCancelled" }));
+    assert_eq!(error, error_json(WebAuthnError::UserCancelled));
+
+    let error = app.register("site-1").expect_err("registration succeeded");
+    assert_eq!(error, json!({ "code": "UserCancelled" }));
+}
+
+#[test]
+fn error_codes_match_the_serialized_code() {
+    for error in [
+        WebAuthnError::PlatformNotSupported,
+        WebAuthnError::UserCancelled,
+        WebAuthnError::InvalidChallenge,
+        WebAuthnError::InvalidInput("site_id".to_string()),
+        WebAuthnError::InvalidChallengeLength(8),
+        WebAuthnError::CredentialNotFound("Y3JlZGVudGlhbA".to_string()),
+        WebAuthnError::BiometricFailed,
+        WebAuthnError::UserVerificationFailed,
+        WebAuthnError::ResidentKeyNotSupported,
+        WebAuthnError::RateLimited { retry_after_seconds: 60 },
+        WebAuthnError::Unauthorized,
+        WebAuthnError::RegistrationInProgress,
+        WebAuthnError::ReplayAttack,
+        WebAuthnError::Timeout,
+        WebAuthnError::SignatureInvalid,
+        WebAuthnError::NotImplemented,
+        WebAuthnError::LimitExceeded { current: 10, max: 10 },
+        WebAuthnError::CredentialLocked,
+        WebAuthnError::SyncControlUnsupported,
+        WebAuthnError::RpIdTampered,
+        WebAuthnError::PlatformUnavailable("busy".to_string()),
+        WebAuthnError::InternalError("boom".to_string()),
+    ] {
+        assert_eq!(error_json(error.clone())["code"], error.code());
+    }
+}
+
+#[test]
+fn detected_device_names_are_numbered_within_a_site() {
+    let app = TestApp::builder().build();
+    let first = app.register("site-1").expect("registration failed");
+    let second = app.register("site-1").expect("registration failed");
+    let other_site = app.register("site-2").expect("registration failed");
+
+    let name = first["authConfig"]["credentials"][0]["deviceName"].as_str().unwrap().to_string();
+    assert_eq!(second["authConfig"]["credentials"][1]["deviceName"], format!("{} (2)", name));
+    assert_eq!(other_site["authConfig"]["credentials"][0]["deviceName"], name);
+}
+
+#[test]
+fn passkeys_per_site_are_limited() {
+    let app = TestApp::builder().build();
+
+    for _ in 0..10 {
+        app.register("site-1").expect("registration failed");
+    }
+    let error = app.register("site-1").expect_err("an 11th passkey was registered");
+    assert_eq!(error, json!({ "code": "LimitExceeded", "detail": { "current": 10, "max": 10 } }));
+
+    // Other sites keep their own count
+    app.register("site-2").expect("another site was limited");
+}
+
+/// The credential IDs a site has in the store
+fn stored_credential_ids(app: &TestApp, site_id: &str) -> Vec<String> {
+    app.webview
+        .state::<CredentialStore>()
+        .load_credential(site_id)
+        .expect("failed to load the site")
+        .map(|config| config.credentials.into_iter().map(|entry| entry.credential_id).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn reenrollment_deletes_the_stale_passkeys_once_the_new_one_is_stored() {
+    let app = TestApp::builder().platform(MockPlatform::new().lost_credentials()).build();
+    app.seed_site("site-1", &auth_config_with_credential("c3RhbGU"));
+    let reenroll = app.invoke("should_reenroll", json!({ "siteId": "site-1" }));
+    assert_eq!(reenroll, Ok(json!(true)));
+
+    let result = app.register("site-1").expect("registration failed");
+
+    let stored = stored_credential_ids(&app, "site-1");
+    assert_eq!(stored.len(), 1);
+    assert_ne!(stored[0], "c3RhbGU");
+    assert_eq!(result["authConfig"]["credentials"].as_array().unwrap().len(), 1);
+    assert_eq!(result["authConfig"]["credentials"][0]["credentialId"], stored[0]);
+}
+
+#[test]
+fn a_failed_reenrollment_keeps_the_stale_passkeys() {
+    let platform = MockPlatform::new().lost_credentials().fail_with(WebAuthnError::UserCancelled);
+    let app = TestApp::builder().platform(platform).build();
+    app.seed_site("site-1", &auth_config_with_credential("c3RhbGU"));
+    let reenroll = app.invoke("should_reenroll", json!({ "siteId": "site-1" }));
+    assert_eq!(reenroll, Ok(json!(true)));
+
+    let error = app.register("site-1").expect_err("a cancelled registration succeeded");
+    assert_eq!(error, json!({ "code": "UserCancelled" }));
+    assert_eq!(stored_credential_ids(&app, "site-1"), ["c3RhbGU"]);
+}
+
+#[test]
+fn stale_passkeys_count_toward_the_limit() {
+    let app = TestApp::builder()
+        .platform(MockPlatform::new().lost_credentials())
+        .max_credentials_per_site(1)
+        .build();
+    app.seed_site("site-1", &auth_config_with_credential("c3RhbGU"));
+    let reenroll = app.invoke("should_reenroll", json!({ "siteId": "site-1" }));
+    assert_eq!(reenroll, Ok(json!(true)));
+
+    let error = app.register("site-1").expect_err("the limit was bypassed");
+    assert_eq!(error, json!({ "code": "LimitExceeded", "detail": { "current": 1, "max": 1 } }));
+    assert_eq!(stored_credential_ids(&app, "site-1"), ["c3RhbGU"]);
+}
+
+#[test]
+fn rate_limit_is_enforced() {
+    let app = TestApp::builder().rate_limit(2, Duration::from_secs(60)).build();
+    let auth_config = auth_config_with_credential("Y3JlZGVudGlhbA");
+
+    app.authenticate("site-1", &auth_config).expect("first attempt failed");
+    app.authenticate("site-1", &auth_config).expect("second attempt failed");
+
+    let error = app
+        .authenticate("site-1", &auth_config)
+        .expect_err("third attempt was allowed");
+    assert_eq!(
+        error,
+        json!({ "code": "RateLimited", "detail": { "retryAfterSeconds": 60 } })
+    );
+
+    // Other sites keep their own attempts
+    app.authenticate("site-2", &auth_config).expect("another site was throttled");
+}
+
+#[test]
+fn expired_challenge_is_rejected() {
+    let app = TestApp::builder().challenge_ttl(Duration::from_millis(10)).build();
+    let challenge = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+
+    thread::sleep(Duration::from_millis(50));
+
+    let error = app
+        .authenticate_with("site-1", &auth_config_with_credential("Y3JlZGVudGlhbA"), &challenge)
+        .expect_err("expired challenge was accepted");
+    assert_eq!(error, json!({ "code": "Timeout" }));
+}
+
+#[test]
+fn rotating_the_seed_drops_pending_challenges() {
+    let app = TestApp::builder().build();
+    let auth_config = auth_config_with_credential("Y3JlZGVudGlhbA");
+    let challenge = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+
+    app.invoke("rotate_challenge_seed", json!({})).expect("failed to rotate the seed");
+
+    let error = app
+        .authenticate_with("site-1", &auth_config, &challenge)
+        .expect_err("a challenge from before the rotation was accepted");
+    assert_eq!(error, json!({ "code": "Timeout" }));
+    let fresh = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+    assert_ne!(fresh, challenge);
+    app.authenticate_with("site-1", &auth_config, &fresh).expect("authentication failed");
+}
+
+#[test]
+fn replayed_challenge_is_rejected() {
+    let app = TestApp::builder().build();
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let challenge = app.invoke("create_challenge", json!({})).expect("failed to issue a challenge");
+    app.authenticate_with("site-1", &auth_config, &challenge).expect("authentication failed");
+
+    let error = app
+        .authenticate_with("site-1", &auth_config, &challenge)
+        .expect_err("replayed challenge was accepted");
+    assert_eq!(error, json!({ "code": "ReplayAttack" }));
+}
+
+#[test]
+fn missing_credential_is_reported() {
+    let app = TestApp::builder().build();
+    let auth_config = json!({
+        "credentials": [],
+        "requiresAuth": true,
+        "userDisplayName": null,
+        "userId": "",
+    });
+
+    let error = app
+        .authenticate("site-1", &auth_config)
+        .expect_err("authenticated without a credential");
+    assert_eq!(error, json!({ "code": "CredentialNotFound", "detail": "site-1" }));
+
+    let error = app
+        .invoke(
+            "delete_passkey",
+            json!({ "siteId": "site-1", "credentialId": "Y3JlZGVudGlhbA" }),
+        )
+        .expect_err("deleted an unknown passkey");
+    assert_eq!(error, json!({ "code": "CredentialNotFound", "detail": "Y3JlZGVudGlhbA" }));
+}
+
+#[test]
+fn frontend_credentials_are_migrated() {
+    let app = TestApp::builder().build();
+    let configs = json!([{
+        "siteId": "site-1",
+        "credentials": [
+            {
+                "credentialId": "Y3JlZGVudGlhbA",
+                "publicKey": "bW9jay1wdWJsaWMta2V5",
+                "registeredAt": "2025-01-01T00:00:00.000Z",
+            },
+            {
+                "credentialId": "b3RoZXI",
+                "publicKey": "",
+                "registeredAt": "2025-01-01T00:00:00.000Z",
+            },
+        ],
+        "requiresAuth": true,
+    }]);
+
+    let report = app
+        .invoke("migrate_credentials_from_frontend", json!({ "configs": configs }))
+        .expect("migration failed");
+    assert_eq!(report["migrated"], 1);
+    assert_eq!(report["skipped"], 0);
+    assert_eq!(
+        report["errors"],
+        json!([{ "siteId": "site-1", "credentialId": "b3RoZXI", "reason": "publicKey must not be empty" }])
+    );
+
+    let stored = app
+        .invoke("load_site_credential", json!({ "siteId": "site-1" }))
+        .expect("failed to load the site");
+    assert_eq!(stored["credentials"].as_array().unwrap().len(), 1);
+    assert_eq!(stored["credentials"][0]["registeredAt"], "2025-01-01T00:00:00.000Z");
+
+    // Running it again leaves the stored credential alone
+    let report = app
+        .invoke("migrate_credentials_from_frontend", json!({ "configs": configs }))
+        .expect("second migration failed");
+    assert_eq!(report["migrated"], 0);
+    assert_eq!(report["skipped"], 1);
+}
+
+#[test]
+fn sync_offers_copy_sites_to_another_device() {
+    let sender = TestApp::builder().build();
+    let auth_config = sender.register("site-1").expect("registration failed")["authConfig"].clone();
+    sender.register("site-2").expect("registration failed");
+    let receiver = TestApp::builder().build();
+    let request = || {
+        receiver
+            .invoke("request_sync_offer", json!({}))
+            .expect("failed to request a sync offer")
+    };
+    let offer = |request: &Value, sites: Value| {
+        sender.invoke("generate_sync_offer", json!({ "sites": sites, "request": request["payload"] }))
+    };
+    let accept = |payload: &Value| receiver.invoke("accept_sync_offer", json!({ "payload": payload }));
+
+    let first = request();
+    assert_keys(&first, &["payload", "qrCodePng", "expiresAt"]);
+    let png = STANDARD.decode(first["qrCodePng"].as_str().unwrap()).expect("QR code is not base64");
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    let error = offer(&first, json!(["site-3"])).expect_err("an offer was made for a site without passkeys");
+    assert_eq!(error["code"], "InvalidInput");
+    let error = offer(&json!({ "payload": "c2l0ZS0x" }), json!(["site-1"])).expect_err("answered a stray request");
+    assert_eq!(error["code"], "InvalidInput");
+    let synced = offer(&first, json!(["site-1"])).expect("failed to make a sync offer");
+    assert_keys(&synced, &["payload", "qrCodePng"]);
+    let png = STANDARD.decode(synced["qrCodePng"].as_str().unwrap()).expect("QR code is not base64");
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    // Only the device that made the request can accept the offer
+    let bystander = TestApp::builder().build();
+    bystander
+        .invoke("request_sync_offer", json!({}))
+        .expect("failed to request a sync offer");
+    let error = bystander
+        .invoke("accept_sync_offer", json!({ "payload": synced["payload"] }))
+        .expect_err("another device accepted the offer");
+    assert_eq!(error["code"], "InvalidInput");
+
+    assert_eq!(
+        accept(&synced["payload"]),
+        Ok(json!({ "siteIds": ["site-1"], "importedCount": 1, "skippedCount": 0 }))
+    );
+    let stored = receiver
+        .invoke("load_site_credential", json!({ "siteId": "site-1" }))
+        .expect("failed to load the site");
+    assert_eq!(stored["credentials"], auth_config["credentials"]);
+    assert_eq!(stored["userId"], auth_config["userId"]);
+    assert_eq!(
+        receiver.invoke("load_site_credential", json!({ "siteId": "site-2" })),
+        Ok(Value::Null)
+    );
+    // Each request is answered once
+    assert_eq!(accept(&synced["payload"]).expect_err("an offer was accepted twice")["code"], "InvalidInput");
+
+    // A tampered offer uses up its request as well
+    let second = offer(&request(), json!(["site-1"])).expect("failed to make a sync offer");
+    let mut tampered = second["payload"].as_str().unwrap().to_string();
+    tampered.replace_range(tampered.len() - 4.., "AAAA");
+    assert_eq!(accept(&json!(tampered)).expect_err("a tampered offer was accepted")["code"], "InvalidInput");
+    assert_eq!(accept(&second["payload"]).expect_err("a used request was answered")["code"], "InvalidInput");
+    assert_eq!(accept(&json!("c2l0ZS0x")).expect_err("a stray payload was accepted")["code"], "InvalidInput");
+
+    // Syncing again leaves the stored credential alone
+    let third = offer(&request(), json!(["site-1"])).expect("failed to make a sync offer");
+    assert_eq!(accept(&third["payload"]).expect("second sync failed")["skippedCount"], 1);
+}
+
+#[test]
+fn sync_requests_expire_on_the_receivers_clock() {
+    let sender = TestApp::builder().build();
+    sender.register("site-1").expect("registration failed");
+    let receiver = TestApp::builder().sync_offer_ttl(Duration::from_millis(50)).build();
+    let request = receiver
+        .invoke("request_sync_offer", json!({}))
+        .expect("failed to request a sync offer");
+    let offer = sender
+        .invoke("generate_sync_offer", json!({ "sites": ["site-1"], "request": request["payload"] }))
+        .expect("failed to make a sync offer");
+
+    thread::sleep(Duration::from_millis(100));
+    let accept = || receiver.invoke("accept_sync_offer", json!({ "payload": offer["payload"] }));
+    assert_eq!(accept(), Err(json!({ "code": "Timeout" })));
+    assert_eq!(accept().expect_err("an expired request was answered")["code"], "InvalidInput");
+    assert_eq!(
+        receiver.invoke("load_site_credential", json!({ "siteId": "site-1" })),
+        Ok(Value::Null)
+    );
+}
+
+#[test]
+fn protected_sites_are_listed() {
+    let app = TestApp::builder().build();
+    let save = |site_id: &str, auth_config: Value| app.seed_site(site_id, &auth_config);
+    let credential = |id: &str, registered_at: &str, last_used_at: Option<&str>| {
+        json!({
+            "credentialId": id,
+            "publicKey": "bW9jay1wdWJsaWMta2V5",
+            "registeredAt": registered_at,
+            "lastUsedAt": last_used_at,
+        })
+    };
+    save(
+        "blog",
+        json!({
+            "credentials": [
+                credential("YQ", "2025-01-01T00:00:00.000Z", Some("2025-03-01T00:00:00.000Z")),
+                credential("Yg", "2025-02-01T00:00:00.000Z", None),
+            ],
+            "requiresAuth": true,
+            "siteName": "My Blog",
+        }),
+    );
+    save(
+        "shop",
+        json!({ "credentials": [credential("Yw", "2025-06-01T00:00:00.000Z", None)], "requiresAuth": true }),
+    );
+    save("public", json!({ "credentials": [], "requiresAuth": false }));
+
+    let sites = app.invoke("list_protected_sites", json!({})).expect("failed to list the sites");
+    assert_eq!(
+        sites,
+        json!([
+            {
+                "siteId": "shop",
+                "siteName": null,
+                "credentialCount": 1,
+                "mostRecentUse": null,
+                "registeredAt": "2025-06-01T00:00:00.000Z",
+            },
+            {
+                "siteId": "blog",
+                "siteName": "My Blog",
+                "credentialCount": 2,
+                "mostRecentUse": "2025-03-01T00:00:00.000Z",
+                "registeredAt": "2025-01-01T00:00:00.000Z",
+            },
+        ])
+    );
+
+    let site_ids = |filter: Value| {
+        let sites = app
+            .invoke("list_protected_sites", json!({ "filter": filter }))
+            .expect("failed to list the sites");
+        sites.as_array().unwrap().iter().map(|site| site["siteId"].clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(site_ids(json!({ "nameContains": "BLOG" })), [json!("blog")]);
+    assert_eq!(site_ids(json!({ "registeredAfter": "2025-03-01T00:00:00Z" })), [json!("shop")]);
+    assert_eq!(
+        site_ids(json!({ "nameContains": "my", "registeredAfter": "2025-03-01T00:00:00Z" })),
+        Vec::<Value>::new()
+    );
+}
+
+#[test]
+fn assertion_signatures_are_verified() {
+    let app = TestApp::builder().build();
+    let rp_id = app.invoke("get_rp_id", json!({})).expect("failed to read the RP ID");
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+    let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+
+    // The public key is 0x04 | x | y; as a COSE key {1: 2, 3: -7, -1: 1, -2: x, -3: y}
+    let point = key_pair.public_key().as_ref();
+    let mut public_key = vec![0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58, 0x20];
+    public_key.extend_from_slice(&point[1..33]);
+    public_key.extend_from_slice(&[0x22, 0x58, 0x20]);
+    public_key.extend_from_slice(&point[33..]);
+
+    let client_data = br#"{"type":"webauthn.get","challenge":"Y2hhbGxlbmdl","origin":"https://example.com"}"#;
+    let auth_data = |rp_id: &str, sign_count: u32| {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(0x05); // UP | UV
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        data
+    };
+    let sign = |auth_data: &[u8]| {
+        let signed = [auth_data, &Sha256::digest(client_data)].concat();
+        key_pair.sign(&rng, &signed).unwrap().as_ref().to_vec()
+    };
+    let verify = |auth_data: &[u8], signature: &[u8], public_key: &[u8]| {
+        app.invoke(
+            "verify_assertion",
+            json!({
+                "credentialId": "Y3JlZGVudGlhbC0x",
+                "clientDataJson": URL_SAFE_NO_PAD.encode(client_data),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(auth_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature),
+                "publicKey": URL_SAFE_NO_PAD.encode(public_key),
+            }),
+        )
+    };
+
+    let ours = auth_data(rp_id.as_str().unwrap(), 7);
+    let result = verify(&ours, &sign(&ours), &public_key).expect("verification failed");
+    assert_eq!(result, json!({ "verified": true, "signCount": 7, "rpIdHashMatches": true }));
+
+    // A signature over other data
+    let error = verify(&ours, &sign(&auth_data(rp_id.as_str().unwrap(), 8)), &public_key)
+        .expect_err("verification succeeded");
+    assert_eq!(error["code"], "SignatureInvalid");
+
+    // Correctly signed, but for another RP
+    let theirs = auth_data("elsewhere.example", 7);
+    let result = verify(&theirs, &sign(&theirs), &public_key).expect("verification failed");
+    assert_eq!(result["verified"], false);
+    assert_eq!(result["rpIdHashMatches"], false);
+
+    let error = verify(&ours, &sign(&ours), b"not-cbor").expect_err("verification succeeded");
+    assert_eq!(error["code"], "InvalidInput");
+}
+
+#[test]
+fn hybrid_auth_hands_out_a_qr_code() {
+    let app = TestApp::builder().build();
+
+    let session = app
+        .invoke("initiate_hybrid_auth", json!({ "siteId": "site-1" }))
+        .expect("failed to start a hybrid sign-in");
+    assert_keys(&session, &["sessionToken", "url", "qrCodePng", "expiresAt"]);
+    let url = session["url"].as_str().unwrap();
+    assert!(url.starts_with("webauthn://hybrid?"), "unexpected link: {}", url);
+    assert!(url.contains("site_id=site-1") && url.contains("challenge="));
+    let png = STANDARD.decode(session["qrCodePng"].as_str().unwrap()).expect("QR code is not base64");
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    let error = app
+        .invoke("poll_hybrid_auth", json!({ "sessionToken": session["sessionToken"] }))
+        .expect_err("polling succeeded");
+    assert_eq!(error, json!({ "code": "NotImplemented" }));
+
+    let error = app
+        .invoke("poll_hybrid_auth", json!({ "sessionToken": "unknown" }))
+        .expect_err("an unknown session was polled");
+    assert_eq!(error["code"], "InvalidInput");
+
+    let error = app
+        .invoke("initiate_hybrid_auth", json!({ "siteId": "../site-1" }))
+        .expect_err("a malformed site ID was accepted");
+    assert_eq!(error["code"], "InvalidInput");
+}
+
+#[test]
+fn config_file_values_are_applied() {
+    let dir = tempfile::tempdir().expect("failed to create the config directory");
+    let path = dir.path().join("webauthn.toml");
+    std::fs::write(
+        &path,
+        "challenge_ttl_seconds = 45\n\
+         max_credentials_per_site = 1\n\
+         rate_limit_max_attempts = 1\n\
+         rate_limit_window_seconds = 30\n",
+    )
+    .expect("failed to write the config file");
+    let config = WebAuthnConfig::load(&path).expect("the config file was not loaded");
+    let app = TestApp::builder().config(config).build();
+
+    let report = app.invoke("webauthn_health_check", json!({})).expect("health check failed");
+    assert_eq!(report["challengeTtlSeconds"], 45);
+
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let error = app.register("site-1").expect_err("a second passkey was registered");
+    assert_eq!(error["code"], "LimitExceeded");
+
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    let error = app
+        .authenticate("site-1", &auth_config)
+        .expect_err("second attempt was allowed");
+    assert_eq!(
+        error,
+        json!({ "code": "RateLimited", "detail": { "retryAfterSeconds": 30 } })
+    );
+
+    // A missing file, an unknown key and a malformed one all leave the defaults
+    assert!(WebAuthnConfig::load(&dir.path().join("missing.toml")).is_none());
+    std::fs::write(&path, "challenge_ttl = 45\n").expect("failed to write the config file");
+    assert!(WebAuthnConfig::load(&path).is_none());
+    std::fs::write(&path, "challenge_ttl_seconds = \"soon\"\n").expect("failed to write the config file");
+    assert!(WebAuthnConfig::load(&path).is_none());
+}
+
+/// Run with `SPARKTYPE_WEBAUTHN_TEST_MODE=1`, as CI does
+#[cfg(feature = "test-mode")]
+#[test]
+fn test_mode_simulates_ceremonies_without_a_mock() {
+    let app = TestAppBuilder {
+        webauthn: WebAuthnBuilder::new(),
+    }
+    .build();
+
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+}
+
+#[test]
+fn authentication_records_when_a_passkey_was_last_used() {
+    let app = TestApp::builder().build();
+    app.invoke("set_grace_period", json!({ "seconds": 0 })).expect("failed to set the grace period");
+    let auth_config = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let credential_id = auth_config["credentials"][0]["credentialId"].clone();
+    let last_used = || {
+        app.invoke("get_credential_last_used", json!({ "siteId": "site-1", "credentialId": credential_id }))
+            .expect("failed to get when the passkey was last used")
+    };
+
+    assert_eq!(last_used(), Value::Null);
+
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    let first = last_used();
+    assert!(first.is_string());
+    thread::sleep(Duration::from_millis(10));
+    app.authenticate("site-1", &auth_config).expect("authentication failed");
+    let second = last_used();
+    assert!(second.as_str() >= first.as_str());
+
+    let error = app
+        .invoke("get_credential_last_used", json!({ "siteId": "site-1", "credentialId": "dW5rbm93bg" }))
+        .expect_err("an unknown passkey had a last use");
+    assert_eq!(error["code"], "CredentialNotFound");
+}
+
+#[test]
+fn repeated_failures_lock_a_passkey_until_unlocked() {
+    let app = TestApp::builder()
+        .platform(MockPlatform::new().fail_with(WebAuthnError::BiometricFailed))
+        .rate_limit(10, Duration::from_secs(60))
+        .lockout_threshold(2)
+        .build();
+    let auth_config = auth_config_with_credential("Y3JlZGVudGlhbA");
+    app.seed_site("site-1", &auth_config);
+    let stored = || {
+        app.invoke("load_site_credential", json!({ "siteId": "site-1" }))
+            .expect("failed to load the site")["credentials"][0]
+            .clone()
+    };
+
+    for _ in 0..2 {
+        let error = app.authenticate("site-1", &auth_config).expect_err("authentication succeeded");
+        assert_eq!(error["code"], "BiometricFailed");
+    }
+    assert_eq!(stored()["failureCount"], 2);
+    assert_eq!(stored()["locked"], true);
+
+    // The frontend's copy of the config cannot lift the lock
+    let error = app.authenticate("site-1", &auth_config).expect_err("a locked passkey was offered");
+    assert_eq!(error, json!({ "code": "CredentialLocked" }));
+
+    app.invoke("unlock_credential", json!({ "siteId": "site-1", "credentialId": "Y3JlZGVudGlhbA" }))
+        .expect("failed to unlock the passkey");
+    assert_eq!(stored()["failureCount"], 0);
+    assert_eq!(stored()["locked"], false);
+    let error = app.authenticate("site-1", &auth_config).expect_err("authentication succeeded");
+    assert_eq!(error["code"], "BiometricFailed");
+
+    let error = app
+        .invoke("unlock_credential", json!({ "siteId": "site-1", "credentialId": "dW5rbm93bg" }))
+        .expect_err("an unknown passkey was unlocked");
+    assert_eq!(error["code"], "CredentialNotFound");
+}
+
+#[test]
+fn saved_configs_keep_what_the_store_owns() {
+    let app = TestApp::builder().build();
+    let registered = app.register("site-1").expect("registration failed")["authConfig"].clone();
+    let credential_id = registered["credentials"][0]["credentialId"].as_str().unwrap().to_string();
+    app.webview
+        .state::<CredentialStore>()
+        .record_failure("site-1", std::slice::from_ref(&credential_id), 1)
+        .expect("failed to lock the passkey");
+    let save = |site_id: &str, auth_config: &Value| {
+        app.invoke("save_site_credential", json!({ "siteId": site_id, "authConfig": auth_config }))
+    };
+    let load = || {
+        app.invoke("load_site_credential", json!({ "siteId": "site-1" }))
+            .expect("failed to load the site")
+    };
+
+    let mut auth_config = registered.clone();
+    auth_config["requiresAuth"] = json!(false);
+    auth_config["userId"] = json!("b3RoZXI");
+    auth_config["siteName"] = json!("Renamed Blog");
+    let entry = &mut auth_config["credentials"][0];
+    entry["locked"] = json!(false);
+    entry["failureCount"] = json!(0);
+    entry["signCount"] = json!(99);
+    entry["deviceName"] = json!("Work laptop");
+    save("site-1", &auth_config).expect("failed to save the site");
+    let stored = load();
+    assert_eq!(stored["siteName"], "Renamed Blog");
+    assert_eq!(stored["credentials"][0]["deviceName"], "Work laptop");
+    assert_eq!(stored["requiresAuth"], true);
+    assert_eq!(stored["userId"], registered["userId"]);
+    assert_eq!(stored["credentials"][0]["locked"], true);
+    assert_eq!(stored["credentials"][0]["failureCount"], 1);
+    assert_eq!(stored["credentials"][0]["signCount"], 0);
+
+    // Passkeys are only added by registering them
+    let mut with_unknown = stored.clone();
+    with_unknown["credentials"]
+        .as_array_mut()
+        .unwrap()
+        .push(auth_config_with_credential("dW5rbm93bg")["credentials"][0].clone());
+    let error = save("site-1", &with_unknown).expect_err("an unknown passkey was saved");
+    assert_eq!(error["code"], "CredentialNotFound");
+    assert_eq!(load(), stored);
+    let error = save("site-2", &auth_config_with_credential("dW5rbm93bg")).expect_err("an unknown site was saved");
+    assert_eq!(error["code"], "CredentialNotFound");
+}
+
+#[test]
+fn discovery_refuses_locked_passkeys() {
+    let app = TestApp::builder().lockout_threshold(1).build();
+    let registration = app.register("site-1").expect("registration failed");
+    let credential_id = registration["authConfig"]["credentials"][0]["credentialId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let discovered = app.invoke("discover_credentials", json!({})).expect("discovery failed");
+    assert_eq!(discovered[0]["siteId"], "site-1");
+    assert_eq!(discovered[0]["credentialId"], credential_id);
+
+    let store = app.webview.state::<CredentialStore>();
+    store
+        .record_failure("site-1", std::slice::from_ref(&credential_id), 1)
+        .expect("failed to record the failure");
+    let error = app.invoke("discover_credentials", json!({})).expect_err("a locked passkey was discovered");
+    assert_eq!(error, json!({ "code": "CredentialLocked" }));
+    // Discovery does not wipe the failures that locked it
+    let stored = app
+        .invoke("load_site_credential", json!({ "siteId": "site-1" }))
+        .expect("failed to load the site")["credentials"][0]
+        .clone();
+    assert_eq!(stored["failureCount"], 1);
+    assert_eq!(stored["locked"], true);
+}
+
+#[test]
+fn passkeys_of_removed_sites_are_purged() {
+    let app = TestApp::builder().build();
+    app.register("site-1").expect("registration failed");
+    app.register("site-2").expect("registration failed");
+    app.register("site-2").expect("registration failed");
+
+    let report = app
+        .invoke("purge_orphaned_credentials", json!({ "activeSiteIds": ["site-1"] }))
+        .expect("purge failed");
+    assert_eq!(report, json!({ "purged": 2, "errors": [] }));
+
+    let sites = app.invoke("list_protected_sites", json!({})).expect("failed to list the sites");
+    let site_ids: Vec<&Value> = sites.as_array().unwrap().iter().map(|site| &site["siteId"]).collect();
+    assert_eq!(site_ids, [&json!("site-1")]);
+}
+
+#[test]
+fn passkey_operations_are_recorded_in_the_audit_log() {
+    let app = TestApp::builder().build();
+    let registration = app.register("site-1").expect("registration failed");
+    app.authenticate("site-1", &registration["authConfig"]).expect("authentication failed");
+    let error = app
+        .invoke("delete_passkey", json!({ "siteId": "site-1", "credentialId": "bWlzc2luZw" }))
+        .expect_err("deleted a passkey the site does not have");
+    assert_eq!(error["code"], "CredentialNotFound");
+
+    let path = app.invoke("get_audit_log_path", json!({})).expect("failed to get the audit log path");
+    let path = std::path::PathBuf::from(path.as_str().unwrap());
+    let contents = std::fs::read_to_string(&path).expect("failed to read the audit log");
+    let records: Vec<Value> = contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    let summary: Vec<(&Value, &Value, &Value)> =
+        records.iter().map(|record| (&record["seq"], &record["event"], &record["outcome"])).collect();
+    assert_eq!(
+        summary,
+        [
+            (&json!(1), &json!("registration"), &json!("success")),
+            (&json!(2), &json!("authentication"), &json!("success")),
+            (&json!(3), &json!("passkey-deletion"), &json!("CredentialNotFound")),
+        ]
+    );
+    assert_eq!(records[1]["credentialId"], registration["authConfig"]["credentials"][0]["credentialId"]);
+    assert!(records[1]["durationMs"].is_u64());
+
+    let store = CredentialStore::with_key(path.parent().unwrap().to_path_buf(), [7; 32]);
+    assert_eq!(verify_audit_log(&store).expect("the audit log did not verify"), 3);
+    std::fs::write(&path, contents.replacen("\"success\"", "\"UserCancelled\"", 1)).unwrap();
+    assert!(verify_audit_log(&store).is_err(), "an altered record verified");
+}
+
+#[test]
+fn no_sync_refuses_passkeys_that_may_sync() {
+    let no_sync = WebAuthnConfig {
+        no_sync: Some(true),
+        ..Default::default()
+    };
+    let app = TestApp::builder()
+        .platform(MockPlatform::new().synced())
+        .config(no_sync.clone())
+        .build();
+    let error = app.register("site-1").expect_err("registered a passkey that may sync");
+    assert_eq!(error, json!({ "code": "SyncControlUnsupported" }));
+    let sites = app.invoke("list_protected_sites", json!({})).expect("failed to list the sites");
+    assert_eq!(sites, json!([]));
+
+    let app = TestApp::builder().config(no_sync).build();
+    let registration = app.register("site-1").expect("registration failed");
+    assert_eq!(registration["backupEligible"], false);
+}
+
+#[test]
+fn login_hints_list_the_passkeys_most_recently_used_first() {
+    let app = TestApp::builder().build();
+    let first = app.register("site-1").expect("registration failed");
+    app.register("site-2").expect("registration failed");
+    // Timestamps have millisecond precision
+    thread::sleep(Duration::from_millis(5));
+    app.authenticate("site-1", &first["authConfig"]).expect("authentication failed");
+
+    let hints = app.invoke("get_login_hints", json!({})).expect("failed to get login hints");
+    let hints = hints.as_array().unwrap();
+    assert_eq!(hints.len(), 2);
+    assert_keys(&hints[0], &["credentialId", "userDisplayName", "siteId", "deviceName"]);
+    assert_eq!(hints[0]["siteId"], "site-1");
+    assert_eq!(hints[0]["credentialId"], first["authConfig"]["credentials"][0]["credentialId"]);
+    assert_eq!(hints[0]["userDisplayName"], "Alice");
+    assert_eq!(hints[1]["siteId"], "site-2");
+
+    // Tests are debug builds, whose RP ID is localhost
+    let hints = app.invoke("get_login_hints", json!({ "rpId": "LOCALHOST" })).expect("failed to get login hints");
+    assert_eq!(hints.as_array().unwrap().len(), 2);
+    let hints = app.invoke("get_login_hints", json!({ "rpId": "example.com" })).expect("failed to get login hints");
+    assert_eq!(hints, json!([]));
+}
+
+#[test]
+fn user_display_name_can_be_changed_after_registration() {
+    let app = TestApp::builder().build();
+    let registration = app.register("site-1").expect("registration failed");
+    let credential_id = registration["authConfig"]["credentials"][0]["credentialId"].clone();
+    let update = |credential_id: &Value, display_name: &str| {
+        app.invoke(
+            "update_credential_display_name",
+            json!({ "siteId": "site-1", "credentialId": credential_id, "displayName": display_name }),
+        )
+    };
+
+    update(&credential_id, "Alice Liddell").expect("failed to update the display name");
+    let config = app
+        .invoke("load_site_credential", json!({ "siteId": "site-1" }))
+        .expect("failed to load the site");
+    assert_eq!(config["userDisplayName"], "Alice Liddell");
+
+    for display_name in [" ", "Alice\nLiddell", &"a".repeat(129)] {
+        let error = update(&credential_id, display_name).expect_err("accepted an invalid display name");
+        assert_eq!(error["code"], "InvalidInput");
+    }
+    let error = update(&json!("bWlzc2luZw"), "Alice").expect_err("renamed a passkey the site does not have");
+    assert_eq!(error["code"], "CredentialNotFound");
+}
+
+#[test]
+fn transient_platform_failures_are_retried() {
+    let app = TestApp::builder().platform(MockPlatform::new().fail_transiently(2)).build();
+    let registration = app.register("site-1").expect("registration failed");
+    let result = app
+        .authenticate("site-1", &registration["authConfig"])
+        .expect("authentication failed");
+    assert_eq!(result["attempts"], 3);
+
+    let two_attempts = WebAuthnConfig {
+        retry: Some(RetryConfig {
+            max_attempts: 2,
+            initial_backoff_ms: 1,
+            backoff_multiplier: 1.0,
+        }),
+        ..Default::default()
+    };
+    let app = TestApp::builder()
+        .platform(MockPlatform::new().fail_transiently(2))
+        .config(two_attempts)
+        .build();
+    let registration = app.register("site-1").expect("registration failed");
+    let error = app
+        .authenticate("site-1", &registration["authConfig"])
+        .expect_err("authenticated although every attempt failed");
+    assert_eq!(error["code"], "PlatformUnavailable");
+}
@@ -0,0 +1,86 @@
+// src/core/services/__tests__/siteAuthConfig.service.test.ts
+
+import { normalizeAuthConfig, normalizeManifestAuth } from '../siteAuthConfig.service';
+import type { Manifest } from '@/core/types';
+
+describe('siteAuthConfig.service', () => {
+  const createManifest = (auth: unknown): Manifest => ({
+    siteId: 'test-site',
+    generatorVersion: '1.0.0',
+    title: 'Test Site',
+    description: 'A test site',
+    theme: {
+      name: 'default',
+      config: {}
+    },
+    structure: [],
+    auth: auth as Manifest['auth']
+  });
+
+  describe('normalizeManifestAuth', () => {
+    it('should move the credential of a legacy manifest into credentials', () => {
+      const manifest = createManifest({
+        credentialId: 'legacy-id',
+        publicKey: 'legacy-key',
+        registeredAt: '2024-06-01T00:00:00.000Z',
+        requiresAuth: true,
+        userDisplayName: 'John Doe'
+      });
+
+      const normalized = normalizeManifestAuth(manifest);
+
+      expect(normalized.auth).toEqual({
+        credentials: [{
+          credentialId: 'legacy-id',
+          publicKey: 'legacy-key',
+          registeredAt: '2024-06-01T00:00:00.000Z'
+        }],
+        requiresAuth: true,
+        userDisplayName: 'John Doe'
+      });
+      // The settings page maps over these
+      expect(normalized.auth?.credentials.map(credential => credential.credentialId)).toEqual(['legacy-id']);
+    });
+
+    it('should give a legacy manifest without a credential an empty list', () => {
+      const normalized = normalizeManifestAuth(createManifest({ requiresAuth: false }));
+
+      expect(normalized.auth).toEqual({ credentials: [], requiresAuth: false });
+    });
+
+    it('should leave current manifests as they are', () => {
+      const auth = {
+        credentials: [{
+          credentialId: 'id',
+          publicKey: 'key',
+          registeredAt: '2025-01-01T00:00:00.000Z',
+          deviceName: 'Laptop'
+        }],
+        requiresAuth: true,
+        userId: 'dXNlcg'
+      };
+
+      expect(normalizeManifestAuth(createManifest(auth)).auth).toEqual(auth);
+    });
+
+    it('should leave manifests without auth as they are', () => {
+      const manifest = createManifest(undefined);
+
+      expect(normalizeManifestAuth(manifest)).toBe(manifest);
+    });
+  });
+
+  describe('normalizeAuthConfig', () => {
+    it('should not add a legacy credential twice', () => {
+      const credential = {
+        credentialId: 'id',
+        publicKey: 'key',
+        registeredAt: '2025-01-01T00:00:00.000Z'
+      };
+
+      const normalized = normalizeAuthConfig({ ...credential, credentials: [credential], requiresAuth: true });
+
+      expect(normalized).toEqual({ credentials: [credential], requiresAuth: true });
+    });
+  });
+});
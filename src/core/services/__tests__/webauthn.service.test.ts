@@ -110,11 +110,13 @@ describe('WebAuthnService', () => {
 
       expect(result.success).toBe(true);
       expect(result.authConfig).toEqual({
-        publicKey: 'mock-public-key',
-        credentialId: 'mock-credential-id',
+        credentials: [{
+          credentialId: 'mock-credential-id',
+          publicKey: 'mock-public-key',
+          registeredAt: expect.any(String)
+        }],
         requiresAuth: true,
-        userDisplayName: 'John Doe',
-        registeredAt: expect.any(String)
+        userDisplayName: 'John Doe'
       });
 
       expect(mockStartRegistration).toHaveBeenCalledWith({
@@ -165,11 +167,13 @@ describe('WebAuthnService', () => {
 
   describe('authenticateForSite', () => {
     const mockAuthConfig: SiteAuthConfig = {
-      publicKey: 'mock-public-key',
-      credentialId: 'mock-credential-id',
+      credentials: [{
+        credentialId: 'mock-credential-id',
+        publicKey: 'mock-public-key',
+        registeredAt: '2025-01-01T00:00:00.000Z'
+      }],
       requiresAuth: true,
-      userDisplayName: 'John Doe',
-      registeredAt: '2025-01-01T00:00:00.000Z'
+      userDisplayName: 'John Doe'
     };
 
     const mockAuthResponse = {
@@ -254,10 +258,12 @@ describe('WebAuthnService', () => {
     it('should return public status when requiresAuth is false', () => {
       const manifest = {
         auth: {
-          publicKey: 'key',
-          credentialId: 'id',
-          requiresAuth: false,
-          registeredAt: '2025-01-01T00:00:00.000Z'
+          credentials: [{
+            credentialId: 'id',
+            publicKey: 'key',
+            registeredAt: '2025-01-01T00:00:00.000Z'
+          }],
+          requiresAuth: false
         }
       };
       
@@ -273,10 +279,12 @@ describe('WebAuthnService', () => {
     it('should return private status for sites requiring auth', () => {
       const manifest = {
         auth: {
-          publicKey: 'key',
-          credentialId: 'id',
-          requiresAuth: true,
-          registeredAt: '2025-01-01T00:00:00.000Z'
+          credentials: [{
+            credentialId: 'id',
+            publicKey: 'key',
+            registeredAt: '2025-01-01T00:00:00.000Z'
+          }],
+          requiresAuth: true
         }
       };
       
@@ -2,6 +2,7 @@
 import { type LocalSiteData, type ParsedMarkdownFile, type Manifest, type RawFile } from '@/core/types';
 import localforage from 'localforage';
 import { stringifyToMarkdown, parseMarkdownString } from '@/core/libraries/markdownParser';
+import { normalizeManifestAuth } from './siteAuthConfig.service';
 
 const DB_NAME = 'SparktypeDB';
 
@@ -46,7 +47,7 @@ export async function loadAllSiteManifests(): Promise<Manifest[]> {
     });
     
     const iteratePromise = siteManifestsStore.iterate((value: Manifest) => {
-      manifests.push(value);
+      manifests.push(normalizeManifestAuth(value));
     });
     
     await Promise.race([iteratePromise, timeoutPromise]);
@@ -106,7 +107,7 @@ async function recoverIndexedDB(): Promise<void> {
  */
 export async function getManifestById(siteId: string): Promise<Manifest | null> {
   const manifest = await siteManifestsStore.getItem<Manifest>(siteId);
-  return manifest ?? null;
+  return manifest ? normalizeManifestAuth(manifest) : null;
 }
 
 /**
@@ -11,6 +11,7 @@ import type {
 import { parseMarkdownString } from '@/core/libraries/markdownParser';
 import { isTauriApp } from '@/core/utils/platform';
 import { importMediaManifest } from './images/mediaManifest.service';
+import { normalizeManifestAuth } from './siteAuthConfig.service';
 
 const SIGNUM_FOLDER = '_site';
 
@@ -94,7 +95,7 @@ export async function processSiteZip(zipData: ArrayBuffer): Promise<LocalSiteDat
   if (!manifestFile) {
     throw new Error(`Invalid site archive: manifest.json is missing from ${SIGNUM_FOLDER} folder.`);
   }
-  const manifest: Manifest = JSON.parse(await manifestFile.async('string'));
+  const manifest: Manifest = normalizeManifestAuth(JSON.parse(await manifestFile.async('string')));
 
   const secretsFile = signumFolder.file('secrets.json');
   const secrets: SiteSecrets = secretsFile ? JSON.parse(await secretsFile.async('string')) : {};
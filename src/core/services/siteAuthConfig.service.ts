@@ -0,0 +1,47 @@
+// src/core/services/siteAuthConfig.service.ts
+
+import type { Manifest } from '@/core/types';
+import type { CredentialEntry, SiteAuthConfig } from './webauthn.service';
+
+/**
+ * The single-credential shape manifests were written in before sites could
+ * hold several passkeys
+ */
+export interface LegacySiteAuthConfig {
+  credentials?: CredentialEntry[];
+  credentialId?: string;
+  publicKey?: string;
+  registeredAt?: string;
+  requiresAuth: boolean;
+  userDisplayName?: string;
+  userId?: string;
+  siteName?: string;
+}
+
+/**
+ * Brings an auth config to the current shape, moving a legacy top-level
+ * credential into `credentials`.
+ * Matches SiteAuthConfigRepr in src-tauri/src/webauthn.rs
+ * @param {SiteAuthConfig | LegacySiteAuthConfig} auth The auth config as stored.
+ * @returns {SiteAuthConfig} The auth config with every credential in `credentials`.
+ */
+export function normalizeAuthConfig(auth: SiteAuthConfig | LegacySiteAuthConfig): SiteAuthConfig {
+  const { credentialId, publicKey, registeredAt, credentials = [], ...rest } = auth as LegacySiteAuthConfig;
+  if (!credentialId || !publicKey || credentials.some(credential => credential.credentialId === credentialId)) {
+    return { ...rest, credentials };
+  }
+  return {
+    ...rest,
+    credentials: [...credentials, { credentialId, publicKey, registeredAt: registeredAt ?? new Date(0).toISOString() }],
+  };
+}
+
+/**
+ * Normalizes the auth config of a manifest as it is loaded, so manifests saved
+ * before sites could hold several passkeys read like current ones.
+ * @param {Manifest} manifest The manifest as stored or imported.
+ * @returns {Manifest} The manifest with its auth config in the current shape.
+ */
+export function normalizeManifestAuth(manifest: Manifest): Manifest {
+  return manifest.auth ? { ...manifest, auth: normalizeAuthConfig(manifest.auth) } : manifest;
+}
@@ -10,6 +10,7 @@ import type  {
 import { stringifyToMarkdown, parseMarkdownString } from '@/core/libraries/markdownParser';
 import { isCoreTheme, isCoreLayout } from './config/configHelpers.service';
 import * as localSiteFs from './localFileSystem.service';
+import { normalizeManifestAuth } from './siteAuthConfig.service';
 
 const SIGNUM_FOLDER = '_site';
 
@@ -86,7 +87,7 @@ export async function importSiteFromZip(zipFile: File): Promise<LocalSiteData &
     
     const manifestFile = signumFolder.file('manifest.json');
     if (!manifestFile) throw new Error("Invalid backup file: manifest.json is missing.");
-    const manifest: Manifest = JSON.parse(await manifestFile.async('string'));
+    const manifest: Manifest = normalizeManifestAuth(JSON.parse(await manifestFile.async('string')));
 
     const secretsFile = signumFolder.file('secrets.json');
     const secrets: SiteSecrets = secretsFile ? JSON.parse(await secretsFile.async('string')) : {};
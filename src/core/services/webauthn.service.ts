@@ -9,17 +9,49 @@ import {
 import { AUTH_CONFIG } from '@/config/editorConfig';
 import { isTauriApp } from '@/core/utils/platform';
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+/**
+ * A passkey registered for a site on one device
+ */
+export interface CredentialEntry {
+  credentialId: string;
+  publicKey: string;
+  registeredAt: string;
+  deviceName?: string;
+  lastUsedAt?: string;
+  /** Highest signature counter seen; 0 when the authenticator keeps none */
+  signCount?: number;
+  /** Failed sign-ins since the passkey last succeeded */
+  failureCount?: number;
+  /** Locked after too many failed sign-ins, until unlockCredential */
+  locked?: boolean;
+}
 
 /**
  * Configuration for site-specific WebAuthn authentication
  * Stored in manifest.json to persist authentication settings
  */
 export interface SiteAuthConfig {
-  publicKey: string;
-  credentialId: string;
+  credentials: CredentialEntry[];
   requiresAuth: boolean;
   userDisplayName?: string;
-  registeredAt: string;
+  /** Opaque WebAuthn user handle (base64url), set by the native app */
+  userId?: string;
+  /** Site name the latest passkey was registered under, set by the native app */
+  siteName?: string;
+}
+
+/**
+ * Time spent in each phase of a native authentication
+ * Matches the OperationTimings struct in src-tauri/src/webauthn.rs
+ */
+export interface OperationTimings {
+  challengeGenUs: number;
+  /** Includes the time the user spends in the system prompt */
+  platformApiMs: number;
+  sessionWriteUs: number;
+  totalMs: number;
 }
 
 /**
@@ -29,6 +61,40 @@ export interface AuthenticationResult {
   success: boolean;
   error?: string;
   credentialId?: string;
+  /** Site name stored with the passkeys, for display; native authentication only */
+  siteName?: string | null;
+  /** Signature counter reported with the assertion; native authentication only */
+  signCount?: number;
+  /**
+   * The signature counter did not advance, so the passkey may have been copied
+   * to another authenticator. Authentication still succeeded; warn the user.
+   */
+  possibleCloneDetected?: boolean;
+  /** 'platform' or 'cross-platform': the authenticator used; native authentication only */
+  authenticatorAttachment?: string;
+  /**
+   * PRF extension output (base64url) when `prf` was given and the authenticator
+   * evaluated it. This is key material: never log or persist it.
+   */
+  prfOutput?: string;
+  /** largeBlob data (base64url) stored with the credential, when `largeBlobRead` was set */
+  largeBlobData?: string;
+  /** Time spent per phase; native debug builds, or with SPARKTYPE_PERF_TRACE=1 */
+  timings?: OperationTimings;
+  /** Set when attempts for the site are throttled; retry after this many seconds */
+  retryAfterSeconds?: number;
+  /** ID the native logs tag this attempt with; native authentication only */
+  operationId?: string;
+  /**
+   * The site authenticated moments ago, within its grace period, so no prompt
+   * was shown and `credentialId` is unset; native authentication only
+   */
+  fromCache?: boolean;
+  /**
+   * Times the platform was asked, more than 1 when it failed in a way that may
+   * pass and was retried; 0 when no prompt was shown. Native authentication only
+   */
+  attempts?: number;
 }
 
 /**
@@ -37,9 +103,536 @@ export interface AuthenticationResult {
 export interface RegistrationResult {
   success: boolean;
   authConfig?: SiteAuthConfig;
+  /** Authenticator model (UUID), when disclosed; native registrations only */
+  aaguid?: string | null;
+  /** Initial signature counter; native registrations only */
+  signCount?: number;
+  /** 'platform' or 'cross-platform'; native registrations only */
+  authenticatorAttachment?: string;
+  /** Whether the passkey is discoverable; native registrations only */
+  isResident?: boolean;
+  /** Whether the authenticator may sync the passkey to other devices; native registrations only */
+  backupEligible?: boolean;
+  /**
+   * Whether the passkey is synced through iCloud Keychain to the user's other
+   * Apple devices; null off Apple platforms
+   */
+  icloudSynced?: boolean | null;
+  /** The credentials were imported from an exported config rather than created */
+  wasImported?: boolean;
+  /** ID the native logs tag this registration with; native registrations only */
+  operationId?: string;
   error?: string;
 }
 
+/**
+ * A passkey the user picked without naming one upfront
+ * Matches the DiscoveredCredential struct in src-tauri/src/webauthn.rs
+ */
+export interface DiscoveredCredential {
+  credentialId: string;
+  userHandle: string | null;
+  /** The site the passkey belongs to, when the native credential store knows it */
+  siteId: string | null;
+  signCount: number;
+}
+
+/**
+ * Whether a site's passkey can be used on this device, checked without a prompt
+ * Matches the CredentialStatus struct in src-tauri/src/webauthn.rs
+ */
+export interface CredentialStatus {
+  /** The authenticator still holds one of the site's passkeys, as far as the platform can tell */
+  existsLocally: boolean;
+  /** The device can verify the user right now */
+  platformAvailable: boolean;
+  /** RFC 3339 timestamp of the site's last successful authentication */
+  lastVerifiedAt: string | null;
+}
+
+/**
+ * Whether a site can be edited without authenticating again
+ * Matches the SiteAuthStatus struct in src-tauri/src/session_store.rs
+ */
+export interface SiteAuthStatus {
+  /** The native credential store holds a passkey for the site */
+  hasCredential: boolean;
+  /** The site was authenticated and the session has not expired */
+  isAuthenticated: boolean;
+  /** RFC 3339 timestamp of the authentication that opened the session */
+  authenticatedAt: string | null;
+  /** RFC 3339 timestamp at which the session ends */
+  expiresAt: string | null;
+}
+
+/**
+ * A site that has passkeys, from listProtectedSites
+ * Matches the SiteCredentialSummary struct in src-tauri/src/credential_store.rs
+ */
+export interface SiteCredentialSummary {
+  siteId: string;
+  siteName: string | null;
+  credentialCount: number;
+  /** RFC 3339 timestamp of the latest use of any of the site's passkeys */
+  mostRecentUse: string | null;
+  /** RFC 3339 timestamp of the site's first passkey registration */
+  registeredAt: string;
+}
+
+/**
+ * A passkey to suggest before the biometric prompt, from getLoginHints
+ * Matches the LoginHint struct in src-tauri/src/credential_store.rs
+ */
+export interface LoginHint {
+  credentialId: string;
+  userDisplayName: string | null;
+  siteId: string;
+  deviceName: string | null;
+}
+
+/**
+ * What purgeOrphanedCredentials removed
+ * Matches the PurgeReport struct in src-tauri/src/credential_purge.rs
+ */
+export interface PurgeReport {
+  /** Passkeys deleted */
+  purged: number;
+  /** Why each passkey that is still there could not be deleted */
+  errors: string[];
+}
+
+/**
+ * What emergencyWipe removed
+ * Matches the WipeReport struct in src-tauri/src/emergency_wipe.rs
+ */
+export interface WipeReport {
+  /** Passkeys deleted from the platform authenticator */
+  credentialsDeleted: number;
+  /** Sessions ended that had not expired yet */
+  sessionsCleared: number;
+  /** Paths of the credential store files removed */
+  filesRemoved: string[];
+  /** What could not be deleted, and why */
+  errors: string[];
+}
+
+/**
+ * Narrows down listProtectedSites; every condition given must hold
+ * Matches the SiteFilter struct in src-tauri/src/credential_store.rs
+ */
+export interface SiteFilter {
+  /** Case-insensitive text the site's ID or name must contain */
+  nameContains?: string;
+  /** RFC 3339 timestamp the site's first passkey must be registered after */
+  registeredAfter?: string;
+}
+
+/**
+ * A site's recent authentication attempts
+ * Matches the RateLimitEntry struct in src-tauri/src/rate_limiter.rs
+ */
+export interface RateLimitEntry {
+  siteId: string;
+  /** Attempts within the current window */
+  attempts: number;
+  /** Set while the site has no attempts left */
+  retryAfterSeconds: number | null;
+}
+
+/**
+ * State of the native WebAuthn subsystem, for diagnostics
+ * Matches the HealthReport struct in src-tauri/src/health_check.rs
+ */
+export interface HealthReport {
+  platform: string;
+  rpId: string;
+  challengeTtlSeconds: number;
+  activeSessions: number;
+  /** Only reported in developer mode */
+  storedCredentialSites?: string[];
+  /** Only reported in developer mode */
+  rateLimitStates?: RateLimitEntry[];
+  /** RFC 3339 timestamp of the last completed background credential refresh */
+  lastCredentialRefresh: string | null;
+  pluginVersion: string;
+  /** Short hash of the commit the app was built from; `unknown` outside a git checkout */
+  gitSha: string;
+  /** RFC 3339 timestamp of the build */
+  buildTimestamp: string;
+  /** The system clock read a time before 2001 at launch; warn that passkey dates will be wrong */
+  clockSkewDetected: boolean;
+}
+
+/**
+ * Native ceremony counts since launch or the last reset
+ * Matches the WebAuthnMetricsSnapshot struct in src-tauri/src/metrics.rs
+ */
+export interface WebAuthnMetricsSnapshot {
+  registrationAttempts: number;
+  registrationSuccesses: number;
+  registrationFailures: number;
+  authenticationAttempts: number;
+  authenticationSuccesses: number;
+  authenticationFailures: number;
+  rateLimitHits: number;
+  challengeExpirations: number;
+}
+
+/**
+ * Outcome of checking an assertion signature natively
+ * Matches the VerificationResult struct in src-tauri/src/signature_verification.rs
+ */
+export interface AssertionVerificationResult {
+  /** The signature verifies and the assertion is scoped to the editing domain */
+  verified: boolean;
+  signCount: number;
+  rpIdHashMatches: boolean;
+}
+
+/**
+ * Summary of a written credential backup
+ * Matches the ExportResult struct in src-tauri/src/credential_backup.rs
+ */
+export interface ExportResult {
+  siteCount: number;
+  credentialCount: number;
+}
+
+/**
+ * Summary of a credential backup merged into the native store
+ * Matches the ImportResult struct in src-tauri/src/credential_backup.rs
+ */
+export interface ImportResult {
+  importedCount: number;
+  /** Credentials the store already held */
+  skippedCount: number;
+}
+
+/**
+ * A request for another device's sites, answered by one offer within 60 seconds
+ * Matches the SyncRequest struct in src-tauri/src/credential_sync.rs
+ */
+export interface SyncRequest {
+  /** What generateSyncOffer takes on the other device */
+  payload: string;
+  /** `payload` as a QR code, a base64-encoded PNG */
+  qrCodePng: string;
+  /** RFC 3339 timestamp at which this device stops accepting an offer for the request */
+  expiresAt: string;
+}
+
+/**
+ * Sites offered to the device that made a sync request
+ * Matches the SyncOffer struct in src-tauri/src/credential_sync.rs
+ */
+export interface SyncOffer {
+  /** What acceptSyncOffer takes on the other device */
+  payload: string;
+  /** `payload` as a QR code, a base64-encoded PNG */
+  qrCodePng: string;
+}
+
+/**
+ * Summary of a sync offer merged into the native store
+ * Matches the SyncAcceptResult struct in src-tauri/src/credential_sync.rs
+ */
+export interface SyncAcceptResult {
+  siteIds: string[];
+  importedCount: number;
+  /** Credentials the store already held */
+  skippedCount: number;
+}
+
+/**
+ * A site configuration kept by the frontend, handed to the native store
+ */
+export interface FrontendSiteConfig extends SiteAuthConfig {
+  siteId: string;
+}
+
+/**
+ * A credential, or a whole site, the native store refused to migrate
+ * Matches the MigrationError struct in src-tauri/src/credential_migration.rs
+ */
+export interface MigrationError {
+  siteId: string;
+  /** Null when the site itself was rejected */
+  credentialId: string | null;
+  reason: string;
+}
+
+/**
+ * Outcome of moving frontend credentials into the native store
+ * Matches the MigrationReport struct in src-tauri/src/credential_migration.rs
+ */
+export interface MigrationReport {
+  migrated: number;
+  /** Credentials the store already held */
+  skipped: number;
+  errors: MigrationError[];
+}
+
+/**
+ * Kinds of authenticators the native app can use
+ * Matches the WebAuthnCapabilities struct in src-tauri/src/webauthn.rs
+ */
+export interface WebAuthnCapabilities {
+  platformAuthenticator: boolean;
+  roamingAuthenticator: boolean;
+  residentKeySupport: boolean;
+  userVerificationSupport: boolean;
+  /** Platform passkeys are kept in secure hardware; null where the platform does not say */
+  hardwareBacked: boolean | null;
+  /** OS release to upgrade to for platform passkeys, e.g. "macOS 13"; null when this one is recent enough */
+  minOsVersionRequired: string | null;
+}
+
+/**
+ * How strongly a ceremony asks the authenticator to verify the user
+ * Matches the UserVerification enum in src-tauri/src/webauthn.rs
+ */
+export type UserVerification = 'required' | 'preferred' | 'discouraged';
+
+/**
+ * Whether registration should create a discoverable passkey
+ * Matches the ResidentKeyRequirement enum in src-tauri/src/webauthn.rs
+ */
+export type ResidentKeyRequirement = 'required' | 'preferred' | 'discouraged';
+
+/**
+ * Which kind of authenticator a ceremony may use: the built-in one, a security
+ * key over USB, NFC or Bluetooth, or whichever the user picks
+ * Matches the AuthenticatorAttachment enum in src-tauri/src/webauthn.rs
+ */
+export type AuthenticatorAttachment = 'platform' | 'cross-platform' | 'any';
+
+/**
+ * How far the first passkey setup got; registering advances it
+ * Matches the OnboardingState enum in src-tauri/src/onboarding.rs
+ */
+export type OnboardingState = 'not-started' | 'platform-check-done' | 'registration-in-progress' | 'complete';
+
+/**
+ * When the app locks itself after the user goes idle
+ * Matches the AppLockConfig struct in src-tauri/src/app_lock.rs
+ */
+export interface AppLockConfig {
+  /** Seconds without recorded activity before every session ends, at most 86400; 0 turns the lock off */
+  idleTimeoutSeconds: number;
+  /** The authentication that unlocks the app must verify the user */
+  requireBiometric?: boolean;
+}
+
+/**
+ * A sign-in waiting for a passkey on another device, from initiateHybridAuth
+ * Matches the HybridAuthSession struct in src-tauri/src/hybrid_auth.rs
+ */
+export interface HybridAuthSession {
+  /** What pollHybridAuth takes */
+  sessionToken: string;
+  /** The link the other device scans */
+  url: string;
+  /** `url` as a QR code, a base64-encoded PNG */
+  qrCodePng: string;
+  /** RFC 3339 timestamp at which the session ends */
+  expiresAt: string;
+}
+
+/**
+ * How far a sign-in with a passkey on another device got
+ * Matches the HybridAuthPollResult enum in src-tauri/src/hybrid_auth.rs
+ */
+export type HybridAuthPollResult =
+  | { status: 'pending' }
+  | { status: 'completed'; siteId: string; credentialId: string; response: unknown };
+
+/**
+ * A PRF extension input; only `first` is evaluated
+ * Matches the PrfValues struct in src-tauri/src/webauthn.rs
+ */
+export interface PrfValues {
+  /** base64url-encoded */
+  first: string;
+}
+
+/**
+ * Inputs for deriving a key from a passkey with the WebAuthn PRF extension
+ * Matches the PrfExtensionInput struct in src-tauri/src/webauthn.rs
+ */
+export interface PrfExtensionInput {
+  eval: PrfValues;
+  /** Inputs for particular credentials, keyed by base64url credential ID */
+  evalByCredential?: Record<string, PrfValues>;
+}
+
+/**
+ * Progress of a native passkey ceremony, emitted while the command is pending,
+ * a site whose passkeys the launch-time credential refresh or shouldReenroll
+ * found missing, a site whose native session was ended, a refused clipboard write,
+ * a sign-in asked for by a webauthn:// deep link, or the report of an emergencyWipe
+ * Matches the WebAuthnEvent enum in src-tauri/src/webauthn_events.rs
+ */
+export type WebAuthnEvent =
+  | { type: 'registrationStarted'; siteId: string }
+  | { type: 'biometricPromptShown'; ceremony: 'registration' | 'authentication' }
+  | {
+      type: 'registrationCompleted';
+      siteId: string;
+      credentialId: string | null;
+      error: TauriWebAuthnError | null;
+    }
+  | { type: 'authenticationStarted'; siteId: string }
+  | {
+      type: 'authenticationCompleted';
+      siteId: string;
+      credentialId: string | null;
+      error: TauriWebAuthnError | null;
+    }
+  | { type: 'credentialStale'; siteId: string }
+  | { type: 'reenrollmentRequired'; siteId: string }
+  | { type: 'sessionInvalidated'; siteId: string }
+  | { type: 'clipboardBlocked'; severity: 'warning' }
+  | { type: 'deepLinkReceived'; siteId: string; credentialId: string }
+  | { type: 'clockSkewDetected' }
+  | { type: 'appLocked' }
+  | { type: 'emergencyWipeCompleted'; severity: 'critical'; report: WipeReport };
+
+const WEBAUTHN_EVENTS = [
+  'webauthn://registration-started',
+  'webauthn://biometric-prompt-shown',
+  'webauthn://registration-completed',
+  'webauthn://authentication-started',
+  'webauthn://authentication-completed',
+  'webauthn://credential-stale',
+  'webauthn://reenrollment-required',
+  'webauthn://session-invalidated',
+  'webauthn://clipboard-blocked',
+  'webauthn://deep-link-received',
+  'webauthn://clock-skew-detected',
+  'webauthn://app-locked',
+  'webauthn://emergency-wipe-completed',
+] as const;
+
+/**
+ * Error rejected by the native WebAuthn commands
+ * Matches the WebAuthnError enum in src-tauri/src/webauthn.rs
+ */
+type TauriWebAuthnError =
+  | {
+      code:
+        | 'PlatformNotSupported'
+        | 'UserCancelled'
+        | 'InvalidChallenge'
+        | 'InvalidInput'
+        | 'InvalidChallengeLength'
+        | 'CredentialNotFound'
+        | 'BiometricFailed'
+        | 'UserVerificationFailed'
+        | 'ResidentKeyNotSupported'
+        | 'Unauthorized'
+        | 'RegistrationInProgress'
+        | 'ReplayAttack'
+        | 'Timeout'
+        | 'SignatureInvalid'
+        | 'NotImplemented'
+        | 'CredentialLocked'
+        | 'SyncControlUnsupported'
+        | 'RpIdTampered'
+        | 'PlatformUnavailable'
+        | 'InternalError';
+      detail?: string;
+    }
+  | { code: 'RateLimited'; detail: { retryAfterSeconds: number } }
+  | { code: 'LimitExceeded'; detail: { current: number; max: number } };
+
+function isTauriWebAuthnError(error: unknown): error is TauriWebAuthnError {
+  return typeof error === 'object' && error !== null && 'code' in error;
+}
+
+/**
+ * Turn a rejected native WebAuthn command into a user-facing message
+ */
+function describeTauriError(error: unknown, fallback: string): string {
+  if (!isTauriWebAuthnError(error)) {
+    return error instanceof Error ? error.message : fallback;
+  }
+
+  switch (error.code) {
+    case 'PlatformNotSupported':
+      return 'WebAuthn not supported on this platform';
+    case 'UserCancelled':
+      return 'Authentication was cancelled';
+    case 'InvalidChallenge':
+      return 'Invalid authentication challenge';
+    case 'InvalidInput':
+      return error.detail ?? 'Invalid input';
+    case 'InvalidChallengeLength':
+      return 'Challenge length must be between 16 and 64 bytes';
+    case 'CredentialNotFound':
+      return 'Credential verification failed';
+    case 'BiometricFailed':
+      return 'Biometric verification failed';
+    case 'UserVerificationFailed':
+      return 'This device cannot verify your identity with biometrics or a PIN';
+    case 'ResidentKeyNotSupported':
+      return 'This authenticator cannot store a passkey that signs in without a username';
+    case 'RateLimited':
+      return `Too many sign-in attempts, try again in ${error.detail.retryAfterSeconds} seconds`;
+    case 'Unauthorized':
+      return 'This window is not allowed to use this passkey feature';
+    case 'RegistrationInProgress':
+      return 'A passkey is already being registered for this site';
+    case 'ReplayAttack':
+      return 'This sign-in request was already used; please try again';
+    case 'Timeout':
+      return 'Authentication timed out';
+    case 'SignatureInvalid':
+      return 'The passkey signature could not be verified';
+    case 'NotImplemented':
+      return 'This passkey feature is not available yet';
+    case 'CredentialLocked':
+      return 'This passkey is locked after too many failed sign-ins; unlock it in the passkey settings';
+    case 'SyncControlUnsupported':
+      return 'Passkeys must stay on this device, but this authenticator only creates ones that sync; try a security key';
+    case 'RpIdTampered':
+      return 'This build of the app is not configured for its passkey domain; reinstall it from the official download';
+    case 'PlatformUnavailable':
+      return 'The passkey service is busy, please try again in a moment';
+    case 'LimitExceeded':
+      return `You have ${error.detail.current}/${error.detail.max} devices registered, please remove one before adding another`;
+    default:
+      return error.detail ?? fallback;
+  }
+}
+
+/**
+ * Oldest native WebAuthn plugin this frontend works with
+ * Raise it when the frontend starts relying on a credential format or command
+ * that older plugins lack
+ */
+const MIN_NATIVE_PLUGIN_VERSION = '0.1.0';
+
+/**
+ * Compare two semver versions by their major, minor and patch numbers
+ *
+ * @returns Less than, equal to or greater than 0 as `a` is older than, the same as or newer than `b`
+ */
+function compareVersions(a: string, b: string): number {
+  const parse = (version: string) =>
+    version
+      .split(/[-+]/)[0]
+      .split('.')
+      .map((part) => Number(part) || 0);
+  const [left, right] = [parse(a), parse(b)];
+  for (let i = 0; i < 3; i++) {
+    const difference = (left[i] ?? 0) - (right[i] ?? 0);
+    if (difference !== 0) {
+      return difference;
+    }
+  }
+  return 0;
+}
+
 /**
  * WebAuthn service for managing site-specific authentication in Sparktype
  * 
@@ -105,6 +698,78 @@ export class WebAuthnService {
     return AUTH_CONFIG.EDITING_DOMAIN;
   }
 
+  /**
+   * Get the RP ID the native backend registers and authenticates against
+   * Useful to confirm it matches getEditingDomain() when debugging failures
+   *
+   * @returns The native RP ID, or null outside the Tauri app
+   */
+  async getNativeRpId(): Promise<string | null> {
+    if (!isTauriApp()) {
+      return null;
+    }
+    return invoke<string>('get_rp_id');
+  }
+
+  /**
+   * Check the native WebAuthn plugin is recent enough for this frontend
+   * Call on startup: an older plugin may not read the credentials this frontend
+   * stores, so asking for an update beats failing on them later
+   *
+   * @returns Success outside the Tauri app, and with the version for a recent enough plugin
+   */
+  async checkNativePluginVersion(): Promise<{ success: boolean; version?: string; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: true };
+    }
+
+    try {
+      const version = await invoke<string>('get_plugin_version');
+      if (compareVersions(version, MIN_NATIVE_PLUGIN_VERSION) < 0) {
+        return {
+          success: false,
+          version,
+          error: `Please update the app to keep using passkeys (version ${MIN_NATIVE_PLUGIN_VERSION} or later is needed)`,
+        };
+      }
+      return { success: true, version };
+    } catch (error) {
+      // Plugins from before get_plugin_version do not have the command
+      console.error('Failed to get the native plugin version:', error);
+      return { success: false, error: 'Please update the app to keep using passkeys' };
+    }
+  }
+
+  /**
+   * Get which kinds of authenticators the native app can use, e.g. to tailor
+   * onboarding to a built-in authenticator or a security key
+   *
+   * @returns The native capabilities, or null outside the Tauri app
+   */
+  async getNativeCapabilities(): Promise<WebAuthnCapabilities | null> {
+    if (!isTauriApp()) {
+      return null;
+    }
+    return invoke<WebAuthnCapabilities>('get_webauthn_capabilities');
+  }
+
+  /**
+   * Follow the progress of native passkey ceremonies, e.g. to show
+   * "waiting for Touch ID" while a registration is pending
+   *
+   * @returns A function that stops listening; a no-op outside the Tauri app
+   */
+  async onWebAuthnEvent(listener: (event: WebAuthnEvent) => void): Promise<UnlistenFn> {
+    if (!isTauriApp()) {
+      return () => {};
+    }
+
+    const unlisteners = await Promise.all(
+      WEBAUTHN_EVENTS.map((name) => listen<WebAuthnEvent>(name, (event) => listener(event.payload)))
+    );
+    return () => unlisteners.forEach((unlisten) => unlisten());
+  }
+
   /**
    * Check if WebAuthn is supported in the current browser environment
    * 
@@ -145,6 +810,11 @@ export class WebAuthnService {
    * @param siteId - Unique identifier for the site
    * @param siteName - Human-readable site name for user display
    * @param userDisplayName - Display name for the credential owner
+   * @param userVerification - Whether biometrics or a PIN must be used
+   * @param promptMessage - Reason shown by the native prompt, where the platform allows one (max 128 characters)
+   * @param residentKey - Whether the passkey should be discoverable; native registrations only
+   * @param authenticatorAttachment - Built-in authenticator, security key or either; defaults to the
+   *   built-in one where there is one
    * @returns Promise resolving to registration result with auth config
    *
    * @example
@@ -164,16 +834,34 @@ export class WebAuthnService {
   async registerCredential(
     siteId: string,
     siteName: string,
-    userDisplayName = 'Site Owner'
+    userDisplayName = 'Site Owner',
+    userVerification: UserVerification = 'preferred',
+    promptMessage?: string,
+    residentKey: ResidentKeyRequirement = 'preferred',
+    authenticatorAttachment?: AuthenticatorAttachment
   ): Promise<RegistrationResult> {
     try {
       // Check if running in Tauri environment
       if (isTauriApp()) {
-        return await this.registerCredentialTauri(siteId, siteName, userDisplayName);
+        return await this.registerCredentialTauri(
+          siteId,
+          siteName,
+          userDisplayName,
+          userVerification,
+          promptMessage,
+          residentKey,
+          authenticatorAttachment
+        );
       }
 
       // Web browser registration
-      return await this.registerCredentialWeb(siteId, siteName, userDisplayName);
+      return await this.registerCredentialWeb(
+        siteId,
+        siteName,
+        userDisplayName,
+        userVerification,
+        authenticatorAttachment ?? 'platform'
+      );
     } catch (error) {
       console.error('WebAuthn registration failed:', error);
       return {
@@ -190,7 +878,9 @@ export class WebAuthnService {
   private async registerCredentialWeb(
     siteId: string,
     siteName: string,
-    userDisplayName: string
+    userDisplayName: string,
+    userVerification: UserVerification,
+    authenticatorAttachment: AuthenticatorAttachment
   ): Promise<RegistrationResult> {
     if (!this.isSupported()) {
       return { success: false, error: 'WebAuthn not supported in this browser' };
@@ -213,8 +903,9 @@ export class WebAuthnService {
         { alg: -257, type: 'public-key' }, // RS256
       ],
       authenticatorSelection: {
-        authenticatorAttachment: 'platform',
-        userVerification: 'preferred',
+        // Left out to let the user pick
+        authenticatorAttachment: authenticatorAttachment === 'any' ? undefined : authenticatorAttachment,
+        userVerification,
         requireResidentKey: false,
       },
       timeout: 60000,
@@ -225,11 +916,13 @@ export class WebAuthnService {
 
     // Create auth config from successful registration
     const authConfig: SiteAuthConfig = {
-      publicKey: registrationResponse.response.publicKey!,
-      credentialId: registrationResponse.id,
+      credentials: [{
+        credentialId: registrationResponse.id,
+        publicKey: registrationResponse.response.publicKey!,
+        registeredAt: new Date().toISOString(),
+      }],
       requiresAuth: true,
       userDisplayName,
-      registeredAt: new Date().toISOString(),
     };
 
     // Cache the credential locally
@@ -245,13 +938,21 @@ export class WebAuthnService {
   private async registerCredentialTauri(
     siteId: string,
     siteName: string,
-    userDisplayName: string
+    userDisplayName: string,
+    userVerification: UserVerification,
+    promptMessage: string | undefined,
+    residentKey: ResidentKeyRequirement,
+    authenticatorAttachment: AuthenticatorAttachment | undefined
   ): Promise<RegistrationResult> {
     try {
       const result = await invoke('register_passkey', {
         siteId,
         siteName,
         userDisplayName,
+        userVerification,
+        promptMessage,
+        residentKey,
+        authenticatorAttachment,
       });
 
       return result as RegistrationResult;
@@ -259,7 +960,7 @@ export class WebAuthnService {
       console.error('Tauri WebAuthn registration failed:', error);
       return {
         success: false,
-        error: error instanceof Error ? error.message : 'Tauri registration failed'
+        error: describeTauriError(error, 'Tauri registration failed')
       };
     }
   }
@@ -277,6 +978,15 @@ export class WebAuthnService {
    *
    * @param siteId - The site requesting authentication
    * @param authConfig - The auth configuration from manifest.json
+   * @param userVerification - Whether biometrics or a PIN must be used
+   * @param promptMessage - Reason shown by the native prompt, where the platform allows one (max 128 characters)
+   * @param authenticatorAttachment - Built-in authenticator, security key or either; defaults to the
+   *   built-in one where there is one; native authentication only
+   * @param prf - PRF extension inputs, to derive a key returned as `prfOutput`; native authentication only
+   * @param largeBlobWrite - Data (base64url, up to about 2 KB) to store with the credential; native authentication only
+   * @param largeBlobRead - Read the stored data back as `largeBlobData`; cannot be combined with `largeBlobWrite`
+   * @param timeoutSeconds - How long to wait for the native prompt (default 60, clamped to 10–300);
+   *   native authentication only
    * @returns Promise resolving to authentication result
    *
    * @example
@@ -296,7 +1006,14 @@ export class WebAuthnService {
    */
   async authenticateForSite(
     siteId: string,
-    authConfig: SiteAuthConfig
+    authConfig: SiteAuthConfig,
+    userVerification: UserVerification = 'preferred',
+    promptMessage?: string,
+    authenticatorAttachment?: AuthenticatorAttachment,
+    prf?: PrfExtensionInput,
+    largeBlobWrite?: string,
+    largeBlobRead?: boolean,
+    timeoutSeconds?: number
   ): Promise<AuthenticationResult> {
     try {
       if (!authConfig.requiresAuth) {
@@ -305,11 +1022,21 @@ export class WebAuthnService {
 
       // Check if running in Tauri environment
       if (isTauriApp()) {
-        return await this.authenticateForSiteTauri(siteId, authConfig);
+        return await this.authenticateForSiteTauri(
+          siteId,
+          authConfig,
+          userVerification,
+          promptMessage,
+          authenticatorAttachment,
+          prf,
+          largeBlobWrite,
+          largeBlobRead,
+          timeoutSeconds
+        );
       }
 
       // Web browser authentication
-      return await this.authenticateForSiteWeb(siteId, authConfig);
+      return await this.authenticateForSiteWeb(siteId, authConfig, userVerification);
     } catch (error) {
       console.error('WebAuthn authentication failed:', error);
       return {
@@ -325,7 +1052,8 @@ export class WebAuthnService {
    */
   private async authenticateForSiteWeb(
     siteId: string,
-    authConfig: SiteAuthConfig
+    authConfig: SiteAuthConfig,
+    userVerification: UserVerification
   ): Promise<AuthenticationResult> {
     if (!this.isSupported()) {
       return { success: false, error: 'WebAuthn not supported in this browser' };
@@ -334,18 +1062,18 @@ export class WebAuthnService {
     // Generate authentication options
     const authenticationOptions: PublicKeyCredentialRequestOptionsJSON = {
       challenge: this.generateChallenge(),
-      allowCredentials: [{
-        id: authConfig.credentialId,
+      allowCredentials: authConfig.credentials.map(credential => ({
+        id: credential.credentialId,
         type: 'public-key',
         transports: ['internal', 'hybrid'],
-      }],
-      userVerification: 'preferred',
+      })),
+      userVerification,
       timeout: 60000,
     };
 
     const authenticationResponse = await startAuthentication({ optionsJSON: authenticationOptions });
 
-    if (authenticationResponse.id === authConfig.credentialId) {
+    if (authConfig.credentials.some(credential => credential.credentialId === authenticationResponse.id)) {
       // Cache successful authentication
       this.registeredCredentials.set(siteId, authConfig);
 
@@ -364,40 +1092,872 @@ export class WebAuthnService {
    */
   private async authenticateForSiteTauri(
     siteId: string,
-    authConfig: SiteAuthConfig
+    authConfig: SiteAuthConfig,
+    userVerification: UserVerification,
+    promptMessage?: string,
+    authenticatorAttachment?: AuthenticatorAttachment,
+    prf?: PrfExtensionInput,
+    largeBlobWrite?: string,
+    largeBlobRead?: boolean,
+    timeoutSeconds?: number
   ): Promise<AuthenticationResult> {
     try {
+      const challenge = await invoke<string>('create_challenge', { siteId });
       const result = await invoke('authenticate_passkey', {
         siteId,
         authConfig,
+        challenge,
+        userVerification,
+        promptMessage,
+        authenticatorAttachment,
+        prf,
+        largeBlobWrite,
+        largeBlobRead,
+        timeoutSeconds,
       });
 
       return result as AuthenticationResult;
     } catch (error) {
       console.error('Tauri WebAuthn authentication failed:', error);
+      return {
+        success: false,
+        error: describeTauriError(error, 'Tauri authentication failed'),
+        ...(isTauriWebAuthnError(error) && error.code === 'RateLimited'
+          ? { retryAfterSeconds: error.detail.retryAfterSeconds }
+          : {})
+      };
+    }
+  }
 
-      // Handle user cancellation gracefully
-      if (error instanceof Error && error.message.includes('cancelled')) {
-        return { success: false, error: 'Authentication was cancelled' };
-      }
+  /**
+   * Sign in with a discoverable passkey, letting the user pick which one
+   *
+   * @param rpId - Relying party to discover passkeys for; defaults to the editing domain
+   */
+  async discoverCredentials(
+    rpId?: string
+  ): Promise<{ success: boolean; credentials?: DiscoveredCredential[]; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Passkey discovery is only available in the desktop app' };
+    }
 
+    try {
+      const credentials = await invoke<DiscoveredCredential[]>('discover_credentials', { rpId });
+      return { success: true, credentials };
+    } catch (error) {
+      console.error('Passkey discovery failed:', error);
       return {
         success: false,
-        error: error instanceof Error ? error.message : 'Tauri authentication failed'
+        error: describeTauriError(error, 'Passkey discovery failed')
       };
     }
   }
 
   /**
-   * Remove authentication from a site (make it public)
+   * Check whether a site's passkey is usable on this device without prompting the user
+   *
+   * Useful before showing a locked document, to skip a ceremony that cannot succeed.
    */
-  async removeAuthentication(siteId: string): Promise<boolean> {
+  async verifyStoredCredential(siteId: string): Promise<CredentialStatus | null> {
+    if (!isTauriApp()) {
+      return null;
+    }
+
     try {
-      this.registeredCredentials.delete(siteId);
-      return true;
+      return await invoke<CredentialStatus>('verify_stored_credential', { siteId });
     } catch (error) {
-      console.error('Failed to remove authentication:', error);
-      return false;
+      console.error('Failed to verify stored credential:', error);
+      return null;
+    }
+  }
+
+  /**
+   * When a site's passkey last signed the user in, as an ISO 8601 timestamp
+   *
+   * `result` is absent for a passkey not used since it was registered.
+   */
+  async getCredentialLastUsed(
+    siteId: string,
+    credentialId: string
+  ): Promise<{ success: boolean; result?: string; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Passkey usage is only available in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<string | null>('get_credential_last_used', { siteId, credentialId });
+      return { success: true, result: result ?? undefined };
+    } catch (error) {
+      console.error('Failed to get when the passkey was last used:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to get when the passkey was last used') };
+    }
+  }
+
+  /**
+   * Check whether a site has to register a new passkey because this device lost
+   * the stored ones, without prompting the user
+   *
+   * When true, the next registerPasskey call for the site replaces the stale passkeys.
+   */
+  async shouldReenroll(siteId: string): Promise<{ success: boolean; result?: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Re-enrollment checks are only available in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<boolean>('should_reenroll', { siteId });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to check for passkey re-enrollment:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to check for passkey re-enrollment') };
+    }
+  }
+
+  /**
+   * Whether a site has a passkey and a native session that has not expired
+   *
+   * Sessions are opened by native authentication and last 30 minutes by default.
+   */
+  async getNativeSiteAuthStatus(siteId: string): Promise<SiteAuthStatus | null> {
+    if (!isTauriApp()) {
+      return null;
+    }
+
+    try {
+      return await invoke<SiteAuthStatus>('get_site_auth_status', { siteId });
+    } catch (error) {
+      console.error('Failed to get site auth status:', error);
+      return null;
+    }
+  }
+
+  /**
+   * List the sites with native passkeys, most recently protected first
+   */
+  async listProtectedSites(
+    filter?: SiteFilter
+  ): Promise<{ success: boolean; result?: SiteCredentialSummary[]; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Protected sites can only be listed in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<SiteCredentialSummary[]>('list_protected_sites', { filter: filter ?? null });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to list protected sites:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to list protected sites') };
+    }
+  }
+
+  /**
+   * List the native passkeys the user could sign in with, most recently used
+   * first, to offer a choice before the biometric prompt
+   */
+  async getLoginHints(rpId?: string): Promise<{ success: boolean; result?: LoginHint[]; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Login hints are only available in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<LoginHint[]>('get_login_hints', { rpId: rpId ?? null });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to get login hints:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to get login hints') };
+    }
+  }
+
+  /**
+   * Delete the native passkeys of every site not in `activeSiteIds`
+   *
+   * Call on startup with all of the app's sites: the background credential
+   * refresh purges against the sites given last.
+   */
+  async purgeOrphanedCredentials(
+    activeSiteIds: string[]
+  ): Promise<{ success: boolean; result?: PurgeReport; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Passkeys can only be purged in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<PurgeReport>('purge_orphaned_credentials', { activeSiteIds });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to purge the passkeys of removed sites:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to purge the passkeys of removed sites') };
+    }
+  }
+
+  /**
+   * Delete every passkey and end every session, for a device that may be compromised
+   *
+   * confirmation must be 'CONFIRM_WIPE'. Passkeys the platform could not delete
+   * are listed in the report's errors; the credential store is removed regardless.
+   */
+  async emergencyWipe(
+    confirmation: string
+  ): Promise<{ success: boolean; result?: WipeReport; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Passkeys can only be wiped in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<WipeReport>('emergency_wipe', { confirmation });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to wipe the passkeys:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to wipe the passkeys') };
+    }
+  }
+
+  /**
+   * Forget the security key PINs kept from recent ceremonies, so the next ones ask again
+   */
+  async clearPinCache(): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Security key PINs are only kept in the desktop app' };
+    }
+
+    try {
+      await invoke('clear_pin_cache');
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to clear the security key PIN cache:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to clear the security key PIN cache') };
+    }
+  }
+
+  /**
+   * Path of the file registrations, authentications and other passkey changes are recorded in
+   */
+  async getAuditLogPath(): Promise<{ success: boolean; result?: string; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'The audit log is only kept in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<string>('get_audit_log_path');
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to get the audit log path:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to get the audit log path') };
+    }
+  }
+
+  /**
+   * Snapshot the native WebAuthn state for support tooling, without prompting the user
+   */
+  async healthCheck(): Promise<{ success: boolean; result?: HealthReport; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'The health check is only available in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<HealthReport>('webauthn_health_check');
+      return { success: true, result };
+    } catch (error) {
+      console.error('WebAuthn health check failed:', error);
+      return { success: false, error: describeTauriError(error, 'WebAuthn health check failed') };
+    }
+  }
+
+  /**
+   * Check whether text may be copied, refusing any that holds a passkey credential ID
+   *
+   * A refusal also emits webauthn://clipboard-blocked. Outside the desktop app
+   * there are no native credential IDs to leak, so every write is allowed.
+   */
+  async isClipboardWriteAllowed(text: string): Promise<boolean> {
+    if (!isTauriApp()) {
+      return true;
+    }
+
+    try {
+      return await invoke<boolean>('check_clipboard_write', { text });
+    } catch (error) {
+      console.error('Failed to check clipboard write:', error);
+      return false;
+    }
+  }
+
+  /**
+   * Read the native ceremony counters, e.g. for an operator dashboard
+   */
+  async getMetrics(): Promise<{ success: boolean; result?: WebAuthnMetricsSnapshot; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'WebAuthn metrics are only available in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<WebAuthnMetricsSnapshot>('get_webauthn_metrics');
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to get WebAuthn metrics:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to get WebAuthn metrics') };
+    }
+  }
+
+  /**
+   * Set the native ceremony counters back to zero
+   */
+  async resetMetrics(): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'WebAuthn metrics are only available in the desktop app' };
+    }
+
+    try {
+      await invoke('reset_webauthn_metrics');
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to reset WebAuthn metrics:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to reset WebAuthn metrics') };
+    }
+  }
+
+  /**
+   * Read how far the first passkey setup got, e.g. to decide whether to show onboarding
+   */
+  async getOnboardingState(): Promise<{ success: boolean; result?: OnboardingState; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Onboarding is only tracked in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<OnboardingState>('get_onboarding_state');
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to get the onboarding state:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to get the onboarding state') };
+    }
+  }
+
+  /**
+   * Start onboarding over, so the setup flow shows again
+   */
+  async resetOnboarding(): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Onboarding is only tracked in the desktop app' };
+    }
+
+    try {
+      await invoke('reset_onboarding');
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to reset onboarding:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to reset onboarding') };
+    }
+  }
+
+  /**
+   * Reseed the native challenge generator without restarting the app
+   *
+   * Challenges issued before can no longer be used; ceremonies waiting on them must be retried.
+   */
+  async rotateChallengeSeed(): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'The challenge seed can only be rotated in the desktop app' };
+    }
+
+    try {
+      await invoke('rotate_challenge_seed');
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to rotate the challenge seed:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to rotate the challenge seed') };
+    }
+  }
+
+  /**
+   * Check the signature of an assertion obtained outside the app's own ceremonies
+   *
+   * Every argument is base64url. An invalid signature is reported as a
+   * SignatureInvalid error, and malformed input as InvalidInput.
+   *
+   * @param publicKey - The credential's COSE public key, as stored in the site's auth config
+   */
+  async verifyAssertion(
+    credentialId: string,
+    clientDataJson: string,
+    authenticatorData: string,
+    signature: string,
+    publicKey: string
+  ): Promise<{ success: boolean; result?: AssertionVerificationResult; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Assertion verification is only available in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<AssertionVerificationResult>('verify_assertion', {
+        credentialId,
+        clientDataJson,
+        authenticatorData,
+        signature,
+        publicKey,
+      });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to verify assertion:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to verify assertion') };
+    }
+  }
+
+  /**
+   * Give a site's native sessions their own lifetime, e.g. a shorter one for sensitive content
+   *
+   * @param ttlSeconds - Between 60 and 86400 (24 hours); applies to an open session too
+   */
+  async setSiteSessionTtl(siteId: string, ttlSeconds: number): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Session lifetimes can only be set in the desktop app' };
+    }
+
+    try {
+      await invoke('set_site_session_ttl', { siteId, ttlSeconds });
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to set the session TTL:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to set the session TTL') };
+    }
+  }
+
+  /**
+   * Set how long after a native authentication the next one succeeds without a prompt
+   *
+   * @param seconds - At most 60; 0 prompts every time. Open sessions keep the grace period they started with
+   */
+  async setGracePeriod(seconds: number): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'The grace period can only be set in the desktop app' };
+    }
+
+    try {
+      await invoke('set_grace_period', { seconds });
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to set the grace period:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to set the grace period') };
+    }
+  }
+
+  /**
+   * Lock the app, ending every native session, after a period without activity
+   *
+   * Report activity with recordActivity. The next native authentication for any
+   * site unlocks the app and reopens the sessions; listen for 'appLocked' events
+   * to show the lock screen.
+   */
+  async configureAppLock(config: AppLockConfig): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'The app lock is only available in the desktop app' };
+    }
+
+    try {
+      await invoke('configure_app_lock', { config });
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to configure the app lock:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to configure the app lock') };
+    }
+  }
+
+  /**
+   * Tell the app lock the user interacted with the app, restarting its idle countdown
+   */
+  async recordActivity(): Promise<void> {
+    if (!isTauriApp()) {
+      return;
+    }
+
+    try {
+      await invoke('record_activity');
+    } catch (error) {
+      console.error('Failed to record activity:', error);
+    }
+  }
+
+  /**
+   * Start signing in to a site with a passkey on another device, such as a phone
+   *
+   * Show the returned QR code for that device to scan, then poll with
+   * pollHybridAuth. The cross-device transport is not implemented yet, so
+   * polling fails until it is.
+   */
+  async initiateHybridAuth(
+    siteId: string
+  ): Promise<{ success: boolean; result?: HybridAuthSession; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Signing in with another device is only available in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<HybridAuthSession>('initiate_hybrid_auth', { siteId });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to start signing in with another device:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to start signing in with another device') };
+    }
+  }
+
+  /**
+   * Check whether the other device has answered a sign-in started by initiateHybridAuth
+   */
+  async pollHybridAuth(
+    sessionToken: string
+  ): Promise<{ success: boolean; result?: HybridAuthPollResult; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Signing in with another device is only available in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<HybridAuthPollResult>('poll_hybrid_auth', { sessionToken });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to check on the sign-in with another device:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to check on the sign-in with another device') };
+    }
+  }
+
+  /**
+   * End the native session for a site, so editing it requires authenticating again
+   *
+   * Succeeds when the site had no session.
+   */
+  async logoutSite(siteId: string): Promise<{ success: boolean; error?: string }> {
+    this.registeredCredentials.delete(siteId);
+    if (!isTauriApp()) {
+      return { success: true };
+    }
+
+    try {
+      await invoke('logout_site', { siteId });
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to log out of site:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to log out') };
+    }
+  }
+
+  /**
+   * End the native session of every site
+   *
+   * @returns How many sessions were still active
+   */
+  async logoutAllSites(): Promise<{ success: boolean; result?: number; error?: string }> {
+    this.registeredCredentials.clear();
+    if (!isTauriApp()) {
+      return { success: true, result: 0 };
+    }
+
+    try {
+      const result = await invoke<number>('logout_all_sites');
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to log out of all sites:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to log out') };
+    }
+  }
+
+  /**
+   * Remove authentication from a site (make it public)
+   */
+  async removeAuthentication(siteId: string): Promise<boolean> {
+    try {
+      this.registeredCredentials.delete(siteId);
+      return true;
+    } catch (error) {
+      console.error('Failed to remove authentication:', error);
+      return false;
+    }
+  }
+
+  /**
+   * Delete one of a site's passkeys from the device and the native credential store
+   *
+   * @returns `notFound` when the passkey was already deleted
+   */
+  async deletePasskey(
+    siteId: string,
+    credentialId: string
+  ): Promise<{ success: boolean; notFound?: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Passkeys can only be deleted in the desktop app' };
+    }
+
+    try {
+      await invoke('delete_passkey', { siteId, credentialId });
+      return { success: true };
+    } catch (error) {
+      if (isTauriWebAuthnError(error) && error.code === 'CredentialNotFound') {
+        return { success: false, notFound: true };
+      }
+      console.error('Failed to delete passkey:', error);
+      return {
+        success: false,
+        error: describeTauriError(error, 'Failed to delete passkey')
+      };
+    }
+  }
+
+  /**
+   * Let a passkey locked after too many failed sign-ins be used again
+   */
+  async unlockCredential(siteId: string, credentialId: string): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Passkeys can only be unlocked in the desktop app' };
+    }
+
+    try {
+      await invoke('unlock_credential', { siteId, credentialId });
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to unlock passkey:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to unlock passkey') };
+    }
+  }
+
+  /**
+   * Rename the user shown with a site's native passkeys; applies to all of the site's passkeys
+   */
+  async updateCredentialDisplayName(
+    siteId: string,
+    credentialId: string,
+    displayName: string
+  ): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Passkeys can only be renamed in the desktop app' };
+    }
+
+    try {
+      await invoke('update_credential_display_name', { siteId, credentialId, displayName });
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to update the passkey display name:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to update the passkey display name') };
+    }
+  }
+
+  /**
+   * Write every site's passkey configuration to a passphrase-protected backup
+   *
+   * @param outputPath Destination chosen with the dialog plugin
+   */
+  async exportCredentials(
+    outputPath: string,
+    passphrase: string
+  ): Promise<{ success: boolean; result?: ExportResult; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Credentials can only be backed up in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<ExportResult>('export_credentials', { outputPath, passphrase });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to export credentials:', error);
+      return {
+        success: false,
+        error: describeTauriError(error, 'Failed to export credentials')
+      };
+    }
+  }
+
+  /**
+   * Merge a passphrase-protected backup into the native credential store
+   *
+   * Passkeys the store already holds are skipped.
+   */
+  async importCredentials(
+    inputPath: string,
+    passphrase: string
+  ): Promise<{ success: boolean; result?: ImportResult; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Credentials can only be restored in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<ImportResult>('import_credentials', { inputPath, passphrase });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to import credentials:', error);
+      return {
+        success: false,
+        error: describeTauriError(error, 'Failed to import credentials')
+      };
+    }
+  }
+
+  /**
+   * Ask another device for its sites' passkey configuration, as a QR code it scans
+   *
+   * The other device answers with an offer for acceptSyncOffer, which only this
+   * device can open, once and within 60 seconds. Only debug builds and builds
+   * with the `allow-credential-import` feature accept offers.
+   */
+  async requestSyncOffer(): Promise<{ success: boolean; result?: SyncRequest; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Credentials can only be synced in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<SyncRequest>('request_sync_offer');
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to create the sync request:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to create the sync request') };
+    }
+  }
+
+  /**
+   * Offer sites' passkey configuration to the device whose sync request was
+   * scanned, as a QR code that device scans in turn
+   */
+  async generateSyncOffer(
+    siteIds: string[],
+    request: string
+  ): Promise<{ success: boolean; result?: SyncOffer; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Credentials can only be synced in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<SyncOffer>('generate_sync_offer', { sites: siteIds, request });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to create the sync offer:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to create the sync offer') };
+    }
+  }
+
+  /**
+   * Merge a sync offer scanned from another device into the native credential store
+   *
+   * The offer must answer a request this device made with requestSyncOffer.
+   * Passkeys the store already holds are skipped.
+   */
+  async acceptSyncOffer(
+    payload: string
+  ): Promise<{ success: boolean; result?: SyncAcceptResult; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Credentials can only be synced in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<SyncAcceptResult>('accept_sync_offer', { payload });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to accept the sync offer:', error);
+      return { success: false, error: describeTauriError(error, 'Failed to accept the sync offer') };
+    }
+  }
+
+  /**
+   * Register a site's exported passkeys on this installation without a ceremony,
+   * e.g. for bulk provisioning
+   *
+   * Only debug builds and builds with the `allow-credential-import` feature accept this.
+   */
+  async importSiteConfig(
+    siteId: string,
+    siteName: string,
+    existingConfig: SiteAuthConfig
+  ): Promise<RegistrationResult> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Passkeys can only be imported in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<RegistrationResult>('register_passkey', { siteId, siteName, existingConfig });
+      if (result.authConfig) {
+        this.registeredCredentials.set(siteId, result.authConfig);
+      }
+      return result;
+    } catch (error) {
+      console.error('Failed to import passkeys:', error);
+      return {
+        success: false,
+        error: describeTauriError(error, 'Failed to import passkeys')
+      };
+    }
+  }
+
+  /**
+   * Protect a site that was public with a new passkey, making it require auth
+   *
+   * The stored config is left as it was when the ceremony fails.
+   */
+  async upgradeSiteToPasskey(
+    siteId: string,
+    siteName: string,
+    userDisplayName?: string
+  ): Promise<RegistrationResult> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Sites can only be protected with a passkey in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<RegistrationResult>('upgrade_site_to_passkey', {
+        siteId,
+        siteName,
+        userDisplayName
+      });
+      if (result.authConfig) {
+        this.registeredCredentials.set(siteId, result.authConfig);
+      }
+      return result;
+    } catch (error) {
+      console.error('Failed to protect the site with a passkey:', error);
+      return {
+        success: false,
+        error: describeTauriError(error, 'Failed to protect the site with a passkey')
+      };
+    }
+  }
+
+  /**
+   * Move site configurations kept in localStorage into the native credential store
+   *
+   * Passkeys the store already holds are skipped, so this can safely run again.
+   * Malformed credentials are reported in the result's errors and left out.
+   */
+  async migrateCredentialsFromFrontend(
+    configs: FrontendSiteConfig[]
+  ): Promise<{ success: boolean; result?: MigrationReport; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: false, error: 'Credentials can only be migrated in the desktop app' };
+    }
+
+    try {
+      const result = await invoke<MigrationReport>('migrate_credentials_from_frontend', { configs });
+      return { success: true, result };
+    } catch (error) {
+      console.error('Failed to migrate credentials:', error);
+      return {
+        success: false,
+        error: describeTauriError(error, 'Failed to migrate credentials')
+      };
+    }
+  }
+
+  /**
+   * Stop the background check of stored passkeys started at launch, e.g. when
+   * the app is shutting down
+   */
+  async cancelCredentialRefresh(): Promise<{ success: boolean; error?: string }> {
+    if (!isTauriApp()) {
+      return { success: true };
+    }
+
+    try {
+      await invoke('cancel_credential_refresh');
+      return { success: true };
+    } catch (error) {
+      console.error('Failed to cancel the credential refresh:', error);
+      return {
+        success: false,
+        error: describeTauriError(error, 'Failed to cancel the credential refresh')
+      };
     }
   }
 
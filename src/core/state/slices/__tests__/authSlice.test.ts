@@ -22,11 +22,13 @@ describe('AuthSlice', () => {
   let store: AuthSlice;
 
   const mockAuthConfig: SiteAuthConfig = {
-    publicKey: 'mock-public-key',
-    credentialId: 'mock-credential-id',
+    credentials: [{
+      credentialId: 'mock-credential-id',
+      publicKey: 'mock-public-key',
+      registeredAt: '2025-01-01T00:00:00.000Z'
+    }],
     requiresAuth: true,
-    userDisplayName: 'Test User',
-    registeredAt: '2025-01-01T00:00:00.000Z'
+    userDisplayName: 'Test User'
   };
 
   beforeEach(() => {
@@ -388,11 +388,17 @@ export interface Manifest {
     };
   };
   auth?: {
-    publicKey: string;
-    credentialId: string;
+    credentials: {
+      credentialId: string;
+      publicKey: string;
+      registeredAt: string;
+      deviceName?: string;
+      lastUsedAt?: string;
+      signCount?: number;
+    }[];
     requiresAuth: boolean;
     userDisplayName?: string;
-    registeredAt: string;
+    userId?: string;
   };
   /** Optional array of data file paths for export/import system (e.g., "data/media.json") */
   dataFiles?: string[];
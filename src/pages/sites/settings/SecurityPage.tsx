@@ -173,18 +173,24 @@ export default function SecuritySettingsPage() {
                       <span className="text-muted-foreground">Owner:</span>
                       <span>{site.manifest.auth.userDisplayName || 'Site Owner'}</span>
                     </div>
-                    <div className="flex justify-between">
-                      <span className="text-muted-foreground">Registered:</span>
-                      <span>
-                        {new Date(site.manifest.auth.registeredAt).toLocaleDateString()}
-                      </span>
-                    </div>
-                    <div className="flex justify-between">
-                      <span className="text-muted-foreground">Credential ID:</span>
-                      <span className="font-mono text-xs truncate max-w-32">
-                        {site.manifest.auth.credentialId}
-                      </span>
-                    </div>
+                    {site.manifest.auth.credentials.map((credential) => (
+                      <div key={credential.credentialId} className="space-y-2">
+                        <div className="flex justify-between">
+                          <span className="text-muted-foreground">
+                            {credential.deviceName || 'Registered'}:
+                          </span>
+                          <span>
+                            {new Date(credential.registeredAt).toLocaleDateString()}
+                          </span>
+                        </div>
+                        <div className="flex justify-between">
+                          <span className="text-muted-foreground">Credential ID:</span>
+                          <span className="font-mono text-xs truncate max-w-32">
+                            {credential.credentialId}
+                          </span>
+                        </div>
+                      </div>
+                    ))}
                   </div>
                 </div>
 